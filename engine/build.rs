@@ -1,10 +1,14 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::convert::AsRef;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::path;
 use std::process::Command;
 
-fn walk_dir(dir: impl AsRef<Path>, extension: &str, f: &impl Fn(&Path)) {
+use serde::Deserialize;
+
+fn walk_dir(dir: impl AsRef<Path>, extension: &str, f: &mut impl FnMut(&Path)) {
     if let Ok(entries) = fs::read_dir(dir) {
         for entry in entries.flatten() {
             let path = entry.path();
@@ -17,27 +21,410 @@ fn walk_dir(dir: impl AsRef<Path>, extension: &str, f: &impl Fn(&Path)) {
     }
 }
 
+// 64-bit FNV-1a. we can't use std's DefaultHasher because the cache has to stay valid across
+// toolchain updates, and its algorithm isn't guaranteed to be stable
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 ^= b as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    // length-prefixed, so ("ab", "c") and ("a", "bc") hash differently
+    fn write_chunk(&mut self, bytes: &[u8]) {
+        self.write(&(bytes.len() as u64).to_le_bytes());
+        self.write(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+// converted assets are stored in here, keyed by a hash of everything that went into them. it
+// lives outside of target/ so a `cargo clean` doesn't throw away all of our conversions
+fn cache_dir() -> PathBuf {
+    println!("cargo::rerun-if-env-changed=MM3DS_ASSET_CACHE");
+    let dir = std::env::var_os("MM3DS_ASSET_CACHE")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| Path::new(&std::env::var("CARGO_MANIFEST_DIR").unwrap()).join(".asset_cache"));
+
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// a hash of the program `tool`, found the way Command finds it, so that a new version of a tool
+// (one writing a newer MESH format, say) doesn't get the old one's conversions. hashed once per
+// build, since the same few tools convert everything
+fn tool_hash(tool: &str) -> u64 {
+    thread_local! {
+        static HASHES: RefCell<HashMap<String, u64>> = RefCell::new(HashMap::new());
+    }
+
+    HASHES.with_borrow_mut(|hashes| *hashes.entry(tool.to_owned()).or_insert_with(|| {
+        let path = Path::new(tool);
+        let found = if path.components().count() > 1 {
+            Some(path.to_owned())
+        } else {
+            std::env::var_os("PATH").and_then(|paths| {
+                std::env::split_paths(&paths)
+                    .flat_map(|dir| [dir.join(tool), dir.join(tool).with_extension(std::env::consts::EXE_EXTENSION)])
+                    .find(|path| path.is_file())
+            })
+        };
+
+        // one that isn't there fails its conversion anyway
+        let mut hasher = Fnv1a::new();
+        hasher.write_chunk(&found.and_then(|path| fs::read(path).ok()).unwrap_or_default());
+        hasher.finish()
+    }))
+}
+
+// produces `output` by running `convert` with the program `tool`, unless the same version of it
+// has converted something with the same `inputs` and `args` before, in which case the cached file
+// gets copied over instead
+fn convert_cached(tool: &str, inputs: &[PathBuf], args: &[String], output: &Path, convert: impl FnOnce(&Path)) {
+    let mut hasher = Fnv1a::new();
+    hasher.write_chunk(tool.as_bytes());
+    hasher.write(&tool_hash(tool).to_le_bytes());
+    for arg in args {
+        hasher.write_chunk(arg.as_bytes());
+    }
+    for input in inputs {
+        hasher.write_chunk(&fs::read(input).unwrap());
+    }
+
+    let extension = output.extension().and_then(|e| e.to_str()).unwrap_or("bin");
+    let cached = cache_dir().join(format!("{:016x}.{}", hasher.finish(), extension));
+
+    fs::create_dir_all(output.parent().unwrap()).unwrap();
+    if cached.exists() {
+        fs::copy(&cached, output).unwrap();
+        return;
+    }
+
+    convert(output);
+
+    // write under a temporary name first so an interrupted build can't leave a truncated file
+    // sitting in the cache
+    let tmp = cached.with_extension("tmp");
+    fs::copy(output, &tmp).unwrap();
+    fs::rename(&tmp, &cached).unwrap();
+}
+
+// gfx/tex3ds.toml sets default tex3ds flags per directory, e.g.
+//
+//     format = "auto-etc1"    # top-level keys apply to everything under gfx/
+//     compression = "auto"
+//
+//     ["ui"]                  # and sections to a directory (and its subdirectories)
+//     format = "rgba8"
+//     mipmap = "linear"
+//
+//     ["sky"]
+//     cubemap = true          # switches are `true` or `false`
+//
+// top-level keys are the defaults, and each table is a directory's. a deeper directory is a table
+// of its own, with a quoted name: ["ui/icons"]
+#[derive(Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Profile {
+    format: Option<String>, // -f
+    compression: Option<String>, // -z
+    mipmap: Option<String>, // -m
+    cubemap: Option<bool>, // --cubemap
+}
+
+impl Profile {
+    // what `other` sets replaces what `self` does
+    fn override_with(&mut self, other: &Profile) {
+        self.format = other.format.clone().or(self.format.take());
+        self.compression = other.compression.clone().or(self.compression.take());
+        self.mipmap = other.mipmap.clone().or(self.mipmap.take());
+        self.cubemap = other.cubemap.or(self.cubemap);
+    }
+}
+
+// by directory, relative to gfx/, with "" for the defaults
+struct TextureProfiles(HashMap<String, Profile>);
+
+impl TextureProfiles {
+    fn load(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        let Ok(file_data) = fs::read_to_string(path) else {
+            return Self(HashMap::new());
+        };
+
+        let file: toml::Table = toml::from_str(&file_data).unwrap_or_else(|e| panic!("{}: {e}", path.display()));
+        let (sections, defaults): (toml::Table, toml::Table) = file.into_iter().partition(|(_, value)| value.is_table());
+        let mut profiles = HashMap::new();
+        for (name, value) in sections.into_iter().chain([(String::new(), toml::Value::Table(defaults))]) {
+            let profile: Profile = value.try_into().unwrap_or_else(|e| panic!("{}: [{name}]: {e}", path.display()));
+            profiles.insert(name.trim_matches('/').to_owned(), profile);
+        }
+
+        Self(profiles)
+    }
+
+    // flags for a t3s in `dir` (relative to gfx/). deeper directories override shallower ones, and
+    // since tex3ds keeps the last value of a repeated flag, anything in the t3s itself wins
+    fn flags_for(&self, dir: &Path) -> Vec<String> {
+        let mut sections = vec![String::new()];
+        let mut current = PathBuf::new();
+        for component in dir.components() {
+            current.push(component);
+            sections.push(current.to_str().unwrap().replace('\\', "/"));
+        }
+
+        let mut profile = Profile::default();
+        for section in sections {
+            if let Some(other) = self.0.get(&section) {
+                profile.override_with(other);
+            }
+        }
+
+        let mut ret = vec![];
+        for (flag, value) in [("-f", profile.format), ("-z", profile.compression), ("-m", profile.mipmap)] {
+            if let Some(value) = value {
+                ret.push(flag.to_owned());
+                ret.push(value);
+            }
+        }
+        if profile.cubemap == Some(true) {
+            ret.push("--cubemap".to_owned());
+        }
+
+        ret
+    }
+}
+
+// everything under here is generated, and gets packed into the RomFS image by cargo-3ds (see
+// romfs_dir in Cargo.toml). the engine reads it back from romfs:/gfx/
+const ROMFS_GFX_DIR: &str = "romfs/gfx";
+
+// or, with MM3DS_PACK_ASSETS set, all packed into this one file, see src/pack.rs
+const ROMFS_GFX_PACK: &str = "romfs/gfx.pack";
+
+// the size of an ANIM file with no clips: a magic, no joints and a count
+const EMPTY_ANIM_SIZE: u64 = 10;
+
+// gfx/folder/file.ext => romfs/gfx/folder/file.<extension>
+fn out_path(path: &Path, extension: &str) -> PathBuf {
+    path::absolute(Path::new(ROMFS_GFX_DIR).join(
+        path.with_extension(extension).strip_prefix("gfx/").unwrap()
+    )).unwrap()
+}
+
+// gfx/ui/button.t3x => UI_BUTTON_T3X
+fn const_name(path: &Path) -> String {
+    let mut name = path.to_str().unwrap()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .collect::<String>();
+
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+
+    name
+}
+
+// writes OUT_DIR/assets.rs, which src/assets.rs includes. every asset that made it into the RomFS
+// image gets a constant, so referencing one that doesn't exist is a compile error
+fn write_manifest(assets: &[(PathBuf, &str)]) {
+    let mut manifest = String::new();
+    let mut all = vec![];
+
+    for (path, kind) in assets {
+        let relative = path.strip_prefix(path::absolute(ROMFS_GFX_DIR).unwrap()).unwrap();
+        let name = const_name(relative);
+        let size = fs::metadata(path).unwrap().len();
+
+        manifest += &format!(
+            "#[allow(dead_code)]\npub const {name}: Asset = Asset {{ path: {:?}, kind: AssetKind::{kind}, size: {size} }};\n",
+            format!("romfs:/gfx/{}", relative.to_str().unwrap().replace('\\', "/")),
+        );
+        all.push(name);
+    }
+
+    manifest += &format!("#[allow(dead_code)]\npub const ALL: &[Asset] = &[{}];\n", all.join(", "));
+
+    fs::write(Path::new(&std::env::var("OUT_DIR").unwrap()).join("assets.rs"), manifest).unwrap();
+}
+
+// the `; stride N` a geometry shader starts with: how many registers it reads per vertex, one per
+// output of the vertex shader. picasso can't say, and counting the outputs in the source would miss
+// masked ones and ones declared together, so it's left to the shader
+fn geometry_stride(geometry: &Path) -> u8 {
+    let source = fs::read_to_string(geometry).unwrap();
+    let stride = source.lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .and_then(|line| line.strip_prefix(';'))
+        .and_then(|line| line.trim_start().strip_prefix("stride"))
+        .and_then(|stride| stride.trim().parse().ok());
+
+    stride.unwrap_or_else(|| panic!("{} has to start with `; stride N`, N being how many outputs the vertex shader has", geometry.display()))
+}
+
+// compiles shaders/<name>.v.pica (plus shaders/<name>.g.pica, if there is one) into a single
+// OUT_DIR/shaders/<name>.shbin, and then writes OUT_DIR/shaders.rs so src/shader.rs can embed
+// every one of them into the registry
+fn compile_shaders() {
+    println!("cargo::rerun-if-changed=shaders");
+
+    let mut names = vec![];
+    walk_dir("shaders", "pica", &mut |path| {
+        let stem = path.file_stem().unwrap().to_str().unwrap();
+        if let Some(name) = stem.strip_suffix(".v") {
+            names.push((path.with_file_name(name), name.to_owned()));
+        }
+    });
+    names.sort();
+
+    let out_dir = Path::new(&std::env::var("OUT_DIR").unwrap()).join("shaders");
+    let mut table = String::new();
+    for (base, name) in names {
+        let vertex = base.with_extension("v.pica");
+        let geometry = base.with_extension("g.pica");
+
+        let mut inputs = vec![vertex];
+
+        let geometry_stride = if geometry.is_file() {
+            let stride = geometry_stride(&geometry);
+            inputs.push(geometry);
+            format!("Some({stride})")
+        } else {
+            "None".to_owned()
+        };
+
+        let output_path = path::absolute(out_dir.join(base.strip_prefix("shaders/").unwrap()).with_extension("shbin")).unwrap();
+        convert_cached("picasso", &inputs, &[], &output_path, |output_path| {
+            let exit_code = Command::new("picasso")
+                .arg("-o")
+                .arg(output_path)
+                .args(&inputs)
+                .status().unwrap();
+            assert!(exit_code.success());
+        });
+
+        table += &format!(
+            "    BuiltinShader {{ name: {name:?}, shbin: include_aligned!({:?}), geometry_stride: {geometry_stride} }},\n",
+            output_path.to_str().unwrap(),
+        );
+    }
+
+    fs::write(
+        Path::new(&std::env::var("OUT_DIR").unwrap()).join("shaders.rs"),
+        format!("static BUILTIN_SHADERS: &[BuiltinShader] = &[\n{table}];\n"),
+    ).unwrap();
+}
+
 fn main() {
-    walk_dir("gfx", "t3s", &|path| {
-        dbg!(path);
-        let file_data = fs::read_to_string(path).unwrap();
+    compile_shaders();
 
-        // gfx/folder/file.t3s => OUT_DIR/folder/file.t3x
-        let output_path = path::absolute(Path::new(&std::env::var("OUT_DIR").unwrap()).join(
-                path.with_extension("t3x").strip_prefix("gfx/").unwrap()
-        )).unwrap();
+    println!("cargo::rerun-if-changed=gfx");
+    let mut assets = vec![];
 
+    // start from scratch so assets deleted from gfx/ don't linger in the image
+    if Path::new(ROMFS_GFX_DIR).exists() {
+        fs::remove_dir_all(ROMFS_GFX_DIR).unwrap();
+    }
+    if Path::new(ROMFS_GFX_PACK).exists() {
+        fs::remove_file(ROMFS_GFX_PACK).unwrap();
+    }
+    fs::create_dir_all(ROMFS_GFX_DIR).unwrap();
+
+    let profiles = TextureProfiles::load("gfx/tex3ds.toml");
+    walk_dir("gfx", "t3s", &mut |path| {
         let t3s_dir = path.ancestors().nth(1).unwrap();
-        let exit_code = Command::new("tex3ds")
-            .current_dir(t3s_dir) // set cwd for tex3ds next to the t3s
-            .args(file_data.split_whitespace()) // pass parameters from t3s
-            .arg("-o") // output path is gfx_build/filename.t3x
-            .arg(output_path)
-            .status().unwrap();
-        assert!(exit_code.success());
 
-        // let input_file = file_data.lines().last().unwrap();
-        // println!("cargo::rerun-if-changed={}", t3s_dir.join(input_file).display());
-        // println!("cargo::rerun-if-changed={}", path.display());
+        let file_data = fs::read_to_string(path).unwrap();
+        let mut args = profiles.flags_for(t3s_dir.strip_prefix("gfx").unwrap());
+        args.extend(file_data.split_whitespace().map(String::from));
+        let output_path = out_path(path, "t3x");
+
+        // any argument that names a file next to the t3s is an input image, so its contents are
+        // part of the cache key
+        let inputs = args.iter()
+            .map(|arg| t3s_dir.join(arg))
+            .filter(|path| path.is_file())
+            .collect::<Vec<_>>();
+
+        convert_cached("tex3ds", &inputs, &args, &output_path, |output_path| {
+            let exit_code = Command::new("tex3ds")
+                .current_dir(t3s_dir) // set cwd for tex3ds next to the t3s
+                .args(&args) // pass parameters from tex3ds.toml and the t3s
+                .arg("-o") // output path is romfs/gfx/filename.t3x
+                .arg(output_path)
+                .status().unwrap();
+            assert!(exit_code.success());
+        });
+        // cubemaps need a different loader, so they get their own kind
+        let cubemap = args.iter().any(|arg| arg == "--cubemap" || arg == "--skybox");
+        assets.push((output_path, if cubemap { "Cubemap" } else { "Texture" }));
     });
+
+    println!("cargo::rerun-if-env-changed=GLTF_TOOL");
+    let gltf_tool = std::env::var("GLTF_TOOL").unwrap_or_else(|_| "gltf_tool".into());
+    for extension in ["glb", "gltf"] {
+        walk_dir("gfx", extension, &mut |path| {
+            let output_path = out_path(path, "mesh");
+
+            // .gltf files can reference external buffers and images, which aren't hashed. touch
+            // the .gltf (or use .glb) after editing those
+            convert_cached(&gltf_tool, &[path.to_owned()], &[], &output_path, |output_path| {
+                let exit_code = Command::new(&gltf_tool)
+                    .arg(path)
+                    .arg(output_path)
+                    .status().unwrap();
+                assert!(exit_code.success());
+            });
+            assets.push((output_path, "Mesh"));
+
+            // and its animations, if it has any
+            let output_path = out_path(path, "anim");
+            convert_cached(&gltf_tool, &[path.to_owned()], &["anim".to_owned()], &output_path, |output_path| {
+                let exit_code = Command::new(&gltf_tool)
+                    .arg(path)
+                    .arg(output_path)
+                    .status().unwrap();
+                assert!(exit_code.success());
+            });
+            if fs::metadata(&output_path).unwrap().len() > EMPTY_ANIM_SIZE {
+                assets.push((output_path, "Animation"));
+            } else {
+                fs::remove_file(&output_path).unwrap();
+            }
+        });
+    }
+
+    // meshes that were converted by hand are packed as-is
+    walk_dir("gfx", "mesh", &mut |path| {
+        let output_path = out_path(path, "mesh");
+        fs::create_dir_all(output_path.parent().unwrap()).unwrap();
+        fs::copy(path, &output_path).unwrap();
+        assets.push((output_path, "Mesh"));
+    });
+
+    assets.sort();
+    write_manifest(&assets);
+
+    // the manifest keeps the loose paths, which Pack::read_asset looks up in the pack
+    println!("cargo::rerun-if-env-changed=MM3DS_PACK_ASSETS");
+    if std::env::var_os("MM3DS_PACK_ASSETS").is_some() {
+        let exit_code = Command::new(&gltf_tool)
+            .arg(ROMFS_GFX_DIR)
+            .arg(ROMFS_GFX_PACK)
+            .status().unwrap();
+        assert!(exit_code.success());
+        fs::remove_dir_all(ROMFS_GFX_DIR).unwrap();
+    }
 }
@@ -0,0 +1,156 @@
+// run with `cargo 3ds run -p mm3ds-engine --example demo`
+use std::f32::consts::PI;
+use std::fs::{self, File};
+use std::io::BufWriter;
+
+use citro3d::math::Matrix4;
+use ctru::prelude::*;
+use ctru::services::romfs::RomFS;
+use ctru::set_panic_hook;
+use glam::Vec3;
+use mm3ds_engine::backlight::Backlight;
+use mm3ds_engine::camera::NoCollision;
+use mm3ds_engine::config::Config;
+use mm3ds_engine::debug_camera::DebugCamera;
+use mm3ds_engine::debug_console::DebugConsole;
+use mm3ds_engine::events::{Action, EventBus};
+use mm3ds_engine::input::Input;
+use mm3ds_engine::material::Material;
+use mm3ds_engine::mesh::{CUBE_VERTICES, Mesh};
+use mm3ds_engine::model::Model;
+use mm3ds_engine::orbit_camera::OrbitCamera;
+use mm3ds_engine::power::PowerPolicy;
+use mm3ds_engine::renderer::Renderer;
+use mm3ds_engine::tweaks::TweakPanel;
+#[cfg(feature = "telemetry")]
+use mm3ds_engine::telemetry::Telemetry;
+
+const CAPTURE_PATH: &str = "sdmc:/mm3ds/frame.txt";
+
+// generated by build.rs from the contents of gfx/
+mod assets {
+    use mm3ds_engine::assets::{Asset, AssetKind};
+
+    include!(concat!(env!("OUT_DIR"), "/assets.rs"));
+}
+
+fn main() {
+    set_panic_hook(false);
+    let apt = Apt::new().unwrap();
+    let mut input = Input::new().unwrap();
+    let gfx = Gfx::new().unwrap();
+    let mut console = DebugConsole::new(&gfx);
+    console.show();
+    let mut tweaks = TweakPanel::new(&gfx);
+    let spin_speed = tweaks.add("spin speed", 1., 0.0..=4.0);
+    let _romfs = RomFS::new().unwrap();
+
+    console.log("Hello, World!");
+
+    let (config, config_error) = Config::load_or_default("romfs:/config.toml");
+    if let Some(e) = config_error {
+        console.log(e);
+    }
+    let mut events = EventBus::new();
+    let mut paused = false;
+    let capture = config.binding("capture_frame");
+    let mut power = config.quality.battery_saver
+        .then(|| PowerPolicy::new(config.quality()).unwrap());
+    let _backlight = config.display.backlight.map(|level| Backlight::new(level).unwrap());
+
+    let mut renderer = Renderer::new(&gfx);
+    config.apply(&mut renderer);
+    let cube = renderer.register_mesh(Mesh::from_data(
+            &CUBE_VERTICES, 
+            None,
+            Some(&assets::LEMON_T3X.read().unwrap()), 
+            Material::default()
+    ));
+
+    let character = Model::from_file_data(&mut renderer, assets::CHARACTER_MESH.open().unwrap()).unwrap();
+    let character = renderer.register_model(character);
+
+    // e.g. MM3DS_TELEMETRY_HOST=192.168.1.2:7777 cargo 3ds run --example demo --features telemetry
+    #[cfg(feature = "telemetry")]
+    let mut telemetry = Telemetry::new(env!("MM3DS_TELEMETRY_HOST")).unwrap();
+
+    // starts out at the origin, looking at the middle of the scene
+    let mut orbit = OrbitCamera::new(Vec3::new(0., 0., -2.5), 2.5);
+    orbit.pitch = 0.;
+    let mut debug_camera = DebugCamera::new();
+
+    let mut angle_x = 0.0_f32;
+    let mut angle_y = 0.0_f32;
+
+    while apt.main_loop() {
+        if let Some(power) = &mut power {
+            power.update();
+        }
+        let quality = power.as_ref().map_or_else(|| config.quality(), |power| *power.quality());
+        renderer.set_depth_strength(if quality.stereo { 1. } else { 0. });
+        for _ in 0..quality.vblanks_per_frame() {
+            gfx.wait_for_vblank();
+        }
+
+        input.update();
+        events.update();
+        config.publish_actions(&input, &mut events);
+        console.update(&input);
+        tweaks.update(&input);
+        if input.just_pressed(KeyPad::SELECT) && !console.is_toggle_held(&input) {
+            break;
+        }
+        if events.read::<Action>().iter().any(|action| action.pressed && action.name == "pause") {
+            paused = !paused;
+        }
+
+        let zoom = input.held(KeyPad::R) as i32 - input.held(KeyPad::L) as i32;
+        let dt = quality.vblanks_per_frame() as f32 / 60.;
+        if !debug_camera.is_active() {
+            orbit.update(input.circle_pad(), zoom as f32, dt, &NoCollision);
+        }
+        debug_camera.follow(orbit.camera());
+        debug_camera.update(&input, dt);
+        renderer.set_camera(&debug_camera.camera().unwrap_or(orbit.camera()));
+
+        for (x, z) in [(0., -2.)] {
+            let mut model = Matrix4::identity();
+            model.rotate_x(angle_x);
+            model.rotate_y(angle_y);
+            model.translate(x, 0., z + angle_x.sin() * 0.5);
+
+            renderer.please_render(cube, model);
+        }
+
+        for (x, z) in [(-1.5, -3.), (1.5, -3.)] {
+            let mut model = Matrix4::identity();
+            model.rotate_x(angle_x);
+            model.rotate_y(angle_y);
+            model.scale(0.3, 0.3, 0.3);
+            model.translate(x, 0., z + angle_x.sin() * 0.5);
+
+            renderer.please_render_model(character, model);
+        }
+
+        if !paused {
+            angle_x += PI / 180. * tweaks.get(spin_speed);
+            angle_y += PI / 360. * tweaks.get(spin_speed);
+        }
+
+        // L+R+X toggles the debug camera
+        if !capture.is_empty() && input.just_pressed(capture) && !debug_camera.is_toggle_held(&input) {
+            let written = fs::create_dir_all("sdmc:/mm3ds")
+                .and_then(|()| File::create(CAPTURE_PATH))
+                .and_then(|file| renderer.capture_frame().write(BufWriter::new(file)));
+            match written {
+                Ok(()) => console.log(format!("captured the frame to {CAPTURE_PATH}")),
+                Err(e) => console.log(format!("couldn't capture the frame: {e}")),
+            }
+        }
+
+        renderer.render();
+
+        #[cfg(feature = "telemetry")]
+        telemetry.send_frame(renderer.stats());
+    }
+}
@@ -0,0 +1,129 @@
+// renders a few fixed scenes and compares a CRC32 of the top screen against golden/golden.txt, so
+// renderer refactors can be checked for changes in output. run on hardware or under Citra with
+// `cargo 3ds run -p mm3ds-engine --example golden`
+use ctru::prelude::*;
+use ctru::services::romfs::RomFS;
+use ctru::set_panic_hook;
+use citro3d::math::Matrix4;
+use mm3ds_engine::material::Material;
+use mm3ds_engine::mesh::{CUBE_VERTICES, Mesh};
+use mm3ds_engine::renderer::{MeshId, Renderer};
+
+mod assets {
+    use mm3ds_engine::assets::{Asset, AssetKind};
+
+    include!(concat!(env!("OUT_DIR"), "/assets.rs"));
+}
+
+const GOLDEN: &str = include_str!("../golden/golden.txt");
+
+// the GPU renders into the back buffer, so both buffers have to hold the scene before reading one
+const SETTLE_FRAMES: usize = 8;
+
+struct Meshes {
+    cube: MeshId,
+    character: Vec<MeshId>,
+}
+
+const SCENES: &[(&str, fn(&mut Renderer, &Meshes))] = &[
+    ("empty", |_, _| {}),
+    ("cube", |renderer, meshes| {
+        let mut model = Matrix4::identity();
+        model.rotate_x(0.5);
+        model.rotate_y(0.25);
+        model.translate(0., 0., -2.);
+        renderer.please_render(meshes.cube, model);
+    }),
+    ("character", |renderer, meshes| {
+        for &mesh_id in &meshes.character {
+            let mut model = Matrix4::identity();
+            model.rotate_y(0.5);
+            model.scale(0.3, 0.3, 0.3);
+            model.translate(0., -0.5, -2.);
+            renderer.please_render(mesh_id, model);
+        }
+    }),
+];
+
+// CRC-32 (the zlib one)
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &b in data {
+        crc ^= b as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb88320 } else { crc >> 1 };
+        }
+    }
+
+    !crc
+}
+
+fn golden_value(name: &str) -> Option<u32> {
+    GOLDEN.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once(char::is_whitespace))
+        .find(|(scene, _)| *scene == name)
+        .map(|(_, crc)| u32::from_str_radix(crc.trim(), 16).expect("bad CRC in golden.txt"))
+}
+
+fn main() {
+    set_panic_hook(false);
+    let apt = Apt::new().unwrap();
+    let mut hid = Hid::new().unwrap();
+    let gfx = Gfx::new().unwrap();
+    let _console = Console::new(gfx.bottom_screen.borrow_mut());
+    let _romfs = RomFS::new().unwrap();
+
+    let mut renderer = Renderer::new(&gfx);
+    let meshes = Meshes {
+        cube: renderer.register_mesh(Mesh::from_data(
+            &CUBE_VERTICES,
+            None,
+            Some(&assets::LEMON_T3X.read().unwrap()),
+            Material::default(),
+        )),
+        character: Mesh::from_file_data(assets::CHARACTER_MESH.open().unwrap()).unwrap()
+            .into_iter()
+            .map(|mesh| renderer.register_mesh(mesh))
+            .collect(),
+    };
+
+    let (mut passed, mut failed, mut unrecorded) = (0, 0, 0);
+    for (name, scene) in SCENES {
+        for _ in 0..SETTLE_FRAMES {
+            gfx.wait_for_vblank();
+            scene(&mut renderer, &meshes);
+            renderer.render();
+        }
+        gfx.wait_for_vblank();
+
+        let crc = crc32(&renderer.read_framebuffer());
+        match golden_value(name) {
+            Some(expected) if expected == crc => {
+                println!("{name}: PASS");
+                passed += 1;
+            }
+            Some(expected) => {
+                println!("{name}: FAIL (got {crc:08x}, expected {expected:08x})");
+                failed += 1;
+            }
+            None => {
+                println!("{name}: no golden value, record `{name} {crc:08x}`");
+                unrecorded += 1;
+            }
+        }
+    }
+
+    println!("golden: {passed} passed, {failed} failed, {unrecorded} unrecorded");
+    println!("press START to exit");
+
+    while apt.main_loop() {
+        gfx.wait_for_vblank();
+
+        hid.scan_input();
+        if hid.keys_down().contains(KeyPad::START) {
+            break;
+        }
+    }
+}
@@ -0,0 +1,63 @@
+// draws the frame captured to sdmc:/mm3ds/frame.txt (by the demo's capture_frame button) over and
+// over, printing how long the systems took on average every couple of seconds, so renderer changes
+// can be compared on the same work. run with `cargo 3ds run -p mm3ds-engine --example replay`
+use std::fs::File;
+use std::io::BufReader;
+
+use ctru::prelude::*;
+use ctru::set_panic_hook;
+use mm3ds_engine::frame_replay::FrameReplay;
+use mm3ds_engine::profiler::{self, HISTORY, System};
+use mm3ds_engine::renderer::Renderer;
+use mm3ds_format::FrameCapture;
+
+const CAPTURE_PATH: &str = "sdmc:/mm3ds/frame.txt";
+// the profiler keeps this many frames, so each report is of frames it hasn't reported yet
+const REPORT_EVERY: usize = HISTORY;
+
+fn main() {
+    set_panic_hook(false);
+    let apt = Apt::new().unwrap();
+    let mut hid = Hid::new().unwrap();
+    let gfx = Gfx::new().unwrap();
+    let _console = Console::new(gfx.bottom_screen.borrow_mut());
+
+    let mut renderer = Renderer::new(&gfx);
+    let capture = File::open(CAPTURE_PATH).and_then(|file| FrameCapture::read(BufReader::new(file)));
+    let replay = match capture {
+        Ok(capture) => {
+            println!("{} draws of {} meshes", capture.draws.len(), capture.meshes.len());
+            Some(FrameReplay::new(&mut renderer, &capture))
+        }
+        Err(e) => {
+            println!("couldn't read {CAPTURE_PATH}: {e}");
+            None
+        }
+    };
+    println!("press START to exit");
+
+    let mut frames = 0;
+    while apt.main_loop() {
+        gfx.wait_for_vblank();
+
+        hid.scan_input();
+        if hid.keys_down().contains(KeyPad::START) {
+            break;
+        }
+
+        if let Some(replay) = &replay {
+            replay.please_render(&mut renderer);
+        }
+        renderer.render();
+
+        frames += 1;
+        if frames % REPORT_EVERY == 0 {
+            let times = profiler::frames();
+            let line = System::ALL.iter().enumerate()
+                .map(|(i, system)| format!("{system:?} {:.2}", times.iter().map(|frame| frame[i]).sum::<f32>() / HISTORY as f32))
+                .collect::<Vec<_>>()
+                .join(", ");
+            println!("ms: {line}");
+        }
+    }
+}
@@ -0,0 +1,279 @@
+// counts what's allocated where, for finding what survives a scene being unloaded. allocations
+// are counted under whichever tag is current when they're made, and a free counts against the tag
+// its allocation was made under:
+//
+//     #[global_allocator]
+//     static ALLOCATOR: TrackingAllocator = TrackingAllocator(System);
+//
+//     alloc_tracker::enable_linear();
+//     let level_tag = alloc_tracker::register("level");
+//     let before = alloc_tracker::checkpoint();
+//     {
+//         let _tag = alloc_tracker::tagged(level_tag);
+//         // load the level
+//     }
+//     // play, then unload it
+//     for leak in alloc_tracker::leaked_since(&before) {
+//         console.log(&leak.to_string());
+//     }
+//     ...
+//     console.log(&alloc_tracker::report());
+//
+// the heap is counted by TrackingAllocator, which has to be the global allocator, and linear
+// memory by LinearPool once enable_linear has been called. textures that citro3d allocates itself
+// (C3D_TexInit, load_t3x) aren't seen until they're moved into the pool. the current tag is shared
+// by every thread, so it's for the main thread's loading, not for threads doing their own
+use std::alloc::{GlobalAlloc, Layout};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+// tags there can be, counting the one for untagged allocations
+const MAX_TAGS: usize = 32;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Tag(u8);
+
+impl Tag {
+    // what's allocated outside of any tagged scope
+    pub const UNTAGGED: Tag = Tag(0);
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Memory {
+    Heap,
+    Linear,
+}
+
+impl Memory {
+    const ALL: [Memory; 2] = [Memory::Heap, Memory::Linear];
+}
+
+struct Counters {
+    allocations: AtomicUsize, // ever made
+    live: AtomicUsize, // not freed yet
+    live_bytes: AtomicUsize,
+    peak_bytes: AtomicUsize,
+}
+
+impl Counters {
+    const fn new() -> Self {
+        Self { allocations: AtomicUsize::new(0), live: AtomicUsize::new(0), live_bytes: AtomicUsize::new(0), peak_bytes: AtomicUsize::new(0) }
+    }
+
+    fn allocated(&self, size: usize) {
+        self.allocations.fetch_add(1, Ordering::Relaxed);
+        self.live.fetch_add(1, Ordering::Relaxed);
+        let bytes = self.live_bytes.fetch_add(size, Ordering::Relaxed) + size;
+        self.peak_bytes.fetch_max(bytes, Ordering::Relaxed);
+    }
+
+    fn freed(&self, size: usize) {
+        self.live.fetch_sub(1, Ordering::Relaxed);
+        self.live_bytes.fetch_sub(size, Ordering::Relaxed);
+    }
+}
+
+// only atomics, since the global allocator can't lock anything that might allocate
+static COUNTERS: [[Counters; MAX_TAGS]; 2] = [const { [const { Counters::new() }; MAX_TAGS] }; 2];
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static NAMES: Mutex<Vec<&'static str>> = Mutex::new(Vec::new()); // after "untagged"
+
+static LINEAR_ENABLED: AtomicBool = AtomicBool::new(false);
+// every linear allocation made while tracking, by address, with its tag and size
+static LINEAR: Mutex<BTreeMap<usize, (Tag, usize)>> = Mutex::new(BTreeMap::new());
+
+fn counters(memory: Memory, tag: Tag) -> &'static Counters {
+    &COUNTERS[memory as usize][tag.0 as usize]
+}
+
+// a new tag called `name`. panics if there are already MAX_TAGS
+pub fn register(name: &'static str) -> Tag {
+    let mut names = NAMES.lock().unwrap();
+    assert!(names.len() + 1 < MAX_TAGS, "there can only be {MAX_TAGS} tags");
+    names.push(name);
+    Tag(names.len() as u8)
+}
+
+pub fn name(tag: Tag) -> &'static str {
+    match tag.0 {
+        0 => "untagged",
+        i => NAMES.lock().unwrap()[i as usize - 1],
+    }
+}
+
+fn tags() -> impl Iterator<Item = Tag> {
+    (0..=NAMES.lock().unwrap().len() as u8).map(Tag)
+}
+
+// makes `tag` current until it's dropped, when the one before comes back
+#[must_use = "the tag stops being current as soon as it's dropped"]
+pub struct TagScope {
+    previous: usize,
+}
+
+impl Drop for TagScope {
+    fn drop(&mut self) {
+        CURRENT.store(self.previous, Ordering::Relaxed);
+    }
+}
+
+pub fn tagged(tag: Tag) -> TagScope {
+    TagScope { previous: CURRENT.swap(tag.0 as usize, Ordering::Relaxed) }
+}
+
+fn current() -> Tag {
+    Tag(CURRENT.load(Ordering::Relaxed) as u8)
+}
+
+// wraps another global allocator, keeping each allocation's tag in the byte before it
+pub struct TrackingAllocator<A>(pub A);
+
+// room for the tag in front of an allocation, keeping it aligned
+fn with_header(layout: Layout) -> Option<(Layout, usize)> {
+    let header = layout.align();
+    Some((Layout::from_size_align(layout.size().checked_add(header)?, layout.align()).ok()?, header))
+}
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for TrackingAllocator<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let Some((full, header)) = with_header(layout) else {
+            return std::ptr::null_mut();
+        };
+        let base = unsafe { self.0.alloc(full) };
+        if base.is_null() {
+            return base;
+        }
+
+        let tag = current();
+        counters(Memory::Heap, tag).allocated(layout.size());
+        unsafe {
+            let ptr = base.add(header);
+            ptr.sub(1).write(tag.0);
+            ptr
+        }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let (full, header) = with_header(layout).unwrap();
+        let tag = Tag(unsafe { ptr.sub(1).read() });
+        counters(Memory::Heap, tag).freed(layout.size());
+        unsafe { self.0.dealloc(ptr.sub(header), full); }
+    }
+}
+
+// starts counting linear memory. what was allocated before this isn't counted, even when it's freed
+pub fn enable_linear() {
+    LINEAR_ENABLED.store(true, Ordering::Relaxed);
+}
+
+// for LinearPool
+pub(crate) fn linear_allocated(addr: usize, size: usize) {
+    if LINEAR_ENABLED.load(Ordering::Relaxed) {
+        let tag = current();
+        counters(Memory::Linear, tag).allocated(size);
+        LINEAR.lock().unwrap().insert(addr, (tag, size));
+    }
+}
+
+pub(crate) fn linear_freed(addr: usize) {
+    if !LINEAR_ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    if let Some((tag, size)) = LINEAR.lock().unwrap().remove(&addr) {
+        counters(Memory::Linear, tag).freed(size);
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Usage {
+    pub allocations: usize, // ever made
+    pub live: usize, // not freed yet
+    pub live_bytes: usize,
+    pub peak_bytes: usize, // the most live_bytes has been
+}
+
+pub fn usage(tag: Tag, memory: Memory) -> Usage {
+    let counters = counters(memory, tag);
+    Usage {
+        allocations: counters.allocations.load(Ordering::Relaxed),
+        live: counters.live.load(Ordering::Relaxed),
+        live_bytes: counters.live_bytes.load(Ordering::Relaxed),
+        peak_bytes: counters.peak_bytes.load(Ordering::Relaxed),
+    }
+}
+
+// what was live at one moment, to compare against later with leaked_since
+#[derive(Clone, Debug)]
+pub struct Checkpoint {
+    live: Vec<(Tag, Memory, usize, usize)>, // live allocations and bytes
+}
+
+pub fn checkpoint() -> Checkpoint {
+    let live = tags()
+        .flat_map(|tag| Memory::ALL.map(|memory| (tag, memory)))
+        .map(|(tag, memory)| {
+            let usage = usage(tag, memory);
+            (tag, memory, usage.live, usage.live_bytes)
+        })
+        .collect();
+
+    Checkpoint { live }
+}
+
+// more of a tag's allocations are live than at the checkpoint
+#[derive(Copy, Clone, Debug)]
+pub struct Leak {
+    pub tag: Tag,
+    pub memory: Memory,
+    pub allocations: usize, // more than there were
+    pub bytes: isize, // can be negative, if fewer but bigger allocations were freed
+}
+
+impl fmt::Display for Leak {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} leaked {} {:?} allocations ({} bytes)", name(self.tag), self.allocations, self.memory, self.bytes)
+    }
+}
+
+// every tag with more allocations live now than at `checkpoint`. tags registered since count from
+// nothing
+pub fn leaked_since(checkpoint: &Checkpoint) -> Vec<Leak> {
+    tags()
+        .flat_map(|tag| Memory::ALL.map(|memory| (tag, memory)))
+        .filter_map(|(tag, memory)| {
+            let (live, bytes) = checkpoint.live.iter()
+                .find(|&&(t, m, _, _)| t == tag && m == memory)
+                .map_or((0, 0), |&(_, _, live, bytes)| (live, bytes));
+            let usage = usage(tag, memory);
+            (usage.live > live).then(|| Leak {
+                tag,
+                memory,
+                allocations: usage.live - live,
+                bytes: usage.live_bytes as isize - bytes as isize,
+            })
+        })
+        .collect()
+}
+
+// a line for each tag that's allocated anything, for the end of a session
+pub fn report() -> String {
+    use std::fmt::Write;
+
+    let mut ret = String::from("tag: heap live/ever (bytes, peak), linear live/ever (bytes, peak)\n");
+    for tag in tags() {
+        let [heap, linear] = Memory::ALL.map(|memory| usage(tag, memory));
+        if heap.allocations + linear.allocations == 0 {
+            continue;
+        }
+        writeln!(
+            ret, "{}: {}/{} ({}, {}), {}/{} ({}, {})",
+            name(tag),
+            heap.live, heap.allocations, heap.live_bytes, heap.peak_bytes,
+            linear.live, linear.allocations, linear.live_bytes, linear.peak_bytes,
+        ).unwrap();
+    }
+
+    ret
+}
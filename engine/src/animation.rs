@@ -0,0 +1,192 @@
+// plays the clips from a .anim file (which gltf_tool writes next to the .mesh for a glTF with
+// animations) and reports the events in them as playback passes them, so gameplay can line up
+// with the animation:
+//
+//     player.update(dt);
+//     while let Some(event) = player.poll() {
+//         if event.name == "footstep" {
+//             play_footstep();
+//         }
+//     }
+//
+// events are added in Blender (or anything else that writes glTF) as a custom property on the
+// action: `events = [{"time": 0.25, "name": "footstep"}, ...]`. a file with a skeleton in it poses
+// the skinned meshes it goes with, see skeleton
+use std::collections::VecDeque;
+use std::io;
+use std::io::Read;
+
+use glam::Mat4;
+
+pub use mm3ds_format::{Clip, ClipEvent};
+
+use crate::events::EventBus;
+use crate::mesh::Playback;
+use crate::profiler;
+use crate::profiler::System;
+use crate::skeleton::{Pose, Skeleton};
+
+// a clip that doesn't loop has got to its end. see AnimationPlayer::publish
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AnimationFinished {
+    pub clip: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnimationEvent {
+    pub clip: String,
+    pub name: String,
+    pub time: f32, // where in the clip it is
+}
+
+pub struct AnimationPlayer {
+    clips: Vec<Clip>,
+    skeleton: Option<Skeleton>,
+    current: Option<usize>,
+    pub playback: Playback,
+    events: VecDeque<AnimationEvent>,
+    finished: bool, // since the last publish
+}
+
+impl AnimationPlayer {
+    pub fn new(clips: Vec<Clip>) -> Self {
+        Self {
+            clips,
+            skeleton: None,
+            current: None,
+            playback: Playback::default(),
+            events: VecDeque::new(),
+            finished: false,
+        }
+    }
+
+    pub fn from_file_data(reader: impl Read) -> io::Result<Self> {
+        let anim = mm3ds_format::read_anim_file(reader)?;
+        let skeleton = anim.skeleton.as_ref().map(Skeleton::new);
+        Ok(Self { skeleton, ..Self::new(anim.clips) })
+    }
+
+    pub fn clips(&self) -> &[Clip] {
+        &self.clips
+    }
+
+    pub fn skeleton(&self) -> Option<&Skeleton> {
+        self.skeleton.as_ref()
+    }
+
+    // the skeleton as the current clip has it now, or at rest if nothing's playing. None without one
+    pub fn pose(&self) -> Option<Pose> {
+        let skeleton = self.skeleton.as_ref()?;
+        Some(match self.clip() {
+            Some(clip) => skeleton.sample(clip, self.time()),
+            None => skeleton.rest_pose(),
+        })
+    }
+
+    // for Renderer::set_bones, see pose. nothing without a skeleton
+    pub fn bones(&self) -> Vec<Mat4> {
+        match (&self.skeleton, self.pose()) {
+            (Some(skeleton), Some(pose)) => skeleton.bones(&pose),
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn clip(&self) -> Option<&Clip> {
+        self.current.map(|i| &self.clips[i])
+    }
+
+    // starts `name` from the beginning. returns false (and keeps playing whatever it was) if there's
+    // no such clip
+    pub fn play(&mut self, name: &str) -> bool {
+        let Some(i) = self.clips.iter().position(|clip| clip.name == name) else { return false };
+        self.current = Some(i);
+        self.playback.time = 0.;
+        self.playback.playing = true;
+        self.finished = false;
+
+        true
+    }
+
+    pub fn stop(&mut self) {
+        self.current = None;
+    }
+
+    // time into the current clip, wrapped around if it's looping
+    pub fn time(&self) -> f32 {
+        match self.clip() {
+            Some(clip) if self.playback.looping && clip.duration > 0. => self.playback.time % clip.duration,
+            Some(clip) => self.playback.time.min(clip.duration),
+            None => 0.,
+        }
+    }
+
+    // advances by `dt` seconds (times the playback speed), queueing every event that's passed.
+    // events only fire going forwards
+    pub fn update(&mut self, dt: f32) {
+        let _animation = profiler::scope(System::Animation);
+        let Some(i) = self.current else { return };
+        if !self.playback.playing {
+            return;
+        }
+
+        let start = self.playback.time;
+        let end = start + dt * self.playback.speed;
+        self.playback.time = end;
+        if end <= start {
+            return;
+        }
+
+        let duration = self.clips[i].duration;
+        if self.playback.looping && duration > 0. {
+            // a long enough step can go around more than once
+            let mut from = start;
+            while from < end {
+                let loop_start = (from / duration).floor() * duration;
+                self.fire(i, from - loop_start, (end - loop_start).min(duration), false);
+                from = loop_start + duration;
+            }
+        } else {
+            self.fire(i, start.min(duration), end.min(duration), true);
+            self.finished |= start < duration && end >= duration;
+        }
+    }
+
+    // queues the events in [from, to), plus any right at the end if the clip is `ending` there
+    fn fire(&mut self, i: usize, from: f32, to: f32, ending: bool) {
+        if from >= to {
+            return;
+        }
+
+        let clip = &self.clips[i];
+        let at_end = ending && to >= clip.duration;
+        for event in &clip.events {
+            if event.time >= from && (event.time < to || at_end) {
+                self.events.push_back(AnimationEvent {
+                    clip: clip.name.clone(),
+                    name: event.name.clone(),
+                    time: event.time,
+                });
+            }
+        }
+    }
+
+    pub fn poll(&mut self) -> Option<AnimationEvent> {
+        self.events.pop_front()
+    }
+
+    // whether the current clip has played to its end and stopped there. looping ones never do
+    pub fn is_finished(&self) -> bool {
+        self.clip().is_some_and(|clip| !self.playback.looping && self.playback.time >= clip.duration)
+    }
+
+    // hands every queued event to the bus instead, for whoever's listening, and an
+    // AnimationFinished if the clip got to its end since last time
+    pub fn publish(&mut self, events: &mut EventBus) {
+        events.publish_all(self.events.drain(..));
+        if std::mem::take(&mut self.finished) {
+            if let Some(clip) = self.clip() {
+                events.publish(AnimationFinished { clip: clip.name.clone() });
+            }
+        }
+    }
+}
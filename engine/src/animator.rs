@@ -0,0 +1,363 @@
+// an animation state machine on top of an AnimationPlayer, described in TOML so a character's
+// locomotion is data instead of a pile of ifs. the game sets parameters, and the machine picks
+// the clip:
+//
+//     initial = "idle"
+//
+//     [parameters]
+//     speed = 0.0
+//     grounded = true
+//
+//     [states.idle]
+//     clip = "Idle"
+//
+//     [[states.idle.transitions]]
+//     to = "walk"
+//     when = ["speed > 0.1", "grounded"]
+//     blend = 0.2                  # seconds to crossfade over
+//
+//     [states.land]
+//     clip = "Land"
+//     looping = false
+//
+//     [[states.land.transitions]]
+//     to = "idle"
+//     when = ["finished"]          # the clip has played to the end (once, if it loops)
+//
+// a condition is a flag (`grounded`, `!grounded`), a comparison of a number with a constant
+// (`speed >= 2`), or `finished`. a transition is taken when all of its conditions hold, and the
+// first one in the list wins
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+
+use glam::Mat4;
+use serde::Deserialize;
+
+use crate::animation::{AnimationEvent, AnimationPlayer};
+use crate::skeleton::Pose;
+
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize)]
+#[serde(untagged)]
+enum Value {
+    Flag(bool),
+    Number(f32),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MachineDef {
+    initial: String,
+    #[serde(default)]
+    parameters: HashMap<String, Value>,
+    states: HashMap<String, StateDef>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StateDef {
+    clip: String,
+    #[serde(default = "default_true")]
+    looping: bool,
+    #[serde(default = "default_speed")]
+    speed: f32,
+    #[serde(default)]
+    transitions: Vec<TransitionDef>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_speed() -> f32 {
+    1.
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TransitionDef {
+    to: String,
+    #[serde(default)]
+    when: Vec<String>,
+    #[serde(default)]
+    blend: f32,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Op {
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Equal,
+    NotEqual,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Condition {
+    Flag(String, bool), // the flag has to be this
+    Compare(String, Op, f32),
+    Finished,
+}
+
+impl Condition {
+    fn parse(text: &str) -> Result<Self, String> {
+        let words: Vec<_> = text.split_whitespace().collect();
+        match words[..] {
+            ["finished"] => Ok(Condition::Finished),
+            [name] => match name.strip_prefix('!') {
+                Some(name) => Ok(Condition::Flag(name.to_owned(), false)),
+                None => Ok(Condition::Flag(name.to_owned(), true)),
+            },
+            [name, op, value] => {
+                let op = match op {
+                    "<" => Op::Less,
+                    "<=" => Op::LessEqual,
+                    ">" => Op::Greater,
+                    ">=" => Op::GreaterEqual,
+                    "==" => Op::Equal,
+                    "!=" => Op::NotEqual,
+                    _ => return Err(format!("unknown comparison {op:?} in {text:?}")),
+                };
+                let value = value.parse().map_err(|_| format!("{value:?} isn't a number, in {text:?}"))?;
+
+                Ok(Condition::Compare(name.to_owned(), op, value))
+            }
+            _ => Err(format!("can't make sense of condition {text:?}")),
+        }
+    }
+
+    // the parameter it reads, and whether that has to be a flag
+    fn parameter(&self) -> Option<(&str, bool)> {
+        match self {
+            Condition::Flag(name, _) => Some((name, true)),
+            Condition::Compare(name, _, _) => Some((name, false)),
+            Condition::Finished => None,
+        }
+    }
+}
+
+struct Transition {
+    to: usize,
+    when: Vec<Condition>,
+    blend: f32,
+}
+
+struct State {
+    name: String,
+    clip: String,
+    looping: bool,
+    speed: f32,
+    transitions: Vec<Transition>,
+}
+
+// the state being faded out of
+#[derive(Clone, Debug)]
+pub struct Blend {
+    pub clip: String,
+    pub time: f32, // where the old clip is, it keeps playing while it fades
+    pub weight: f32, // of the new state, going from 0 to 1
+}
+
+struct Fade {
+    clip: String,
+    looping: bool,
+    duration: f32, // of the clip
+    speed: f32,
+    time: f32,
+    elapsed: f32,
+    length: f32, // of the fade
+}
+
+pub struct Animator {
+    player: AnimationPlayer,
+    states: Vec<State>,
+    current: usize,
+    parameters: HashMap<String, Value>,
+    fade: Option<Fade>,
+}
+
+impl Animator {
+    // checks that every state, clip and parameter the machine mentions exists
+    pub fn from_toml(text: &str, player: AnimationPlayer) -> Result<Self, Box<dyn Error>> {
+        let def: MachineDef = toml::from_str(text)?;
+
+        // sorted, so state indices don't depend on HashMap order
+        let mut names: Vec<&String> = def.states.keys().collect();
+        names.sort();
+        let index = |name: &str| names.iter().position(|n| *n == name);
+
+        let mut states = Vec::with_capacity(names.len());
+        for &name in &names {
+            let state = &def.states[name];
+            if !player.clips().iter().any(|clip| clip.name == state.clip) {
+                return Err(format!("states.{name}: there's no clip called {:?}", state.clip).into());
+            }
+
+            let mut transitions = Vec::with_capacity(state.transitions.len());
+            for transition in &state.transitions {
+                let to = index(&transition.to).ok_or_else(|| format!("states.{name}: there's no state called {:?}", transition.to))?;
+                if !(transition.blend.is_finite() && transition.blend >= 0.) {
+                    return Err(format!("states.{name}: blend must be a number of seconds, not {}", transition.blend).into());
+                }
+
+                let when = transition.when.iter()
+                    .map(|text| Condition::parse(text))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|e| format!("states.{name}: {e}"))?;
+                for (parameter, flag) in when.iter().filter_map(Condition::parameter) {
+                    match def.parameters.get(parameter) {
+                        None => return Err(format!("states.{name}: there's no parameter called {parameter:?}").into()),
+                        Some(Value::Flag(_)) if !flag => return Err(format!("states.{name}: {parameter} is a flag, not a number").into()),
+                        Some(Value::Number(_)) if flag => return Err(format!("states.{name}: {parameter} is a number, not a flag").into()),
+                        _ => {}
+                    }
+                }
+
+                transitions.push(Transition { to, when, blend: transition.blend });
+            }
+
+            states.push(State {
+                name: name.clone(),
+                clip: state.clip.clone(),
+                looping: state.looping,
+                speed: state.speed,
+                transitions,
+            });
+        }
+
+        let current = index(&def.initial).ok_or_else(|| format!("initial: there's no state called {:?}", def.initial))?;
+        let mut ret = Self {
+            player,
+            states,
+            current,
+            parameters: def.parameters,
+            fade: None,
+        };
+        ret.enter(current);
+
+        Ok(ret)
+    }
+
+    pub fn load(path: &str, player: AnimationPlayer) -> Result<Self, Box<dyn Error>> {
+        Ok(Self::from_toml(&fs::read_to_string(path)?, player).map_err(|e| format!("{path}: {e}"))?)
+    }
+
+    fn enter(&mut self, state: usize) {
+        let state_def = &self.states[state];
+        self.player.play(&state_def.clip);
+        self.player.playback.looping = state_def.looping;
+        self.player.playback.speed = state_def.speed;
+        self.current = state;
+    }
+
+    // panics if there's no such parameter, or it's a flag
+    pub fn set_number(&mut self, name: &str, value: f32) {
+        match self.parameters.get_mut(name) {
+            Some(Value::Number(number)) => *number = value,
+            _ => panic!("no number parameter called {name:?}"),
+        }
+    }
+
+    // panics if there's no such parameter, or it's a number
+    pub fn set_flag(&mut self, name: &str, value: bool) {
+        match self.parameters.get_mut(name) {
+            Some(Value::Flag(flag)) => *flag = value,
+            _ => panic!("no flag parameter called {name:?}"),
+        }
+    }
+
+    fn holds(&self, condition: &Condition) -> bool {
+        match condition {
+            Condition::Finished => self.player.clip().is_some_and(|clip| self.player.playback.time >= clip.duration),
+            Condition::Flag(name, want) => self.parameters[name] == Value::Flag(*want),
+            Condition::Compare(name, op, value) => {
+                let Value::Number(number) = self.parameters[name] else { return false };
+                match op {
+                    Op::Less => number < *value,
+                    Op::LessEqual => number <= *value,
+                    Op::Greater => number > *value,
+                    Op::GreaterEqual => number >= *value,
+                    Op::Equal => number == *value,
+                    Op::NotEqual => number != *value,
+                }
+            }
+        }
+    }
+
+    // takes at most one transition, then advances the animation by `dt` seconds
+    pub fn update(&mut self, dt: f32) {
+        let taken = self.states[self.current].transitions.iter()
+            .find(|transition| transition.when.iter().all(|condition| self.holds(condition)))
+            .map(|transition| (transition.to, transition.blend));
+
+        if let Some((to, blend)) = taken {
+            self.fade = (blend > 0.).then(|| Fade {
+                clip: self.states[self.current].clip.clone(),
+                looping: self.player.playback.looping,
+                duration: self.player.clip().map_or(0., |clip| clip.duration),
+                speed: self.player.playback.speed,
+                time: self.player.time(),
+                elapsed: 0.,
+                length: blend,
+            });
+            self.enter(to);
+        }
+
+        if let Some(fade) = &mut self.fade {
+            fade.elapsed += dt;
+            fade.time += dt * fade.speed;
+            if fade.looping && fade.duration > 0. {
+                fade.time %= fade.duration;
+            } else {
+                fade.time = fade.time.min(fade.duration);
+            }
+
+            if fade.elapsed >= fade.length {
+                self.fade = None;
+            }
+        }
+
+        self.player.update(dt);
+    }
+
+    pub fn state(&self) -> &str {
+        &self.states[self.current].name
+    }
+
+    // the state being faded out of, if the last transition had a blend that isn't over yet
+    pub fn blend(&self) -> Option<Blend> {
+        self.fade.as_ref().map(|fade| Blend {
+            clip: fade.clip.clone(),
+            time: fade.time,
+            weight: fade.elapsed / fade.length,
+        })
+    }
+
+    pub fn player(&self) -> &AnimationPlayer {
+        &self.player
+    }
+
+    // the player's pose (see AnimationPlayer::pose), crossfaded from the state being left while
+    // there's a blend
+    pub fn pose(&self) -> Option<Pose> {
+        let pose = self.player.pose()?;
+        let Some(blend) = self.blend() else { return Some(pose) };
+        let Some(clip) = self.player.clips().iter().find(|clip| clip.name == blend.clip) else { return Some(pose) };
+
+        Some(self.player.skeleton()?.sample(clip, blend.time).blend(&pose, blend.weight))
+    }
+
+    // for Renderer::set_bones, see pose. nothing without a skeleton
+    pub fn bones(&self) -> Vec<Mat4> {
+        match (self.player.skeleton(), self.pose()) {
+            (Some(skeleton), Some(pose)) => skeleton.bones(&pose),
+            _ => Vec::new(),
+        }
+    }
+
+    // events from the current state's clip, see AnimationPlayer::poll
+    pub fn poll(&mut self) -> Option<AnimationEvent> {
+        self.player.poll()
+    }
+}
@@ -0,0 +1,121 @@
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use crate::animation::AnimationPlayer;
+use crate::mesh::Mesh;
+use crate::model::Model;
+use crate::renderer::Renderer;
+use crate::texture_cache::{TextureCache, TextureId};
+
+// where build.rs puts what it converts, which is the start of an Asset's path
+pub const GFX_DIR: &str = "romfs:/gfx/";
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AssetKind {
+    Texture,
+    Cubemap, // a t3s with --cubemap, see cubemap::Cubemap
+    Mesh,
+    Animation, // the clips of a glTF, see animation::AnimationPlayer
+}
+
+// build.rs generates one of these for every file packed into romfs:/gfx/, named after its path
+// (gfx/ui/button.t3s => assets::UI_BUTTON_T3X). if the build packed them (MM3DS_PACK_ASSETS), read
+// them with pack::Pack::read_asset instead of Asset::read. pull them into your crate with
+//
+//     mod assets {
+//         use mm3ds_engine::assets::{Asset, AssetKind};
+//         include!(concat!(env!("OUT_DIR"), "/assets.rs"));
+//     }
+#[derive(Copy, Clone, Debug)]
+pub struct Asset {
+    pub path: &'static str,
+    pub kind: AssetKind,
+    pub size: u64,
+}
+
+impl Asset {
+    pub fn read(&self) -> io::Result<Vec<u8>> {
+        fs::read(self.path)
+    }
+
+    pub fn open(&self) -> io::Result<BufReader<File>> {
+        Ok(BufReader::new(File::open(self.path)?))
+    }
+}
+
+// loads assets by name, for names that are only known at runtime (a level file saying which meshes
+// are in it, say) rather than from build.rs's constants:
+//
+//     let _romfs = RomFS::new()?;
+//     let assets = Assets::romfs();
+//     let character = assets.load_mesh("character.mesh", renderer.texture_cache())?;
+//     let lemon = assets.load_texture("lemon.t3x", renderer.texture_cache())?;
+//     let player = assets.load_animation("character.anim")?;
+//
+// meshes and animations are parsed as they're read, instead of being read into memory whole first.
+// the RomFS has to stay mounted for as long as anything's loaded from it
+#[derive(Clone, Debug)]
+pub struct Assets {
+    dir: PathBuf,
+}
+
+// says which file went wrong. running out of memory is left as it is, so the OutOfMemory inside
+// can still be had
+fn in_file(path: &Path, e: io::Error) -> io::Error {
+    if e.kind() == io::ErrorKind::OutOfMemory {
+        return e;
+    }
+    io::Error::new(e.kind(), format!("{}: {e}", path.display()))
+}
+
+impl Assets {
+    // what build.rs converted, in GFX_DIR
+    pub fn romfs() -> Self {
+        Self::in_dir(GFX_DIR)
+    }
+
+    // anything else, e.g. a mod's directory on the SD card
+    pub fn in_dir(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(name)
+    }
+
+    pub fn exists(&self, name: &str) -> bool {
+        self.path(name).is_file()
+    }
+
+    pub fn open(&self, name: &str) -> io::Result<BufReader<File>> {
+        let path = self.path(name);
+        Ok(BufReader::new(File::open(&path).map_err(|e| in_file(&path, e))?))
+    }
+
+    pub fn read(&self, name: &str) -> io::Result<Vec<u8>> {
+        let path = self.path(name);
+        fs::read(&path).map_err(|e| in_file(&path, e))
+    }
+
+    // the meshes of a MESH file, with their textures shared through `textures`
+    pub fn load_mesh(&self, name: &str, textures: &mut TextureCache) -> io::Result<Vec<Mesh>> {
+        Mesh::from_file_data_in(self.open(name)?, textures).map_err(|e| in_file(&self.path(name), e))
+    }
+
+    // a MESH file as a model, with its nodes, see Model::from_file_data
+    pub fn load_model(&self, name: &str, renderer: &mut Renderer) -> io::Result<Model> {
+        Model::from_file_data(renderer, self.open(name)?).map_err(|e| in_file(&self.path(name), e))
+    }
+
+    // a t3x, which citro3d can only import from memory, so it's read whole
+    pub fn load_texture(&self, name: &str, textures: &mut TextureCache) -> io::Result<TextureId> {
+        textures.load(&self.read(name)?).map_err(|e| in_file(&self.path(name), e))
+    }
+
+    pub fn load_animation(&self, name: &str) -> io::Result<AnimationPlayer> {
+        AnimationPlayer::from_file_data(self.open(name)?).map_err(|e| in_file(&self.path(name), e))
+    }
+}
@@ -0,0 +1,55 @@
+// the screens' backlight, through the gsp::Lcd service. dark scenes can be unreadable on some
+// screens at the system brightness, so a game can turn it up while it runs. the brightness the
+// system had is put back when the Backlight is dropped. see also Renderer::set_brightness, which
+// brightens the picture itself
+fn check(res: ctru_sys::Result) -> ctru::Result<()> {
+    if ctru_sys::R_FAILED(res) {
+        Err(res.into())
+    } else {
+        Ok(())
+    }
+}
+
+// the levels the HOME menu offers
+pub const MIN_LEVEL: u8 = 1;
+pub const MAX_LEVEL: u8 = 5;
+
+pub struct Backlight {
+    // raw brightness of the top and bottom screens before we changed them
+    original: [u32; 2],
+}
+
+impl Backlight {
+    // sets both screens to `level`, from MIN_LEVEL to MAX_LEVEL
+    pub fn new(level: u8) -> ctru::Result<Self> {
+        check(unsafe { ctru_sys::gspLcdInit() })?;
+
+        let mut original = [0; 2];
+        for (screen, brightness) in [ctru_sys::GSPLCD_SCREEN_TOP, ctru_sys::GSPLCD_SCREEN_BOTTOM].into_iter().zip(&mut original) {
+            if let Err(e) = check(unsafe { ctru_sys::GSPLCD_GetBrightness(screen, brightness) }) {
+                unsafe { ctru_sys::gspLcdExit(); }
+                return Err(e);
+            }
+        }
+
+        let ret = Self { original };
+        ret.set_level(level)?;
+
+        Ok(ret)
+    }
+
+    pub fn set_level(&self, level: u8) -> ctru::Result<()> {
+        let level = level.clamp(MIN_LEVEL, MAX_LEVEL) as u32;
+        check(unsafe { ctru_sys::GSPLCD_SetBrightness(ctru_sys::GSPLCD_SCREEN_BOTH, level) })
+    }
+}
+
+impl Drop for Backlight {
+    fn drop(&mut self) {
+        unsafe {
+            ctru_sys::GSPLCD_SetBrightnessRaw(ctru_sys::GSPLCD_SCREEN_TOP, self.original[0]);
+            ctru_sys::GSPLCD_SetBrightnessRaw(ctru_sys::GSPLCD_SCREEN_BOTTOM, self.original[1]);
+            ctru_sys::gspLcdExit();
+        }
+    }
+}
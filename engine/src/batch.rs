@@ -0,0 +1,125 @@
+// static batching: merges meshes that never move into as few meshes as possible, so a level made
+// of hundreds of props costs a handful of draw calls instead of hundreds. meshes are merged when
+// they'd be drawn the same way (same material, texture, shader and so on), with their transforms
+// baked into the vertices. see Renderer::batch_static
+use std::io;
+use std::rc::Rc;
+
+use citro3d::math::Matrix4;
+use glam::{Mat3, Vec3};
+
+use crate::material::{AlphaTest, BlendMode, Material};
+use crate::math::from_matrix4;
+use crate::mesh::{Mesh, OwnedTexture, Vertex};
+
+// indices are u16, so a batch is split once it'd have more vertices than that
+const MAX_VERTICES: usize = u16::MAX as usize + 1;
+
+// a mesh's state that can't vary within a single draw
+#[derive(PartialEq)]
+struct Key {
+    material: [u32; 16],
+    alpha_test: Option<AlphaTest>,
+    double_sided: bool,
+    blend: BlendMode,
+    texture: Option<*mut std::ffi::c_void>,
+    emission_map: Option<*mut std::ffi::c_void>,
+    reflectivity: u32,
+    shader: Option<String>,
+    params: Vec<(String, [u32; 4])>,
+    flipbook: Option<mm3ds_format::Flipbook>,
+}
+
+fn material_bits(material: &Material) -> [u32; 16] {
+    let mut ret = [0; 16];
+    for (i, v) in [material.ambient, material.diffuse, material.specular, material.emission].iter().enumerate() {
+        ret[i * 4..][..4].copy_from_slice(&[v.x().to_bits(), v.y().to_bits(), v.z().to_bits(), v.w().to_bits()]);
+    }
+
+    ret
+}
+
+fn key(mesh: &Mesh) -> Key {
+    Key {
+        material: material_bits(&mesh.material),
+        alpha_test: mesh.material.alpha_test,
+        double_sided: mesh.material.double_sided,
+        blend: mesh.material.blend,
+        texture: mesh.texture.as_ref().map(|texture| unsafe { texture.__bindgen_anon_1.data }),
+        emission_map: mesh.emission_map.as_ref().map(|texture| unsafe { texture.__bindgen_anon_1.data }),
+        reflectivity: mesh.reflectivity.to_bits(),
+        shader: mesh.shader.clone(),
+        params: mesh.params.iter().map(|(name, v)| (name.clone(), v.to_array().map(f32::to_bits))).collect(),
+        flipbook: mesh.flipbook,
+    }
+}
+
+struct Batch<'a> {
+    key: Key,
+    like: &'a Mesh, // where the batch's material etc. come from
+    vertices: Vec<Vertex>,
+    indices: Vec<u16>,
+    textures: Vec<Rc<OwnedTexture>>, // what the originals own, kept alive for as long as the batch is
+}
+
+impl Batch<'_> {
+    fn add(&mut self, mesh: &Mesh, transform: Matrix4) {
+        for texture in &mesh.owned_textures {
+            if !self.textures.iter().any(|shared| Rc::ptr_eq(shared, texture)) {
+                self.textures.push(texture.clone());
+            }
+        }
+
+        let transform = from_matrix4(transform);
+        let normal_transform = Mat3::from_mat4(transform).inverse().transpose();
+
+        let base = self.vertices.len() as u16;
+        self.vertices.extend(mesh.vertices().iter().map(|vertex| Vertex {
+            pos: transform.transform_point3(Vec3::from(vertex.pos)).into(),
+            uv: vertex.uv,
+            normal: (normal_transform * Vec3::from(vertex.normal)).normalize_or_zero().into(),
+        }));
+
+        match &mesh.index_data {
+            Some(indices) => self.indices.extend(indices.iter().map(|i| base + i)),
+            None => self.indices.extend((0..mesh.vertices().len() as u16).map(|i| base + i)),
+        }
+    }
+}
+
+// merges every `(mesh, transform)` into new meshes, in the order they first appear. skinned meshes
+// are left out, since baking a transform into them would undo their bones, and so are lightmapped
+// ones, since each has its own lightmap, and ones with streams, which can change. so are ones with
+// sub-meshes, which are already as few draws as their materials allow
+pub(crate) fn merge(parts: &[(&Mesh, Matrix4)]) -> io::Result<Vec<Mesh>> {
+    let mergeable = |mesh: &Mesh| !mesh.is_skinned() && !mesh.is_lightmapped() && mesh.streams() == 0 && mesh.sub_meshes() == 0;
+    let mut batches: Vec<Batch> = Vec::new();
+    for &(mesh, transform) in parts.iter().filter(|(mesh, _)| mergeable(mesh)) {
+        let key = key(mesh);
+        let fits = |batch: &&mut Batch| batch.key == key && batch.vertices.len() + mesh.vertices().len() <= MAX_VERTICES;
+        let batch = match batches.iter_mut().find(fits) {
+            Some(batch) => batch,
+            None => {
+                batches.push(Batch { key, like: mesh, vertices: Vec::new(), indices: Vec::new(), textures: Vec::new() });
+                batches.last_mut().unwrap()
+            }
+        };
+
+        batch.add(mesh, transform);
+    }
+
+    batches.into_iter().map(|batch| {
+        let like = batch.like;
+        let mut mesh = Mesh::try_from_data(&batch.vertices, Some(&batch.indices), None, like.material)?;
+        mesh.texture = like.texture; // shared with the originals, so unregistering them doesn't free it
+        mesh.owned_textures = batch.textures;
+        mesh.emission_map = like.emission_map;
+        mesh.reflectivity = like.reflectivity;
+        mesh.shader = like.shader.clone();
+        mesh.params = like.params.clone();
+        mesh.param_uniforms = like.param_uniforms.clone();
+        mesh.flipbook = like.flipbook;
+
+        Ok(mesh)
+    }).collect()
+}
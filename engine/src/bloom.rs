@@ -0,0 +1,51 @@
+// bloom: whatever's brighter than a threshold glows onto what's around it, for lamps, lava and
+// magic that should look bright rather than just white:
+//
+//     renderer.set_bloom(Some(Bloom { threshold: 0.7, intensity: 0.8 }));
+//
+// the scene is drawn again into a small render texture with the threshold taken off every color,
+// so only the bright parts are left. that's halved twice more, each halving blurring it a bit
+// (it's filtered linearly), and all three are stretched back over the picture and added on. it
+// costs the scene's draw calls a second time, into far fewer pixels. passes get it with
+// PostStep::Bloom, which RenderPass::new has
+use crate::mesh::Vertex;
+use crate::render_texture::{RenderTexture, screen_uv};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Bloom {
+    pub threshold: f32, // from 0 to 1, how bright a color has to be to glow
+    pub intensity: f32, // how much of the glow's added on
+}
+
+impl Default for Bloom {
+    fn default() -> Self {
+        Self { threshold: 0.7, intensity: 0.6 }
+    }
+}
+
+pub(crate) const LEVELS: usize = 3;
+// of the first level. the top screen's framebuffer is 240x400, in the layout render textures share
+const SIZE: (u16, u16) = (64, 128);
+
+pub(crate) type Levels = [RenderTexture; LEVELS];
+
+// None if there isn't the VRAM for them
+pub(crate) fn levels() -> Option<Levels> {
+    let [first, second, third] = [0, 1, 2].map(|i| RenderTexture::new(SIZE.0 >> i, SIZE.1 >> i, i == 0));
+    Some([first?, second?, third?])
+}
+
+const fn vertex(x: f32, y: f32) -> Vertex {
+    Vertex { pos: [x, y, -0.5], uv: screen_uv(x, y), normal: [0., 0., 1.] }
+}
+
+// covers the screen, showing a render texture the right way up
+pub(crate) const QUAD: [Vertex; 6] = [
+    vertex(0., 0.),
+    vertex(1., 0.),
+    vertex(1., 1.),
+
+    vertex(1., 1.),
+    vertex(0., 1.),
+    vertex(0., 0.),
+];
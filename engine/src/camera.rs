@@ -0,0 +1,76 @@
+// where the scene is seen from. give one to Renderer::set_camera, or let one of the controllers
+// (orbit_camera, ...) move it around
+use glam::{Mat4, Quat, Vec3};
+
+// -z is forward and +y is up, like the view space the renderer draws in
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Camera {
+    pub position: Vec3,
+    pub rotation: Quat,
+}
+
+impl Default for Camera {
+    // at the origin, looking down -z. the same as not having a camera
+    fn default() -> Self {
+        Self { position: Vec3::ZERO, rotation: Quat::IDENTITY }
+    }
+}
+
+impl Camera {
+    pub fn looking_at(position: Vec3, target: Vec3) -> Self {
+        let mut ret = Self { position, ..Default::default() };
+        ret.look_at(target);
+        ret
+    }
+
+    // turns to face `target`, keeping +y up. does nothing if it's right where the camera is
+    pub fn look_at(&mut self, target: Vec3) {
+        let forward = (target - self.position).normalize_or_zero();
+        if forward == Vec3::ZERO {
+            return;
+        }
+
+        // straight up or down has no yaw to keep, so any will do
+        let up = if forward.cross(Vec3::Y).length_squared() < 1e-6 { Vec3::Z } else { Vec3::Y };
+        self.rotation = Quat::from_mat4(&Mat4::look_to_rh(Vec3::ZERO, forward, up).inverse());
+    }
+
+    pub fn forward(&self) -> Vec3 {
+        self.rotation * Vec3::NEG_Z
+    }
+
+    pub fn right(&self) -> Vec3 {
+        self.rotation * Vec3::X
+    }
+
+    pub fn up(&self) -> Vec3 {
+        self.rotation * Vec3::Y
+    }
+
+    // world space to view space
+    pub fn view(&self) -> Mat4 {
+        Mat4::from_rotation_translation(self.rotation, self.position).inverse()
+    }
+}
+
+// how far a ray from `origin` along `dir` (normalized) gets before hitting something solid, if it
+// does within `max`. cameras use this to stay out of walls; implement it with whatever collision
+// the game has, or pass a closure
+pub trait Raycast {
+    fn raycast(&self, origin: Vec3, dir: Vec3, max: f32) -> Option<f32>;
+}
+
+impl<F: Fn(Vec3, Vec3, f32) -> Option<f32>> Raycast for F {
+    fn raycast(&self, origin: Vec3, dir: Vec3, max: f32) -> Option<f32> {
+        self(origin, dir, max)
+    }
+}
+
+// for a scene without collision
+pub struct NoCollision;
+
+impl Raycast for NoCollision {
+    fn raycast(&self, _origin: Vec3, _dir: Vec3, _max: f32) -> Option<f32> {
+        None
+    }
+}
@@ -0,0 +1,186 @@
+// live video from one of the cameras, copied into a texture as frames arrive. draw it behind the
+// scene with please_render once a frame for AR, or sample texture() yourself
+use citro3d::sys;
+use glam::Vec2;
+
+use crate::renderer::Renderer;
+
+// the camera's output is the size of the top screen
+const WIDTH: usize = 400;
+const HEIGHT: usize = 240;
+
+// ...but textures have to be a power of two
+const TEX_WIDTH: usize = 512;
+const TEX_HEIGHT: usize = 256;
+
+fn check(res: ctru_sys::Result) -> ctru::Result<()> {
+    if ctru_sys::R_FAILED(res) {
+        Err(res.into())
+    } else {
+        Ok(())
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Which {
+    Inner, // facing the player
+    Outer, // facing away, the right one of the pair
+}
+
+impl Which {
+    fn select(self) -> u32 {
+        match self {
+            Which::Inner => ctru_sys::SELECT_IN1,
+            Which::Outer => ctru_sys::SELECT_OUT1,
+        }
+    }
+}
+
+// bit-interleaves x and y (0..8) into an offset within an 8x8 tile, which is how the GPU wants
+// texels laid out
+pub(crate) fn morton(x: usize, y: usize) -> usize {
+    (x & 1) | (y & 1) << 1 | (x & 2) << 1 | (y & 2) << 2 | (x & 4) << 2 | (y & 4) << 3
+}
+
+pub struct CameraFeed {
+    which: Which,
+    event: ctru_sys::Handle,
+    transfer_unit: u32,
+    // RGB565, in reading order. the camera writes into `receiving` while we read `latest`, and they
+    // swap whenever a frame arrives
+    receiving: Vec<u16>,
+    latest: Vec<u16>,
+    texture: sys::C3D_Tex,
+}
+
+impl CameraFeed {
+    pub fn new(which: Which) -> ctru::Result<Self> {
+        check(unsafe { ctru_sys::camInit() })?;
+
+        let mut texture = unsafe { std::mem::zeroed() };
+        if !unsafe { sys::C3D_TexInit(&mut texture, TEX_WIDTH as u16, TEX_HEIGHT as u16, ctru_sys::GPU_RGB565) } {
+            unsafe { ctru_sys::camExit(); }
+            return Err(ctru::Error::Other("couldn't allocate the camera texture".to_owned()));
+        }
+        unsafe { sys::C3D_TexSetFilter(&mut texture, ctru_sys::GPU_LINEAR, ctru_sys::GPU_LINEAR); }
+
+        let mut ret = Self {
+            which,
+            event: 0,
+            transfer_unit: 0,
+            receiving: vec![0; WIDTH * HEIGHT],
+            latest: vec![0; WIDTH * HEIGHT],
+            texture,
+        };
+        ret.start()?;
+
+        Ok(ret)
+    }
+
+    fn start(&mut self) -> ctru::Result<()> {
+        let select = self.which.select();
+        unsafe {
+            check(ctru_sys::CAMU_SetSize(select, ctru_sys::SIZE_CTR_TOP_LCD, ctru_sys::CONTEXT_A))?;
+            check(ctru_sys::CAMU_SetOutputFormat(select, ctru_sys::OUTPUT_RGB_565, ctru_sys::CONTEXT_A))?;
+            check(ctru_sys::CAMU_SetNoiseFilter(select, true))?;
+            check(ctru_sys::CAMU_SetAutoExposure(select, true))?;
+            check(ctru_sys::CAMU_SetAutoWhiteBalance(select, true))?;
+            if self.which == Which::Inner {
+                // so it works like a mirror
+                check(ctru_sys::CAMU_FlipImage(select, ctru_sys::FLIP_HORIZONTAL, ctru_sys::CONTEXT_A))?;
+            }
+            check(ctru_sys::CAMU_SetTrimming(ctru_sys::PORT_CAM1, false))?;
+
+            check(ctru_sys::CAMU_GetMaxBytes(&mut self.transfer_unit, WIDTH as i16, HEIGHT as i16))?;
+            check(ctru_sys::CAMU_SetTransferBytes(ctru_sys::PORT_CAM1, self.transfer_unit, WIDTH as i16, HEIGHT as i16))?;
+
+            check(ctru_sys::CAMU_Activate(select))?;
+            check(ctru_sys::CAMU_ClearBuffer(ctru_sys::PORT_CAM1))?;
+            check(ctru_sys::CAMU_StartCapture(ctru_sys::PORT_CAM1))?;
+        }
+
+        self.receive()
+    }
+
+    // asks for the next frame to be written into self.receiving, signalling self.event when it's done
+    fn receive(&mut self) -> ctru::Result<()> {
+        if self.event != 0 {
+            unsafe { ctru_sys::svcCloseHandle(self.event); }
+            self.event = 0;
+        }
+
+        check(unsafe {
+            ctru_sys::CAMU_SetReceiving(
+                &mut self.event,
+                self.receiving.as_mut_ptr().cast(),
+                ctru_sys::PORT_CAM1,
+                (self.receiving.len() * 2) as u32,
+                self.transfer_unit as i16,
+            )
+        })
+    }
+
+    // copies the latest frame into the texture if one has arrived, without waiting. returns whether
+    // the texture changed
+    pub fn update(&mut self) -> bool {
+        if unsafe { ctru_sys::svcWaitSynchronization(self.event, 0) } != 0 {
+            return false;
+        }
+
+        std::mem::swap(&mut self.receiving, &mut self.latest);
+        // if this fails the feed just freezes on the last frame, which is better than stopping
+        let _ = self.receive();
+
+        let texels = unsafe {
+            std::slice::from_raw_parts_mut(self.texture.__bindgen_anon_1.data as *mut u16, TEX_WIDTH * TEX_HEIGHT)
+        };
+        for (y, row) in self.latest.chunks_exact(WIDTH).enumerate() {
+            // the texture's first row is its bottom
+            let ty = HEIGHT - 1 - y;
+            let tile_row = (ty / 8) * (TEX_WIDTH / 8);
+            for (x, &texel) in row.iter().enumerate() {
+                let offset = (tile_row + x / 8) * 64 + morton(x % 8, ty % 8);
+                texels[offset] = texel;
+            }
+        }
+        unsafe { sys::C3D_TexFlush(&mut self.texture); }
+
+        true
+    }
+
+    // the last frame that arrived, as 400x240 RGB565 pixels from the top left
+    pub fn frame(&self) -> &[u16] {
+        &self.latest
+    }
+
+    pub fn size(&self) -> (usize, usize) {
+        (WIDTH, HEIGHT)
+    }
+
+    pub fn texture(&self) -> &sys::C3D_Tex {
+        &self.texture
+    }
+
+    // the part of texture() the picture covers, see Renderer::please_render_background
+    pub fn uv_max(&self) -> Vec2 {
+        Vec2::new(WIDTH as f32 / TEX_WIDTH as f32, HEIGHT as f32 / TEX_HEIGHT as f32)
+    }
+
+    pub fn please_render(&self, renderer: &mut Renderer) {
+        renderer.please_render_background(&self.texture, self.uv_max());
+    }
+}
+
+impl Drop for CameraFeed {
+    fn drop(&mut self) {
+        unsafe {
+            ctru_sys::CAMU_StopCapture(ctru_sys::PORT_CAM1);
+            ctru_sys::CAMU_Activate(ctru_sys::SELECT_NONE);
+            if self.event != 0 {
+                ctru_sys::svcCloseHandle(self.event);
+            }
+            sys::C3D_TexDelete(&mut self.texture);
+            ctru_sys::camExit();
+        }
+    }
+}
@@ -0,0 +1,104 @@
+// requests recorded away from the renderer, to hand it all at once with Renderer::submit. one can
+// be kept and submitted every frame for what doesn't change (a level's scenery, say), or submitted
+// more than once with Renderer::submit_transformed to put the same things in several places:
+//
+//     let mut scenery = CommandBuffer::new();
+//     for (mesh_id, model) in level.props() {
+//         scenery.please_render(mesh_id, model);
+//     }
+//     ...
+//     renderer.submit(&scenery);
+//     renderer.please_render(player, player_model);
+//     renderer.render();
+//
+// only ids and matrices are recorded, so building one doesn't need the renderer. animations are
+// recorded as they are at the time, not as they are when it's submitted
+use citro3d::math::Matrix4;
+use citro3d::sys;
+use glam::{Vec2, Vec4};
+
+use crate::decal::Decal;
+use crate::material_animation::{MaterialAnimation, MaterialOverride};
+use crate::math::to_matrix4;
+use crate::renderer::{MeshId, ModelId, TargetScreen};
+
+// one of the Renderer::please_render* calls
+#[derive(Copy, Clone)]
+pub(crate) enum Command {
+    Mesh { mesh_id: MeshId, model: Matrix4, material: Option<MaterialOverride> },
+    Model { model_id: ModelId, model: Matrix4, material: Option<MaterialOverride> },
+    Batched { mesh_id: MeshId, model: Matrix4 },
+    Decal { mesh_id: MeshId, model: Matrix4 },
+    Sprite { screen: TargetScreen, mesh_id: MeshId, model: Matrix4, color: Vec4, depth: f32 },
+    Overlay { texture: Option<sys::C3D_Tex>, min: Vec2, size: Vec2, color: Vec4 },
+}
+
+#[derive(Clone, Default)]
+pub struct CommandBuffer {
+    commands: Vec<Command>,
+}
+
+// the overlays' textures are only pointed at, by the GPU once they're submitted, so a buffer can be
+// built on another thread (see frame_worker)
+unsafe impl Send for CommandBuffer {}
+
+impl CommandBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn commands(&self) -> &[Command] {
+        &self.commands
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    // keeps the memory, for building it again
+    pub fn clear(&mut self) {
+        self.commands.clear();
+    }
+
+    // each of these is the Renderer method of the same name
+
+    pub fn please_render(&mut self, mesh_id: MeshId, model: Matrix4) {
+        self.commands.push(Command::Mesh { mesh_id, model, material: None });
+    }
+
+    pub fn please_render_animated(&mut self, mesh_id: MeshId, model: Matrix4, animation: &MaterialAnimation) {
+        self.commands.push(Command::Mesh { mesh_id, model, material: Some(animation.current()) });
+    }
+
+    pub fn please_render_model(&mut self, model_id: ModelId, model: Matrix4) {
+        self.commands.push(Command::Model { model_id, model, material: None });
+    }
+
+    pub fn please_render_model_animated(&mut self, model_id: ModelId, model: Matrix4, animation: &MaterialAnimation) {
+        self.commands.push(Command::Model { model_id, model, material: Some(animation.current()) });
+    }
+
+    pub fn please_render_batched(&mut self, mesh_id: MeshId, model: Matrix4) {
+        self.commands.push(Command::Batched { mesh_id, model });
+    }
+
+    pub fn please_render_decal(&mut self, mesh_id: MeshId, decal: &Decal) {
+        self.commands.push(Command::Decal { mesh_id, model: to_matrix4(decal.transform()) });
+    }
+
+    pub fn please_render_2d(&mut self, screen: TargetScreen, mesh_id: MeshId, model: Matrix4, color: Vec4) {
+        self.please_render_2d_at_depth(screen, mesh_id, model, color, 0.);
+    }
+
+    pub fn please_render_2d_at_depth(&mut self, screen: TargetScreen, mesh_id: MeshId, model: Matrix4, color: Vec4, depth: f32) {
+        self.commands.push(Command::Sprite { screen, mesh_id, model, color, depth: depth.clamp(-1., 1.) });
+    }
+
+    pub fn please_render_overlay(&mut self, texture: Option<&sys::C3D_Tex>, min: Vec2, size: Vec2, color: Vec4) {
+        self.commands.push(Command::Overlay { texture: texture.copied(), min, size, color });
+    }
+}
@@ -0,0 +1,245 @@
+// engine and game settings, read from a TOML file (usually romfs:/config.toml) at startup so builds
+// can be tuned without code changes. every field has a default, so a config only needs to list what
+// it changes. see romfs/config.toml for an example
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+
+use citro3d::math::ClipPlanes;
+use ctru::services::hid::KeyPad;
+use serde::Deserialize;
+
+use crate::backlight::{MAX_LEVEL, MIN_LEVEL};
+use crate::events::{Action, EventBus};
+use crate::input::Input;
+use crate::power::Quality;
+use crate::renderer::{DepthMapping, Fog, Renderer};
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub renderer: RendererConfig,
+    pub fog: FogConfig,
+    pub quality: QualityConfig,
+    pub follow_camera: FollowCameraConfig,
+    pub display: DisplayConfig,
+
+    // action name => button names, e.g. jump = ["A", "B"]
+    pub controls: HashMap<String, Vec<String>>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RendererConfig {
+    pub clear_color: [u8; 3],
+    pub fov: f32, // vertical, in degrees
+    pub near: f32,
+    pub far: f32,
+    pub w_buffer: bool, // see renderer::DepthMapping
+    pub depth_range: [f32; 2], // what near and far are kept as in the depth buffer
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FogConfig {
+    pub enabled: bool,
+    pub color: [u8; 3],
+    pub density: f32,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct QualityConfig {
+    pub target_fps: u32, // 60 or 30
+    pub battery_saver: bool, // turn quality down on low battery, see power::PowerPolicy
+}
+
+// see follow_camera::FollowCamera. distances are in world units
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FollowCameraConfig {
+    pub distance: f32, // behind the target
+    pub height: f32, // above the target
+    pub target_height: f32, // where on the target to look, e.g. its head
+    pub look_ahead: f32, // seconds of the target's movement to look ahead by
+    pub smoothing: f32, // roughly how many seconds the camera takes to catch up
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DisplayConfig {
+    pub backlight: Option<u8>, // 1 to 5 like the HOME menu, or leave out to keep the system's. see backlight::Backlight
+    pub brightness: f32, // 0 to 1, see Renderer::set_brightness
+}
+
+impl Default for RendererConfig {
+    fn default() -> Self {
+        Self {
+            clear_color: [0x68, 0xb0, 0xd8],
+            fov: 80.,
+            near: 0.01,
+            far: 100.,
+            w_buffer: false,
+            depth_range: [1., 0.],
+        }
+    }
+}
+
+impl Default for FogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            color: [0xff, 0xff, 0xff],
+            density: 0.5,
+        }
+    }
+}
+
+impl Default for FollowCameraConfig {
+    fn default() -> Self {
+        Self {
+            distance: 4.,
+            height: 1.5,
+            target_height: 1.,
+            look_ahead: 0.3,
+            smoothing: 0.2,
+        }
+    }
+}
+
+impl Default for QualityConfig {
+    fn default() -> Self {
+        Self { target_fps: 60, battery_saver: true }
+    }
+}
+
+const BUTTONS: &[(&str, KeyPad)] = &[
+    ("A", KeyPad::A),
+    ("B", KeyPad::B),
+    ("X", KeyPad::X),
+    ("Y", KeyPad::Y),
+    ("L", KeyPad::L),
+    ("R", KeyPad::R),
+    ("ZL", KeyPad::ZL),
+    ("ZR", KeyPad::ZR),
+    ("START", KeyPad::START),
+    ("SELECT", KeyPad::SELECT),
+    ("UP", KeyPad::DPAD_UP),
+    ("DOWN", KeyPad::DPAD_DOWN),
+    ("LEFT", KeyPad::DPAD_LEFT),
+    ("RIGHT", KeyPad::DPAD_RIGHT),
+];
+
+fn button(name: &str) -> Option<KeyPad> {
+    BUTTONS.iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|&(_, key)| key)
+}
+
+fn rgb(color: [u8; 3]) -> u32 {
+    (color[0] as u32) << 16 | (color[1] as u32) << 8 | color[2] as u32
+}
+
+impl Config {
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        let config: Config = toml::from_str(&fs::read_to_string(path)?)
+            .map_err(|e| format!("{path}: {e}"))?;
+        config.validate().map_err(|e| format!("{path}: {e}"))?;
+
+        Ok(config)
+    }
+
+    // falls back to the defaults if the file is missing or invalid, with why for the game to show
+    pub fn load_or_default(path: &str) -> (Self, Option<String>) {
+        match Self::load(path) {
+            Ok(config) => (config, None),
+            Err(e) => (Self::default(), Some(format!("using default config: {e}"))),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let r = &self.renderer;
+        if !(1.0..180.0).contains(&r.fov) {
+            return Err(format!("renderer.fov must be between 1 and 180 degrees, not {}", r.fov));
+        }
+        if !(r.near > 0. && r.far > r.near) {
+            return Err(format!("renderer.near and far must satisfy 0 < near < far, not {} and {}", r.near, r.far));
+        }
+        let [near, far] = r.depth_range;
+        if !(0. <= far && far < near && near <= 1.) {
+            return Err(format!("renderer.depth_range must satisfy 0 <= far < near <= 1, not {:?}", r.depth_range));
+        }
+
+        if self.fog.density < 0. {
+            return Err(format!("fog.density can't be negative ({})", self.fog.density));
+        }
+
+        if ![30, 60].contains(&self.quality.target_fps) {
+            return Err(format!("quality.target_fps must be 30 or 60, not {}", self.quality.target_fps));
+        }
+
+        let d = &self.display;
+        if d.backlight.is_some_and(|level| !(MIN_LEVEL..=MAX_LEVEL).contains(&level)) {
+            return Err(format!("display.backlight must be from {MIN_LEVEL} to {MAX_LEVEL}, not {}", d.backlight.unwrap()));
+        }
+        if !(0. ..=1.).contains(&d.brightness) {
+            return Err(format!("display.brightness must be from 0 to 1, not {}", d.brightness));
+        }
+
+        let f = &self.follow_camera;
+        if !(f.distance >= 0. && f.smoothing >= 0. && f.look_ahead >= 0.) {
+            return Err("follow_camera.distance, smoothing and look_ahead can't be negative".to_owned());
+        }
+
+        for (action, buttons) in &self.controls {
+            if let Some(bad) = buttons.iter().find(|b| button(b).is_none()) {
+                return Err(format!("controls.{action}: unknown button {bad:?}"));
+            }
+        }
+
+        Ok(())
+    }
+
+    // every button bound to `action`, or empty if it isn't bound
+    pub fn binding(&self, action: &str) -> KeyPad {
+        self.controls.get(action)
+            .into_iter()
+            .flatten()
+            .filter_map(|b| button(b))
+            .fold(KeyPad::empty(), |keys, key| keys | key)
+    }
+
+    // publishes an Action for every action with a button that went down this frame, and for every
+    // one whose buttons are all up again after one came up
+    pub fn publish_actions(&self, input: &Input, events: &mut EventBus) {
+        for action in self.controls.keys() {
+            let keys = self.binding(action);
+            if keys.iter().any(|key| input.just_pressed(key)) {
+                events.publish(Action { name: action.clone(), pressed: true });
+            } else if keys.iter().any(|key| input.just_released(key)) && !keys.iter().any(|key| input.held(key)) {
+                events.publish(Action { name: action.clone(), pressed: false });
+            }
+        }
+    }
+
+    pub fn quality(&self) -> Quality {
+        Quality {
+            target_fps: self.quality.target_fps,
+            ..Default::default()
+        }
+    }
+
+    pub fn apply(&self, renderer: &mut Renderer) {
+        let r = &self.renderer;
+        renderer.set_clear_color(rgb(r.clear_color) << 8 | 0xff);
+        renderer.set_perspective(r.fov.to_radians(), ClipPlanes { near: r.near, far: r.far });
+        let [near, far] = r.depth_range;
+        renderer.set_depth_mapping(DepthMapping { w_buffer: r.w_buffer, near, far });
+        renderer.set_brightness(self.display.brightness);
+
+        renderer.set_fog(self.fog.enabled.then(|| Fog {
+            color: rgb(self.fog.color),
+            density: self.fog.density,
+        }));
+    }
+}
@@ -0,0 +1,48 @@
+use std::io;
+
+use citro3d::sys;
+
+// a cube texture, for Renderer::set_skybox. made from a t3x that tex3ds built with --cubemap (see
+// gfx/tex3ds.toml), which lays the six faces out in one image
+pub struct Cubemap {
+    pub(crate) texture: sys::C3D_Tex,
+    _faces: Box<sys::C3D_TexCube>, // the texture points into this
+}
+
+impl Cubemap {
+    pub fn from_t3x(t3x_data: &[u8]) -> io::Result<Self> {
+        let mut texture = unsafe { std::mem::zeroed::<sys::C3D_Tex>() };
+        let mut faces = Box::new(unsafe { std::mem::zeroed::<sys::C3D_TexCube>() });
+
+        unsafe {
+            let t3x = sys::Tex3DS_TextureImport(
+                t3x_data.as_ptr().cast(),
+                t3x_data.len(),
+                &mut texture,
+                faces.as_mut(),
+                false,
+            );
+            if t3x.is_null() {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "not a cubemap t3x"));
+            }
+            sys::Tex3DS_TextureFree(t3x);
+
+            // 2D textures leave the faces alone
+            if faces.data[0].is_null() {
+                sys::C3D_TexDelete(&mut texture);
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "t3x is a 2D texture, not a cubemap"));
+            }
+
+            sys::C3D_TexSetFilter(&mut texture, ctru_sys::GPU_LINEAR, ctru_sys::GPU_LINEAR);
+            sys::C3D_TexSetWrap(&mut texture, ctru_sys::GPU_CLAMP_TO_EDGE, ctru_sys::GPU_CLAMP_TO_EDGE);
+        }
+
+        Ok(Self { texture, _faces: faces })
+    }
+}
+
+impl Drop for Cubemap {
+    fn drop(&mut self) {
+        unsafe { sys::C3D_TexDelete(&mut self.texture); }
+    }
+}
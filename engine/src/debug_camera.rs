@@ -0,0 +1,98 @@
+// a camera that flies free of the game's, for looking around a level on the console (what's
+// culled, what's hidden behind what) while the game carries on. hold L+R and press X to switch to
+// it and back; it starts wherever the game's camera was:
+//
+//     debug_camera.follow(orbit.camera());
+//     debug_camera.update(&input, dt);
+//     let camera = debug_camera.camera().unwrap_or(orbit.camera());
+//     renderer.set_camera(&camera);
+//
+// the circle pad flies along the ground and the c-stick (or the d-pad, without one) looks around.
+// ZR and ZL (and up and down on the d-pad, when it's not looking) go up and down, and holding B
+// goes faster. games should ignore their own controls while it's active, with is_active
+use std::f32::consts::{FRAC_PI_2, TAU};
+
+use ctru::services::hid::KeyPad;
+use glam::{Quat, Vec2, Vec3};
+
+use crate::camera::Camera;
+use crate::input::Input;
+
+const TOGGLE_HELD: KeyPad = KeyPad::L.union(KeyPad::R);
+const TOGGLE_PRESSED: KeyPad = KeyPad::X;
+
+pub struct DebugCamera {
+    pub fly_speed: f32, // units per second
+    pub fast_multiplier: f32, // while B is held
+    pub look_speed: f32, // radians per second
+
+    active: Option<(Vec3, f32, f32)>, // position, yaw and pitch while flying
+    last_camera: Camera, // the game's, to start from
+}
+
+impl Default for DebugCamera {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DebugCamera {
+    pub fn new() -> Self {
+        Self { fly_speed: 4., fast_multiplier: 4., look_speed: 2., active: None, last_camera: Camera::default() }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active.is_some()
+    }
+
+    // true while the toggle combo is being held, so games can ignore the buttons it uses
+    pub fn is_toggle_held(&self, input: &Input) -> bool {
+        input.held(TOGGLE_HELD)
+    }
+
+    // the game's camera, for where to start flying from next time. call it every frame before
+    // update, with whatever the game would have drawn with
+    pub fn follow(&mut self, camera: Camera) {
+        self.last_camera = camera;
+    }
+
+    // call once per frame, after Input::update
+    pub fn update(&mut self, input: &Input, dt: f32) {
+        if input.held(TOGGLE_HELD) && input.just_pressed(TOGGLE_PRESSED) {
+            self.active = match self.active {
+                Some(_) => None,
+                None => {
+                    let forward = self.last_camera.forward();
+                    let yaw = (-forward.x).atan2(-forward.z);
+                    let pitch = forward.y.clamp(-1., 1.).asin();
+                    Some((self.last_camera.position, yaw, pitch))
+                }
+            };
+        }
+        let Some((position, yaw, pitch)) = &mut self.active else {
+            return;
+        };
+
+        let axis = |positive: KeyPad, negative: KeyPad| input.held(positive) as i32 as f32 - input.held(negative) as i32 as f32;
+        let has_c_stick = input.is_circle_pad_pro_connected();
+        let look = if has_c_stick { input.c_stick() } else { Vec2::new(axis(KeyPad::DPAD_RIGHT, KeyPad::DPAD_LEFT), axis(KeyPad::DPAD_UP, KeyPad::DPAD_DOWN)) };
+        let rise = if has_c_stick { axis(KeyPad::DPAD_UP, KeyPad::DPAD_DOWN) } else { 0. } + axis(KeyPad::ZR, KeyPad::ZL);
+
+        *yaw = (*yaw - look.x * self.look_speed * dt) % TAU;
+        *pitch = (*pitch + look.y * self.look_speed * dt).clamp(-FRAC_PI_2 + 0.01, FRAC_PI_2 - 0.01);
+
+        let speed = if input.held(KeyPad::B) { self.fly_speed * self.fast_multiplier } else { self.fly_speed };
+        let walk = input.circle_pad().clamp_length_max(1.);
+        let forward = Quat::from_rotation_y(*yaw) * Vec3::NEG_Z;
+        let right = Quat::from_rotation_y(*yaw) * Vec3::X;
+        *position += (forward * walk.y + right * walk.x + Vec3::Y * rise.clamp(-1., 1.)) * speed * dt;
+    }
+
+    // where to draw from while it's active
+    pub fn camera(&self) -> Option<Camera> {
+        self.active.map(|(position, yaw, pitch)| Camera {
+            position,
+            rotation: Quat::from_rotation_y(yaw) * Quat::from_rotation_x(pitch),
+        })
+    }
+}
@@ -0,0 +1,90 @@
+use std::collections::VecDeque;
+use std::fmt::Display;
+
+use ctru::console::Console;
+use ctru::services::gfx::Gfx;
+use ctru::services::hid::KeyPad;
+
+use crate::input::Input;
+
+// how many lines are kept around while the console is hidden
+const SCROLLBACK: usize = 256;
+
+// hold L+R and press SELECT to show or hide
+const TOGGLE_HELD: KeyPad = KeyPad::L.union(KeyPad::R);
+const TOGGLE_PRESSED: KeyPad = KeyPad::SELECT;
+
+// a text console on the bottom screen that only claims the screen while it's visible, so the
+// bottom screen can be rendered to the rest of the time. lines logged while it's hidden are
+// buffered and shown once it's opened again
+pub struct DebugConsole<'gfx> {
+    gfx: &'gfx Gfx,
+    console: Option<Console<'gfx>>,
+    lines: VecDeque<String>,
+}
+
+impl<'gfx> DebugConsole<'gfx> {
+    pub fn new(gfx: &'gfx Gfx) -> Self {
+        Self {
+            gfx,
+            console: None,
+            lines: VecDeque::with_capacity(SCROLLBACK),
+        }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.console.is_some()
+    }
+
+    // returns false if something else is using the bottom screen
+    pub fn show(&mut self) -> bool {
+        if self.console.is_some() {
+            return true;
+        }
+
+        let Ok(screen) = self.gfx.bottom_screen.try_borrow_mut() else {
+            return false;
+        };
+
+        let console = Console::new(screen);
+        for line in &self.lines {
+            println!("{line}");
+        }
+        self.console = Some(console);
+
+        true
+    }
+
+    // gives the bottom screen back
+    pub fn hide(&mut self) {
+        self.console = None;
+    }
+
+    // call once per frame, after Input::update
+    pub fn update(&mut self, input: &Input) {
+        if input.held(TOGGLE_HELD) && input.just_pressed(TOGGLE_PRESSED) {
+            if self.is_visible() {
+                self.hide();
+            } else {
+                self.show();
+            }
+        }
+    }
+
+    // true while the toggle combo is being held, so games can ignore the buttons it uses
+    pub fn is_toggle_held(&self, input: &Input) -> bool {
+        input.held(TOGGLE_HELD)
+    }
+
+    pub fn log(&mut self, line: impl Display) {
+        let line = line.to_string();
+        if self.is_visible() {
+            println!("{line}");
+        }
+
+        if self.lines.len() == SCROLLBACK {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line);
+    }
+}
@@ -0,0 +1,53 @@
+// decals: textured quads laid flat on a surface, for bullet holes, footprints and blob shadows.
+// they're drawn after everything else, pulled slightly towards the camera in the depth buffer so
+// they win against the surface they're on instead of z-fighting with it
+//
+//     let footprint = renderer.register_mesh(decal::mesh(&assets::FOOTPRINT_T3X.read()?, Material::default()));
+//     ...
+//     renderer.please_render_decal(footprint, &Decal::new(hit.position, hit.normal, Vec2::splat(0.3)));
+//
+// a decal hanging over the edge of what it's on isn't clipped, so keep them small
+use glam::{Mat4, Quat, Vec2, Vec3};
+
+use crate::material::Material;
+use crate::mesh::{Mesh, Vertex};
+
+// a unit quad on the xy plane, facing +z
+const QUAD: [Vertex; 6] = [
+    Vertex { pos: [-0.5, -0.5, 0.], uv: [0., 0.], normal: [0., 0., 1.] },
+    Vertex { pos: [ 0.5, -0.5, 0.], uv: [1., 0.], normal: [0., 0., 1.] },
+    Vertex { pos: [ 0.5,  0.5, 0.], uv: [1., 1.], normal: [0., 0., 1.] },
+
+    Vertex { pos: [ 0.5,  0.5, 0.], uv: [1., 1.], normal: [0., 0., 1.] },
+    Vertex { pos: [-0.5,  0.5, 0.], uv: [0., 1.], normal: [0., 0., 1.] },
+    Vertex { pos: [-0.5, -0.5, 0.], uv: [0., 0.], normal: [0., 0., 1.] },
+];
+
+// a quad showing `t3x_data`, for please_render_decal. transparent parts of the texture let the
+// surface show through
+pub fn mesh(t3x_data: &[u8], material: Material) -> Mesh {
+    Mesh::from_data(&QUAD, None, Some(t3x_data), material)
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Decal {
+    pub position: Vec3, // on the surface
+    pub normal: Vec3, // of the surface, pointing out of it
+    pub size: Vec2,
+    pub angle: f32, // radians around the normal
+}
+
+impl Decal {
+    pub fn new(position: Vec3, normal: Vec3, size: Vec2) -> Self {
+        Self { position, normal, size, angle: 0. }
+    }
+
+    pub fn transform(&self) -> Mat4 {
+        let facing = Quat::from_rotation_arc(Vec3::Z, self.normal.normalize_or(Vec3::Z));
+        Mat4::from_scale_rotation_translation(
+            self.size.extend(1.),
+            facing * Quat::from_rotation_z(self.angle),
+            self.position,
+        )
+    }
+}
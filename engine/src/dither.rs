@@ -0,0 +1,62 @@
+// ordered dithering, for screens set to 16-bit framebuffers (to save VRAM and transfer time), where
+// skies and other smooth gradients come out in bands. the GPU still draws in 8 bits per channel,
+// and the transfer to the screen just drops the low bits; adding a tiled 8x8 Bayer pattern of less
+// than one step of the screen's format first makes the rounding vary from pixel to pixel instead,
+// which the eye averages back into the gradient. see Renderer::set_dither
+use citro3d::sys;
+use glam::Vec4;
+
+use crate::camera_feed::morton;
+
+const SIZE: usize = 8;
+
+// 0 to 63, each once, spread so that any run of them is as even as it can be
+fn bayer(x: usize, y: usize) -> u8 {
+    let mut ret = 0;
+    for bit in 0..3 {
+        ret |= ((x ^ y) >> bit & 1) << (5 - 2 * bit) | (y >> bit & 1) << (4 - 2 * bit);
+    }
+
+    ret as u8
+}
+
+// the pattern, from 0 up to (not quite) 1, repeating every 8 pixels
+pub(crate) fn texture() -> Option<sys::C3D_Tex> {
+    let mut texture: sys::C3D_Tex = unsafe { std::mem::zeroed() };
+    if !unsafe { sys::C3D_TexInit(&mut texture, SIZE as u16, SIZE as u16, ctru_sys::GPU_L8) } {
+        return None;
+    }
+
+    unsafe {
+        let data = std::slice::from_raw_parts_mut(texture.__bindgen_anon_1.data as *mut u8, SIZE * SIZE);
+        for y in 0..SIZE {
+            for x in 0..SIZE {
+                data[morton(x, y)] = bayer(x, y) * 4;
+            }
+        }
+        sys::C3D_TexFlush(&mut texture);
+        sys::C3D_TexSetFilter(&mut texture, ctru_sys::GPU_NEAREST, ctru_sys::GPU_NEAREST);
+        sys::C3D_TexSetWrap(&mut texture, ctru_sys::GPU_REPEAT, ctru_sys::GPU_REPEAT);
+    }
+
+    Some(texture)
+}
+
+// how much one step of each channel of a screen in `format` (a GSPGPU_FramebufferFormat) is, out
+// of 1, or None if it has all 8 bits and doesn't need dithering
+pub(crate) fn step(format: ctru_sys::GSPGPU_FramebufferFormat) -> Option<Vec4> {
+    let bits = match format {
+        ctru_sys::GSP_RGB565_OES => [5, 6, 5],
+        ctru_sys::GSP_RGB5_A1_OES => [5, 5, 5],
+        ctru_sys::GSP_RGBA4_OES => [4, 4, 4],
+        _ => return None,
+    };
+
+    let [r, g, b] = bits.map(|bits: u32| (1 << (8 - bits)) as f32 / 255.);
+    Some(Vec4::new(r, g, b, 1.))
+}
+
+// repeats the pattern once every SIZE pixels across a screen `size` pixels big, as a uv_transform
+pub(crate) fn uv_scale(width: f32, height: f32) -> Vec4 {
+    Vec4::new(width / SIZE as f32, height / SIZE as f32, 0., 0.)
+}
@@ -0,0 +1,183 @@
+// fetches files over HTTP with the system httpc service, so asset packs and update manifests can
+// live on a server instead of in the .3dsx/.cia. downloads go to a `.part` file next to the
+// destination which is only renamed into place once complete, so an interrupted download never
+// leaves a truncated file where the game expects a good one
+use std::error::Error;
+use std::ffi::CString;
+use std::fs;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+const CHUNK_SIZE: usize = 4096;
+const MAX_REDIRECTS: u32 = 5;
+
+#[derive(Copy, Clone, Debug)]
+pub struct Progress {
+    pub downloaded: u64,
+    pub total: Option<u64>, // None if the server didn't send a Content-Length
+}
+
+fn check(res: ctru_sys::Result) -> ctru::Result<()> {
+    if ctru_sys::R_FAILED(res) {
+        Err(res.into())
+    } else {
+        Ok(())
+    }
+}
+
+// an open request, closed on drop
+struct Context(ctru_sys::httpcContext);
+
+impl Context {
+    fn open(url: &str) -> Result<Self, Box<dyn Error>> {
+        let url = CString::new(url)?;
+        let mut context = Context(unsafe { std::mem::zeroed() });
+
+        unsafe {
+            check(ctru_sys::httpcOpenContext(&mut context.0, ctru_sys::HTTPC_METHOD_GET, url.as_ptr(), 1))?;
+            check(ctru_sys::httpcAddRequestHeaderField(&mut context.0, c"User-Agent".as_ptr(), c"mm3ds".as_ptr()))?;
+            check(ctru_sys::httpcBeginRequest(&mut context.0))?;
+        }
+
+        Ok(context)
+    }
+
+    fn status(&mut self) -> ctru::Result<u32> {
+        let mut status = 0;
+        check(unsafe { ctru_sys::httpcGetResponseStatusCode(&mut self.0, &mut status) })?;
+        Ok(status)
+    }
+
+    fn location(&mut self) -> Result<String, Box<dyn Error>> {
+        let mut buf = [0u8; 1024];
+        check(unsafe {
+            ctru_sys::httpcGetResponseHeader(&mut self.0, c"Location".as_ptr(), buf.as_mut_ptr().cast(), buf.len() as u32)
+        })?;
+
+        let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+        Ok(String::from_utf8(buf[..len].to_vec())?)
+    }
+
+    fn total_size(&mut self) -> Option<u64> {
+        let (mut downloaded, mut total) = (0, 0);
+        check(unsafe { ctru_sys::httpcGetDownloadSizeState(&mut self.0, &mut downloaded, &mut total) }).ok()?;
+        (total != 0).then_some(total as u64)
+    }
+
+    // reads the body in chunks, handing each to `sink`. returns the number of bytes read
+    fn read_body(
+        &mut self,
+        sink: &mut dyn FnMut(&[u8]) -> Result<(), Box<dyn Error>>,
+        progress: &mut dyn FnMut(Progress),
+    ) -> Result<u64, Box<dyn Error>> {
+        let total = self.total_size();
+        let mut buf = [0u8; CHUNK_SIZE];
+        let mut downloaded = 0;
+
+        loop {
+            let mut len = 0;
+            let res = unsafe { ctru_sys::httpcDownloadData(&mut self.0, buf.as_mut_ptr(), buf.len() as u32, &mut len) };
+            let pending = res as u32 == ctru_sys::HTTPC_RESULTCODE_DOWNLOADPENDING;
+            if !pending {
+                check(res)?;
+            }
+
+            sink(&buf[..len as usize])?;
+            downloaded += len as u64;
+            progress(Progress { downloaded, total });
+
+            if !pending {
+                return Ok(downloaded);
+            }
+        }
+    }
+}
+
+impl Drop for Context {
+    fn drop(&mut self) {
+        unsafe { ctru_sys::httpcCloseContext(&mut self.0); }
+    }
+}
+
+// keeps the httpc service running. only one is needed for any number of downloads
+pub struct Downloader {
+    _private: (),
+}
+
+impl Downloader {
+    pub fn new() -> ctru::Result<Self> {
+        check(unsafe { ctru_sys::httpcInit(0) })?;
+        Ok(Self { _private: () })
+    }
+
+    // follows redirects, and fails on anything but a 200
+    fn open(&self, url: &str) -> Result<Context, Box<dyn Error>> {
+        let mut url = url.to_owned();
+        for _ in 0..=MAX_REDIRECTS {
+            let mut context = Context::open(&url)?;
+            match context.status()? {
+                200 => return Ok(context),
+                301 | 302 | 303 | 307 | 308 => url = context.location()?,
+                status => return Err(format!("{url}: HTTP {status}").into()),
+            }
+        }
+
+        Err(format!("{url}: too many redirects").into())
+    }
+
+    // `progress` is called after every chunk
+    pub fn get(&self, url: &str, mut progress: impl FnMut(Progress)) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut context = self.open(url)?;
+        let mut data = Vec::with_capacity(context.total_size().unwrap_or(0) as usize);
+        context.read_body(&mut |chunk| {
+            data.extend_from_slice(chunk);
+            Ok(())
+        }, &mut progress)?;
+
+        Ok(data)
+    }
+
+    // downloads `url` to `path` (e.g. sdmc:/3ds/mm3ds/pack.bin), replacing whatever was there.
+    // returns the size of the file
+    pub fn download_to(
+        &self,
+        url: &str,
+        path: impl AsRef<Path>,
+        mut progress: impl FnMut(Progress),
+    ) -> Result<u64, Box<dyn Error>> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let part = path.with_extension("part");
+        let result: Result<u64, Box<dyn Error>> = (|| {
+            let mut context = self.open(url)?;
+            let mut file = File::create(&part)?;
+            let size = context.read_body(&mut |chunk| Ok(file.write_all(chunk)?), &mut progress)?;
+            file.sync_all()?;
+
+            Ok(size)
+        })();
+
+        match result {
+            Ok(size) => {
+                // sdmc can't rename over an existing file
+                let _ = fs::remove_file(path);
+                fs::rename(&part, path)?;
+                Ok(size)
+            }
+            Err(e) => {
+                let _ = fs::remove_file(&part);
+                Err(e)
+            }
+        }
+    }
+}
+
+impl Drop for Downloader {
+    fn drop(&mut self) {
+        unsafe { ctru_sys::httpcExit(); }
+    }
+}
@@ -0,0 +1,126 @@
+// messages between systems that don't know about each other. whatever happens during a frame's
+// update is published, and everyone reads it the next frame, so the order systems run in doesn't
+// decide who hears about what:
+//
+//     let mut events = EventBus::new();
+//     ...
+//     events.update();
+//     config.publish_actions(&input, &mut events);
+//     player_animation.update(dt);
+//     player_animation.publish(&mut events);
+//     for action in events.read::<Action>() {
+//         if action.pressed && action.name == "jump" {
+//             jump();
+//         }
+//     }
+//     for event in events.read::<AnimationEvent>() {
+//         if event.name == "footstep" {
+//             play_footstep();
+//         }
+//     }
+//     if hit {
+//         events.publish(Hit { damage: 3 });
+//     }
+//
+// an event is any 'static type, so games add their own (collisions, say, from whatever is doing
+// their collision) next to the engine's. reading doesn't take the events away, so any number of
+// systems can read the same ones, and they're gone after a frame whether anyone read them or not
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+
+// a pressed or released action from the config's controls, see Config::publish_actions
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Action {
+    pub name: String,
+    pub pressed: bool, // false when it's let go
+}
+
+struct Queue<T> {
+    published: Vec<T>, // this frame
+    readable: Vec<T>, // last frame's
+}
+
+// a Queue of whatever type, so they can all be kept in one map and flipped together
+trait AnyQueue {
+    fn flip(&mut self);
+    fn clear(&mut self);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: 'static> AnyQueue for Queue<T> {
+    fn flip(&mut self) {
+        self.readable.clear();
+        std::mem::swap(&mut self.published, &mut self.readable);
+    }
+
+    fn clear(&mut self) {
+        self.published.clear();
+        self.readable.clear();
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[derive(Default)]
+pub struct EventBus {
+    queues: HashMap<TypeId, Box<dyn AnyQueue>>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // makes what was published last frame readable and drops what was read. call once a frame,
+    // before anything publishes or reads
+    pub fn update(&mut self) {
+        for queue in self.queues.values_mut() {
+            queue.flip();
+        }
+    }
+
+    // readable from the next update on
+    pub fn publish<T: 'static>(&mut self, event: T) {
+        self.queue_mut::<T>().published.push(event);
+    }
+
+    pub fn publish_all<T: 'static>(&mut self, events: impl IntoIterator<Item = T>) {
+        self.queue_mut::<T>().published.extend(events);
+    }
+
+    // everything of type T published last frame, in the order it was published
+    pub fn read<T: 'static>(&self) -> &[T] {
+        self.queues.get(&TypeId::of::<T>())
+            .and_then(|queue| queue.as_any().downcast_ref::<Queue<T>>())
+            .map_or(&[], |queue| &queue.readable)
+    }
+
+    // like read, but takes them, for events only one system should act on
+    pub fn drain<T: 'static>(&mut self) -> Vec<T> {
+        self.queues.get_mut(&TypeId::of::<T>())
+            .and_then(|queue| queue.as_any_mut().downcast_mut::<Queue<T>>())
+            .map_or_else(Vec::new, |queue| std::mem::take(&mut queue.readable))
+    }
+
+    // forgets every event, published or readable, e.g. when a scene is unloaded
+    pub fn clear(&mut self) {
+        for queue in self.queues.values_mut() {
+            queue.clear();
+        }
+    }
+
+    fn queue_mut<T: 'static>(&mut self) -> &mut Queue<T> {
+        self.queues.entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(Queue::<T> { published: Vec::new(), readable: Vec::new() }))
+            .as_any_mut()
+            .downcast_mut()
+            .unwrap() // queues are only ever put under their own type's id
+    }
+}
@@ -0,0 +1,77 @@
+// a third-person camera that trails behind something (usually the player), easing after it
+// instead of being bolted on, looking a little ahead of where it's going, and pulling in when a
+// wall gets between the two. tune it in the [follow_camera] section of the config:
+//
+//     let mut follow = FollowCamera::new(config.follow_camera.clone());
+//     ...
+//     follow.update(player_position, player_velocity, dt, &level);
+//     renderer.set_camera(&follow.camera());
+use glam::Vec3;
+
+use crate::camera::{Camera, Raycast};
+use crate::config::FollowCameraConfig;
+
+// how far to stay from a wall the camera was pulled in front of
+const WALL_MARGIN: f32 = 0.2;
+
+pub struct FollowCamera {
+    pub config: FollowCameraConfig,
+    position: Vec3,
+    look_at: Vec3,
+    started: bool,
+}
+
+impl FollowCamera {
+    pub fn new(config: FollowCameraConfig) -> Self {
+        Self { config, position: Vec3::ZERO, look_at: Vec3::ZERO, started: false }
+    }
+
+    // `velocity` is how fast the target is moving, which decides which way is behind it and how
+    // far to look ahead. a target that stands still keeps the camera where it was
+    pub fn update(&mut self, target: Vec3, velocity: Vec3, dt: f32, world: &impl Raycast) {
+        let c = &self.config;
+        let flat_velocity = velocity.with_y(0.);
+        let heading = flat_velocity.normalize_or_zero();
+
+        let ahead = target + flat_velocity * c.look_ahead;
+        let focus = ahead + Vec3::Y * c.target_height;
+
+        // behind the target (or wherever we already are, if it isn't going anywhere), at height
+        let behind = if heading != Vec3::ZERO {
+            -heading
+        } else {
+            (self.position - target).with_y(0.).normalize_or(Vec3::Z)
+        };
+        let mut wanted = target + behind * c.distance + Vec3::Y * c.height;
+
+        // raycast from what we're looking at, so the target itself stays in view
+        let to_camera = wanted - focus;
+        let length = to_camera.length();
+        if length > 0. {
+            if let Some(hit) = world.raycast(focus, to_camera / length, length) {
+                wanted = focus + to_camera / length * (hit - WALL_MARGIN).max(0.);
+            }
+        }
+
+        if !self.started {
+            self.position = wanted;
+            self.look_at = focus;
+            self.started = true;
+        }
+
+        // framerate independent exponential smoothing: the gap shrinks by the same fraction each
+        // second however it's sliced up
+        let t = 1. - (-dt / c.smoothing.max(1e-3)).exp();
+        self.position = self.position.lerp(wanted, t);
+        self.look_at = self.look_at.lerp(focus, t);
+    }
+
+    // skips the smoothing, e.g. after a teleport or a cutscene
+    pub fn snap(&mut self) {
+        self.started = false;
+    }
+
+    pub fn camera(&self) -> Camera {
+        Camera::looking_at(self.position, self.look_at)
+    }
+}
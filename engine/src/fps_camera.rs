@@ -0,0 +1,112 @@
+// a first-person camera: look around with a stick (or by turning the console, with the gyro) and
+// walk along the ground. there's no character controller yet, so walking only knows about walls
+// through a Raycast, and stops just short of them
+//
+//     let mut fps = FpsCamera::new(Vec3::new(0., 0., 5.));
+//     ...
+//     // look around with the gyro, walk with the circle pad
+//     fps.update(Vec2::ZERO, input.gyro(), input.circle_pad(), dt, &level);
+//     renderer.set_camera(&fps.camera());
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+use glam::{Mat4, Quat, Vec2, Vec3};
+
+use crate::camera::{Camera, Raycast};
+
+// how close the eye gets to a wall
+const RADIUS: f32 = 0.3;
+
+#[derive(Copy, Clone, Debug)]
+pub struct HeadBob {
+    pub height: f32, // how far the eye goes up and down
+    pub steps_per_unit: f32, // bobs per unit walked
+}
+
+impl Default for HeadBob {
+    fn default() -> Self {
+        Self { height: 0.05, steps_per_unit: 0.8 }
+    }
+}
+
+pub struct FpsCamera {
+    pub position: Vec3, // of the feet
+    pub eye_height: f32,
+    pub yaw: f32, // radians, 0 looks down -z and positive turns left
+    pub pitch: f32, // radians, positive looks up
+
+    pub look_speed: Vec2, // radians per second with the stick at full tilt
+    pub gyro_scale: f32, // 1 turns the view exactly as much as the console
+    pub walk_speed: f32, // units per second
+    pub head_bob: Option<HeadBob>,
+    pub invert_y: bool,
+
+    bob_phase: f32, // radians
+}
+
+impl FpsCamera {
+    pub fn new(position: Vec3) -> Self {
+        Self {
+            position,
+            eye_height: 1.6,
+            yaw: 0.,
+            pitch: 0.,
+
+            look_speed: Vec2::new(2.5, 1.8),
+            gyro_scale: 1.,
+            walk_speed: 3.,
+            head_bob: Some(HeadBob::default()),
+            invert_y: false,
+
+            bob_phase: 0.,
+        }
+    }
+
+    // `look` and `walk` are sticks (-1..=1 on each axis, up is forward), and `gyro` is what
+    // Input::gyro returns. `world` keeps walking out of walls
+    pub fn update(&mut self, look: Vec2, gyro: Option<Vec3>, walk: Vec2, dt: f32, world: &impl Raycast) {
+        let look_y = if self.invert_y { -look.y } else { look.y };
+        self.yaw -= look.x * self.look_speed.x * dt;
+        self.pitch += look_y * self.look_speed.y * dt;
+        if let Some(gyro) = gyro {
+            self.yaw += gyro.y * self.gyro_scale * dt;
+            self.pitch += gyro.x * self.gyro_scale * dt;
+        }
+        self.yaw %= TAU;
+        self.pitch = self.pitch.clamp(-FRAC_PI_2 + 0.01, FRAC_PI_2 - 0.01);
+
+        // walking goes along the ground whichever way the camera is pitched
+        let forward = Quat::from_rotation_y(self.yaw) * Vec3::NEG_Z;
+        let right = Quat::from_rotation_y(self.yaw) * Vec3::X;
+        let walk = walk.clamp_length_max(1.);
+        let step = (forward * walk.y + right * walk.x) * self.walk_speed * dt;
+
+        let length = step.length();
+        if length > 0. {
+            let eye = self.position + Vec3::Y * self.eye_height;
+            let dir = step / length;
+            let allowed = world.raycast(eye, dir, length + RADIUS).map_or(length, |hit| (hit - RADIUS).max(0.));
+            self.position += dir * allowed.min(length);
+
+            if let Some(bob) = &self.head_bob {
+                self.bob_phase = (self.bob_phase + allowed.min(length) * bob.steps_per_unit * TAU) % TAU;
+            }
+        } else {
+            // settle back down rather than freezing mid-bob
+            let rest = (self.bob_phase / PI).round() * PI;
+            self.bob_phase += (rest - self.bob_phase).clamp(-4. * dt, 4. * dt);
+        }
+    }
+
+    pub fn camera(&self) -> Camera {
+        let bob = self.head_bob.map_or(0., |bob| self.bob_phase.sin().abs() * bob.height);
+        Camera {
+            position: self.position + Vec3::Y * (self.eye_height + bob),
+            rotation: Quat::from_rotation_y(self.yaw) * Quat::from_rotation_x(self.pitch),
+        }
+    }
+
+    // world space to view space, for anything that wants the matrix itself
+    pub fn view(&self) -> Mat4 {
+        self.camera().view()
+    }
+}
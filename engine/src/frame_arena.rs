@@ -0,0 +1,168 @@
+// a bump allocator for things that only live for a frame: render requests, particle vertices,
+// debug lines, UI batches. allocating is moving a pointer along one block, and freeing is mostly
+// nothing, until reset() takes the whole block back at once:
+//
+//     let mut arena = FrameArena::new(32 << 10);
+//     loop {
+//         arena.reset();
+//         let mut lines = Vec::new_in(&arena);
+//         ...
+//     }
+//
+// the borrow checker makes sure nothing allocated in it is still around when it's reset. it never
+// runs out: whatever doesn't fit goes to the heap it came from instead, and is counted in
+// overflows(), which is a sign that it should be bigger
+//
+// FrameArena::linear puts the block in linear memory, for vertices the GPU reads. the GPU can still
+// be drawing the last frame while the next one's being built, so anything it reads has to stay put
+// for a frame longer; keep two and alternate between them, the way the renderer does with its
+// dynamic batches
+use std::alloc::{AllocError, Allocator, Global, Layout};
+use std::cell::Cell;
+use std::ptr;
+use std::ptr::NonNull;
+
+use ctru::linear::LinearAllocator;
+
+// enough for anything a float vector needs
+const BLOCK_ALIGN: usize = 16;
+
+pub struct FrameArena {
+    block: NonNull<u8>,
+    capacity: usize,
+    linear: bool,
+    used: Cell<usize>,
+    peak: Cell<usize>,
+    overflows: Cell<u32>,
+}
+
+impl FrameArena {
+    pub fn new(capacity: usize) -> Self {
+        Self::with_backing(capacity, false)
+    }
+
+    pub fn linear(capacity: usize) -> Self {
+        Self::with_backing(capacity, true)
+    }
+
+    fn with_backing(capacity: usize, linear: bool) -> Self {
+        let layout = Layout::from_size_align(capacity.max(1), BLOCK_ALIGN).unwrap();
+        let block = if linear { LinearAllocator.allocate(layout) } else { Global.allocate(layout) }
+            .expect("out of memory for a frame arena");
+
+        Self {
+            block: block.cast(),
+            capacity,
+            linear,
+            used: Cell::new(0),
+            peak: Cell::new(0),
+            overflows: Cell::new(0),
+        }
+    }
+
+    // frees everything allocated in it
+    pub fn reset(&mut self) {
+        self.used.set(0);
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    // bytes in use since the last reset
+    pub fn used(&self) -> usize {
+        self.used.get()
+    }
+
+    // the most that's ever been in use at once
+    pub fn peak(&self) -> usize {
+        self.peak.get()
+    }
+
+    // how many allocations didn't fit, and went to the heap instead
+    pub fn overflows(&self) -> u32 {
+        self.overflows.get()
+    }
+
+    // where in the block `ptr` is, if it's in there
+    fn offset_of(&self, ptr: NonNull<u8>) -> Option<usize> {
+        let offset = (ptr.as_ptr() as usize).wrapping_sub(self.block.as_ptr() as usize);
+        (offset < self.capacity).then_some(offset)
+    }
+
+    fn backing_allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.overflows.set(self.overflows.get() + 1);
+        if self.linear { LinearAllocator.allocate(layout) } else { Global.allocate(layout) }
+    }
+}
+
+impl Drop for FrameArena {
+    fn drop(&mut self) {
+        let layout = Layout::from_size_align(self.capacity.max(1), BLOCK_ALIGN).unwrap();
+        unsafe {
+            if self.linear {
+                LinearAllocator.deallocate(self.block, layout);
+            } else {
+                Global.deallocate(self.block, layout);
+            }
+        }
+    }
+}
+
+unsafe impl Allocator for &FrameArena {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let base = self.block.as_ptr() as usize;
+        let start = (base + self.used.get()).next_multiple_of(layout.align()) - base;
+        let end = start.checked_add(layout.size()).ok_or(AllocError)?;
+        if end > self.capacity {
+            return self.backing_allocate(layout);
+        }
+
+        self.used.set(end);
+        self.peak.set(self.peak.get().max(end));
+        let ptr = unsafe { NonNull::new_unchecked(self.block.as_ptr().add(start)) };
+        Ok(NonNull::slice_from_raw_parts(ptr, layout.size()))
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        if layout.size() == 0 {
+            return;
+        }
+
+        match self.offset_of(ptr) {
+            // the last allocation can be taken back straight away, which is what happens to a
+            // scratch Vec that's dropped before anything else is allocated
+            Some(offset) => if offset + layout.size() == self.used.get() {
+                self.used.set(offset);
+            },
+            None => unsafe {
+                if self.linear {
+                    LinearAllocator.deallocate(ptr, layout);
+                } else {
+                    Global.deallocate(ptr, layout);
+                }
+            },
+        }
+    }
+
+    unsafe fn grow(&self, ptr: NonNull<u8>, old_layout: Layout, new_layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        // the last allocation can grow where it is, so a Vec being pushed to doesn't leave copies
+        // of itself all over the block
+        if let Some(offset) = self.offset_of(ptr).filter(|_| old_layout.size() > 0)
+            && offset + old_layout.size() == self.used.get()
+            && (ptr.as_ptr() as usize).is_multiple_of(new_layout.align())
+            && offset + new_layout.size() <= self.capacity
+        {
+            self.used.set(offset + new_layout.size());
+            self.peak.set(self.peak.get().max(offset + new_layout.size()));
+            return Ok(NonNull::slice_from_raw_parts(ptr, new_layout.size()));
+        }
+
+        let new = self.allocate(new_layout)?;
+        unsafe {
+            ptr::copy_nonoverlapping(ptr.as_ptr(), new.cast::<u8>().as_ptr(), old_layout.size());
+            self.deallocate(ptr, old_layout);
+        }
+        Ok(new)
+    }
+}
@@ -0,0 +1,75 @@
+// plays a frame capture (see Renderer::capture_frame) back, asking for the same draws every frame,
+// so a renderer change can be timed and looked at against exactly the same work:
+//
+//     let capture = FrameCapture::read(BufReader::new(File::open("sdmc:/mm3ds/frame.txt")?))?;
+//     let replay = FrameReplay::new(&mut renderer, &capture);
+//     while apt.main_loop() {
+//         replay.please_render(&mut renderer);
+//         renderer.render();
+//     }
+//
+// meshes come back with their vertices, indices and color, but not their textures, which captures
+// don't keep, and decals come back as plain meshes. the clear color and perspective are put back
+// from the capture's state; the rest of it is just for reading
+use std::collections::HashMap;
+
+use citro3d::math::ClipPlanes;
+use glam::{Mat4, Vec4};
+use mm3ds_format::FrameCapture;
+
+use crate::camera::Camera;
+use crate::command_buffer::CommandBuffer;
+use crate::material::Material;
+use crate::math::to_matrix4;
+use crate::mesh::Mesh;
+use crate::renderer::Renderer;
+
+pub struct FrameReplay {
+    camera: Camera,
+    commands: CommandBuffer,
+}
+
+impl FrameReplay {
+    // registers the capture's meshes with `renderer`, so it's only done once
+    pub fn new(renderer: &mut Renderer, capture: &FrameCapture) -> Self {
+        let mut meshes = HashMap::new();
+        for mesh in &capture.meshes {
+            let material = Material { diffuse: Vec4::from(mesh.color).into(), ..Default::default() };
+            let indices = (!mesh.indices.is_empty()).then_some(&mesh.indices[..]);
+            meshes.insert(mesh.id, renderer.register_mesh(Mesh::from_data(&mesh.vertices, indices, None, material)));
+        }
+
+        // FrameCapture::read makes sure every draw's mesh is there
+        let mut commands = CommandBuffer::new();
+        for draw in &capture.draws {
+            let (mesh_id, model) = (meshes[&draw.mesh], to_matrix4(Mat4::from_cols_array(&draw.model)));
+            if draw.state.iter().any(|(name, value)| name == "kind" && value == "batched") {
+                commands.please_render_batched(mesh_id, model);
+            } else {
+                commands.please_render(mesh_id, model);
+            }
+        }
+
+        let state = |name: &str| capture.state.iter().find(|(n, _)| n == name).map(|(_, value)| value.as_str());
+        if let Some(color) = state("clear_color").and_then(|color| u32::from_str_radix(color, 16).ok()) {
+            renderer.set_clear_color(color);
+        }
+        let fov_y = state("fov_y").and_then(|fov_y| fov_y.parse::<f32>().ok());
+        let clip_planes = state("clip_planes").and_then(|planes| {
+            let (near, far) = planes.split_once(' ')?;
+            Some(ClipPlanes { near: near.parse().ok()?, far: far.parse().ok()? })
+        });
+        if let (Some(fov_y), Some(clip_planes)) = (fov_y, clip_planes) {
+            renderer.set_perspective(fov_y.to_radians(), clip_planes);
+        }
+
+        let (_, rotation, position) = Mat4::from_cols_array(&capture.view).inverse().to_scale_rotation_translation();
+        Self { camera: Camera { position, rotation }, commands }
+    }
+
+    // the captured camera and draws, for this frame
+    pub fn please_render(&self, renderer: &mut Renderer) {
+        renderer.set_camera(&self.camera);
+        renderer.submit(&self.commands);
+    }
+}
@@ -0,0 +1,121 @@
+// builds each frame's requests on another core while the main thread has the renderer draw the
+// frame before, so game logic, animation and the game's own culling overlap the renderer's work
+// instead of coming before it:
+//
+//     let mut worker = FrameWorker::spawn(input.circle_pad(), move |stick, commands| {
+//         game.update(stick);
+//         for thing in &game.things {
+//             commands.please_render(thing.mesh, thing.model());
+//         }
+//     }).unwrap();
+//     while apt.main_loop() {
+//         input.update();
+//         let commands = worker.next_frame(input.circle_pad());
+//         renderer.submit(commands);
+//         renderer.render();
+//     }
+//
+// there are two command buffers: the worker fills one while the other is submitted, and they swap
+// in next_frame. so what's drawn is a frame behind the input the worker was last sent, and the
+// worker only sees what it's sent (or shares itself), never the renderer, which stays on the main
+// thread. it runs on the New 3DS's third core, or on the old one's second, which the system only
+// lends out for WORKER_CPU_LIMIT percent of the time
+use std::ffi::c_void;
+use std::sync::mpsc::{Receiver, Sender, channel};
+
+use crate::command_buffer::CommandBuffer;
+
+const STACK_SIZE: usize = 256 * 1024;
+// of the old 3DS's second core, see APT_SetAppCpuTimeLimit
+pub const WORKER_CPU_LIMIT: u32 = 30;
+
+// a libctru thread, since std's threads can't be put on a core
+struct Thread(ctru_sys::Thread);
+
+type Job = Box<dyn FnOnce() + Send>;
+
+extern "C" fn run(arg: *mut c_void) {
+    let job = unsafe { Box::from_raw(arg.cast::<Job>()) };
+    job();
+}
+
+fn spawn_on(core: i32, job: Job) -> Result<Thread, Job> {
+    let mut priority = 0;
+    unsafe { ctru_sys::svcGetThreadPriority(&mut priority, ctru_sys::CUR_THREAD_HANDLE); }
+
+    let arg = Box::into_raw(Box::new(job));
+    // the same priority as the main thread, so neither starves the other on a shared core
+    let thread = unsafe { ctru_sys::threadCreate(Some(run), arg.cast(), STACK_SIZE, priority, core, false) };
+    if thread.is_null() {
+        return Err(*unsafe { Box::from_raw(arg) });
+    }
+    Ok(Thread(thread))
+}
+
+// where the worker goes: the New 3DS's spare core, or the old 3DS's system core
+fn worker_core() -> i32 {
+    let mut new_3ds = false;
+    unsafe { ctru_sys::APT_CheckNew3DS(&mut new_3ds); }
+    if new_3ds {
+        return 2;
+    }
+
+    unsafe { ctru_sys::APT_SetAppCpuTimeLimit(WORKER_CPU_LIMIT); }
+    1
+}
+
+pub struct FrameWorker<T> {
+    to_worker: Option<Sender<(CommandBuffer, T)>>, // None once it's being dropped
+    from_worker: Receiver<CommandBuffer>,
+    current: CommandBuffer, // what next_frame last gave out
+    thread: Thread,
+}
+
+impl<T: Send + 'static> FrameWorker<T> {
+    // starts building the first frame from `first` right away. `build` gets each input it's sent,
+    // and a cleared buffer to record the frame in. None if there's no thread to be had
+    pub fn spawn(first: T, mut build: impl FnMut(T, &mut CommandBuffer) + Send + 'static) -> Option<Self> {
+        let (to_worker, jobs) = channel::<(CommandBuffer, T)>();
+        let (done, from_worker) = channel();
+        let job: Job = Box::new(move || {
+            // until the FrameWorker's dropped
+            while let Ok((mut commands, input)) = jobs.recv() {
+                build(input, &mut commands);
+                if done.send(commands).is_err() {
+                    break;
+                }
+            }
+        });
+
+        // on the main thread's core if the other one can't be had, which still works but doesn't
+        // overlap anything
+        let thread = spawn_on(worker_core(), job).or_else(|job| spawn_on(-2, job)).ok()?;
+        to_worker.send((CommandBuffer::new(), first)).ok()?;
+        Some(Self { to_worker: Some(to_worker), from_worker, current: CommandBuffer::new(), thread })
+    }
+
+    // waits for the frame being built, and starts the next one from `input`
+    pub fn next_frame(&mut self, input: T) -> &CommandBuffer {
+        // a panic in `build` can't unwind out of the thread, so it takes the app down with it and
+        // the worker's always there
+        let built = self.from_worker.recv().unwrap();
+        let mut done = std::mem::replace(&mut self.current, built);
+        done.clear();
+        if let Some(to_worker) = &self.to_worker {
+            to_worker.send((done, input)).unwrap();
+        }
+
+        &self.current
+    }
+}
+
+impl<T> Drop for FrameWorker<T> {
+    fn drop(&mut self) {
+        // the worker finishes what it's building, finds nothing more coming and returns
+        self.to_worker = None;
+        unsafe {
+            ctru_sys::threadJoin(self.thread.0, u64::MAX);
+            ctru_sys::threadFree(self.thread.0);
+        }
+    }
+}
@@ -0,0 +1,252 @@
+use std::ptr;
+
+use ctru::services::hid::{Hid, KeyPad};
+use glam::{Vec2, Vec3};
+
+// the circle pad reports roughly -156..=156 on each axis
+const CIRCLE_PAD_MAX: f32 = 156.;
+// and the circle pad pro's stick (or the new 3ds's c-stick, which pretends to be one) roughly
+// -146..=146
+const C_STICK_MAX: f32 = 146.;
+// how many frames the circle pad pro can go without a report before it counts as unplugged. it
+// reports a few times a frame while it's there
+const CIRCLE_PAD_PRO_TIMEOUT: u32 = 30;
+// bits in a KeyPad
+const KEYS: usize = 32;
+
+fn check(res: ctru_sys::Result) -> ctru::Result<()> {
+    if ctru_sys::R_FAILED(res) {
+        Err(res.into())
+    } else {
+        Ok(())
+    }
+}
+
+// what came over infrared from the circle pad pro, through the ir:rst service
+struct CirclePadPro {
+    last_tick: u64, // of the latest report in shared memory
+    quiet_frames: u32, // since it changed
+    held: KeyPad, // ZL, ZR and the c-stick directions
+    previous: KeyPad, // held, the frame before
+    stick: Vec2,
+}
+
+impl CirclePadPro {
+    fn is_connected(&self) -> bool {
+        self.quiet_frames < CIRCLE_PAD_PRO_TIMEOUT
+    }
+}
+
+// key repeat, see Input::repeated. in frames
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Repeat {
+    pub delay: u32, // from going down to the first repeat
+    pub rate: u32, // between repeats after that
+}
+
+impl Default for Repeat {
+    fn default() -> Self {
+        Self { delay: 24, rate: 6 }
+    }
+}
+
+// the bits of `keys`, as indices into Input's per-key arrays
+fn key_bits(keys: KeyPad) -> impl Iterator<Item = usize> {
+    (0..KEYS).filter(move |bit| keys.bits() & 1 << bit != 0)
+}
+
+pub struct Input {
+    hid: Hid,
+    gyro_scale: Option<f32>, // raw gyro units to degrees per second, once it's enabled
+    circle_pad_pro: Option<CirclePadPro>, // once it's enabled
+    held_for: [u32; KEYS], // frames each key has been down, 0 while it's up
+    since_press: [u32; KEYS], // frames since each key last went down, u32::MAX if it hasn't
+    repeat: Repeat,
+}
+
+impl Input {
+    pub fn new() -> ctru::Result<Self> {
+        Ok(Self {
+            hid: Hid::new()?,
+            gyro_scale: None,
+            circle_pad_pro: None,
+            held_for: [0; KEYS],
+            since_press: [u32::MAX; KEYS],
+            repeat: Repeat::default(),
+        })
+    }
+
+    // starts listening for a circle pad pro, which can be plugged in and out whenever. its buttons
+    // (ZL and ZR) then show up in just_pressed, held and just_released like any others, and its
+    // stick in c_stick. a new 3ds's own c-stick, ZL and ZR come through the same way, and count as
+    // one that's always plugged in
+    pub fn enable_circle_pad_pro(&mut self) -> ctru::Result<()> {
+        if self.circle_pad_pro.is_some() {
+            return Ok(());
+        }
+
+        check(unsafe { ctru_sys::irrstInit() })?;
+        self.circle_pad_pro = Some(CirclePadPro {
+            last_tick: 0,
+            quiet_frames: CIRCLE_PAD_PRO_TIMEOUT, // until it says something
+            held: KeyPad::empty(),
+            previous: KeyPad::empty(),
+            stick: Vec2::ZERO,
+        });
+
+        Ok(())
+    }
+
+    pub fn is_circle_pad_pro_connected(&self) -> bool {
+        self.circle_pad_pro.as_ref().is_some_and(CirclePadPro::is_connected)
+    }
+
+    // the circle pad pro's stick, -1..=1 on each axis with +y up. zero while there isn't one
+    pub fn c_stick(&self) -> Vec2 {
+        self.circle_pad_pro.as_ref().map_or(Vec2::ZERO, |pro| pro.stick)
+    }
+
+    // the gyroscope is off until this is called, since it costs battery
+    pub fn enable_gyro(&mut self) -> ctru::Result<()> {
+        if self.gyro_scale.is_some() {
+            return Ok(());
+        }
+
+        let mut scale = 0.;
+        check(unsafe { ctru_sys::HIDUSER_EnableGyroscope() })?;
+        check(unsafe { ctru_sys::HIDUSER_GetGyroscopeRawToDpsCoefficient(&mut scale) })?;
+        self.gyro_scale = Some(scale);
+
+        Ok(())
+    }
+
+    // how fast the console is turning, in radians per second around its own axes: x is tilting
+    // the top away or towards you, y is turning left and right, z is rolling. None until
+    // enable_gyro
+    pub fn gyro(&self) -> Option<Vec3> {
+        let scale = self.gyro_scale?;
+        let mut rate = unsafe { std::mem::zeroed::<ctru_sys::angularRate>() };
+        unsafe { ctru_sys::hidGyroRead(&mut rate); }
+
+        Some(Vec3::new(rate.x as f32, rate.y as f32, rate.z as f32) * scale.to_radians())
+    }
+
+    // call once per frame, before querying anything
+    pub fn update(&mut self) {
+        self.hid.scan_input();
+
+        if let Some(pro) = &mut self.circle_pad_pro {
+            unsafe { ctru_sys::irrstScanInput(); }
+            // shared memory starts with the tick of the latest report, which only moves while
+            // something's sending them
+            let shared = unsafe { ctru_sys::irrstSharedMem };
+            let tick = if shared.is_null() { 0 } else { unsafe { ptr::read_volatile(shared as *const u64) } };
+            if tick != pro.last_tick {
+                pro.last_tick = tick;
+                pro.quiet_frames = 0;
+            } else {
+                pro.quiet_frames = pro.quiet_frames.saturating_add(1);
+            }
+
+            pro.previous = pro.held;
+            if pro.is_connected() {
+                let mut position = ctru_sys::circlePosition { dx: 0, dy: 0 };
+                unsafe { ctru_sys::irrstCstickRead(&mut position); }
+                pro.held = KeyPad::from_bits_truncate(unsafe { ctru_sys::irrstKeysHeld() });
+                pro.stick = (Vec2::new(position.dx as f32, position.dy as f32) / C_STICK_MAX).clamp(Vec2::NEG_ONE, Vec2::ONE);
+            } else {
+                pro.held = KeyPad::empty();
+                pro.stick = Vec2::ZERO;
+            }
+        }
+
+        let (pro_held, pro_previous) = self.circle_pad_pro_keys();
+        let held = (self.hid.keys_held() | pro_held).bits();
+        let down = (self.hid.keys_down() | (pro_held - pro_previous)).bits();
+        for bit in 0..KEYS {
+            self.held_for[bit] = if held & 1 << bit != 0 { self.held_for[bit].saturating_add(1) } else { 0 };
+            self.since_press[bit] = if down & 1 << bit != 0 { 0 } else { self.since_press[bit].saturating_add(1) };
+        }
+    }
+
+    // the keys from the circle pad pro, now and the frame before
+    fn circle_pad_pro_keys(&self) -> (KeyPad, KeyPad) {
+        self.circle_pad_pro.as_ref().map_or((KeyPad::empty(), KeyPad::empty()), |pro| (pro.held, pro.previous))
+    }
+
+    // true if every key in `keys` went down this frame
+    pub fn just_pressed(&self, keys: KeyPad) -> bool {
+        let (held, previous) = self.circle_pad_pro_keys();
+        (self.hid.keys_down() | (held - previous)).contains(keys)
+    }
+
+    pub fn held(&self, keys: KeyPad) -> bool {
+        (self.hid.keys_held() | self.circle_pad_pro_keys().0).contains(keys)
+    }
+
+    pub fn just_released(&self, keys: KeyPad) -> bool {
+        let (held, previous) = self.circle_pad_pro_keys();
+        (self.hid.keys_up() | (previous - held)).contains(keys)
+    }
+
+    // how many frames every key in `keys` has been held, counting this one: 1 the frame the last of
+    // them went down, and 0 while any of them is up
+    pub fn held_frames(&self, keys: KeyPad) -> u32 {
+        key_bits(keys).map(|bit| self.held_for[bit]).min().unwrap_or(0)
+    }
+
+    // true if every key in `keys` went down in the last `frames` frames, counting this one, so a
+    // press made a little early (during a menu's animation, or the last move of a combo) still
+    // counts. pressed_within(keys, 1) is just_pressed. see consume
+    pub fn pressed_within(&self, keys: KeyPad, frames: u32) -> bool {
+        !keys.is_empty() && key_bits(keys).all(|bit| self.since_press[bit] < frames)
+    }
+
+    // forgets that `keys` were pressed, so pressed_within doesn't see the same press twice
+    pub fn consume(&mut self, keys: KeyPad) {
+        for bit in key_bits(keys) {
+            self.since_press[bit] = u32::MAX;
+        }
+    }
+
+    pub fn set_repeat(&mut self, repeat: Repeat) {
+        self.repeat = repeat;
+    }
+
+    // true the frame `keys` go down, and then, once they've been held for the repeat's delay, every
+    // rate frames after, like a keyboard's. for moving through menus
+    pub fn repeated(&self, keys: KeyPad) -> bool {
+        let Repeat { delay, rate } = self.repeat;
+        match self.held_frames(keys) {
+            0 => false,
+            1 => true,
+            held => held > delay && (held - 1 - delay) % rate.max(1) == 0,
+        }
+    }
+
+    // -1..=1 on each axis, +y is up
+    pub fn circle_pad(&self) -> Vec2 {
+        let (x, y) = self.hid.circlepad_position();
+        (Vec2::new(x as f32, y as f32) / CIRCLE_PAD_MAX).clamp(Vec2::NEG_ONE, Vec2::ONE)
+    }
+
+    // in bottom screen pixels, while the stylus is down
+    pub fn touch(&self) -> Option<(u16, u16)> {
+        self.held(KeyPad::TOUCH).then(|| self.hid.touch_position())
+    }
+
+    pub fn hid(&self) -> &Hid {
+        &self.hid
+    }
+}
+
+impl Drop for Input {
+    fn drop(&mut self) {
+        if self.gyro_scale.is_some() {
+            unsafe { ctru_sys::HIDUSER_DisableGyroscope(); }
+        }
+        if self.circle_pad_pro.is_some() {
+            unsafe { ctru_sys::irrstExit(); }
+        }
+    }
+}
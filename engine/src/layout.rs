@@ -0,0 +1,135 @@
+// placing 2d things (widgets, nine-patches, HUD pieces) relative to the screen or to each other
+// instead of at fixed pixels. a Layout pins a point of the element (its pivot) to a point of the
+// area it's in (its anchor), then nudges it by an offset, and sizes it in pixels or as a
+// percentage of the area:
+//
+//     let screen = safe_area(TargetScreen::Top, DEFAULT_SAFE_MARGIN);
+//     let health = Layout { anchor: Anchor::TopLeft, width: Length::Pixels(96.), height: Length::Pixels(16.), ..Default::default() };
+//     let bar = health.resolve(screen);
+//
+// areas nest: resolve a panel against the screen, then its contents against the panel. layouts
+// can come from TOML too, with sizes as numbers of pixels or strings of percentages:
+//
+//     anchor = "bottom_right"
+//     offset = [-4.0, 4.0]
+//     width = "30%"
+//     height = 24
+//
+// the 2d layer is measured in the same pixels whatever mode the screen's in, so a layout that
+// fits the top screen in 400x240 also fits it in 800px wide mode
+use glam::Vec2;
+use serde::Deserialize;
+
+use crate::renderer::TargetScreen;
+use crate::widgets::Rect;
+
+// in pixels, how far in from the edges to keep things so that they don't end up right against
+// the bezel, where they're hard to see
+pub const DEFAULT_SAFE_MARGIN: f32 = 8.;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    #[default]
+    Centre,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    // where it is in a rectangle, from (0, 0) at the bottom left to (1, 1) at the top right
+    pub fn point(self) -> Vec2 {
+        match self {
+            Anchor::TopLeft => Vec2::new(0., 1.),
+            Anchor::Top => Vec2::new(0.5, 1.),
+            Anchor::TopRight => Vec2::new(1., 1.),
+            Anchor::Left => Vec2::new(0., 0.5),
+            Anchor::Centre => Vec2::new(0.5, 0.5),
+            Anchor::Right => Vec2::new(1., 0.5),
+            Anchor::BottomLeft => Vec2::new(0., 0.),
+            Anchor::Bottom => Vec2::new(0.5, 0.),
+            Anchor::BottomRight => Vec2::new(1., 0.),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize)]
+#[serde(try_from = "LengthDef")]
+pub enum Length {
+    Pixels(f32),
+    Percent(f32), // of the area's width or height
+}
+
+impl Default for Length {
+    fn default() -> Self {
+        Length::Percent(100.)
+    }
+}
+
+impl Length {
+    fn resolve(self, of: f32) -> f32 {
+        match self {
+            Length::Pixels(pixels) => pixels,
+            Length::Percent(percent) => of * percent / 100.,
+        }
+    }
+}
+
+// what a Length looks like in TOML: 24 or "30%"
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LengthDef {
+    Pixels(f32),
+    Text(String),
+}
+
+impl TryFrom<LengthDef> for Length {
+    type Error = String;
+
+    fn try_from(def: LengthDef) -> Result<Self, String> {
+        match def {
+            LengthDef::Pixels(pixels) => Ok(Length::Pixels(pixels)),
+            LengthDef::Text(text) => text.trim().strip_suffix('%')
+                .and_then(|percent| percent.trim().parse().ok())
+                .map(Length::Percent)
+                .ok_or_else(|| format!("{text:?} isn't a number of pixels or a percentage")),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Layout {
+    pub anchor: Anchor,
+    // the point of the element that goes on the anchor, from (0, 0) at its bottom left to (1, 1)
+    // at its top right. None puts the same point as the anchor there, so an element anchored to
+    // the top right corner sits inside it
+    pub pivot: Option<[f32; 2]>,
+    pub offset: [f32; 2], // in pixels, +y up
+    pub width: Length,
+    pub height: Length,
+}
+
+impl Layout {
+    pub fn resolve(&self, area: Rect) -> Rect {
+        let size = Vec2::new(self.width.resolve(area.size.x), self.height.resolve(area.size.y));
+        let anchor = area.min + area.size * self.anchor.point();
+        let pivot = self.pivot.map_or(self.anchor.point(), Vec2::from);
+
+        Rect { min: anchor + Vec2::from(self.offset) - size * pivot, size }
+    }
+}
+
+// the whole of `screen` in 2d layer pixels, less `margin` on every side
+pub fn safe_area(screen: TargetScreen, margin: f32) -> Rect {
+    let size = screen.size();
+    let margin = margin.clamp(0., size.min_element() / 2.);
+
+    Rect { min: Vec2::splat(margin), size: size - 2. * margin }
+}
@@ -0,0 +1,63 @@
+#![feature(allocator_api)]
+pub mod alloc_tracker;
+pub mod animation;
+pub mod animator;
+pub mod assets;
+pub mod backlight;
+pub mod batch;
+pub mod bloom;
+pub mod camera;
+pub mod camera_feed;
+pub mod command_buffer;
+pub mod config;
+pub mod cubemap;
+pub mod debug_camera;
+pub mod debug_console;
+pub mod decal;
+pub mod dither;
+pub mod download;
+pub mod events;
+pub mod follow_camera;
+pub mod fps_camera;
+pub mod frame_arena;
+pub mod frame_replay;
+pub mod frame_worker;
+pub mod input;
+pub mod layout;
+pub mod light;
+pub mod linear_pool;
+pub mod localization;
+pub mod material;
+pub mod material_animation;
+pub mod math;
+pub mod mesh;
+pub mod model;
+pub mod multiplayer;
+pub mod nfc;
+pub mod nine_patch;
+pub mod occlusion;
+pub mod orbit_camera;
+pub mod overlay;
+pub mod pack;
+pub mod power;
+pub mod profiler;
+pub mod qr;
+pub mod remote;
+pub mod render_graph;
+pub mod render_texture;
+pub mod renderer;
+pub mod scheduler;
+pub mod shader;
+pub mod shader_reload;
+pub mod skeleton;
+pub mod tasks;
+#[cfg(feature = "telemetry")]
+pub mod telemetry;
+pub mod texture_cache;
+pub mod tilemap;
+pub mod time;
+pub mod transition;
+pub mod tweaks;
+pub mod tween;
+pub mod water;
+pub mod widgets;
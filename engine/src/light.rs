@@ -0,0 +1,76 @@
+// what lights the scene: the sun, lamps, a torch the player's carrying. the shaders light each
+// vertex with a single light, so the lights that reach a mesh are put together into one for it,
+// coming from wherever most of its light does:
+//
+//     renderer.set_lights(&[
+//         Light::directional(Vec3::new(-1., -2., -1.), Vec3::new(1., 0.95, 0.8)),
+//         Light::point(torch, Vec3::new(1., 0.6, 0.2), 6.),
+//     ]);
+//
+// a point light is worked out at the middle of each mesh, so a big one is lit evenly rather than
+// brightest nearest the light. without any lights the renderer lights everything the way it always
+// has, all in white
+use glam::{Mat4, Vec3, Vec4};
+
+use crate::renderer::LUMINANCE;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum LightKind {
+    Directional { direction: Vec3 }, // the way it shines, in the world
+    Point { position: Vec3, range: f32 }, // fading out to nothing `range` away
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Light {
+    pub kind: LightKind,
+    pub color: Vec3, // over 1 for a brighter light
+}
+
+// lightVec, lightHalfVec and lightClr for everything, with no lights set
+pub(crate) const UNLIT: [Vec4; 3] = [Vec4::Z, Vec4::Z, Vec4::ONE];
+
+impl Light {
+    pub fn directional(direction: Vec3, color: Vec3) -> Self {
+        Self { kind: LightKind::Directional { direction }, color }
+    }
+
+    pub fn point(position: Vec3, color: Vec3, range: f32) -> Self {
+        Self { kind: LightKind::Point { position, range }, color }
+    }
+
+    // the way it shines at `at`, and how much of it gets there. None if none of it does
+    fn at(&self, at: Vec3) -> Option<(Vec3, Vec3)> {
+        match self.kind {
+            LightKind::Directional { direction } => Some((direction.normalize_or(Vec3::NEG_Y), self.color)),
+            LightKind::Point { position, range } => {
+                let offset = at - position;
+                let distance = offset.length();
+                if distance >= range {
+                    return None;
+                }
+
+                let falloff = 1. - distance / range;
+                Some((offset.normalize_or(Vec3::NEG_Y), self.color * falloff * falloff))
+            }
+        }
+    }
+}
+
+// lightVec, lightHalfVec and lightClr for a mesh whose middle is at `center`, as the shaders have
+// them: in view space, pointing the way the light goes
+pub(crate) fn uniforms(lights: &[Light], center: Vec3, view: Mat4) -> [Vec4; 3] {
+    let mut direction = Vec3::ZERO;
+    let mut color = Vec3::ZERO;
+    for (towards, amount) in lights.iter().filter_map(|light| light.at(center)) {
+        // the brighter ones count for more of where it comes from
+        direction += towards * amount.dot(Vec3::from(LUMINANCE));
+        color += amount;
+    }
+    let direction = view.transform_vector3(direction).normalize_or(Vec3::Z);
+
+    // halfway between the ways to the light and to the camera, which is at the origin
+    let to_eye = (-view.transform_point3(center)).normalize_or(Vec3::Z);
+    let half = (to_eye - direction).normalize_or(to_eye);
+
+    [direction.extend(0.), (-half).extend(0.), color.extend(1.)]
+}
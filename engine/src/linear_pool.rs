@@ -0,0 +1,289 @@
+// where meshes keep their vertex buffers and small textures. the linear heap hands every
+// allocation its own piece of itself, so after a while of loading and throwing things away (and of
+// the renderer rebuilding its dynamic batches every frame) it's full of holes too small for anything.
+// the pool asks it for big slabs instead, and cuts each into blocks of one size class, so a freed
+// block is always exactly the right size for the next thing of that class:
+//
+//     let mut vertices = Vec::with_capacity_in(n, LinearPool);
+//
+// anything bigger than the largest class goes to the linear heap as before. on a load screen,
+// Renderer::compact_meshes moves what's left down into the lowest free blocks, so the slabs above
+// them empty out and go back to the linear heap (see trim)
+//
+// running out of linear memory is an OutOfMemory from the try_ constructors of Mesh, saying how
+// much it wanted and how much there was, so a game can load something smaller or say what's wrong
+use std::alloc::{AllocError, Allocator, Layout};
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::ptr;
+use std::ptr::NonNull;
+use std::sync::Mutex;
+
+use citro3d::sys;
+use ctru::linear::LinearAllocator;
+
+use crate::alloc_tracker;
+
+// block sizes, smallest first
+const CLASSES: [usize; 6] = [256, 1 << 10, 4 << 10, 16 << 10, 64 << 10, 128 << 10];
+const SLAB_SIZE: usize = 512 << 10;
+// slabs are aligned to this, and so every block is aligned to its size, up to this
+const SLAB_ALIGN: usize = 4096;
+// what the GPU wants texture data aligned to
+const TEXTURE_ALIGN: usize = 0x80;
+
+fn slab_layout() -> Layout {
+    Layout::from_size_align(SLAB_SIZE, SLAB_ALIGN).unwrap()
+}
+
+fn class_of(layout: Layout) -> Option<usize> {
+    CLASSES.iter().position(|&size| size >= layout.size() && size.min(SLAB_ALIGN) >= layout.align())
+}
+
+fn to_ptr(addr: usize) -> NonNull<u8> {
+    NonNull::new(ptr::with_exposed_provenance_mut(addr)).unwrap()
+}
+
+struct Slab {
+    class: usize, // into CLASSES
+    base: usize,
+    free: Vec<u16>, // the blocks nothing's in, highest first so that pop() takes the lowest
+}
+
+impl Slab {
+    fn blocks(&self) -> usize {
+        SLAB_SIZE / CLASSES[self.class]
+    }
+
+    fn is_empty(&self) -> bool {
+        self.free.len() == self.blocks()
+    }
+
+    fn lowest_free(&self) -> Option<usize> {
+        self.free.last().map(|&block| self.base + block as usize * CLASSES[self.class])
+    }
+}
+
+struct Pool {
+    slabs: Vec<Slab>, // by address
+}
+
+static POOL: Mutex<Pool> = Mutex::new(Pool { slabs: Vec::new() });
+
+impl Pool {
+    // the lowest free block of `class`, in a new slab if none have room
+    fn allocate(&mut self, class: usize) -> Option<usize> {
+        let i = match self.slabs.iter().position(|slab| slab.class == class && !slab.free.is_empty()) {
+            Some(i) => i,
+            None => {
+                let base = LinearAllocator.allocate(slab_layout()).ok()?.cast::<u8>().as_ptr().expose_provenance();
+                let blocks = SLAB_SIZE / CLASSES[class];
+                let i = self.slabs.partition_point(|slab| slab.base < base);
+                self.slabs.insert(i, Slab { class, base, free: (0..blocks as u16).rev().collect() });
+                i
+            }
+        };
+
+        let slab = &mut self.slabs[i];
+        let block = slab.free.pop().unwrap() as usize;
+        Some(slab.base + block * CLASSES[class])
+    }
+
+    // false if `addr` isn't in any slab
+    fn deallocate(&mut self, addr: usize) -> bool {
+        let i = self.slabs.partition_point(|slab| slab.base <= addr);
+        let Some(slab) = i.checked_sub(1).map(|i| &mut self.slabs[i]).filter(|slab| addr < slab.base + SLAB_SIZE) else {
+            return false;
+        };
+
+        let block = ((addr - slab.base) / CLASSES[slab.class]) as u16;
+        let at = slab.free.partition_point(|&free| free > block);
+        slab.free.insert(at, block);
+        true
+    }
+}
+
+// allocates from the pool. it's a handle to one pool that everything shares, so it's free to copy
+#[derive(Copy, Clone, Debug, Default)]
+pub struct LinearPool;
+
+unsafe impl Allocator for LinearPool {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let ret = allocate(layout);
+        if let (Ok(block), true) = (ret, layout.size() > 0) {
+            alloc_tracker::linear_allocated(block.cast::<u8>().as_ptr().expose_provenance(), layout.size());
+        }
+        ret
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        if layout.size() == 0 {
+            return;
+        }
+
+        alloc_tracker::linear_freed(ptr.as_ptr().expose_provenance());
+        if class_of(layout).is_none() || !POOL.lock().unwrap().deallocate(ptr.as_ptr().expose_provenance()) {
+            unsafe { LinearAllocator.deallocate(ptr, layout); }
+        }
+    }
+}
+
+// the pool, or the linear heap for what the pool can't take
+fn allocate(layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+    if layout.size() == 0 {
+        let dangling = NonNull::new(ptr::without_provenance_mut(layout.align())).unwrap();
+        return Ok(NonNull::slice_from_raw_parts(dangling, 0));
+    }
+
+    let Some(class) = class_of(layout) else {
+        return LinearAllocator.allocate(layout);
+    };
+    match POOL.lock().unwrap().allocate(class) {
+        Some(addr) => Ok(NonNull::slice_from_raw_parts(to_ptr(addr), layout.size())),
+        // there might still be room for the block on its own, if not for a whole slab
+        None => LinearAllocator.allocate(layout),
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct OutOfMemory {
+    pub what: &'static str, // e.g. "vertex buffer"
+    pub requested: usize, // bytes
+    pub free: usize, // all of the linear heap that was free at the time
+    pub largest_block: usize, // and the most of it in one piece, which is what could have been had
+}
+
+impl OutOfMemory {
+    pub(crate) fn new(what: &'static str, requested: usize) -> Self {
+        Self {
+            what,
+            requested,
+            free: unsafe { ctru_sys::linearSpaceFree() } as usize,
+            largest_block: largest_free_block(),
+        }
+    }
+}
+
+impl fmt::Display for OutOfMemory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f, "out of linear memory for a {}: asked for {} bytes, with {} free and at most {} in one block",
+            self.what, self.requested, self.free, self.largest_block,
+        )
+    }
+}
+
+impl Error for OutOfMemory {}
+
+impl From<OutOfMemory> for io::Error {
+    fn from(e: OutOfMemory) -> Self {
+        io::Error::new(io::ErrorKind::OutOfMemory, e)
+    }
+}
+
+// the biggest allocation the linear heap can make right now, to the nearest 4KiB. it finds out by
+// trying, so it's only for when something's already gone wrong
+pub fn largest_free_block() -> usize {
+    const STEP: usize = 4096;
+    let (mut lo, mut hi) = (0, unsafe { ctru_sys::linearSpaceFree() } as usize / STEP);
+    while lo < hi {
+        let mid = (lo + hi).div_ceil(2);
+        let block = unsafe { ctru_sys::linearAlloc(mid * STEP) };
+        if block.is_null() {
+            hi = mid - 1;
+        } else {
+            unsafe { ctru_sys::linearFree(block); }
+            lo = mid;
+        }
+    }
+
+    lo * STEP
+}
+
+// `data` in the pool
+pub(crate) fn try_copy<T: Copy>(what: &'static str, data: &[T]) -> Result<Vec<T, LinearPool>, OutOfMemory> {
+    let mut ret = Vec::new_in(LinearPool);
+    ret.try_reserve_exact(data.len()).map_err(|_| OutOfMemory::new(what, size_of_val(data)))?;
+    ret.extend_from_slice(data);
+    Ok(ret)
+}
+
+// whether something `layout` big at `ptr` would end up lower down if it were allocated again
+pub(crate) fn has_lower_block(ptr: *const u8, layout: Layout) -> bool {
+    let Some(class) = class_of(layout).filter(|_| layout.size() > 0) else {
+        return false;
+    };
+
+    let addr = ptr.expose_provenance();
+    POOL.lock().unwrap().slabs.iter()
+        .filter(|slab| slab.class == class)
+        .any(|slab| slab.lowest_free().is_some_and(|free| free < addr))
+}
+
+// moves a texture's data (from load_t3x) into the pool, if it's small enough. free it with
+// delete_texture rather than C3D_TexDelete after this
+pub(crate) fn pool_texture(texture: &mut sys::C3D_Tex) {
+    let layout = Layout::from_size_align(texture.size as usize, TEXTURE_ALIGN).unwrap();
+    if class_of(layout).is_none() {
+        return;
+    }
+    let Ok(block) = LinearPool.allocate(layout) else {
+        return;
+    };
+
+    unsafe {
+        let data = texture.__bindgen_anon_1.data;
+        ptr::copy_nonoverlapping(data as *const u8, block.cast::<u8>().as_ptr(), layout.size());
+        ctru_sys::linearFree(data);
+        texture.__bindgen_anon_1.data = block.cast().as_ptr();
+        sys::C3D_TexFlush(texture);
+    }
+}
+
+// frees a texture whether or not its data was moved into the pool
+//
+// # Safety
+// nothing can be using it anymore, including the GPU
+pub unsafe fn delete_texture(texture: &mut sys::C3D_Tex) {
+    let data = unsafe { texture.__bindgen_anon_1.data };
+    if !data.is_null() && POOL.lock().unwrap().deallocate(data.expose_provenance()) {
+        alloc_tracker::linear_freed(data.expose_provenance());
+    } else {
+        unsafe { sys::C3D_TexDelete(texture); }
+    }
+}
+
+// gives the slabs with nothing in them back to the linear heap, and returns how many bytes that
+// was
+pub fn trim() -> usize {
+    let mut pool = POOL.lock().unwrap();
+    let before = pool.slabs.len();
+    pool.slabs.retain(|slab| {
+        if slab.is_empty() {
+            unsafe { LinearAllocator.deallocate(to_ptr(slab.base), slab_layout()); }
+        }
+        !slab.is_empty()
+    });
+
+    (before - pool.slabs.len()) * SLAB_SIZE
+}
+
+#[derive(Copy, Clone, Debug, Default)]
+pub struct PoolStats {
+    pub slabs: usize,
+    pub used: usize, // bytes of blocks with something in them
+    pub free: usize, // and without
+}
+
+pub fn stats() -> PoolStats {
+    let pool = POOL.lock().unwrap();
+    let mut ret = PoolStats { slabs: pool.slabs.len(), ..Default::default() };
+    for slab in &pool.slabs {
+        let free = slab.free.len() * CLASSES[slab.class];
+        ret.free += free;
+        ret.used += SLAB_SIZE - free;
+    }
+
+    ret
+}
@@ -0,0 +1,201 @@
+// the game's text in every language it's translated into, looked up by message id. each language
+// is a TOML file in one directory, named by its code (see Language::code), with the messages as
+// strings. tables nest, and make a dotted id:
+//
+//     # romfs:/lang/en.toml
+//     [menu]
+//     start = "Start"
+//     greeting = "Hello, {name}!"
+//
+//     let strings = Strings::load_system("romfs:/lang")?;
+//     strings.get("menu.start");
+//     strings.format("menu.greeting", &[("name", &player)]);
+//
+// a message missing from the language falls back to English, and after that to its id, so an
+// untranslated message is still something to read (and easy to spot). there's no text rendering
+// yet, so nothing here knows which characters a font has
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Display;
+use std::fs;
+use std::path::Path;
+
+fn check(res: ctru_sys::Result) -> ctru::Result<()> {
+    if ctru_sys::R_FAILED(res) {
+        Err(res.into())
+    } else {
+        Ok(())
+    }
+}
+
+// the languages the system settings offer, in the system's numbering
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Language {
+    Japanese,
+    English,
+    French,
+    German,
+    Italian,
+    Spanish,
+    SimplifiedChinese,
+    Korean,
+    Dutch,
+    Portuguese,
+    Russian,
+    TraditionalChinese,
+}
+
+// what's used when a message isn't translated, or there's no file for the system's language
+pub const FALLBACK_LANGUAGE: Language = Language::English;
+
+impl Language {
+    const ALL: [Language; 12] = [
+        Language::Japanese,
+        Language::English,
+        Language::French,
+        Language::German,
+        Language::Italian,
+        Language::Spanish,
+        Language::SimplifiedChinese,
+        Language::Korean,
+        Language::Dutch,
+        Language::Portuguese,
+        Language::Russian,
+        Language::TraditionalChinese,
+    ];
+
+    // the name of its file, without the .toml
+    pub fn code(self) -> &'static str {
+        match self {
+            Language::Japanese => "ja",
+            Language::English => "en",
+            Language::French => "fr",
+            Language::German => "de",
+            Language::Italian => "it",
+            Language::Spanish => "es",
+            Language::SimplifiedChinese => "zh_CN",
+            Language::Korean => "ko",
+            Language::Dutch => "nl",
+            Language::Portuguese => "pt",
+            Language::Russian => "ru",
+            Language::TraditionalChinese => "zh_TW",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|language| language.code() == code)
+    }
+
+    // the language in the system settings, through the cfg:u service
+    pub fn system() -> ctru::Result<Self> {
+        check(unsafe { ctru_sys::cfguInit() })?;
+        let mut language = 0;
+        let res = check(unsafe { ctru_sys::CFGU_GetSystemLanguage(&mut language) });
+        unsafe { ctru_sys::cfguExit(); }
+        res?;
+
+        Ok(Self::ALL.get(language as usize).copied().unwrap_or(FALLBACK_LANGUAGE))
+    }
+}
+
+// the messages in a TOML table, by dotted id
+fn flatten(prefix: &str, table: toml::Table, into: &mut HashMap<String, String>) -> Result<(), String> {
+    for (key, value) in table {
+        let id = if prefix.is_empty() { key } else { format!("{prefix}.{key}") };
+        match value {
+            toml::Value::String(text) => {
+                into.insert(id, text);
+            }
+            toml::Value::Table(table) => flatten(&id, table, into)?,
+            _ => return Err(format!("{id} has to be a string")),
+        }
+    }
+
+    Ok(())
+}
+
+fn read_table(path: &Path) -> Result<HashMap<String, String>, Box<dyn Error>> {
+    let table: toml::Table = toml::from_str(&fs::read_to_string(path)?).map_err(|e| format!("{}: {e}", path.display()))?;
+    let mut messages = HashMap::new();
+    flatten("", table, &mut messages).map_err(|e| format!("{}: {e}", path.display()))?;
+
+    Ok(messages)
+}
+
+pub struct Strings {
+    language: Language,
+    messages: HashMap<String, String>,
+    fallback: HashMap<String, String>, // empty if `language` is the fallback
+}
+
+impl Strings {
+    // reads `language`'s file from `dir`, and the fallback language's if it's there
+    pub fn load(dir: impl AsRef<Path>, language: Language) -> Result<Self, Box<dyn Error>> {
+        let dir = dir.as_ref();
+        let messages = read_table(&dir.join(format!("{}.toml", language.code())))?;
+        let fallback_path = dir.join(format!("{}.toml", FALLBACK_LANGUAGE.code()));
+        let fallback = if language != FALLBACK_LANGUAGE && fallback_path.exists() {
+            read_table(&fallback_path)?
+        } else {
+            HashMap::new()
+        };
+
+        Ok(Self { language, messages, fallback })
+    }
+
+    // the system's language if the game's translated into it, the fallback language otherwise
+    pub fn load_system(dir: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let dir = dir.as_ref();
+        let language = Language::system()
+            .ok()
+            .filter(|language| dir.join(format!("{}.toml", language.code())).exists())
+            .unwrap_or(FALLBACK_LANGUAGE);
+
+        Self::load(dir, language)
+    }
+
+    pub fn language(&self) -> Language {
+        self.language
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.messages.contains_key(id)
+    }
+
+    pub fn get<'a>(&'a self, id: &'a str) -> &'a str {
+        self.messages.get(id)
+            .or_else(|| self.fallback.get(id))
+            .map_or(id, String::as_str)
+    }
+
+    // the message with each {name} replaced by its argument. {{ and }} are a literal { and }
+    pub fn format(&self, id: &str, args: &[(&str, &dyn Display)]) -> String {
+        let message = self.get(id);
+        let mut ret = String::with_capacity(message.len());
+        let mut rest = message;
+        while let Some(i) = rest.find(['{', '}']) {
+            ret.push_str(&rest[..i]);
+            let escaped = &rest[i..i + 1];
+            if rest[i + 1..].starts_with(escaped) {
+                ret.push_str(escaped);
+                rest = &rest[i + 2..];
+                continue;
+            }
+
+            let Some(end) = rest[i..].find('}').filter(|_| escaped == "{") else {
+                ret.push_str(escaped);
+                rest = &rest[i + 1..];
+                continue;
+            };
+            let name = &rest[i + 1..i + end];
+            match args.iter().find(|(arg, _)| *arg == name) {
+                Some((_, value)) => ret.push_str(&value.to_string()),
+                None => ret.push_str(&rest[i..=i + end]), // left in, so it shows
+            }
+            rest = &rest[i + end + 1..];
+        }
+        ret.push_str(rest);
+
+        ret
+    }
+}
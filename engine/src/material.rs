@@ -0,0 +1,118 @@
+use citro3d::math::FVec4;
+use citro3d::math::Matrix4;
+use citro3d::uniform::Uniform;
+use glam::vec4;
+use mm3ds_format::AlphaMode;
+
+// how a fragment's alpha is compared with the reference to decide whether it's drawn
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AlphaFunc {
+    Never,
+    Always,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+impl AlphaFunc {
+    pub(crate) fn gpu(self) -> ctru_sys::GPU_TESTFUNC {
+        match self {
+            AlphaFunc::Never => ctru_sys::GPU_NEVER,
+            AlphaFunc::Always => ctru_sys::GPU_ALWAYS,
+            AlphaFunc::Equal => ctru_sys::GPU_EQUAL,
+            AlphaFunc::NotEqual => ctru_sys::GPU_NOTEQUAL,
+            AlphaFunc::Less => ctru_sys::GPU_LESS,
+            AlphaFunc::LessEqual => ctru_sys::GPU_LEQUAL,
+            AlphaFunc::Greater => ctru_sys::GPU_GREATER,
+            AlphaFunc::GreaterEqual => ctru_sys::GPU_GEQUAL,
+        }
+    }
+}
+
+// fragments are only drawn if `alpha func reference`, with alpha from 0 to 255
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct AlphaTest {
+    pub func: AlphaFunc,
+    pub reference: u8,
+}
+
+impl AlphaTest {
+    // what an alpha mode from a MESH file means
+    pub fn from_mode(mode: AlphaMode) -> Option<Self> {
+        match mode {
+            AlphaMode::Opaque => None,
+            AlphaMode::Mask(cutoff) => Some(Self { func: AlphaFunc::GreaterEqual, reference: (cutoff * 255.).round() as u8 }),
+            // blended (see BlendMode), so this only skips the parts too clear to see
+            AlphaMode::Blend => Self::default_cutout(),
+        }
+    }
+
+    // drops the almost fully transparent parts of a texture
+    fn default_cutout() -> Option<Self> {
+        Some(Self { func: AlphaFunc::Greater, reference: 0x10 })
+    }
+}
+
+// how a material's drawn over what's already there
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BlendMode {
+    Opaque, // covers it completely, whatever its alpha and the alpha test
+    AlphaTest, // covers it where the alpha test passes and leaves it alone everywhere else
+    // mixed with it by alpha. these don't write depth, and are drawn after everything else in the
+    // scene (but before decals), the furthest away first
+    AlphaBlend,
+}
+
+impl BlendMode {
+    // what an alpha mode from a MESH file means
+    pub fn from_mode(mode: AlphaMode) -> Self {
+        match mode {
+            AlphaMode::Opaque => BlendMode::Opaque,
+            AlphaMode::Mask(_) => BlendMode::AlphaTest,
+            AlphaMode::Blend => BlendMode::AlphaBlend,
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct Material {
+    pub ambient: FVec4,
+    pub diffuse: FVec4,
+    pub specular: FVec4,
+    pub emission: FVec4,
+    pub lightmap: bool, // multiply in the mesh's lightmap, if it has one
+    pub alpha_test: Option<AlphaTest>, // None draws every fragment. ignored by BlendMode::Opaque
+    pub double_sided: bool, // otherwise the backs of triangles (clockwise on screen) are culled
+    pub blend: BlendMode,
+}
+
+impl From<Material> for Uniform {
+    fn from(value: Material) -> Self {
+        Matrix4::from_rows([
+            value.ambient,
+            value.diffuse,
+            value.specular,
+            value.emission,
+        ]).into()
+    }
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self {
+            ambient: vec4(0.2, 0.2, 0.2, 0.0).into(),
+            diffuse: vec4(0.4, 0.4, 0.4, 0.0).into(),
+            specular: vec4(0.8, 0.8, 0.8, 0.0).into(),
+            emission: vec4(0.0, 0.0, 0.0, 1.0).into(),
+            lightmap: false,
+            alpha_test: AlphaTest::default_cutout(),
+            // meshes made in code don't all agree on which way they face
+            double_sided: true,
+            blend: BlendMode::AlphaTest,
+        }
+    }
+}
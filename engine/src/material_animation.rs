@@ -0,0 +1,200 @@
+// animates how a mesh looks over time (hit flashes, fade-outs, pulsing pickups) without changing
+// its material, so every other copy of it stays as it was:
+//
+//     let mut hit = MaterialAnimation::flash(Vec3::ONE, 0.2);
+//     ...
+//     if took_damage {
+//         hit.restart();
+//     }
+//     hit.update(dt);
+//     renderer.please_render_animated(enemy, model, &hit);
+//
+// an animation is some tracks, each easing one property of the material from one value to another
+// over its own duration, running side by side. a finished track keeps its last value, so a fade
+// out stays faded
+use citro3d::math::FVec4;
+use glam::Vec3;
+
+use crate::material::{BlendMode, Material};
+use crate::math::{from_fvec4, to_fvec4};
+
+// how a track gets from its start to its end
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Easing {
+    Linear,
+    EaseIn, // starts slow
+    EaseOut, // ends slow
+    EaseInOut,
+    Overshoot, // ends slow, after going a little past the end and coming back
+}
+
+impl Easing {
+    // `t` from 0 to 1, eased
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0., 1.);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => t * (2. - t),
+            Easing::EaseInOut => t * t * (3. - 2. * t),
+            Easing::Overshoot => {
+                // the usual "back" curve, going about 10% past
+                const C: f32 = 1.70158;
+                let t = t - 1.;
+                1. + (C + 1.) * t * t * t + C * t * t
+            }
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Repeat {
+    Once,
+    Loop, // jumps back to the start
+    PingPong, // goes back to the start as it came, then again
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Property {
+    Diffuse(Vec3, Vec3), // from, to
+    Emission(Vec3, Vec3), // the light it gives off, whatever the light on it
+    Alpha(f32, f32), // blends it over what's behind while it's below 1
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Track {
+    pub property: Property,
+    pub duration: f32, // seconds, for one way
+    pub easing: Easing,
+    pub repeat: Repeat,
+}
+
+impl Track {
+    // how far along it is at `time`, from 0 to 1 and eased
+    fn progress(&self, time: f32) -> f32 {
+        if self.duration <= 0. {
+            return 1.;
+        }
+
+        let t = time / self.duration;
+        let t = match self.repeat {
+            Repeat::Once => t.min(1.),
+            Repeat::Loop => t.fract(),
+            Repeat::PingPong => 1. - (t % 2. - 1.).abs(),
+        };
+        self.easing.apply(t)
+    }
+}
+
+// what an animation does to a material at one moment. anything that's None is left alone
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct MaterialOverride {
+    pub diffuse: Option<Vec3>,
+    pub emission: Option<Vec3>,
+    pub alpha: Option<f32>,
+}
+
+fn with_xyz(v: FVec4, xyz: Vec3) -> FVec4 {
+    to_fvec4(xyz.extend(from_fvec4(v).w))
+}
+
+fn with_w(v: FVec4, w: f32) -> FVec4 {
+    to_fvec4(from_fvec4(v).with_w(w))
+}
+
+impl MaterialOverride {
+    pub fn apply(&self, material: &Material) -> Material {
+        let mut ret = *material;
+        if let Some(diffuse) = self.diffuse {
+            ret.diffuse = with_xyz(ret.diffuse, diffuse);
+        }
+        if let Some(emission) = self.emission {
+            ret.emission = with_xyz(ret.emission, emission);
+        }
+        if let Some(alpha) = self.alpha {
+            // the lit color's alpha is the sum of the material's, so it all goes in the emission
+            ret.ambient = with_w(ret.ambient, 0.);
+            ret.diffuse = with_w(ret.diffuse, 0.);
+            ret.specular = with_w(ret.specular, 0.);
+            ret.emission = with_w(ret.emission, alpha.clamp(0., 1.));
+            if alpha < 1. {
+                ret.blend = BlendMode::AlphaBlend;
+            }
+        }
+
+        ret
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MaterialAnimation {
+    tracks: Vec<Track>,
+    time: f32, // seconds since it started
+}
+
+impl MaterialAnimation {
+    pub fn new(tracks: Vec<Track>) -> Self {
+        Self { tracks, time: 0. }
+    }
+
+    // glows `color` and fades back to normal over `seconds`, for when something's hit
+    pub fn flash(color: Vec3, seconds: f32) -> Self {
+        Self::new(vec![Track {
+            property: Property::Emission(color, Vec3::ZERO),
+            duration: seconds,
+            easing: Easing::EaseOut,
+            repeat: Repeat::Once,
+        }])
+    }
+
+    // fades away over `seconds`, and stays gone
+    pub fn fade_out(seconds: f32) -> Self {
+        Self::new(vec![Track { property: Property::Alpha(1., 0.), duration: seconds, easing: Easing::Linear, repeat: Repeat::Once }])
+    }
+
+    // glows `color` and back every `period` seconds, forever
+    pub fn pulse(color: Vec3, period: f32) -> Self {
+        Self::new(vec![Track {
+            property: Property::Emission(Vec3::ZERO, color),
+            duration: period / 2.,
+            easing: Easing::EaseInOut,
+            repeat: Repeat::PingPong,
+        }])
+    }
+
+    pub fn add_track(&mut self, track: Track) {
+        self.tracks.push(track);
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.time += dt;
+    }
+
+    pub fn restart(&mut self) {
+        self.time = 0.;
+    }
+
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    // whether every track has stopped changing. repeating tracks never do
+    pub fn is_finished(&self) -> bool {
+        self.tracks.iter().all(|track| track.repeat == Repeat::Once && self.time >= track.duration)
+    }
+
+    // where every track is now. if two tracks animate the same property, the later one wins
+    pub fn current(&self) -> MaterialOverride {
+        let mut ret = MaterialOverride::default();
+        for track in &self.tracks {
+            let t = track.progress(self.time);
+            match track.property {
+                Property::Diffuse(from, to) => ret.diffuse = Some(from.lerp(to, t)),
+                Property::Emission(from, to) => ret.emission = Some(from.lerp(to, t)),
+                Property::Alpha(from, to) => ret.alpha = Some(from + (to - from) * t),
+            }
+        }
+
+        ret
+    }
+}
@@ -0,0 +1,72 @@
+// going between glam, which the engine does its maths in, and citro3d's types, which is what the
+// GPU is given. the conversions are exact both ways
+use citro3d::math::{FVec3, FVec4, Matrix4};
+use glam::{Mat4, Quat, Vec3, Vec4};
+
+// glam matrices are column-major, citro3d's are built from rows
+pub fn to_matrix4(m: Mat4) -> Matrix4 {
+    Matrix4::from_rows([
+        m.row(0).into(),
+        m.row(1).into(),
+        m.row(2).into(),
+        m.row(3).into(),
+    ])
+}
+
+// the inverse of to_matrix4
+pub fn from_matrix4(m: Matrix4) -> Mat4 {
+    // each row is stored backwards, as wzyx
+    let rows = m.as_raw().r.map(|row| unsafe { Vec4::new(row.c[3], row.c[2], row.c[1], row.c[0]) });
+    Mat4::from_cols(rows[0], rows[1], rows[2], rows[3]).transpose()
+}
+
+pub fn to_fvec4(v: Vec4) -> FVec4 {
+    v.into()
+}
+
+pub fn from_fvec4(v: FVec4) -> Vec4 {
+    Vec4::new(v.x(), v.y(), v.z(), v.w())
+}
+
+pub fn to_fvec3(v: Vec3) -> FVec3 {
+    FVec3::new(v.x, v.y, v.z)
+}
+
+pub fn from_fvec3(v: FVec3) -> Vec3 {
+    Vec3::new(v.x(), v.y(), v.z())
+}
+
+// a matrix made of a scale, then a rotation, then a translation, taken back apart into them. one
+// that's skewed (scaled unevenly after being rotated) or projects can't be, and comes apart wrong
+pub fn decompose(m: Matrix4) -> (Vec3, Quat, Vec3) {
+    from_matrix4(m).to_scale_rotation_translation()
+}
+
+// the inverse of decompose
+pub fn compose(scale: Vec3, rotation: Quat, translation: Vec3) -> Matrix4 {
+    to_matrix4(Mat4::from_scale_rotation_translation(scale, rotation, translation))
+}
+
+// from `a` at 0 to `b` at 1, the short way round. the rotations don't have to be normalized
+pub fn slerp(a: Quat, b: Quat, t: f32) -> Quat {
+    a.normalize().slerp(b.normalize(), t)
+}
+
+// like slerp but cheaper, and not at an even speed. fine for rotations that are close together,
+// like one frame's to the next
+pub fn nlerp(a: Quat, b: Quat, t: f32) -> Quat {
+    a.normalize().lerp(b.normalize(), t)
+}
+
+// between two placements, through their scales, rotations and translations separately, so that an
+// object turning doesn't shrink halfway through like it would with the matrices' entries lerped
+pub fn interpolate(a: Matrix4, b: Matrix4, t: f32) -> Matrix4 {
+    let (a_scale, a_rotation, a_translation) = decompose(a);
+    let (b_scale, b_rotation, b_translation) = decompose(b);
+    compose(a_scale.lerp(b_scale, t), slerp(a_rotation, b_rotation, t), a_translation.lerp(b_translation, t))
+}
+
+// for colors and material terms
+pub fn lerp_fvec4(a: FVec4, b: FVec4, t: f32) -> FVec4 {
+    to_fvec4(from_fvec4(a).lerp(from_fvec4(b), t))
+}
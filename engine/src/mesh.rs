@@ -0,0 +1,806 @@
+use std::alloc::Layout;
+use std::io;
+use std::io::Read;
+use std::mem;
+use std::mem::MaybeUninit;
+use std::ops::Range;
+use std::ptr;
+use std::ptr::NonNull;
+use std::rc::Rc;
+
+use citro3d::attrib;
+use citro3d::attrib::Format;
+use citro3d::attrib::Register;
+use citro3d::sys;
+use citro3d::uniform;
+use glam::{Vec3, Vec4};
+use mm3ds_format::{Flipbook, MeshData};
+
+use crate::linear_pool;
+use crate::linear_pool::{LinearPool, OutOfMemory};
+use crate::material::{AlphaTest, BlendMode, Material};
+use crate::occlusion::Aabb;
+use crate::texture_cache::TextureCache;
+
+pub use mm3ds_format::{SkinVertex, Vertex};
+
+// the most bones a skinned mesh can have, as many as shaders/skinned.v.pica has room for. gltf_tool
+// checks meshes against its own copy
+pub const MAX_BONES: usize = 20;
+
+// the most streams a mesh can have: they go in v3 and up, and the PICA has 12 attributes
+pub const MAX_STREAMS: usize = 9;
+
+// indices are u16, so that's as many vertices as sub-meshes can share
+const MAX_SHARED_VERTICES: usize = u16::MAX as usize + 1;
+
+// where a flipbook mesh is in its animation. every draw of the mesh shows the same frame
+#[derive(Copy, Clone, Debug)]
+pub struct Playback {
+    pub playing: bool,
+    pub looping: bool, // otherwise it stops on the last frame
+    pub speed: f32,
+    pub time: f32, // seconds since the first frame, at speed 1
+}
+
+impl Default for Playback {
+    fn default() -> Self {
+        Self { playing: true, looping: true, speed: 1., time: 0. }
+    }
+}
+
+impl Playback {
+    pub fn frame(&self, flipbook: &Flipbook) -> u16 {
+        let frame = (self.time * flipbook.fps).max(0.) as u32;
+        if self.looping {
+            (frame % flipbook.frames as u32) as u16
+        } else {
+            frame.min(flipbook.frames as u32 - 1) as u16
+        }
+    }
+}
+
+// a unit cube centered on the origin, textured on every face
+pub const CUBE_VERTICES: [Vertex; 36] = [
+    Vertex { pos: [-0.5, -0.5,  0.5], uv: [0., 0.], normal: [0., 0.,  1.] },
+    Vertex { pos: [ 0.5, -0.5,  0.5], uv: [1., 0.], normal: [0., 0.,  1.] },
+    Vertex { pos: [ 0.5,  0.5,  0.5], uv: [1., 1.], normal: [0., 0.,  1.] },
+
+    Vertex { pos: [ 0.5,  0.5,  0.5], uv: [1., 1.], normal: [0., 0.,  1.] },
+    Vertex { pos: [-0.5,  0.5,  0.5], uv: [0., 1.], normal: [0., 0.,  1.] },
+    Vertex { pos: [-0.5, -0.5,  0.5], uv: [0., 0.], normal: [0., 0.,  1.] },
+
+
+    Vertex { pos: [-0.5, -0.5, -0.5], uv: [0., 0.], normal: [0., 0., -1.] },
+    Vertex { pos: [-0.5,  0.5, -0.5], uv: [1., 0.], normal: [0., 0., -1.] },
+    Vertex { pos: [ 0.5,  0.5, -0.5], uv: [1., 1.], normal: [0., 0., -1.] },
+
+    Vertex { pos: [ 0.5,  0.5, -0.5], uv: [1., 1.], normal: [0., 0., -1.] },
+    Vertex { pos: [ 0.5, -0.5, -0.5], uv: [0., 1.], normal: [0., 0., -1.] },
+    Vertex { pos: [-0.5, -0.5, -0.5], uv: [0., 0.], normal: [0., 0., -1.] },
+
+
+    Vertex { pos: [ 0.5, -0.5, -0.5], uv: [0., 0.], normal: [-1., 0., 0.] },
+    Vertex { pos: [ 0.5,  0.5, -0.5], uv: [1., 0.], normal: [-1., 0., 0.] },
+    Vertex { pos: [ 0.5,  0.5,  0.5], uv: [1., 1.], normal: [-1., 0., 0.] },
+
+    Vertex { pos: [ 0.5,  0.5,  0.5], uv: [1., 1.], normal: [-1., 0., 0.] },
+    Vertex { pos: [ 0.5, -0.5,  0.5], uv: [0., 1.], normal: [-1., 0., 0.] },
+    Vertex { pos: [ 0.5, -0.5, -0.5], uv: [0., 0.], normal: [-1., 0., 0.] },
+
+
+    Vertex { pos: [-0.5, -0.5, -0.5], uv: [0., 0.], normal: [ 1., 0., 0.] },
+    Vertex { pos: [-0.5, -0.5,  0.5], uv: [1., 0.], normal: [ 1., 0., 0.] },
+    Vertex { pos: [-0.5,  0.5,  0.5], uv: [1., 1.], normal: [ 1., 0., 0.] },
+
+    Vertex { pos: [-0.5,  0.5,  0.5], uv: [1., 1.], normal: [ 1., 0., 0.] },
+    Vertex { pos: [-0.5,  0.5, -0.5], uv: [0., 1.], normal: [ 1., 0., 0.] },
+    Vertex { pos: [-0.5, -0.5, -0.5], uv: [0., 0.], normal: [ 1., 0., 0.] },
+
+
+    Vertex { pos: [-0.5,  0.5, -0.5], uv: [0., 0.], normal: [0.,  1., 0.] },
+    Vertex { pos: [-0.5,  0.5,  0.5], uv: [1., 0.], normal: [0.,  1., 0.] },
+    Vertex { pos: [ 0.5,  0.5,  0.5], uv: [1., 1.], normal: [0.,  1., 0.] },
+
+    Vertex { pos: [ 0.5,  0.5,  0.5], uv: [1., 1.], normal: [0.,  1., 0.] },
+    Vertex { pos: [ 0.5,  0.5, -0.5], uv: [0., 1.], normal: [0.,  1., 0.] },
+    Vertex { pos: [-0.5,  0.5, -0.5], uv: [0., 0.], normal: [0.,  1., 0.] },
+
+
+    Vertex { pos: [-0.5, -0.5, -0.5], uv: [0., 0.], normal: [0., -1., 0.] },
+    Vertex { pos: [ 0.5, -0.5, -0.5], uv: [1., 0.], normal: [0., -1., 0.] },
+    Vertex { pos: [ 0.5, -0.5,  0.5], uv: [1., 1.], normal: [0., -1., 0.] },
+
+    Vertex { pos: [ 0.5, -0.5,  0.5], uv: [1., 1.], normal: [0., -1., 0.] },
+    Vertex { pos: [-0.5, -0.5,  0.5], uv: [0., 1.], normal: [0., -1., 0.] },
+    Vertex { pos: [-0.5, -0.5, -0.5], uv: [0., 0.], normal: [0., -1., 0.] },
+];
+
+// a mesh's vertex buffers, in linear memory where the GPU can read them
+struct VertexBuffers {
+    vertices: Vec<Vertex, LinearPool>,
+    // kept in a second vertex buffer, so the vertices are laid out like an unskinned mesh's
+    skin: Option<Vec<SkinVertex, LinearPool>>,
+    // the lightmap's uvs are in a second vertex buffer too
+    lightmap_uvs: Option<Vec<[f32; 2], LinearPool>>,
+    // and each stream in one of its own, see Mesh::from_streamed_data
+    streams: Vec<Vec<[f32; 4], LinearPool>>,
+}
+
+impl VertexBuffers {
+    fn new(vertices: Vec<Vertex, LinearPool>) -> Self {
+        Self { vertices, skin: None, lightmap_uvs: None, streams: Vec::new() }
+    }
+}
+
+// the vertex buffers, the buffer info that points the GPU at them, and the index buffers, all
+// owned. the buffer info only holds where the buffers are in physical memory, which moving this
+// around doesn't change; what keeps it right is that the buffers are never moved or resized while
+// they're in here
+pub(crate) struct GpuBuffers {
+    info: sys::C3D_BufInfo,
+    buffers: VertexBuffers,
+    indices: Vec<Vec<u16, LinearPool>>, // one, or one per sub-mesh
+}
+
+// adds `data` to `info` as the buffer of the attributes in `registers`, one after the other in
+// each element. each attribute's index is its register, see Mesh::draw_attr_info
+fn add_buffer<T>(info: &mut sys::C3D_BufInfo, data: &[T], registers: Range<u64>) {
+    let permutation = registers.clone().rev().fold(0, |permutation, register| permutation << 4 | register);
+    let added = unsafe { sys::BufInfo_Add(info, data.as_ptr().cast(), size_of::<T>() as isize, registers.count() as i32, permutation) };
+    // more than 12 buffers, or one outside of linear memory
+    assert!(added >= 0, "couldn't add a vertex buffer ({added})");
+}
+
+impl GpuBuffers {
+    fn new(buffers: VertexBuffers, indices: &[&[u16]]) -> Result<Self, OutOfMemory> {
+        let indices = indices.iter().map(|indices| linear_pool::try_copy("index buffer", indices)).collect::<Result<_, _>>()?;
+        Ok(Self::with_indices(buffers, indices))
+    }
+
+    // with index buffers that are already in linear memory, e.g. another GpuBuffers'
+    fn with_indices(buffers: VertexBuffers, indices: Vec<Vec<u16, LinearPool>>) -> Self {
+        let mut info = MaybeUninit::uninit();
+        let mut info = unsafe {
+            sys::BufInfo_Init(info.as_mut_ptr());
+            info.assume_init()
+        };
+        add_buffer(&mut info, &buffers.vertices, 0..3);
+        if let Some(skin) = &buffers.skin {
+            add_buffer(&mut info, skin, 3..5);
+        }
+        if let Some(uvs) = &buffers.lightmap_uvs {
+            add_buffer(&mut info, uvs, 3..4);
+        }
+        for (i, stream) in buffers.streams.iter().enumerate() {
+            let register = 3 + i as u64;
+            add_buffer(&mut info, stream, register..register + 1);
+        }
+
+        Self { info, buffers, indices }
+    }
+
+    fn buffers(&self) -> &VertexBuffers {
+        &self.buffers
+    }
+
+    // for changing what's in one. the buffer info points at it, so it can't be moved or resized,
+    // which a slice can't be
+    fn stream_mut(&mut self, i: usize) -> &mut [[f32; 4]] {
+        &mut self.buffers.streams[i]
+    }
+
+    fn indices(&self) -> &[Vec<u16, LinearPool>] {
+        &self.indices
+    }
+
+    // moves the vertex and index buffers out, for new GpuBuffers. this draws nothing after that.
+    // the index buffers stay where they are, so the GPU can carry on reading them
+    fn take(&mut self) -> (VertexBuffers, Vec<Vec<u16, LinearPool>>) {
+        self.info.bufCount = 0;
+        (mem::replace(&mut self.buffers, VertexBuffers::new(Vec::new_in(LinearPool))), mem::take(&mut self.indices))
+    }
+
+    // `indices` (of these vertices), or every vertex in order. only while a frame's being drawn,
+    // with the mesh's attributes, program and uniforms set up
+    fn draw(&self, indices: Option<&[u16]>) {
+        if self.info.bufCount == 0 {
+            return;
+        }
+        unsafe {
+            // citro3d copies the buffer info, so it doesn't have to outlive the draw
+            sys::C3D_SetBufInfo(ptr::from_ref(&self.info).cast_mut());
+            match indices {
+                Some(indices) => {
+                    sys::C3D_DrawElements(ctru_sys::GPU_TRIANGLES, indices.len() as i32, sys::C3D_UNSIGNED_SHORT as i32, indices.as_ptr().cast());
+                }
+                None => sys::C3D_DrawArrays(ctru_sys::GPU_TRIANGLES, 0, self.buffers.vertices.len() as i32),
+            }
+        }
+    }
+}
+
+pub struct Mesh {
+    pub(crate) material: Material,
+    pub(crate) reflectivity: f32, // how much of the skybox to mix in, see Renderer::set_reflectivity
+    pub(crate) flipbook: Option<Flipbook>,
+    pub(crate) shader: Option<String>, // see Renderer::set_shader
+    pub(crate) params: Vec<(String, Vec4)>,
+    pub(crate) param_uniforms: Vec<Option<uniform::Index>>, // where each is in `shader`, if it's there
+    pub(crate) playback: Playback,
+    // the top three rows of each bone's matrix, see Renderer::set_bones
+    pub(crate) bones: Vec<[Vec4; 3]>,
+    pub(crate) lightmap: Option<sys::C3D_Tex>,
+    // the light it gives off, added after lighting on texture unit 2 with the same uvs as `texture`
+    pub(crate) emission_map: Option<sys::C3D_Tex>,
+    pub(crate) index_data: Option<Vec<u16>>, // a copy of what's in the index buffer, for batching
+    pub(crate) bounds: Aabb, // in the mesh's own space
+    pub(crate) texture: Option<sys::C3D_Tex>,
+    // drawn instead of the whole mesh with `material` and `texture`, if there are any
+    pub(crate) sub_meshes: Vec<SubMesh>,
+    buffers: GpuBuffers,
+    // the textures it loaded for itself, which go when the last mesh sharing them does: batches
+    // share their originals'. ones from a texture cache are the cache's
+    pub(crate) owned_textures: Vec<Rc<OwnedTexture>>,
+}
+
+// a texture in the pool that a mesh loaded for itself, deleted when it's dropped
+pub(crate) struct OwnedTexture(sys::C3D_Tex);
+
+impl OwnedTexture {
+    pub(crate) fn data(&self) -> *mut std::ffi::c_void {
+        unsafe { self.0.__bindgen_anon_1.data }
+    }
+}
+
+impl Drop for OwnedTexture {
+    fn drop(&mut self) {
+        unsafe { linear_pool::delete_texture(&mut self.0); }
+    }
+}
+
+// one material's share of a mesh with sub-meshes, see Mesh::from_sub_mesh_data
+pub struct SubMeshData<'a> {
+    pub indices: &'a [u16], // into the mesh's vertices
+    pub t3x_data: Option<&'a [u8]>,
+    pub material: Material,
+}
+
+pub(crate) struct SubMesh {
+    pub(crate) material: Material,
+    pub(crate) texture: Option<sys::C3D_Tex>,
+    range: Range<usize>, // of the mesh's index_data
+}
+
+// what's drawn in one go: all of a mesh, or one of its sub-meshes
+pub(crate) struct Part<'a> {
+    pub(crate) material: &'a Material,
+    pub(crate) texture: Option<&'a sys::C3D_Tex>,
+    indices: Option<&'a [u16]>, // None to draw the vertices in order, in linear memory
+}
+
+// the index buffers a mesh has on the GPU
+fn index_lists<'a>(index_data: Option<&'a [u16]>, sub_meshes: &[SubMesh]) -> Vec<&'a [u16]> {
+    match index_data {
+        Some(indices) if !sub_meshes.is_empty() => sub_meshes.iter().map(|sub_mesh| &indices[sub_mesh.range.clone()]).collect(),
+        Some(indices) => vec![indices],
+        None => Vec::new(),
+    }
+}
+
+impl Mesh {
+    pub(crate) fn attr_info() -> attrib::Info {
+        let mut ret = attrib::Info::new();
+        ret.add_loader(Register::new(0).unwrap(), Format::Float, 3).unwrap(); // v0=position
+        ret.add_loader(Register::new(1).unwrap(), Format::Float, 2).unwrap(); // v1=uv
+        ret.add_loader(Register::new(2).unwrap(), Format::Float, 3).unwrap(); // v2=normal
+
+        ret
+    }
+
+    // every buffer the mesh has, for drawing. the renderer sets this before each mesh, since what
+    // comes after v2 depends on what was in its MESH file. GpuBuffers::new lays the buffers out to
+    // match
+    pub(crate) fn draw_attr_info(&self) -> attrib::Info {
+        let mut ret = Self::attr_info();
+        let buffers = self.buffers.buffers();
+        if buffers.skin.is_some() {
+            ret.add_loader(Register::new(3).unwrap(), Format::UnsignedByte, 4).unwrap(); // v3=bone indices
+            ret.add_loader(Register::new(4).unwrap(), Format::Float, 4).unwrap(); // v4=bone weights
+        }
+        if buffers.lightmap_uvs.is_some() {
+            ret.add_loader(Register::new(3).unwrap(), Format::Float, 2).unwrap(); // v3=lightmap uv
+        }
+        for i in 0..buffers.streams.len() {
+            ret.add_loader(Register::new(3 + i as u16).unwrap(), Format::Float, 4).unwrap(); // v3+i=stream i
+        }
+
+        ret
+    }
+
+    pub fn bounds(&self) -> Aabb {
+        self.bounds
+    }
+
+    pub fn is_skinned(&self) -> bool {
+        self.buffers.buffers().skin.is_some()
+    }
+
+    pub fn is_lightmapped(&self) -> bool {
+        self.lightmap.is_some()
+    }
+
+    pub fn vertices(&self) -> &[Vertex] {
+        &self.buffers.buffers().vertices
+    }
+
+    pub fn streams(&self) -> usize {
+        self.buffers.buffers().streams.len()
+    }
+
+    pub fn stream(&self, i: usize) -> &[[f32; 4]] {
+        &self.buffers.buffers().streams[i]
+    }
+
+    // every vertex, in order, with everything set up for it
+    pub(crate) fn draw_vertices(&self) {
+        self.buffers.draw(None);
+    }
+
+    // one of parts()
+    pub(crate) fn draw(&self, part: &Part) {
+        self.buffers.draw(part.indices);
+    }
+
+    pub fn sub_meshes(&self) -> usize {
+        self.sub_meshes.len()
+    }
+
+    // every texture it draws with, its sub-meshes' too
+    pub(crate) fn textures(&self) -> impl Iterator<Item = &sys::C3D_Tex> {
+        let sub_meshes = self.sub_meshes.iter().filter_map(|sub_mesh| sub_mesh.texture.as_ref());
+        self.texture.iter().chain(&self.emission_map).chain(&self.lightmap).chain(sub_meshes)
+    }
+
+    pub(crate) fn parts(&self) -> impl Iterator<Item = Part<'_>> {
+        let indices = self.buffers.indices();
+        let whole = self.sub_meshes.is_empty().then(|| Part {
+            material: &self.material,
+            texture: self.texture.as_ref(),
+            indices: indices.first().map(|indices| &indices[..]),
+        });
+        let sub_meshes = self.sub_meshes.iter().zip(indices).map(|(sub_mesh, indices)| Part {
+            material: &sub_mesh.material,
+            texture: sub_mesh.texture.as_ref(),
+            indices: Some(&indices[..]),
+        });
+
+        whole.into_iter().chain(sub_meshes)
+    }
+
+    // running out of linear memory is an io::ErrorKind::OutOfMemory, with an OutOfMemory inside, and
+    // a texture that isn't a t3x is io::ErrorKind::InvalidData. meshes in the file with the same
+    // texture share it. each mesh is in its own space, without
+    // the file's nodes putting it where it goes; see Model::from_file_data for that
+    pub fn from_file_data(reader: impl Read) -> io::Result<Vec<Mesh>> {
+        Self::from_file_data_in(reader, &mut TextureCache::new())
+    }
+
+    // like from_file_data, with the textures shared with everything else loaded through `textures`
+    // too, e.g. Renderer::texture_cache
+    pub fn from_file_data_in(reader: impl Read, textures: &mut TextureCache) -> io::Result<Vec<Mesh>> {
+        mm3ds_format::read_mesh_file(reader)?
+            .iter()
+            .map(|data| Mesh::try_from_mesh_data_in(data, textures))
+            .collect()
+    }
+
+    pub fn from_mesh_data(data: &MeshData) -> Self {
+        Self::try_from_mesh_data(data).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    pub fn try_from_mesh_data(data: &MeshData) -> io::Result<Self> {
+        Self::try_from_mesh_data_in(data, &mut TextureCache::new())
+    }
+
+    pub fn try_from_mesh_data_in(data: &MeshData, textures: &mut TextureCache) -> io::Result<Self> {
+        let material = material_of(data);
+        // the file allows at most one of these. the textures come from the cache after
+        let mut mesh = if let Some(skin) = &data.skin {
+            Self::try_from_skinned_data(&data.vertices, skin, Some(&data.indices), None, material)?
+        } else if let Some(colors) = &data.colors {
+            Self::try_from_streamed_data(&data.vertices, &[colors.as_slice()], Some(&data.indices), None, material)?
+        } else if let Some(lightmap) = &data.lightmap {
+            Self::try_from_lightmapped_data(&data.vertices, &lightmap.uvs, Some(&data.indices), None, &lightmap.texture, material)?
+        } else {
+            Self::try_from_data(&data.vertices, Some(&data.indices), None, material)?
+        };
+        mesh.flipbook = data.flipbook;
+        mesh.texture = cached_t3x(data.texture.as_deref(), textures)?;
+        mesh.emission_map = cached_t3x(data.emission_map.as_deref(), textures)?;
+
+        Ok(mesh)
+    }
+
+    // like from_file_data, but the meshes that can share a vertex buffer do, as the sub-meshes of as
+    // few meshes as the vertex limit allows. meshes with more than positions, uvs and normals
+    // (lightmaps, skins, colors), flipbook meshes and ones with emission maps are kept apart, after
+    // the shared ones, since they need a draw of their own anyway
+    pub fn from_file_data_shared(reader: impl Read) -> io::Result<Vec<Mesh>> {
+        Self::from_file_data_shared_in(reader, &mut TextureCache::new())
+    }
+
+    // see from_file_data_in
+    pub fn from_file_data_shared_in(reader: impl Read, textures: &mut TextureCache) -> io::Result<Vec<Mesh>> {
+        let file = mm3ds_format::read_mesh_file(reader)?;
+        let plain = |data: &&MeshData| data.lightmap.is_none() && data.skin.is_none() && data.colors.is_none() && data.flipbook.is_none() && data.emission_map.is_none();
+        let (plain, apart): (Vec<_>, Vec<_>) = file.iter().partition(plain);
+
+        let mut ret = Vec::new();
+        let mut group: Vec<&MeshData> = Vec::new();
+        let mut n_vertices = 0;
+        for data in plain {
+            if n_vertices + data.vertices.len() > MAX_SHARED_VERTICES && !group.is_empty() {
+                ret.push(Self::try_from_shared(&group, textures)?);
+                (group, n_vertices) = (Vec::new(), 0);
+            }
+            n_vertices += data.vertices.len();
+            group.push(data);
+        }
+        if !group.is_empty() {
+            ret.push(Self::try_from_shared(&group, textures)?);
+        }
+
+        for data in apart {
+            ret.push(Self::try_from_mesh_data_in(data, textures)?);
+        }
+
+        Ok(ret)
+    }
+
+    fn try_from_shared(group: &[&MeshData], textures: &mut TextureCache) -> io::Result<Self> {
+        let mut vertices = Vec::new();
+        let mut indices = Vec::with_capacity(group.len());
+        for data in group {
+            let base = vertices.len() as u16;
+            vertices.extend_from_slice(&data.vertices);
+            indices.push(data.indices.iter().map(|i| base + i).collect::<Vec<_>>());
+        }
+
+        let parts: Vec<_> = group.iter().zip(&indices).map(|(data, indices)| SubMeshData {
+            indices,
+            t3x_data: None,
+            material: material_of(data),
+        }).collect();
+        let mut ret = Self::try_from_sub_mesh_data(&vertices, &parts)?;
+        for (sub_mesh, data) in ret.sub_meshes.iter_mut().zip(group) {
+            sub_mesh.texture = cached_t3x(data.texture.as_deref(), textures)?;
+        }
+
+        Ok(ret)
+    }
+
+    // a mesh made of parts that share one vertex buffer, each drawn as its own range of indices with
+    // its own material and texture, e.g. the material groups of a converted model. Renderer::
+    // set_material sets every part's
+    pub fn from_sub_mesh_data(vertices: &[Vertex], parts: &[SubMeshData]) -> Self {
+        Self::try_from_sub_mesh_data(vertices, parts).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    pub fn try_from_sub_mesh_data(vertices: &[Vertex], parts: &[SubMeshData]) -> io::Result<Self> {
+        assert!(!parts.is_empty(), "a mesh needs at least one sub-mesh");
+        let buffers = VertexBuffers::new(linear_pool::try_copy("vertex buffer", vertices)?);
+
+        let mut index_data = Vec::new();
+        let mut sub_meshes = Vec::with_capacity(parts.len());
+        let mut owned_textures = Vec::new();
+        for part in parts {
+            let start = index_data.len();
+            index_data.extend_from_slice(part.indices);
+            sub_meshes.push(SubMesh {
+                material: part.material,
+                texture: part.t3x_data.map(|t3x_data| load_owned_t3x(t3x_data, &mut owned_textures)).transpose()?,
+                range: start..index_data.len(),
+            });
+        }
+
+        let mut ret = Self::try_build(buffers, Some(index_data), sub_meshes, None, Material::default())?;
+        ret.owned_textures = owned_textures;
+        Ok(ret)
+    }
+
+    pub fn flipbook(&self) -> Option<&Flipbook> {
+        self.flipbook.as_ref()
+    }
+
+    // panics if there isn't enough linear memory for it or the t3x is bad, the try_ version returns
+    // the error, as from_file_data does
+    pub fn from_data(vertices: &[Vertex], indices: Option<&[u16]>, t3x_data: Option<&[u8]>, material: Material) -> Self {
+        Self::try_from_data(vertices, indices, t3x_data, material).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    pub fn try_from_data(vertices: &[Vertex], indices: Option<&[u16]>, t3x_data: Option<&[u8]>, material: Material) -> io::Result<Self> {
+        let vbo_data = linear_pool::try_copy("vertex buffer", vertices)?;
+        Self::try_from_data_prealloc(vbo_data, indices, t3x_data, material)
+    }
+
+    // a mesh drawn with the skinned shader, which moves each vertex with the bones in `skin` (one
+    // per vertex). pose it with Renderer::set_bones
+    pub fn from_skinned_data(vertices: &[Vertex], skin: &[SkinVertex], indices: Option<&[u16]>, t3x_data: Option<&[u8]>, material: Material) -> Self {
+        Self::try_from_skinned_data(vertices, skin, indices, t3x_data, material).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    pub fn try_from_skinned_data(vertices: &[Vertex], skin: &[SkinVertex], indices: Option<&[u16]>, t3x_data: Option<&[u8]>, material: Material) -> io::Result<Self> {
+        assert_eq!(vertices.len(), skin.len(), "every vertex needs a SkinVertex");
+        let buffers = VertexBuffers {
+            skin: Some(linear_pool::try_copy("skin buffer", skin)?),
+            ..VertexBuffers::new(linear_pool::try_copy("vertex buffer", vertices)?)
+        };
+
+        let mut mesh = Self::try_from_buffers(buffers, indices, t3x_data, material)?;
+        mesh.bones = vec![[Vec4::X, Vec4::Y, Vec4::Z]; MAX_BONES];
+        Ok(mesh)
+    }
+
+    // a mesh with baked lighting (see gltf_tool's lightmap_size), laid over it with a second set of
+    // uvs, one per vertex. it's drawn with the lightmap shader while the material's lightmap flag is
+    // set. can't be skinned as well
+    pub fn from_lightmapped_data(vertices: &[Vertex], lightmap_uvs: &[[f32; 2]], indices: Option<&[u16]>, t3x_data: Option<&[u8]>, lightmap_t3x: &[u8], material: Material) -> Self {
+        Self::try_from_lightmapped_data(vertices, lightmap_uvs, indices, t3x_data, lightmap_t3x, material).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    pub fn try_from_lightmapped_data(vertices: &[Vertex], lightmap_uvs: &[[f32; 2]], indices: Option<&[u16]>, t3x_data: Option<&[u8]>, lightmap_t3x: &[u8], material: Material) -> io::Result<Self> {
+        assert_eq!(vertices.len(), lightmap_uvs.len(), "every vertex needs a lightmap uv");
+        let buffers = VertexBuffers {
+            lightmap_uvs: Some(linear_pool::try_copy("lightmap uv buffer", lightmap_uvs)?),
+            ..VertexBuffers::new(linear_pool::try_copy("vertex buffer", vertices)?)
+        };
+
+        let mut owned_textures = Vec::new();
+        let lightmap = load_owned_t3x(lightmap_t3x, &mut owned_textures)?;
+        let mut mesh = Self::try_from_buffers(buffers, indices, t3x_data, material)?;
+        mesh.lightmap = Some(lightmap);
+        mesh.owned_textures.extend(owned_textures);
+        Ok(mesh)
+    }
+
+    // a mesh with more attributes than the usual three, each in a buffer of its own (a stream) so it
+    // can be changed without touching the rest, e.g. colors that change every frame on a mesh that
+    // doesn't move. `streams[i]` has one value per vertex, and goes in v3+i for shaders to read; see
+    // shaders/vertex_color.v.pica, which draws it unless it has a shader of its own. change them
+    // with Renderer::update_stream. can't be skinned or lightmapped as well, since those use v3 too
+    pub fn from_streamed_data(vertices: &[Vertex], streams: &[&[[f32; 4]]], indices: Option<&[u16]>, t3x_data: Option<&[u8]>, material: Material) -> Self {
+        Self::try_from_streamed_data(vertices, streams, indices, t3x_data, material).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    pub fn try_from_streamed_data(vertices: &[Vertex], streams: &[&[[f32; 4]]], indices: Option<&[u16]>, t3x_data: Option<&[u8]>, material: Material) -> io::Result<Self> {
+        assert!(streams.len() <= MAX_STREAMS, "{} streams, the limit is {MAX_STREAMS}", streams.len());
+        assert!(streams.iter().all(|stream| stream.len() == vertices.len()), "every stream needs a value for every vertex");
+        let buffers = VertexBuffers {
+            streams: streams.iter().map(|stream| linear_pool::try_copy("stream buffer", stream)).collect::<Result<_, _>>()?,
+            ..VertexBuffers::new(linear_pool::try_copy("vertex buffer", vertices)?)
+        };
+
+        Self::try_from_buffers(buffers, indices, t3x_data, material)
+    }
+
+    pub fn from_data_prealloc(vbo_data: Vec<Vertex, LinearPool>, indices: Option<&[u16]>, t3x_data: Option<&[u8]>, material: Material) -> Self {
+        Self::try_from_data_prealloc(vbo_data, indices, t3x_data, material).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    pub fn try_from_data_prealloc(vbo_data: Vec<Vertex, LinearPool>, indices: Option<&[u16]>, t3x_data: Option<&[u8]>, material: Material) -> io::Result<Self> {
+        Self::try_from_buffers(VertexBuffers::new(vbo_data), indices, t3x_data, material)
+    }
+
+    fn try_from_buffers(buffers: VertexBuffers, indices: Option<&[u16]>, t3x_data: Option<&[u8]>, material: Material) -> io::Result<Self> {
+        let mut owned_textures = Vec::new();
+        let texture = t3x_data.map(|t3x_data| load_owned_t3x(t3x_data, &mut owned_textures)).transpose()?;
+        let mut ret = Self::try_build(buffers, indices.map(<[u16]>::to_vec), Vec::new(), texture, material)?;
+        ret.owned_textures = owned_textures;
+        Ok(ret)
+    }
+
+    fn try_build(
+        buffers: VertexBuffers,
+        index_data: Option<Vec<u16>>,
+        sub_meshes: Vec<SubMesh>,
+        texture: Option<sys::C3D_Tex>,
+        material: Material,
+    ) -> Result<Self, OutOfMemory> {
+        let bounds = Aabb::from_points(buffers.vertices.iter().map(|vertex| Vec3::from(vertex.pos)));
+        let gpu_buffers = GpuBuffers::new(buffers, &index_lists(index_data.as_deref(), &sub_meshes))?;
+
+        Ok(Mesh {
+            material,
+            reflectivity: 0.,
+            flipbook: None,
+            shader: None,
+            params: Vec::new(),
+            param_uniforms: Vec::new(),
+            playback: Playback::default(),
+            bones: Vec::new(),
+            lightmap: None,
+            emission_map: None,
+            index_data,
+            bounds,
+            texture,
+            sub_meshes,
+            buffers: gpu_buffers,
+            owned_textures: Vec::new(),
+        })
+    }
+
+    // moves its vertex buffers into lower blocks of the pool, if there are free ones, so that the
+    // slabs above can empty out. what it was using is handed back instead of freed, since the GPU
+    // could still be drawing from it. the index buffers stay where they are, so a buffer that
+    // doesn't fit lower down is just left, and nothing can run out of memory. see
+    // Renderer::compact_meshes
+    pub(crate) fn compact(&mut self) -> Option<RetiredBuffers> {
+        let current = self.buffers.buffers();
+        let vertices = relocated(&current.vertices);
+        let skin = current.skin.as_deref().and_then(relocated);
+        let lightmap_uvs = current.lightmap_uvs.as_deref().and_then(relocated);
+        let mut streams: Vec<_> = current.streams.iter().map(|stream| relocated(stream)).collect();
+        if vertices.is_none() && skin.is_none() && lightmap_uvs.is_none() && streams.iter().all(Option::is_none) {
+            return None;
+        }
+
+        // the ones that didn't move carry on where they are
+        let (mut buffers, indices) = self.buffers.take();
+        let mut replaced = VertexBuffers::new(Vec::new_in(LinearPool));
+        if let Some(vertices) = vertices {
+            replaced.vertices = mem::replace(&mut buffers.vertices, vertices);
+        }
+        if let Some(skin) = skin {
+            replaced.skin = buffers.skin.replace(skin);
+        }
+        if let Some(uvs) = lightmap_uvs {
+            replaced.lightmap_uvs = buffers.lightmap_uvs.replace(uvs);
+        }
+        for (stream, moved) in buffers.streams.iter_mut().zip(&mut streams) {
+            if let Some(moved) = moved.take() {
+                replaced.streams.push(mem::replace(stream, moved));
+            }
+        }
+
+        let new = GpuBuffers::with_indices(buffers, indices);
+        Some(RetiredBuffers {
+            _buffers: mem::replace(&mut self.buffers, new),
+            _replaced: replaced,
+        })
+    }
+
+    // a write of `data` over stream `i` from vertex `start` on, for the renderer to do once the GPU
+    // isn't reading it. see Renderer::update_stream
+    pub(crate) fn write_stream(&mut self, i: usize, start: usize, data: Vec<[f32; 4]>) -> StreamWrite {
+        let stream = self.buffers.stream_mut(i);
+        assert!(start + data.len() <= stream.len(), "stream {i} has {} values, not {}", stream.len(), start + data.len());
+
+        StreamWrite { to: NonNull::new(stream[start..].as_mut_ptr()).unwrap(), data }
+    }
+
+    // where its vertex buffer is, for compacting the highest ones first
+    pub(crate) fn vertices_addr(&self) -> usize {
+        self.vertices().as_ptr().expose_provenance()
+    }
+}
+
+pub(crate) struct StreamWrite {
+    to: NonNull<[f32; 4]>,
+    data: Vec<[f32; 4]>,
+}
+
+impl StreamWrite {
+    // # Safety
+    // the mesh hasn't been dropped or compacted since, and the GPU isn't drawing it
+    pub(crate) unsafe fn apply(&self) {
+        unsafe {
+            ptr::copy_nonoverlapping(self.data.as_ptr(), self.to.as_ptr(), self.data.len());
+            // the GPU reads linear memory, not the CPU's cache
+            ctru_sys::GSPGPU_FlushDataCache(self.to.as_ptr().cast(), size_of_val(&self.data[..]) as u32);
+        }
+    }
+}
+
+fn material_of(data: &MeshData) -> Material {
+    Material {
+        diffuse: Vec4::from(data.color).into(),
+        emission: Vec3::from(data.emission).extend(1.).into(),
+        lightmap: data.lightmap.is_some(),
+        alpha_test: AlphaTest::from_mode(data.alpha),
+        double_sided: data.double_sided,
+        blend: BlendMode::from_mode(data.alpha),
+        ..Default::default()
+    }
+}
+
+// what a mesh was using before it was compacted, kept until the GPU's done with it
+pub(crate) struct RetiredBuffers {
+    _buffers: GpuBuffers,
+    _replaced: VertexBuffers,
+}
+
+// a copy of `data` lower down in the pool, if there's room for one
+fn relocated<T: Copy>(data: &[T]) -> Option<Vec<T, LinearPool>> {
+    if !linear_pool::has_lower_block(data.as_ptr().cast(), Layout::array::<T>(data.len()).ok()?) {
+        return None;
+    }
+
+    linear_pool::try_copy("vertex buffer", data).ok()
+}
+
+// a mesh's own texture, kept in `owned` straight away so that it's freed whatever fails after
+fn load_owned_t3x(t3x_data: &[u8], owned: &mut Vec<Rc<OwnedTexture>>) -> io::Result<sys::C3D_Tex> {
+    let texture = load_pooled_t3x(t3x_data)?;
+    owned.push(Rc::new(OwnedTexture(texture)));
+    Ok(texture)
+}
+
+// a texture in the pool. free it with linear_pool::delete_texture, as OwnedTexture's drop does
+pub(crate) fn load_pooled_t3x(t3x_data: &[u8]) -> io::Result<sys::C3D_Tex> {
+    let mut texture = try_load_t3x(t3x_data)?;
+    linear_pool::pool_texture(&mut texture);
+    Ok(texture)
+}
+
+// a copy of the cache's, which the mesh shares
+fn cached_t3x(t3x_data: Option<&[u8]>, textures: &mut TextureCache) -> io::Result<Option<sys::C3D_Tex>> {
+    t3x_data.map(|t3x_data| textures.load(t3x_data).map(|id| *textures.get(id))).transpose()
+}
+
+// how much memory a t3x's texture takes once it's loaded, from its header: a u16 (the number of
+// subtextures), then the width and height as 3 bits each of log2(size) - 3 and a cubemap bit, then
+// the format and the number of mipmaps
+fn t3x_size(t3x_data: &[u8]) -> usize {
+    let [_, _, dimensions, format, mipmaps, ..] = *t3x_data else {
+        return 0;
+    };
+
+    let width = 8usize << (dimensions & 7);
+    let height = 8usize << ((dimensions >> 3) & 7);
+    let faces = if dimensions & 0x40 != 0 { 6 } else { 1 };
+    let bits = match format as u32 {
+        ctru_sys::GPU_RGBA8 => 32,
+        ctru_sys::GPU_RGB8 => 24,
+        ctru_sys::GPU_RGBA5551 | ctru_sys::GPU_RGB565 | ctru_sys::GPU_RGBA4 | ctru_sys::GPU_LA8 | ctru_sys::GPU_HILO8 => 16,
+        ctru_sys::GPU_L8 | ctru_sys::GPU_A8 | ctru_sys::GPU_LA4 | ctru_sys::GPU_ETC1A4 => 8,
+        _ => 4,
+    };
+
+    let base = width * height * bits / 8;
+    faces * (0..=mipmaps as usize).map(|level| base >> (2 * level)).sum::<usize>()
+}
+
+// like load_t3x, but running out of memory for the texture, or a t3x that isn't one, is an error
+// instead of a panic
+pub(crate) fn try_load_t3x(t3x_data: &[u8]) -> io::Result<sys::C3D_Tex> {
+    let mut texture = MaybeUninit::<sys::C3D_Tex>::uninit();
+    unsafe {
+        let t3x = sys::Tex3DS_TextureImport(
+            t3x_data.as_ptr().cast(),
+            t3x_data.len(),
+            texture.as_mut_ptr(),
+            ptr::null_mut(),
+            false
+        );
+
+        if t3x.is_null() {
+            let error = OutOfMemory::new("texture", t3x_size(t3x_data));
+            // with that much room, it was the t3x that was wrong
+            if error.requested <= error.largest_block {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid t3x data"));
+            }
+            return Err(error.into());
+        }
+        // "Delete the t3x object since we don't need it."
+        sys::Tex3DS_TextureFree(t3x);
+
+        sys::C3D_TexSetFilter(texture.as_mut_ptr(), ctru_sys::GPU_LINEAR, ctru_sys::GPU_NEAREST);
+    }
+
+    Ok(unsafe { texture.assume_init() })
+}
+
+pub(crate) fn load_t3x(t3x_data: &[u8]) -> sys::C3D_Tex {
+    try_load_t3x(t3x_data).unwrap_or_else(|e| panic!("{e}"))
+}
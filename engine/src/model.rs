@@ -0,0 +1,131 @@
+// several meshes drawn as one thing, e.g. everything in a MESH file, with one handle and one
+// transform instead of a Vec of MeshIds to loop over:
+//
+//     let character = Model::from_file_data(&mut renderer, file)?;
+//     let character = renderer.register_model(character);
+//     renderer.please_render_model(character, model);
+//
+// the meshes can hang off a hierarchy of nodes, each placed relative to its parent, so that moving
+// an arm (set_transform) takes the hand with it. a model's skinned meshes share a skeleton, posed
+// all at once with Renderer::set_model_bones
+use std::io;
+use std::io::Read;
+
+use glam::Mat4;
+
+use crate::mesh::Mesh;
+use crate::renderer::{MeshId, Renderer};
+
+pub struct ModelNode {
+    pub name: String, // from the MESH file, or empty
+    pub parent: Option<usize>, // always a node before this one
+    pub transform: Mat4, // relative to the parent, or to the model
+    pub meshes: Vec<MeshId>,
+}
+
+pub struct Model {
+    nodes: Vec<ModelNode>, // the first is the root
+    world: Vec<Mat4>, // each node's transform relative to the model
+    skinned: Vec<MeshId>,
+}
+
+impl Model {
+    // a model of just a root node holding `meshes`
+    pub fn new(meshes: Vec<MeshId>) -> Self {
+        Self {
+            nodes: vec![ModelNode { name: String::new(), parent: None, transform: Mat4::IDENTITY, meshes }],
+            world: vec![Mat4::IDENTITY],
+            skinned: Vec::new(),
+        }
+    }
+
+    // registers every mesh with the renderer, in the root node
+    pub fn from_meshes(renderer: &mut Renderer, meshes: impl IntoIterator<Item = Mesh>) -> Self {
+        let mut skinned = Vec::new();
+        let ids = meshes.into_iter().map(|mesh| {
+            let is_skinned = mesh.is_skinned();
+            let id = renderer.register_mesh(mesh);
+            if is_skinned {
+                skinned.push(id);
+            }
+            id
+        }).collect();
+
+        Self { skinned, ..Self::new(ids) }
+    }
+
+    // everything in a MESH file, with its nodes under the root (node i of the file is node i + 1)
+    // and each drawing its meshes. a mesh on more than one node is registered once and drawn on
+    // each. a file without nodes has its meshes in the root, like from_meshes. textures are shared
+    // through Renderer::texture_cache, and the errors are Mesh::from_file_data's
+    pub fn from_file_data(renderer: &mut Renderer, reader: impl Read) -> io::Result<Self> {
+        let scene = mm3ds_format::read_scene_file(reader)?;
+        let meshes = scene.meshes.iter()
+            .map(|data| Mesh::try_from_mesh_data_in(data, renderer.texture_cache()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut ret = Self::from_meshes(renderer, meshes);
+        if scene.nodes.is_empty() {
+            return Ok(ret);
+        }
+
+        let ids = std::mem::take(&mut ret.nodes[0].meshes);
+        for node in &scene.nodes {
+            let parent = node.parent.map_or(0, |parent| parent as usize + 1);
+            let meshes = node.meshes.iter().map(|&mesh| ids[mesh as usize]).collect();
+            let i = ret.add_node(parent, Mat4::from_cols_array(&node.transform), meshes);
+            ret.nodes[i].name = node.name.clone();
+        }
+
+        Ok(ret)
+    }
+
+    // adds a node under `parent` and returns its index. panics if there's no such node
+    pub fn add_node(&mut self, parent: usize, transform: Mat4, meshes: Vec<MeshId>) -> usize {
+        assert!(parent < self.nodes.len(), "there's no node {parent}");
+        self.world.push(self.world[parent] * transform);
+        self.nodes.push(ModelNode { name: String::new(), parent: Some(parent), transform, meshes });
+
+        self.nodes.len() - 1
+    }
+
+    // marks meshes as skinned with the model's skeleton, for Renderer::set_model_bones. from_meshes
+    // does this for the ones it registers
+    pub fn add_skinned(&mut self, mesh_id: MeshId) {
+        self.skinned.push(mesh_id);
+    }
+
+    pub fn nodes(&self) -> &[ModelNode] {
+        &self.nodes
+    }
+
+    // the first node called `name`, e.g. an arm to move with set_transform
+    pub fn node(&self, name: &str) -> Option<usize> {
+        self.nodes.iter().position(|node| node.name == name)
+    }
+
+    pub fn set_transform(&mut self, node: usize, transform: Mat4) {
+        self.nodes[node].transform = transform;
+        // children always come after their parents, so one pass from here on is enough
+        for i in node..self.nodes.len() {
+            let parent = self.nodes[i].parent.map_or(Mat4::IDENTITY, |parent| self.world[parent]);
+            self.world[i] = parent * self.nodes[i].transform;
+        }
+    }
+
+    // every mesh in the model, with where it is relative to the model
+    pub fn meshes(&self) -> impl Iterator<Item = (MeshId, Mat4)> + '_ {
+        self.nodes.iter().zip(&self.world).flat_map(|(node, &world)| node.meshes.iter().map(move |&mesh_id| (mesh_id, world)))
+    }
+
+    pub fn skinned(&self) -> &[MeshId] {
+        &self.skinned
+    }
+
+    // takes an unregistered mesh off every node, see Renderer::unregister_mesh
+    pub(crate) fn forget_mesh(&mut self, mesh_id: MeshId) {
+        for node in &mut self.nodes {
+            node.meshes.retain(|&id| id != mesh_id);
+        }
+        self.skinned.retain(|&id| id != mesh_id);
+    }
+}
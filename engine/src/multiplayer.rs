@@ -0,0 +1,374 @@
+// local wireless multiplayer over the UDS service (what download play and most local multiplayer
+// games use). one console hosts a session, the others scan for it and join. UDS itself only
+// delivers unreliable datagrams, so reliable messages are numbered per peer, acknowledged, and
+// resent until acknowledged. they arrive in the order they were sent
+use std::collections::{HashMap, VecDeque};
+use std::ffi::c_void;
+use std::time::{Duration, Instant};
+
+const DATA_CHANNEL: u8 = 1;
+const SCAN_BUFFER_SIZE: usize = 0x4000;
+const RESEND_INTERVAL: Duration = Duration::from_millis(100);
+
+// the largest payload that fits in one frame, after our header
+pub const MAX_MESSAGE_SIZE: usize = ctru_sys::UDS_DATAFRAME_MAXSIZE as usize - HEADER_SIZE;
+
+const HEADER_SIZE: usize = 3;
+const KIND_UNRELIABLE: u8 = 0;
+const KIND_RELIABLE: u8 = 1;
+const KIND_ACK: u8 = 2;
+
+unsafe extern "C" {
+    fn free(ptr: *mut c_void);
+}
+
+fn check(res: ctru_sys::Result) -> ctru::Result<()> {
+    if ctru_sys::R_FAILED(res) {
+        Err(res.into())
+    } else {
+        Ok(())
+    }
+}
+
+fn username(node: &ctru_sys::udsNodeInfo) -> String {
+    let mut buf = [0u8; 64];
+    if ctru_sys::R_FAILED(unsafe { ctru_sys::udsGetNodeInfoUsername(node, buf.as_mut_ptr().cast()) }) {
+        return String::new();
+    }
+
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    String::from_utf8_lossy(&buf[..len]).into_owned()
+}
+
+// a UDS network node id. the host is always HOST (1), clients are numbered from 2
+pub type NodeId = u16;
+
+pub const HOST: NodeId = ctru_sys::UDS_HOST_NETWORKNODEID as NodeId;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Target {
+    Node(NodeId),
+    Everyone,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Channel {
+    // may be dropped, duplicated, or arrive out of order. use for state that's resent every frame
+    Unreliable,
+    // always arrives, exactly once and in order
+    Reliable,
+}
+
+#[derive(Debug)]
+pub enum Event {
+    Joined(NodeId),
+    Left(NodeId), // a client sees the host leaving as Left(HOST), which ends the session
+    Message { from: NodeId, channel: Channel, data: Vec<u8> },
+}
+
+// a session found by Uds::scan
+pub struct Network {
+    info: ctru_sys::udsNetworkScanInfo,
+}
+
+impl Network {
+    pub fn host_name(&self) -> String {
+        username(&self.info.nodes[0])
+    }
+
+    pub fn players(&self) -> u8 {
+        self.info.network.total_nodes
+    }
+
+    pub fn max_players(&self) -> u8 {
+        self.info.network.max_nodes
+    }
+}
+
+// keeps the UDS service running
+pub struct Uds {
+    _private: (),
+}
+
+impl Uds {
+    pub fn new() -> ctru::Result<Self> {
+        check(unsafe { ctru_sys::udsInit(0x3000, std::ptr::null()) })?;
+        Ok(Self { _private: () })
+    }
+
+    // `comm_id` identifies your game, sessions from other games aren't listed. takes about a second
+    pub fn scan(&mut self, comm_id: u32) -> ctru::Result<Vec<Network>> {
+        let mut buf = vec![0u8; SCAN_BUFFER_SIZE];
+        let mut networks = std::ptr::null_mut();
+        let mut total = 0;
+
+        check(unsafe {
+            ctru_sys::udsScanBeacons(
+                buf.as_mut_ptr().cast(),
+                buf.len(),
+                &mut networks,
+                &mut total,
+                comm_id,
+                0,
+                std::ptr::null(),
+                false,
+            )
+        })?;
+
+        if networks.is_null() {
+            return Ok(Vec::new());
+        }
+
+        // libctru mallocs this list for us
+        let ret = unsafe { std::slice::from_raw_parts(networks, total) }
+            .iter()
+            .map(|&info| Network { info })
+            .collect();
+        unsafe { free(networks.cast()); }
+
+        Ok(ret)
+    }
+
+    pub fn host(&mut self, comm_id: u32, max_players: u8, passphrase: &[u8]) -> ctru::Result<Session<'_>> {
+        let mut network = unsafe { std::mem::zeroed() };
+        let mut bind = Box::new(unsafe { std::mem::zeroed() });
+
+        unsafe {
+            ctru_sys::udsGenerateDefaultNetworkStruct(&mut network, comm_id, 0, max_players);
+            check(ctru_sys::udsCreateNetwork(
+                &network,
+                passphrase.as_ptr().cast(),
+                passphrase.len(),
+                &mut *bind,
+                DATA_CHANNEL,
+                ctru_sys::UDS_DEFAULT_RECVBUFSIZE,
+            ))?;
+        }
+
+        Ok(Session::new(self, bind, true))
+    }
+
+    pub fn join(&mut self, network: &Network, passphrase: &[u8]) -> ctru::Result<Session<'_>> {
+        let mut bind = Box::new(unsafe { std::mem::zeroed() });
+
+        check(unsafe {
+            ctru_sys::udsConnectNetwork(
+                &network.info.network,
+                passphrase.as_ptr().cast(),
+                passphrase.len(),
+                &mut *bind,
+                ctru_sys::UDS_BROADCAST_NETWORKNODEID as u16,
+                ctru_sys::UDSCONTYPE_Client,
+                DATA_CHANNEL,
+                ctru_sys::UDS_DEFAULT_RECVBUFSIZE,
+            )
+        })?;
+
+        Ok(Session::new(self, bind, false))
+    }
+}
+
+impl Drop for Uds {
+    fn drop(&mut self) {
+        unsafe { ctru_sys::udsExit(); }
+    }
+}
+
+#[derive(Default)]
+struct Peer {
+    next_send: u16,
+    next_recv: u16,
+    unacked: VecDeque<(u16, Vec<u8>)>,
+    last_resend: Option<Instant>,
+}
+
+// a hosted or joined session, left on drop
+pub struct Session<'uds> {
+    _uds: &'uds mut Uds,
+    bind: Box<ctru_sys::udsBindContext>,
+    is_host: bool,
+
+    node_bitmask: u16,
+    peers: HashMap<NodeId, Peer>,
+    events: VecDeque<Event>,
+    packet: Vec<u8>,
+}
+
+impl<'uds> Session<'uds> {
+    fn new(uds: &'uds mut Uds, bind: Box<ctru_sys::udsBindContext>, is_host: bool) -> Self {
+        Self {
+            _uds: uds,
+            bind,
+            is_host,
+
+            node_bitmask: 0,
+            peers: HashMap::new(),
+            events: VecDeque::new(),
+            packet: vec![0; ctru_sys::UDS_DATAFRAME_MAXSIZE as usize],
+        }
+    }
+
+    pub fn is_host(&self) -> bool {
+        self.is_host
+    }
+
+    pub fn node_id(&self) -> NodeId {
+        self.status().cur_NetworkNodeID
+    }
+
+    // everyone in the session, including us
+    pub fn nodes(&self) -> impl Iterator<Item = NodeId> {
+        let bitmask = self.node_bitmask;
+        (0..16).filter(move |i| bitmask & (1 << i) != 0).map(|i| i + 1)
+    }
+
+    fn status(&self) -> ctru_sys::udsConnectionStatus {
+        let mut status = unsafe { std::mem::zeroed() };
+        unsafe { ctru_sys::udsGetConnectionStatus(&mut status); }
+        status
+    }
+
+    fn send_raw(&self, to: NodeId, kind: u8, seq: u16, data: &[u8]) {
+        let mut frame = Vec::with_capacity(HEADER_SIZE + data.len());
+        frame.push(kind);
+        frame.extend_from_slice(&seq.to_le_bytes());
+        frame.extend_from_slice(data);
+
+        // an unreachable peer shows up as a leave, so send errors aren't interesting
+        unsafe {
+            ctru_sys::udsSendTo(to, DATA_CHANNEL, ctru_sys::UDS_SENDFLAG_Default as u8, frame.as_ptr().cast(), frame.len());
+        }
+    }
+
+    // panics if `data` is longer than MAX_MESSAGE_SIZE
+    pub fn send(&mut self, to: Target, channel: Channel, data: &[u8]) {
+        assert!(data.len() <= MAX_MESSAGE_SIZE, "message is {} bytes, the limit is {MAX_MESSAGE_SIZE}", data.len());
+
+        let me = self.node_id();
+        let targets = match to {
+            Target::Node(node) => vec![node],
+            Target::Everyone => self.nodes().filter(|&node| node != me).collect(),
+        };
+
+        for node in targets {
+            match channel {
+                Channel::Unreliable => self.send_raw(node, KIND_UNRELIABLE, 0, data),
+                Channel::Reliable => {
+                    let peer = self.peers.entry(node).or_default();
+                    let seq = peer.next_send;
+                    peer.next_send = seq.wrapping_add(1);
+                    peer.unacked.push_back((seq, data.to_vec()));
+                    self.send_raw(node, KIND_RELIABLE, seq, data);
+                }
+            }
+        }
+    }
+
+    // receives packets, resends unacknowledged ones and notices joins and leaves. call once a frame,
+    // then drain poll()
+    pub fn update(&mut self) {
+        if unsafe { ctru_sys::udsWaitConnectionStatusEvent(false, false) } {
+            self.update_nodes();
+        }
+
+        loop {
+            let (mut len, mut from) = (0, 0);
+            let res = unsafe {
+                ctru_sys::udsPullPacket(&*self.bind, self.packet.as_mut_ptr().cast(), self.packet.len(), &mut len, &mut from)
+            };
+            if ctru_sys::R_FAILED(res) || len == 0 {
+                break;
+            }
+            if len < HEADER_SIZE {
+                continue;
+            }
+
+            let kind = self.packet[0];
+            let seq = u16::from_le_bytes([self.packet[1], self.packet[2]]);
+            let data = self.packet[HEADER_SIZE..len].to_vec();
+            self.receive(from, kind, seq, data);
+        }
+
+        let now = Instant::now();
+        let mut resends = Vec::new();
+        for (&node, peer) in &mut self.peers {
+            if peer.unacked.is_empty() || peer.last_resend.is_some_and(|t| now - t < RESEND_INTERVAL) {
+                continue;
+            }
+
+            peer.last_resend = Some(now);
+            resends.extend(peer.unacked.iter().map(|(seq, data)| (node, *seq, data.clone())));
+        }
+        for (node, seq, data) in resends {
+            self.send_raw(node, KIND_RELIABLE, seq, &data);
+        }
+    }
+
+    fn receive(&mut self, from: NodeId, kind: u8, seq: u16, data: Vec<u8>) {
+        match kind {
+            KIND_UNRELIABLE => self.events.push_back(Event::Message { from, channel: Channel::Unreliable, data }),
+            KIND_RELIABLE => {
+                let peer = self.peers.entry(from).or_default();
+                if seq == peer.next_recv {
+                    peer.next_recv = seq.wrapping_add(1);
+                    self.events.push_back(Event::Message { from, channel: Channel::Reliable, data });
+                }
+
+                // anything else is a resend of something we already have, or arrived after a
+                // message that was lost and will be resent along with it. either way, tell the
+                // sender where we're at
+                let ack = peer.next_recv;
+                self.send_raw(from, KIND_ACK, ack, &[]);
+            }
+            KIND_ACK => {
+                // `seq` is the next message they expect, so everything before it arrived
+                let Some(peer) = self.peers.get_mut(&from) else { return };
+                while peer.unacked.front().is_some_and(|&(sent, _)| (seq.wrapping_sub(sent) as i16) > 0) {
+                    peer.unacked.pop_front();
+                    peer.last_resend = None;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn update_nodes(&mut self) {
+        let status = self.status();
+        let me = status.cur_NetworkNodeID;
+        let (old, new) = (self.node_bitmask, status.node_bitmask);
+        self.node_bitmask = new;
+
+        for i in 0..16 {
+            let node = i + 1;
+            let bit = 1 << i;
+            if node == me || old & bit == new & bit {
+                continue;
+            }
+
+            if new & bit != 0 {
+                self.peers.entry(node).or_default();
+                self.events.push_back(Event::Joined(node));
+            } else {
+                self.peers.remove(&node);
+                self.events.push_back(Event::Left(node));
+            }
+        }
+    }
+
+    pub fn poll(&mut self) -> Option<Event> {
+        self.events.pop_front()
+    }
+}
+
+impl Drop for Session<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            if self.is_host {
+                ctru_sys::udsDestroyNetwork();
+            } else {
+                ctru_sys::udsDisconnectNetwork();
+            }
+            ctru_sys::udsUnbind(&mut *self.bind);
+        }
+    }
+}
@@ -0,0 +1,128 @@
+// amiibo reading through the NFC service, either the New 3DS's built-in reader or the old 3DS's
+// NFC adapter. scanning starts as soon as an Nfc is made; call update() once a frame and handle
+// what comes out of poll()
+use std::collections::VecDeque;
+
+const APP_DATA_SIZE: usize = 0xd8;
+
+fn check(res: ctru_sys::Result) -> ctru::Result<()> {
+    if ctru_sys::R_FAILED(res) {
+        Err(res.into())
+    } else {
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Amiibo {
+    pub uid: Vec<u8>, // unique to this figure
+
+    // together these identify what the figure is. e.g. every Link figure has the same character,
+    // and variant tells them apart
+    pub character: u16,
+    pub variant: u8,
+    pub figure_type: u8, // 0 figure, 1 card, 2 yarn
+    pub amiibo_id: u16,
+    pub series: u8,
+
+    // the game-specific save area, if the figure has been set up for the app id given to Nfc::new
+    pub app_data: Option<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub enum NfcEvent {
+    Detected(Amiibo),
+    Removed,
+    // something was tapped but couldn't be read, e.g. it isn't an amiibo or was pulled away too fast
+    Failed(ctru::Error),
+}
+
+pub struct Nfc {
+    app_id: Option<u32>,
+    state: ctru_sys::NFC_TagState,
+    events: VecDeque<NfcEvent>,
+}
+
+impl Nfc {
+    // `app_id` is the 32-bit id your game's app data area was created with, if it has one
+    pub fn new(app_id: Option<u32>) -> ctru::Result<Self> {
+        check(unsafe { ctru_sys::nfcInit(ctru_sys::NFC_OpType_NFCTag) })?;
+        if let Err(e) = check(unsafe { ctru_sys::nfcStartScanning(ctru_sys::NFC_STARTSCAN_DEFAULTINPUT as u16) }) {
+            unsafe { ctru_sys::nfcExit(); }
+            return Err(e);
+        }
+
+        Ok(Self {
+            app_id,
+            state: ctru_sys::NFC_TagState_Scanning,
+            events: VecDeque::new(),
+        })
+    }
+
+    pub fn update(&mut self) {
+        let mut state = 0;
+        if ctru_sys::R_FAILED(unsafe { ctru_sys::nfcGetTagState(&mut state) }) || state == self.state {
+            return;
+        }
+        self.state = state;
+
+        match state {
+            ctru_sys::NFC_TagState_InRange => {
+                if let Err(e) = check(unsafe { ctru_sys::nfcLoadAmiiboData() }) {
+                    self.events.push_back(NfcEvent::Failed(e));
+                }
+            }
+            ctru_sys::NFC_TagState_DataReady => {
+                self.events.push_back(match self.read() {
+                    Ok(amiibo) => NfcEvent::Detected(amiibo),
+                    Err(e) => NfcEvent::Failed(e),
+                });
+            }
+            ctru_sys::NFC_TagState_OutOfRange => {
+                self.events.push_back(NfcEvent::Removed);
+                unsafe { ctru_sys::nfcResetTagScanState(); }
+            }
+            _ => {}
+        }
+    }
+
+    fn read(&self) -> ctru::Result<Amiibo> {
+        let mut info = unsafe { std::mem::zeroed::<ctru_sys::NFC_TagInfo>() };
+        let mut config = unsafe { std::mem::zeroed::<ctru_sys::NFC_AmiiboConfig>() };
+        check(unsafe { ctru_sys::nfcGetTagInfo(&mut info) })?;
+        check(unsafe { ctru_sys::nfcGetAmiiboConfig(&mut config) })?;
+
+        // figures that were never set up for this game just don't have app data, that's not an error
+        let app_data = self.app_id.and_then(|app_id| {
+            check(unsafe { ctru_sys::nfcOpenAppData(app_id) }).ok()?;
+
+            let mut data = vec![0u8; APP_DATA_SIZE];
+            check(unsafe { ctru_sys::nfcReadAppData(data.as_mut_ptr().cast(), data.len()) }).ok()?;
+            Some(data)
+        });
+
+        let uid_len = (info.id_offset_size as usize).min(info.id.len());
+        Ok(Amiibo {
+            uid: info.id[..uid_len].to_vec(),
+            character: u16::from_be_bytes([config.characterID[0], config.characterID[1]]),
+            variant: config.characterID[2],
+            figure_type: config.type_,
+            amiibo_id: config.amiiboID,
+            series: config.series,
+            app_data,
+        })
+    }
+
+    pub fn poll(&mut self) -> Option<NfcEvent> {
+        self.events.pop_front()
+    }
+}
+
+impl Drop for Nfc {
+    fn drop(&mut self) {
+        unsafe {
+            ctru_sys::nfcStopScanning();
+            ctru_sys::nfcExit();
+        }
+    }
+}
@@ -0,0 +1,113 @@
+// a picture that stretches to any size without stretching its edges, for UI panels and buttons.
+// it's cut into nine pieces by `borders`: the corners are always drawn at their own size, the
+// edges only stretch along their length, and the middle fills whatever's left:
+//
+//     let panel = NinePatch::new(&mut renderer, &assets::PANEL_T3X.read()?, Vec2::new(48., 48.), Borders::all(12.));
+//     ...
+//     panel.please_render(&mut renderer, TargetScreen::Bottom, Vec2::new(20., 20.), Vec2::new(200., 80.), Vec4::ONE);
+//
+// it's drawn in the 2d layer, so positions and sizes are in pixels, see Renderer::please_render_2d
+use citro3d::sys;
+use glam::{Mat4, Vec2, Vec4};
+
+use crate::material::Material;
+use crate::math::to_matrix4;
+use crate::mesh::{Mesh, Vertex, load_t3x};
+use crate::renderer::{MeshId, Renderer, TargetScreen};
+
+// how far in from each edge of the picture the cuts are, in its pixels
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Borders {
+    pub left: f32,
+    pub right: f32,
+    pub top: f32,
+    pub bottom: f32,
+}
+
+impl Borders {
+    pub fn all(border: f32) -> Self {
+        Self { left: border, right: border, top: border, bottom: border }
+    }
+}
+
+pub struct NinePatch {
+    pieces: [MeshId; 9], // rows from the top, left to right
+    size: Vec2,
+    borders: Borders,
+}
+
+// where the cuts are along one side, as pixels from the start, given the border at each end
+fn cuts(length: f32, start: f32, end: f32) -> [f32; 4] {
+    [0., start, length - end, length]
+}
+
+// how big each of the three pieces along one side are drawn when that side is `length` long. if
+// it's too short for both borders, they shrink to fit and there's no middle
+fn spans(length: f32, start: f32, end: f32) -> [f32; 3] {
+    let scale = if start + end > length && start + end > 0. { length / (start + end) } else { 1. };
+    let (start, end) = (start * scale, end * scale);
+    [start, (length - start - end).max(0.), end]
+}
+
+impl NinePatch {
+    // `size` is how big the picture is, in the top left of the texture
+    pub fn new(renderer: &mut Renderer, t3x_data: &[u8], size: Vec2, borders: Borders) -> Self {
+        Self::from_texture(renderer, load_t3x(t3x_data), Vec2::ZERO, size, borders)
+    }
+
+    // a picture `size` pixels big at `min` (from the top left) in a texture that can have other things
+    // in it too. the texture's shared with the pieces, which live as long as the renderer does
+    pub fn from_texture(renderer: &mut Renderer, mut texture: sys::C3D_Tex, min: Vec2, size: Vec2, borders: Borders) -> Self {
+        unsafe { sys::C3D_TexSetWrap(&mut texture, ctru_sys::GPU_CLAMP_TO_EDGE, ctru_sys::GPU_CLAMP_TO_EDGE); }
+        let texture_size = Vec2::new(texture.width as f32, texture.height as f32);
+
+        let xs = cuts(size.x, borders.left, borders.right);
+        let ys = cuts(size.y, borders.top, borders.bottom);
+        let pieces = std::array::from_fn(|i| {
+            let (column, row) = (i % 3, i / 3);
+            // tex3ds puts v = 1 at the top of the texture
+            let uv = |x: f32, y: f32| [(min.x + x) / texture_size.x, 1. - (min.y + y) / texture_size.y];
+            let (left, right, top, bottom) = (xs[column], xs[column + 1], ys[row], ys[row + 1]);
+
+            // a unit square, scaled to the size the piece is drawn at
+            let vertex = |pos: [f32; 3], uv: [f32; 2]| Vertex { pos, uv, normal: [0., 0., 1.] };
+            let vertices = [
+                vertex([0., 0., -0.5], uv(left, bottom)),
+                vertex([1., 0., -0.5], uv(right, bottom)),
+                vertex([1., 1., -0.5], uv(right, top)),
+                vertex([0., 1., -0.5], uv(left, top)),
+            ];
+
+            let mut mesh = Mesh::from_data(&vertices, Some(&[0, 1, 2, 2, 3, 0]), None, Material::default());
+            mesh.texture = Some(texture);
+            renderer.register_mesh(mesh)
+        });
+
+        Self { pieces, size, borders }
+    }
+
+    // the size it was cut from, which is the size it looks right at
+    pub fn size(&self) -> Vec2 {
+        self.size
+    }
+
+    // for this frame, covering `size` pixels from `min` at the bottom left of `screen`, with the
+    // picture multiplied by `color`
+    pub fn please_render(&self, renderer: &mut Renderer, screen: TargetScreen, min: Vec2, size: Vec2, color: Vec4) {
+        let widths = spans(size.x, self.borders.left, self.borders.right);
+        let heights = spans(size.y, self.borders.top, self.borders.bottom);
+
+        let mut y = min.y + size.y;
+        for (row, height) in heights.into_iter().enumerate() {
+            y -= height;
+            let mut x = min.x;
+            for (column, width) in widths.into_iter().enumerate() {
+                if width > 0. && height > 0. {
+                    let model = Mat4::from_translation(Vec2::new(x, y).extend(0.)) * Mat4::from_scale(Vec2::new(width, height).extend(1.));
+                    renderer.please_render_2d(screen, self.pieces[row * 3 + column], to_matrix4(model), color);
+                }
+                x += width;
+            }
+        }
+    }
+}
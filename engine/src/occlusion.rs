@@ -0,0 +1,77 @@
+// coarse occlusion culling against boxes the game marks as solid (walls, big pillars, closed
+// doors). a request whose bounds are entirely hidden behind one of them from the camera isn't
+// drawn. it's cheap enough to run on every request, and in an indoor level most of the world is
+// behind a wall from anywhere. see Renderer::add_occluder
+use glam::{Mat4, Vec3};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        Self { min: min.min(max), max: min.max(max) }
+    }
+
+    // a box around `points`, or a point at the origin if there aren't any
+    pub fn from_points(points: impl IntoIterator<Item = Vec3>) -> Self {
+        let mut points = points.into_iter();
+        let Some(first) = points.next() else { return Self::new(Vec3::ZERO, Vec3::ZERO) };
+        points.fold(Self::new(first, first), |aabb, p| Self { min: aabb.min.min(p), max: aabb.max.max(p) })
+    }
+
+    pub fn corners(&self) -> [Vec3; 8] {
+        let (a, b) = (self.min, self.max);
+        [
+            Vec3::new(a.x, a.y, a.z), Vec3::new(b.x, a.y, a.z),
+            Vec3::new(a.x, b.y, a.z), Vec3::new(b.x, b.y, a.z),
+            Vec3::new(a.x, a.y, b.z), Vec3::new(b.x, a.y, b.z),
+            Vec3::new(a.x, b.y, b.z), Vec3::new(b.x, b.y, b.z),
+        ]
+    }
+
+    // a box around this one after `transform`
+    pub fn transformed(&self, transform: Mat4) -> Self {
+        Self::from_points(self.corners().map(|corner| transform.transform_point3(corner)))
+    }
+
+    pub fn contains(&self, p: Vec3) -> bool {
+        p.cmpge(self.min).all() && p.cmple(self.max).all()
+    }
+
+    // whether the segment from `a` to `b` touches the box
+    fn hits_segment(&self, a: Vec3, b: Vec3) -> bool {
+        let dir = b - a;
+        let (mut enter, mut exit) = (0.0_f32, 1.0_f32);
+        for axis in 0..3 {
+            if dir[axis].abs() < 1e-6 {
+                // parallel to this pair of faces, so it has to be between them
+                if a[axis] < self.min[axis] || a[axis] > self.max[axis] {
+                    return false;
+                }
+                continue;
+            }
+
+            let t0 = (self.min[axis] - a[axis]) / dir[axis];
+            let t1 = (self.max[axis] - a[axis]) / dir[axis];
+            enter = enter.max(t0.min(t1));
+            exit = exit.min(t0.max(t1));
+            if enter > exit {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+// whether `bounds` can't be seen from `eye` past any one of `occluders`. the occluders are convex,
+// so if every corner is hidden behind the same one, the whole box is
+pub(crate) fn is_occluded(eye: Vec3, bounds: &Aabb, occluders: &[Aabb]) -> bool {
+    let corners = bounds.corners();
+    occluders.iter().any(|occluder| {
+        !occluder.contains(eye) && corners.iter().all(|&corner| occluder.hits_segment(eye, corner))
+    })
+}
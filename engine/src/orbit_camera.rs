@@ -0,0 +1,85 @@
+// a camera circling a target, like a model viewer or a third-person game where the player steers
+// the camera. turn it with a stick (the circle pad, or the C-stick on a New 3DS) and zoom with
+// whatever buttons the game likes:
+//
+//     let mut orbit = OrbitCamera::new(player_position, 4.);
+//     ...
+//     orbit.target = player_position;
+//     orbit.update(input.circle_pad(), zoom, dt, &NoCollision);
+//     renderer.set_camera(&orbit.camera());
+use std::f32::consts::FRAC_PI_2;
+use std::ops::RangeInclusive;
+
+use glam::{Vec2, Vec3};
+
+use crate::camera::{Camera, Raycast};
+
+// how far to stay from anything the camera would otherwise go through, so the near plane doesn't
+// clip into it
+const WALL_MARGIN: f32 = 0.2;
+
+pub struct OrbitCamera {
+    pub target: Vec3,
+    pub distance: f32, // what the player zoomed to. the camera gets closer if something's in the way
+    pub yaw: f32, // radians. 0 is behind the target, looking down -z
+    pub pitch: f32, // radians, positive is above the target looking down
+
+    pub turn_speed: Vec2, // radians per second at full tilt
+    pub zoom_speed: f32, // units per second
+    pub distance_range: RangeInclusive<f32>,
+    pub pitch_range: RangeInclusive<f32>,
+    pub invert_y: bool,
+
+    actual_distance: f32,
+}
+
+impl OrbitCamera {
+    pub fn new(target: Vec3, distance: f32) -> Self {
+        Self {
+            target,
+            distance,
+            yaw: 0.,
+            pitch: 0.3,
+
+            turn_speed: Vec2::new(3., 2.),
+            zoom_speed: 4.,
+            distance_range: 1.0..=20.0,
+            pitch_range: -1.2..=1.4,
+            invert_y: false,
+
+            actual_distance: distance,
+        }
+    }
+
+    // `stick` turns the camera (-1..=1 on each axis, like Input::circle_pad), and `zoom` moves it
+    // in (positive) or out (negative). `world` pulls the camera in front of walls between it and
+    // the target
+    pub fn update(&mut self, stick: Vec2, zoom: f32, dt: f32, world: &impl Raycast) {
+        let y = if self.invert_y { -stick.y } else { stick.y };
+        self.yaw -= stick.x * self.turn_speed.x * dt;
+        self.pitch -= y * self.turn_speed.y * dt;
+
+        // past straight up or down the camera would flip over
+        let (min, max) = self.pitch_range.clone().into_inner();
+        self.pitch = self.pitch.clamp(min.max(-FRAC_PI_2 + 0.01), max.min(FRAC_PI_2 - 0.01));
+
+        self.distance -= zoom * self.zoom_speed * dt;
+        self.distance = self.distance.clamp(*self.distance_range.start(), *self.distance_range.end());
+
+        self.actual_distance = match world.raycast(self.target, self.direction(), self.distance) {
+            Some(hit) => (hit - WALL_MARGIN).max(0.),
+            None => self.distance,
+        };
+    }
+
+    // from the target towards the camera
+    fn direction(&self) -> Vec3 {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+        Vec3::new(sin_yaw * cos_pitch, sin_pitch, cos_yaw * cos_pitch)
+    }
+
+    pub fn camera(&self) -> Camera {
+        Camera::looking_at(self.target + self.direction() * self.actual_distance, self.target)
+    }
+}
@@ -0,0 +1,128 @@
+// things drawn over the whole finished frame: vignettes, damage borders, cinematic letterboxing.
+// each fades (or for letterboxing, slides) in and out on its own time:
+//
+//     let mut hurt = Overlay::image(&assets::DAMAGE_BORDER_T3X.read()?);
+//     let mut bars = Overlay::letterbox(0.12);
+//     ...
+//     if took_damage {
+//         hurt.set_visibility(1.);
+//         hurt.hide(0.5);
+//     }
+//     hurt.update(dt);
+//     bars.update(dt);
+//     hurt.please_render(&mut renderer);
+//     bars.please_render(&mut renderer);
+use citro3d::sys;
+use glam::{Vec2, Vec4};
+
+use crate::mesh::load_t3x;
+use crate::renderer::Renderer;
+
+enum Shape {
+    Screen,
+    Letterbox(f32), // the height of each bar
+}
+
+pub struct Overlay {
+    texture: Option<sys::C3D_Tex>,
+    shape: Shape,
+    pub color: Vec4, // multiplies the image, or is the color of the bars
+    visibility: f32, // 0 is hidden, 1 fully shown
+    target: f32,
+    speed: f32, // of visibility, per second
+}
+
+impl Overlay {
+    fn new(texture: Option<sys::C3D_Tex>, shape: Shape, color: Vec4) -> Self {
+        Self { texture, shape, color, visibility: 0., target: 0., speed: 0. }
+    }
+
+    // stretched over the whole screen. it's alpha blended, so the picture wants to be transparent
+    // wherever the scene should show through
+    pub fn image(t3x_data: &[u8]) -> Self {
+        let mut texture = load_t3x(t3x_data);
+        unsafe { sys::C3D_TexSetWrap(&mut texture, ctru_sys::GPU_CLAMP_TO_EDGE, ctru_sys::GPU_CLAMP_TO_EDGE); }
+
+        Self::new(Some(texture), Shape::Screen, Vec4::ONE)
+    }
+
+    // a flat color over the whole screen
+    pub fn color(color: Vec4) -> Self {
+        Self::new(None, Shape::Screen, color)
+    }
+
+    // black bars along the top and bottom, each `height` of the screen tall once fully shown
+    pub fn letterbox(height: f32) -> Self {
+        Self::new(None, Shape::Letterbox(height.clamp(0., 0.5)), Vec4::W)
+    }
+
+    // fades in over `seconds`
+    pub fn show(&mut self, seconds: f32) {
+        self.fade_to(1., seconds);
+    }
+
+    // fades out over `seconds`
+    pub fn hide(&mut self, seconds: f32) {
+        self.fade_to(0., seconds);
+    }
+
+    fn fade_to(&mut self, target: f32, seconds: f32) {
+        self.target = target;
+        if seconds > 0. {
+            self.speed = 1. / seconds;
+        } else {
+            self.visibility = target;
+        }
+    }
+
+    // jumps straight to `visibility`, stopping any fade
+    pub fn set_visibility(&mut self, visibility: f32) {
+        self.visibility = visibility.clamp(0., 1.);
+        self.target = self.visibility;
+    }
+
+    pub fn visibility(&self) -> f32 {
+        self.visibility
+    }
+
+    pub fn is_fading(&self) -> bool {
+        self.visibility != self.target
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        let step = self.speed * dt;
+        self.visibility = if self.visibility < self.target {
+            (self.visibility + step).min(self.target)
+        } else {
+            (self.visibility - step).max(self.target)
+        };
+    }
+
+    // for this frame, after the scene. does nothing while it's hidden
+    pub fn please_render(&self, renderer: &mut Renderer) {
+        if self.visibility <= 0. {
+            return;
+        }
+
+        match self.shape {
+            Shape::Screen => {
+                let color = self.color.with_w(self.color.w * self.visibility);
+                renderer.please_render_overlay(self.texture.as_ref(), Vec2::ZERO, Vec2::ONE, color);
+            }
+            Shape::Letterbox(height) => {
+                // the bars slide in from the edges
+                let size = Vec2::new(1., height * self.visibility);
+                renderer.please_render_overlay(None, Vec2::ZERO, size, self.color);
+                renderer.please_render_overlay(None, Vec2::new(0., 1. - size.y), size, self.color);
+            }
+        }
+    }
+}
+
+impl Drop for Overlay {
+    fn drop(&mut self) {
+        if let Some(texture) = &mut self.texture {
+            unsafe { sys::C3D_TexDelete(texture); }
+        }
+    }
+}
@@ -0,0 +1,62 @@
+// reading files out of a PACK (see mm3ds_format), which is lots of small files in one. opening a
+// file on the RomFS or the SD card is slow next to reading from one that's already open, so a pack
+// keeps its file open and seeks to whatever's asked for:
+//
+//     let mut pack = Pack::open(GFX_PACK)?;
+//     let meshes = Mesh::from_file_data(&pack.read_asset(&assets::CHARACTER_MESH)?[..])?;
+//
+// gltf_tool makes one out of a directory (`gltf_tool romfs/sounds romfs/sounds.pack`), and build.rs
+// packs everything it converts into GFX_PACK when MM3DS_PACK_ASSETS is set
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use mm3ds_format::PackEntry;
+
+use crate::assets::{Asset, GFX_DIR};
+
+// what GFX_DIR had in it, when it's packed
+pub const GFX_PACK: &str = "romfs:/gfx.pack";
+
+pub struct Pack {
+    file: File,
+    entries: HashMap<String, PackEntry>,
+}
+
+impl Pack {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let mut reader = BufReader::new(File::open(path)?);
+        let index = mm3ds_format::read_pack_index(&mut reader)
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", path.display())))?;
+        let entries = index.into_iter().map(|entry| (entry.name.clone(), entry)).collect();
+
+        Ok(Self { file: reader.into_inner(), entries })
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.entries.contains_key(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.entries.keys().map(String::as_str)
+    }
+
+    // the file called `name` (its path in the directory that was packed), decompressed
+    pub fn read(&mut self, name: &str) -> io::Result<Vec<u8>> {
+        let entry = self.entries.get(name)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{name} isn't in the pack")))?;
+
+        let mut stored = vec![0u8; entry.stored_size as usize];
+        self.file.seek(SeekFrom::Start(entry.offset as u64))?;
+        self.file.read_exact(&mut stored)?;
+        entry.unpack(stored)
+    }
+
+    // an asset from GFX_PACK
+    pub fn read_asset(&mut self, asset: &Asset) -> io::Result<Vec<u8>> {
+        self.read(asset.path.strip_prefix(GFX_DIR).unwrap_or(asset.path))
+    }
+}
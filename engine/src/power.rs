@@ -0,0 +1,157 @@
+// battery state, and an optional policy that turns quality down while the battery is low and back
+// up once it's charging. the engine only acts on target_fps itself; stereo is for passing on to
+// Renderer::set_depth_strength, particle_scale for whatever spawns particles, and on_change lets
+// games react too
+use std::time::{Duration, Instant};
+
+// how often the battery is checked, it doesn't change quickly
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+fn check(res: ctru_sys::Result) -> ctru::Result<()> {
+    if ctru_sys::R_FAILED(res) {
+        Err(res.into())
+    } else {
+        Ok(())
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PowerState {
+    pub level: u8, // 0 (empty) to 5 (full), like the HOME menu's battery icon
+    pub charging: bool,
+    pub plugged_in: bool,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Quality {
+    pub target_fps: u32, // 60 or 30
+    pub stereo: bool,
+    pub particle_scale: f32, // multiply particle counts by this
+}
+
+impl Default for Quality {
+    fn default() -> Self {
+        Self { target_fps: 60, stereo: true, particle_scale: 1. }
+    }
+}
+
+impl Quality {
+    // what PowerPolicy switches to by default when the battery is low
+    pub fn battery_saver() -> Self {
+        Self { target_fps: 30, stereo: false, particle_scale: 0.5 }
+    }
+
+    // how many vblanks to wait for per frame
+    pub fn vblanks_per_frame(&self) -> u32 {
+        60 / self.target_fps.clamp(1, 60)
+    }
+}
+
+// keeps the ptm:u service running
+pub struct Power {
+    _private: (),
+}
+
+impl Power {
+    pub fn new() -> ctru::Result<Self> {
+        check(unsafe { ctru_sys::ptmuInit() })?;
+        Ok(Self { _private: () })
+    }
+
+    pub fn state(&self) -> ctru::Result<PowerState> {
+        let (mut level, mut charging, mut plugged_in) = (0, 0, false);
+        unsafe {
+            check(ctru_sys::PTMU_GetBatteryLevel(&mut level))?;
+            check(ctru_sys::PTMU_GetBatteryChargeState(&mut charging))?;
+            check(ctru_sys::PTMU_GetAdapterState(&mut plugged_in))?;
+        }
+
+        Ok(PowerState { level, charging: charging != 0, plugged_in })
+    }
+}
+
+impl Drop for Power {
+    fn drop(&mut self) {
+        unsafe { ctru_sys::ptmuExit(); }
+    }
+}
+
+pub struct PowerPolicy {
+    power: Power,
+    full: Quality,
+    reduced: Quality,
+    low_level: u8,
+
+    state: Option<PowerState>,
+    saving: bool,
+    last_poll: Option<Instant>,
+    callbacks: Vec<Box<dyn FnMut(PowerState, &Quality)>>,
+}
+
+impl PowerPolicy {
+    // uses `full` normally, and Quality::battery_saver() once the battery drops to 1 bar (about 10%)
+    // without a charger
+    pub fn new(full: Quality) -> ctru::Result<Self> {
+        Ok(Self {
+            power: Power::new()?,
+            full,
+            reduced: Quality::battery_saver(),
+            low_level: 1,
+
+            state: None,
+            saving: false,
+            last_poll: None,
+            callbacks: Vec::new(),
+        })
+    }
+
+    pub fn with_reduced(mut self, reduced: Quality) -> Self {
+        self.reduced = reduced;
+        self
+    }
+
+    // switch to the reduced quality at this battery level (0 to 5) or below
+    pub fn with_low_level(mut self, level: u8) -> Self {
+        self.low_level = level;
+        self
+    }
+
+    // called whenever the quality changes, with the battery state that caused it
+    pub fn on_change(&mut self, callback: impl FnMut(PowerState, &Quality) + 'static) {
+        self.callbacks.push(Box::new(callback));
+    }
+
+    // checks the battery every few seconds. call once a frame
+    pub fn update(&mut self) {
+        let now = Instant::now();
+        if self.last_poll.is_some_and(|t| now - t < POLL_INTERVAL) {
+            return;
+        }
+        self.last_poll = Some(now);
+
+        let Ok(state) = self.power.state() else { return };
+        self.state = Some(state);
+
+        let saving = state.level <= self.low_level && !state.plugged_in;
+        if saving != self.saving {
+            self.saving = saving;
+            let quality = *self.quality();
+            for callback in &mut self.callbacks {
+                callback(state, &quality);
+            }
+        }
+    }
+
+    pub fn quality(&self) -> &Quality {
+        if self.saving { &self.reduced } else { &self.full }
+    }
+
+    pub fn is_saving(&self) -> bool {
+        self.saving
+    }
+
+    // None until the first update
+    pub fn state(&self) -> Option<PowerState> {
+        self.state
+    }
+}
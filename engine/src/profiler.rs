@@ -0,0 +1,156 @@
+// where each frame's time goes, system by system, drawn as bars on the bottom screen across the
+// last second or so, so a spike shows which part of the frame it was in. timing something is a
+// guard that counts until it's dropped:
+//
+//     {
+//         let _update = profiler::scope(System::Update);
+//         // game logic
+//     }
+//
+// the engine times its own systems (animation, culling, render submit, and the GPU's drawing, as
+// citro3d measures it), and Renderer::render ends the frame. to see it:
+//
+//     renderer.enable_bottom_screen(&gfx);
+//     let view = ProfilerView::new(&mut renderer);
+//     ...
+//     view.please_render(&mut renderer);
+//     renderer.render();
+//
+// a system timed more than once in a frame adds up. timing costs a tick read and a lock when the
+// scope's dropped, so scopes go around whole systems, not around each thing in them
+use std::sync::Mutex;
+use std::time::Instant;
+
+use glam::{Mat4, Vec2, Vec4};
+
+use crate::material::Material;
+use crate::math::to_matrix4;
+use crate::mesh::{Mesh, Vertex};
+use crate::renderer::{BOTTOM_SCREEN_SIZE, MeshId, Renderer, TargetScreen};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum System {
+    Update, // the game's, timed by the game
+    Animation,
+    Culling,
+    RenderSubmit, // building the frame's commands for the GPU
+    Gpu, // drawing them
+}
+
+impl System {
+    pub const ALL: [System; SYSTEMS] = [System::Update, System::Animation, System::Culling, System::RenderSubmit, System::Gpu];
+
+    // the bar's color, in the view
+    pub fn color(self) -> Vec4 {
+        match self {
+            System::Update => Vec4::new(0.3, 0.6, 1., 1.),
+            System::Animation => Vec4::new(1., 0.5, 0.8, 1.),
+            System::Culling => Vec4::new(1., 0.8, 0.2, 1.),
+            System::RenderSubmit => Vec4::new(0.4, 0.9, 0.4, 1.),
+            System::Gpu => Vec4::new(1., 0.35, 0.3, 1.),
+        }
+    }
+}
+
+const SYSTEMS: usize = 5;
+// frames kept, a bit over a second at 60fps
+pub const HISTORY: usize = 80;
+
+// milliseconds spent in each system, in System::ALL's order
+pub type FrameTimes = [f32; SYSTEMS];
+
+struct Profiler {
+    current: FrameTimes, // the frame so far
+    frames: [FrameTimes; HISTORY], // a ring, with the oldest at `next`
+    next: usize,
+}
+
+static PROFILER: Mutex<Profiler> = Mutex::new(Profiler { current: [0.; SYSTEMS], frames: [[0.; SYSTEMS]; HISTORY], next: 0 });
+
+// times `system` until it's dropped
+#[must_use = "the scope stops timing as soon as it's dropped"]
+pub struct Scope {
+    system: System,
+    start: Instant,
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        record(self.system, self.start.elapsed().as_secs_f32() * 1000.);
+    }
+}
+
+pub fn scope(system: System) -> Scope {
+    Scope { system, start: Instant::now() }
+}
+
+// adds `ms` to `system` for this frame, for time measured some other way
+pub fn record(system: System, ms: f32) {
+    PROFILER.lock().unwrap().current[system as usize] += ms;
+}
+
+// keeps this frame's times and starts on the next one. Renderer::render calls it
+pub fn end_frame() {
+    let mut profiler = PROFILER.lock().unwrap();
+    let i = profiler.next;
+    profiler.frames[i] = std::mem::take(&mut profiler.current);
+    profiler.next = (i + 1) % HISTORY;
+}
+
+// the last HISTORY frames, oldest first
+pub fn frames() -> [FrameTimes; HISTORY] {
+    let profiler = PROFILER.lock().unwrap();
+    std::array::from_fn(|i| profiler.frames[(profiler.next + i) % HISTORY])
+}
+
+// the frame times as stacked bars across the bottom screen, the newest on the right, with a line
+// at each 60fps frame's worth of time
+pub struct ProfilerView {
+    pub ms_height: f32, // pixels per millisecond
+    pub visible: bool,
+    quad: MeshId,
+}
+
+// 1000 / 60
+const FRAME_BUDGET_MS: f32 = 16.667;
+
+impl ProfilerView {
+    pub fn new(renderer: &mut Renderer) -> Self {
+        let vertex = |x: f32, y: f32| Vertex { pos: [x, y, -0.5], uv: [x, y], normal: [0., 0., 1.] };
+        let vertices = [vertex(0., 0.), vertex(1., 0.), vertex(1., 1.), vertex(0., 1.)];
+        let quad = renderer.register_mesh(Mesh::from_data(&vertices, Some(&[0, 1, 2, 2, 3, 0]), None, Material::default()));
+
+        Self { ms_height: 6., visible: true, quad }
+    }
+
+    fn rect(&self, renderer: &mut Renderer, min: Vec2, size: Vec2, color: Vec4) {
+        let model = Mat4::from_translation(min.extend(0.)) * Mat4::from_scale(size.extend(1.));
+        renderer.please_render_2d(TargetScreen::Bottom, self.quad, to_matrix4(model), color);
+    }
+
+    // for this frame, in the 2d layer. the bottom screen has to be enabled
+    pub fn please_render(&self, renderer: &mut Renderer) {
+        if !self.visible {
+            return;
+        }
+
+        let width = BOTTOM_SCREEN_SIZE.x / HISTORY as f32;
+        for (i, times) in frames().iter().enumerate() {
+            let mut y = 0.;
+            for (system, ms) in System::ALL.into_iter().zip(times) {
+                let height = ms * self.ms_height;
+                if height > 0. {
+                    self.rect(renderer, Vec2::new(i as f32 * width, y), Vec2::new(width - 1., height), system.color());
+                }
+                y += height;
+            }
+        }
+
+        let line = Vec4::new(1., 1., 1., 0.5);
+        let mut y = FRAME_BUDGET_MS * self.ms_height;
+        while self.ms_height > 0. && y < BOTTOM_SCREEN_SIZE.y {
+            self.rect(renderer, Vec2::new(0., y), Vec2::new(BOTTOM_SCREEN_SIZE.x, 1.), line);
+            y += FRAME_BUDGET_MS * self.ms_height;
+        }
+    }
+}
@@ -0,0 +1,31 @@
+// QR code decoding for camera frames, using rqrr (a port of quirc). decoding a whole frame takes a
+// noticeable chunk of a frame on the old 3DS, so scan every few frames rather than every one
+use crate::camera_feed::CameraFeed;
+
+// the text of every QR code found in a 400x240 (or any size) RGB565 image, `width * height` pixels
+// a row at a time from the top left. nothing if `pixels` is any other length
+pub fn scan_rgb565(pixels: &[u16], width: usize, height: usize) -> Vec<String> {
+    if width.checked_mul(height) != Some(pixels.len()) {
+        return Vec::new();
+    }
+
+    let mut image = rqrr::PreparedImage::prepare_from_greyscale(width, height, |x, y| {
+        let p = pixels[y * width + x];
+        let (r, g, b) = ((p >> 11) & 0x1f, (p >> 5) & 0x3f, p & 0x1f);
+
+        // green has an extra bit, r and b are scaled up to match
+        ((r * 2 * 77 + g * 150 + b * 2 * 29) >> 6) as u8
+    });
+
+    image.detect_grids()
+        .iter()
+        .filter_map(|grid| grid.decode().ok())
+        .map(|(_, text)| text)
+        .collect()
+}
+
+// scans the last frame the feed received
+pub fn scan(feed: &CameraFeed) -> Vec<String> {
+    let (width, height) = feed.size();
+    scan_rgb565(feed.frame(), width, height)
+}
@@ -0,0 +1,296 @@
+// a small protocol for talking to the console over the network: a PC debug console sending
+// commands, or two consoles playing over the internet. every message is a frame of
+//
+//     len: u32, kind: u8, id: u32, payload: [u8; len - 5]
+//
+// (little endian) over TCP, or a single frame per datagram over UDP. requests carry a command
+// line like `teleport 0 1.5 -3`, and whoever handles one replies with the same id. game code
+// decides what the commands mean:
+//
+//     while let Some(event) = remote.poll() {
+//         if let Event::Request(request) = event {
+//             let result = match request.command.as_str() {
+//                 "reload" => reload_asset(&request.args).map(|_| "ok"),
+//                 _ => Err("unknown command"),
+//             };
+//             remote.reply(&request, result);
+//         }
+//     }
+use std::collections::VecDeque;
+use std::fmt::Display;
+use std::io;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket};
+
+use ctru::services::soc::Soc;
+
+const HEADER_SIZE: usize = 9;
+const MAX_FRAME_SIZE: usize = 64 * 1024;
+
+const KIND_REQUEST: u8 = 0;
+const KIND_OK: u8 = 1;
+const KIND_ERR: u8 = 2;
+const KIND_MESSAGE: u8 = 3;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Peer {
+    Tcp(usize),
+    Udp(SocketAddr),
+}
+
+#[derive(Clone, Debug)]
+pub struct Request {
+    pub from: Peer,
+    pub id: u32,
+    pub command: String,
+    pub args: Vec<String>,
+}
+
+#[derive(Debug)]
+pub enum Event {
+    Connected(Peer),
+    Disconnected(Peer),
+    Request(Request),
+    Reply { from: Peer, id: u32, result: Result<String, String> },
+    Message { from: Peer, data: Vec<u8> }, // raw bytes sent with Remote::send, e.g. netplay state
+}
+
+fn encode(kind: u8, id: u32, payload: &[u8], out: &mut Vec<u8>) {
+    out.extend_from_slice(&((payload.len() + HEADER_SIZE - 4) as u32).to_le_bytes());
+    out.push(kind);
+    out.extend_from_slice(&id.to_le_bytes());
+    out.extend_from_slice(payload);
+}
+
+// the frame at the start of `buf`, and how many bytes it took up
+fn decode(buf: &[u8]) -> io::Result<Option<(u8, u32, &[u8], usize)>> {
+    if buf.len() < 4 {
+        return Ok(None);
+    }
+
+    let len = u32::from_le_bytes(buf[..4].try_into().unwrap()) as usize;
+    if !(HEADER_SIZE - 4..=MAX_FRAME_SIZE).contains(&len) {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("bad frame length {len}")));
+    }
+    if buf.len() < 4 + len {
+        return Ok(None);
+    }
+
+    let kind = buf[4];
+    let id = u32::from_le_bytes(buf[5..9].try_into().unwrap());
+    Ok(Some((kind, id, &buf[HEADER_SIZE..4 + len], 4 + len)))
+}
+
+struct Connection {
+    stream: TcpStream,
+    inbox: Vec<u8>,
+    outbox: Vec<u8>,
+}
+
+impl Connection {
+    fn new(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+        Ok(Self { stream, inbox: Vec::new(), outbox: Vec::new() })
+    }
+
+    // false once the connection is gone
+    fn pump(&mut self) -> bool {
+        let mut buf = [0u8; 4096];
+        loop {
+            match self.stream.read(&mut buf) {
+                Ok(0) => return false,
+                Ok(n) => self.inbox.extend_from_slice(&buf[..n]),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(_) => return false,
+            }
+        }
+
+        while !self.outbox.is_empty() {
+            match self.stream.write(&self.outbox) {
+                Ok(0) => return false,
+                Ok(n) => { self.outbox.drain(..n); }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(_) => return false,
+            }
+        }
+
+        true
+    }
+}
+
+pub struct Remote {
+    _soc: Soc,
+    listener: Option<TcpListener>,
+    udp: Option<UdpSocket>,
+    connections: Vec<Option<Connection>>,
+    events: VecDeque<Event>,
+    next_id: u32,
+    datagram: Vec<u8>,
+}
+
+impl Remote {
+    fn new() -> io::Result<Self> {
+        Ok(Self {
+            _soc: Soc::new().map_err(io::Error::other)?,
+            listener: None,
+            udp: None,
+            connections: Vec::new(),
+            events: VecDeque::new(),
+            next_id: 0,
+            datagram: vec![0; 4 + MAX_FRAME_SIZE],
+        })
+    }
+
+    // accepts TCP connections and UDP datagrams on `port`
+    pub fn listen(port: u16) -> io::Result<Self> {
+        let mut ret = Self::new()?;
+
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        listener.set_nonblocking(true)?;
+        ret.listener = Some(listener);
+
+        let udp = UdpSocket::bind(("0.0.0.0", port))?;
+        udp.set_nonblocking(true)?;
+        ret.udp = Some(udp);
+
+        Ok(ret)
+    }
+
+    // connects to another Remote (or a PC tool) over TCP. it's Peer::Tcp(0)
+    pub fn connect(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let mut ret = Self::new()?;
+        ret.add_connection(TcpStream::connect(addr)?)?;
+
+        Ok(ret)
+    }
+
+    fn add_connection(&mut self, stream: TcpStream) -> io::Result<()> {
+        let connection = Some(Connection::new(stream)?);
+        let index = match self.connections.iter().position(Option::is_none) {
+            Some(index) => {
+                self.connections[index] = connection;
+                index
+            }
+            None => {
+                self.connections.push(connection);
+                self.connections.len() - 1
+            }
+        };
+
+        self.events.push_back(Event::Connected(Peer::Tcp(index)));
+        Ok(())
+    }
+
+    fn handle_frame(&mut self, from: Peer, kind: u8, id: u32, payload: &[u8]) {
+        let text = || String::from_utf8_lossy(payload).into_owned();
+        self.events.push_back(match kind {
+            KIND_REQUEST => {
+                let line = text();
+                let mut words = line.split_whitespace().map(str::to_owned);
+                Event::Request(Request {
+                    from,
+                    id,
+                    command: words.next().unwrap_or_default(),
+                    args: words.collect(),
+                })
+            }
+            KIND_OK => Event::Reply { from, id, result: Ok(text()) },
+            KIND_ERR => Event::Reply { from, id, result: Err(text()) },
+            KIND_MESSAGE => Event::Message { from, data: payload.to_vec() },
+            _ => return,
+        });
+    }
+
+    // accepts connections, sends what's queued and reads what's arrived. call once a frame, then
+    // drain poll()
+    pub fn update(&mut self) {
+        while let Some(Ok((stream, _))) = self.listener.as_ref().map(TcpListener::accept) {
+            let _ = self.add_connection(stream);
+        }
+
+        for index in 0..self.connections.len() {
+            let Some(connection) = &mut self.connections[index] else { continue };
+            let mut alive = connection.pump();
+
+            let inbox = std::mem::take(&mut connection.inbox);
+            let mut read = 0;
+            loop {
+                match decode(&inbox[read..]) {
+                    Ok(Some((kind, id, payload, len))) => {
+                        self.handle_frame(Peer::Tcp(index), kind, id, payload);
+                        read += len;
+                    }
+                    Ok(None) => break,
+                    Err(_) => {
+                        alive = false; // garbage, there's no way to resync
+                        break;
+                    }
+                }
+            }
+
+            if alive {
+                self.connections[index].as_mut().unwrap().inbox = inbox[read..].to_vec();
+            } else {
+                self.connections[index] = None;
+                self.events.push_back(Event::Disconnected(Peer::Tcp(index)));
+            }
+        }
+
+        let mut buf = std::mem::take(&mut self.datagram);
+        while let Some(Ok((n, addr))) = self.udp.as_ref().map(|udp| udp.recv_from(&mut buf)) {
+            if let Ok(Some((kind, id, payload, _))) = decode(&buf[..n]) {
+                self.handle_frame(Peer::Udp(addr), kind, id, payload);
+            }
+        }
+        self.datagram = buf;
+    }
+
+    pub fn poll(&mut self) -> Option<Event> {
+        self.events.pop_front()
+    }
+
+    fn send_frame(&mut self, to: Peer, kind: u8, id: u32, payload: &[u8]) {
+        match to {
+            Peer::Tcp(index) => {
+                if let Some(Some(connection)) = self.connections.get_mut(index) {
+                    encode(kind, id, payload, &mut connection.outbox);
+                }
+            }
+            Peer::Udp(addr) => {
+                let mut frame = Vec::new();
+                encode(kind, id, payload, &mut frame);
+                if let Some(udp) = &self.udp {
+                    let _ = udp.send_to(&frame, addr); // best effort, like UDP itself
+                }
+            }
+        }
+    }
+
+    pub fn reply(&mut self, request: &Request, result: Result<impl Display, impl Display>) {
+        match result {
+            Ok(value) => self.send_frame(request.from, KIND_OK, request.id, value.to_string().as_bytes()),
+            Err(e) => self.send_frame(request.from, KIND_ERR, request.id, e.to_string().as_bytes()),
+        }
+    }
+
+    // sends a command to the other end. the reply comes back as an Event::Reply with the returned id
+    pub fn call(&mut self, to: Peer, command: &str) -> u32 {
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        self.send_frame(to, KIND_REQUEST, id, command.as_bytes());
+
+        id
+    }
+
+    pub fn send(&mut self, to: Peer, data: &[u8]) {
+        self.send_frame(to, KIND_MESSAGE, 0, data);
+    }
+
+    pub fn peers(&self) -> impl Iterator<Item = Peer> + '_ {
+        self.connections.iter()
+            .enumerate()
+            .filter(|(_, connection)| connection.is_some())
+            .map(|(index, _)| Peer::Tcp(index))
+    }
+}
@@ -0,0 +1,138 @@
+// how a frame is put together: a list of passes, each drawing some layers of what was asked for
+// onto one screen from one camera, done in order. games that want more than the one picture (a map
+// of the level on the bottom screen, say) declare their passes once instead of the renderer
+// growing another special case:
+//
+//     let mut graph = RenderGraph::new();
+//     graph.add(RenderPass::new(TargetScreen::Top));
+//     graph.add(RenderPass::new(TargetScreen::Bottom)
+//         .camera(Camera::looking_at(Vec3::new(0., 30., 0.01), Vec3::ZERO))
+//         .layers(Layers::SCENE | Layers::SPRITES)
+//         .post(vec![]));
+//     renderer.enable_bottom_screen(&gfx);
+//     renderer.set_render_graph(Some(graph));
+//
+// without one, the renderer uses RenderGraph::default_for its screens. passes on the bottom screen
+// are skipped while it isn't enabled
+//
+// a screen is cleared when the frame begins, before any pass draws, whichever pass asks for it. the
+// requests are only culled once a frame, from the renderer's camera, so occlusion culling is off
+// while any pass has a camera of its own
+use std::ops::{BitOr, BitOrAssign};
+
+use citro3d::render::ClearFlags;
+use glam::{Vec3, Vec4};
+
+use crate::camera::Camera;
+use crate::renderer::TargetScreen;
+
+// which of the things asked for a pass draws
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Layers(u8);
+
+impl Layers {
+    pub const NONE: Layers = Layers(0);
+    pub const BACKGROUND: Layers = Layers(1 << 0); // see Renderer::please_render_background
+    pub const SKYBOX: Layers = Layers(1 << 1);
+    pub const SCENE: Layers = Layers(1 << 2); // meshes, models, batches and decals
+    pub const SPRITES: Layers = Layers(1 << 3); // the 2d layer, see Renderer::please_render_2d
+    pub const OVERLAYS: Layers = Layers(1 << 4); // see Renderer::please_render_overlay
+    pub const ALL: Layers = Layers(0x1f);
+
+    pub fn contains(self, other: Layers) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for Layers {
+    type Output = Layers;
+
+    fn bitor(self, rhs: Layers) -> Layers {
+        Layers(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for Layers {
+    fn bitor_assign(&mut self, rhs: Layers) {
+        self.0 |= rhs.0;
+    }
+}
+
+// done over the pass's picture once its scene is drawn, before its sprites and overlays
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PostStep {
+    Bloom, // the renderer's, if it has any. see bloom
+    ColorGrade, // the renderer's, see Renderer::set_color_grade and set_brightness
+    Tint(Vec3), // multiplies the picture
+    Fade(Vec4), // blends the color over the picture by its alpha
+}
+
+#[derive(Clone, Debug)]
+pub struct RenderPass {
+    pub screen: TargetScreen,
+    pub clear: Option<ClearFlags>, // with the renderer's clear color
+    pub camera: Option<Camera>, // None for the renderer's
+    pub layers: Layers,
+    pub post: Vec<PostStep>,
+}
+
+impl RenderPass {
+    // everything, from the renderer's camera, with bloom and color grading, on a cleared `screen`
+    pub fn new(screen: TargetScreen) -> Self {
+        Self { screen, clear: Some(ClearFlags::ALL), camera: None, layers: Layers::ALL, post: vec![PostStep::Bloom, PostStep::ColorGrade] }
+    }
+
+    pub fn clear(mut self, clear: Option<ClearFlags>) -> Self {
+        self.clear = clear;
+        self
+    }
+
+    pub fn camera(mut self, camera: Camera) -> Self {
+        self.camera = Some(camera);
+        self
+    }
+
+    pub fn layers(mut self, layers: Layers) -> Self {
+        self.layers = layers;
+        self
+    }
+
+    pub fn post(mut self, post: Vec<PostStep>) -> Self {
+        self.post = post;
+        self
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct RenderGraph {
+    passes: Vec<RenderPass>,
+}
+
+impl RenderGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // what the renderer draws without a graph of its own: the top screen gets everything, and the
+    // bottom one (if it's enabled) gets everything in dual screen, or just the 2d layer and the
+    // overlays otherwise
+    pub fn default_for(bottom_screen: bool, dual_screen: bool) -> Self {
+        let mut ret = Self::new();
+        ret.add(RenderPass::new(TargetScreen::Top));
+        match (bottom_screen, dual_screen) {
+            (true, true) => ret.add(RenderPass::new(TargetScreen::Bottom)),
+            (true, false) => ret.add(RenderPass::new(TargetScreen::Bottom).layers(Layers::SPRITES | Layers::OVERLAYS).post(vec![])),
+            (false, _) => {}
+        }
+
+        ret
+    }
+
+    pub fn add(&mut self, pass: RenderPass) {
+        self.passes.push(pass);
+    }
+
+    pub fn passes(&self) -> &[RenderPass] {
+        &self.passes
+    }
+}
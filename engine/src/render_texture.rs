@@ -0,0 +1,72 @@
+// a texture the GPU can draw into, for effects that draw something once and then use it as a
+// texture (see bloom). it lives in VRAM, which is what the GPU can draw into, and its width and
+// height have to be powers of two from 8 to 1024 like any texture's
+//
+// it's drawn into with the same projections as the screens, so what's in it is laid out like a
+// screen's framebuffer, turned a quarter. see screen_uv
+use citro3d::sys;
+
+// where on a render texture drawn with a screen's projection the point (x, y) of that screen ends
+// up, from (0, 0) at the bottom left to (1, 1) at the top right. Mtx_OrthoTilt and Mtx_PerspTilt
+// turn what's drawn so the LCDs (which are on their sides) show it upright
+pub(crate) const fn screen_uv(x: f32, y: f32) -> [f32; 2] {
+    [y, 1. - x]
+}
+
+pub struct RenderTexture {
+    texture: Box<sys::C3D_Tex>, // the target points into it
+    target: *mut sys::C3D_RenderTarget,
+}
+
+impl RenderTexture {
+    // an RGB565 texture, filtered linearly, with a depth buffer if `depth`. None if there's no room
+    // in VRAM for it
+    pub fn new(width: u16, height: u16, depth: bool) -> Option<Self> {
+        let mut texture: Box<sys::C3D_Tex> = Box::new(unsafe { std::mem::zeroed() });
+        if !unsafe { sys::C3D_TexInitVRAM(&mut *texture, width, height, ctru_sys::GPU_RGB565) } {
+            return None;
+        }
+        unsafe { sys::C3D_TexSetFilter(&mut *texture, ctru_sys::GPU_LINEAR, ctru_sys::GPU_LINEAR); }
+
+        let depth = if depth {
+            sys::C3D_DEPTHTYPE { __e: ctru_sys::GPU_RB_DEPTH24_STENCIL8 }
+        } else {
+            sys::C3D_DEPTHTYPE { __i: -1 }
+        };
+        let target = unsafe { sys::C3D_RenderTargetCreateFromTex(&mut *texture, ctru_sys::GPU_TEXFACE_2D, 0, depth) };
+        if target.is_null() {
+            unsafe { sys::C3D_TexDelete(&mut *texture); }
+            return None;
+        }
+
+        Some(Self { texture, target })
+    }
+
+    pub fn texture(&self) -> &sys::C3D_Tex {
+        &self.texture
+    }
+
+    pub fn size(&self) -> (u16, u16) {
+        (self.texture.width, self.texture.height)
+    }
+
+    // to black (and the depth buffer to the far plane), when the frame begins. see
+    // Renderer::render's clears
+    pub(crate) fn clear(&self) {
+        unsafe { sys::C3D_RenderTargetClear(self.target, sys::C3D_CLEAR_ALL, 0, 0); }
+    }
+
+    // makes the draws that follow go into it, in place of RenderPass::select_render_target
+    pub(crate) fn draw_on(&self) {
+        unsafe { sys::C3D_FrameDrawOn(self.target); }
+    }
+}
+
+impl Drop for RenderTexture {
+    fn drop(&mut self) {
+        unsafe {
+            sys::C3D_RenderTargetDelete(self.target);
+            sys::C3D_TexDelete(&mut *self.texture);
+        }
+    }
+}
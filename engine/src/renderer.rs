@@ -0,0 +1,2106 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::ops::{Index, IndexMut};
+use std::rc::Rc;
+use std::time::Instant;
+
+use citro3d::math::AspectRatio;
+use citro3d::math::ClipPlanes;
+use citro3d::math::FVec4;
+use citro3d::math::Matrix4;
+use citro3d::math::Projection;
+use citro3d::shader::Program;
+use citro3d::render::DepthFormat;
+use citro3d::render::Target;
+use citro3d::sys;
+use citro3d::texenv;
+use citro3d::uniform;
+use citro3d::uniform::Uniform;
+use citro3d::Instance;
+use ctru::services::gfx::Gfx;
+use ctru::services::gfx::RawFrameBuffer;
+use ctru::services::gfx::Screen;
+use ctru::services::gfx::TopScreen3D;
+use ctru::services::gspgpu::FramebufferFormat;
+use glam::{Mat4, Quat, Vec2, Vec3, Vec4, vec4};
+use mm3ds_format::{CapturedDraw, CapturedMesh, FrameCapture};
+
+use crate::batch;
+use crate::bloom;
+use crate::bloom::Bloom;
+use crate::camera::Camera;
+use crate::command_buffer::{Command, CommandBuffer};
+use crate::cubemap::Cubemap;
+use crate::decal::Decal;
+use crate::dither;
+use crate::frame_arena::FrameArena;
+use crate::material::{AlphaTest, BlendMode, Material};
+use crate::material_animation::{MaterialAnimation, MaterialOverride};
+use crate::math::{from_fvec4, from_matrix4, to_matrix4};
+use crate::light;
+use crate::light::Light;
+use crate::linear_pool;
+use crate::mesh::{CUBE_VERTICES, MAX_BONES, Mesh, Part, Playback, RetiredBuffers, StreamWrite, Vertex};
+use crate::model::Model;
+use crate::occlusion;
+use crate::occlusion::Aabb;
+use crate::profiler;
+use crate::render_graph::{Layers, PostStep, RenderGraph};
+use crate::profiler::System;
+use crate::shader::ShaderRegistry;
+use crate::texture_cache::TextureCache;
+
+// the generation goes up each time unregister_mesh empties its slot, so an id that's been
+// unregistered never means whichever mesh has the slot now
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MeshId {
+    index: u32,
+    generation: u32,
+}
+
+#[derive(Copy, Clone)]
+pub struct ModelId(usize);
+
+const DEFAULT_CLEAR_COLOR: u32 = 0x68b0d8ff;
+const DEFAULT_FOV_Y: f32 = 80.0_f32.to_radians();
+const DEFAULT_CLIP_PLANES: ClipPlanes = ClipPlanes { near: 0.01, far: 100.0 };
+// between the bottom of the top screen's picture and the top of the bottom one's, in pixels
+const DEFAULT_SCREEN_GAP: f32 = 96.;
+// how much closer decals are in the depth buffer than they really are
+const DECAL_DEPTH_BIAS: f32 = 0.0002;
+
+// how the depth buffer holds distance, see Renderer::set_depth_mapping. by default it's z/w, which
+// is precise up close and coarser further out, so far-off things can fight over which is in front
+// when far is many times near. a w-buffer holds the distance itself, as precise everywhere
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DepthMapping {
+    pub w_buffer: bool,
+    // what the near and far planes are kept as, from 0 to 1 with near the bigger
+    pub near: f32,
+    pub far: f32,
+}
+
+impl Default for DepthMapping {
+    fn default() -> Self {
+        Self { w_buffer: false, near: 1., far: 0. }
+    }
+}
+
+impl DepthMapping {
+    // what C3D_DepthMap wants for `clip_planes`, with everything `bias` closer
+    fn scale_offset(self, clip_planes: ClipPlanes, bias: f32) -> (f32, f32) {
+        let (near, far) = (self.near + bias, self.far + bias);
+        if !self.w_buffer {
+            // z/w goes from -1 at the near plane to 0 at the far one
+            return (far - near, far);
+        }
+
+        // the GPU keeps z * scale + w * offset, and Projection::perspective makes z = a * w + b,
+        // where w is the distance
+        let ClipPlanes { near: n, far: f } = clip_planes;
+        let (a, b) = (n / (f - n), -n * f / (f - n));
+        let slope = (far - near) / (f - n);
+        let scale = (near - slope * n) / b;
+        (scale, slope - scale * a)
+    }
+}
+
+// exponential distance fog
+#[derive(Copy, Clone, Debug)]
+pub struct Fog {
+    pub color: u32, // 0xRRGGBB
+    pub density: f32,
+}
+
+// a look for the whole scene, for day and night, underwater, damage flashes and so on. set a new
+// one every frame to animate it, see ColorGrade::lerp
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ColorGrade {
+    pub tint: Vec3, // multiplies every color, white leaves them alone
+    pub saturation: f32, // 0 is greyscale, 1 leaves colors alone. doesn't reach the skybox or background
+    pub flash: Vec4, // mixes in rgb by a, e.g. (1, 0, 0, 0.5) for half red
+}
+
+impl Default for ColorGrade {
+    fn default() -> Self {
+        Self { tint: Vec3::ONE, saturation: 1., flash: Vec4::ZERO }
+    }
+}
+
+impl ColorGrade {
+    pub fn lerp(&self, other: &ColorGrade, t: f32) -> Self {
+        Self {
+            tint: self.tint.lerp(other.tint, t),
+            saturation: self.saturation + (other.saturation - self.saturation) * t,
+            flash: self.flash.lerp(other.flash, t),
+        }
+    }
+}
+
+// how much each channel counts towards brightness, for desaturating
+pub(crate) const LUMINANCE: [f32; 3] = [0.299, 0.587, 0.114];
+
+// counters for the last frame that was rendered
+#[derive(Copy, Clone, Debug, Default)]
+pub struct FrameStats {
+    pub requests: u32,
+    pub draw_calls: u32,
+    pub occluded: u32, // requests that weren't drawn because they were behind an occluder
+    pub arena_bytes: u32, // of render()'s scratch space
+    pub skipped_state: u32, // texture binds and state changes the scene didn't need, see DrawState
+}
+
+// scratch space for the lists render() builds each frame. anything that doesn't fit goes on the
+// heap instead, see FrameArena::overflows
+const FRAME_ARENA_SIZE: usize = 64 << 10;
+
+// covers the screen once scaled by the background's uv range, see please_render_background
+const BACKGROUND_QUAD: [Vertex; 6] = [
+    Vertex { pos: [0., 0., -0.5], uv: [0., 0.], normal: [0., 0., 1.] },
+    Vertex { pos: [1., 0., -0.5], uv: [1., 0.], normal: [0., 0., 1.] },
+    Vertex { pos: [1., 1., -0.5], uv: [1., 1.], normal: [0., 0., 1.] },
+
+    Vertex { pos: [1., 1., -0.5], uv: [1., 1.], normal: [0., 0., 1.] },
+    Vertex { pos: [0., 1., -0.5], uv: [0., 1.], normal: [0., 0., 1.] },
+    Vertex { pos: [0., 0., -0.5], uv: [0., 0.], normal: [0., 0., 1.] },
+];
+
+// the registered meshes, with the slots of unregistered ones reused
+#[derive(Default)]
+struct Meshes {
+    slots: Vec<(u32, Option<Mesh>)>, // the generation, and the mesh if it's still registered
+    free: Vec<u32>,
+}
+
+impl Meshes {
+    fn insert(&mut self, mesh: Mesh) -> MeshId {
+        match self.free.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index as usize];
+                slot.1 = Some(mesh);
+                MeshId { index, generation: slot.0 }
+            }
+            None => {
+                self.slots.push((0, Some(mesh)));
+                MeshId { index: self.slots.len() as u32 - 1, generation: 0 }
+            }
+        }
+    }
+
+    // None if it's already been removed
+    fn remove(&mut self, id: MeshId) -> Option<Mesh> {
+        let slot = self.slots.get_mut(id.index as usize).filter(|slot| slot.0 == id.generation)?;
+        let mesh = slot.1.take()?;
+        slot.0 = slot.0.wrapping_add(1);
+        self.free.push(id.index);
+        Some(mesh)
+    }
+
+    fn get(&self, id: MeshId) -> Option<&Mesh> {
+        self.slots.get(id.index as usize).filter(|slot| slot.0 == id.generation)?.1.as_ref()
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &Mesh> {
+        self.slots.iter().filter_map(|slot| slot.1.as_ref())
+    }
+
+    fn iter_mut(&mut self) -> impl Iterator<Item = &mut Mesh> {
+        self.slots.iter_mut().filter_map(|slot| slot.1.as_mut())
+    }
+}
+
+impl Index<MeshId> for Meshes {
+    type Output = Mesh;
+
+    fn index(&self, id: MeshId) -> &Mesh {
+        self.get(id).unwrap_or_else(|| panic!("{id:?} was unregistered"))
+    }
+}
+
+impl IndexMut<MeshId> for Meshes {
+    fn index_mut(&mut self, id: MeshId) -> &mut Mesh {
+        self.slots.get_mut(id.index as usize)
+            .filter(|slot| slot.0 == id.generation)
+            .and_then(|slot| slot.1.as_mut())
+            .unwrap_or_else(|| panic!("{id:?} was unregistered"))
+    }
+}
+
+struct Request {
+    mesh_id: MeshId,
+    model: Matrix4,
+    material: Option<MaterialOverride>, // see please_render_animated
+}
+
+// the scene spread over both screens, see enable_dual_screen
+struct DualScreen {
+    gap: f32,
+    projections: [Matrix4; 2], // top, bottom
+}
+
+// the screens in pixels, which is what the 2d layer is measured in. see please_render_2d
+pub const TOP_SCREEN_SIZE: Vec2 = Vec2::new(400., 240.);
+pub const BOTTOM_SCREEN_SIZE: Vec2 = Vec2::new(320., 240.);
+
+// how a screen's picture is kept while it's drawn, and on its way to the LCD. 16 bits a pixel
+// takes half the VRAM and transfer time of 32, for bands in smooth gradients (see set_dither)
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ColorFormat {
+    Rgba8,
+    #[default]
+    Rgb8, // what the screens start out as
+    Rgb565,
+}
+
+impl ColorFormat {
+    fn framebuffer_format(self) -> FramebufferFormat {
+        match self {
+            ColorFormat::Rgba8 => FramebufferFormat::Rgba8,
+            ColorFormat::Rgb8 => FramebufferFormat::Bgr8,
+            ColorFormat::Rgb565 => FramebufferFormat::Rgb565,
+        }
+    }
+}
+
+// a screen's render target, see Renderer::with_formats
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TargetFormat {
+    pub color: ColorFormat,
+    // Depth16 and Depth24 save VRAM over the default, where there's no need for the precision or
+    // the stencil. without one, everything's drawn over what was drawn before it, which is only
+    // right for the 2d layer
+    pub depth: Option<DepthFormat>,
+}
+
+impl Default for TargetFormat {
+    fn default() -> Self {
+        Self { color: ColorFormat::default(), depth: Some(DepthFormat::Depth24Stencil8) }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TargetScreen {
+    Top,
+    Bottom, // needs Renderer::enable_bottom_screen
+}
+
+impl TargetScreen {
+    // in pixels
+    pub fn size(self) -> Vec2 {
+        match self {
+            TargetScreen::Top => TOP_SCREEN_SIZE,
+            TargetScreen::Bottom => BOTTOM_SCREEN_SIZE,
+        }
+    }
+}
+
+// both screens are 240 pixels tall (held the usual way), and their pixels are about the same size
+const SCREEN_HEIGHT: f32 = 240.;
+
+// the projections for the top and bottom screens, as two parts of one window that's `fov_y` tall,
+// with `gap` pixels of it hidden between them
+fn dual_screen_projections(fov_y: f32, gap: f32, clip_planes: ClipPlanes) -> [Matrix4; 2] {
+    let height = 2. * SCREEN_HEIGHT + gap;
+    let pixel = 2. * (fov_y / 2.).tan() / height; // how big a pixel is, a unit away from the camera
+    let screen_fov = 2. * (pixel * SCREEN_HEIGHT / 2.).atan();
+    let offset = pixel * (height - SCREEN_HEIGHT) / 2.; // between the middle and a screen's centre
+
+    [(AspectRatio::TopScreen, offset), (AspectRatio::BottomScreen, -offset)].map(|(aspect, offset)| {
+        let projection: Matrix4 = Projection::perspective(screen_fov, aspect, clip_planes).into();
+        // aims the screen up or down without turning it, by sliding what it sees the other way,
+        // more the further away it is
+        let mut shear = Mat4::IDENTITY;
+        shear.z_axis.y = offset;
+        to_matrix4(from_matrix4(projection) * shear)
+    })
+}
+
+struct SpriteRequest {
+    screen: TargetScreen,
+    mesh_id: MeshId,
+    model: Matrix4,
+    color: Vec4,
+    depth: f32, // see please_render_2d_at_depth
+}
+
+// how far apart the two eyes see something in the 2d layer at a depth of 1, in pixels
+const PARALLAX_2D: f32 = 10.;
+// how far apart the eyes are in the scene with the slider all the way up, in world units
+const EYE_SEPARATION: f32 = 0.2;
+// how far from the camera things are when they look level with the screen. nearer ones come out
+// of it, further ones go in
+const SCREEN_DEPTH: f32 = 2.;
+
+// `projection` for one eye, `eye` (as in parallax_shift) times half the separation to the side.
+// what's SCREEN_DEPTH away is sheared back to where it was, so only what's nearer or further
+// moves, the way Mtx_PerspStereoTilt does it. works for any of the screens' projections
+fn stereo_projection(projection: Matrix4, eye: f32) -> Matrix4 {
+    let offset = eye * EYE_SEPARATION / 2.;
+    let mut shift = Mat4::from_translation(Vec3::new(-offset, 0., 0.));
+    shift.z_axis.x = -offset / SCREEN_DEPTH;
+    to_matrix4(from_matrix4(projection) * shift)
+}
+
+// how far a 2d element `depth` behind the screen moves sideways for one eye, in pixels. `eye` is
+// -1 for the left eye and 1 for the right, times how far the stereo's turned up (see
+// Renderer::stereo), and 0 when there's only one picture
+fn parallax_shift(depth: f32, eye: f32) -> f32 {
+    depth * eye * PARALLAX_2D / 2.
+}
+
+struct StreamUpdate {
+    mesh_id: MeshId,
+    stream: usize,
+    start: usize,
+    data: Vec<[f32; 4]>,
+}
+
+struct OverlayRequest {
+    texture: Option<sys::C3D_Tex>,
+    min: Vec2,
+    size: Vec2,
+    color: Vec4,
+}
+
+// a material that comes out exactly as `color`, whatever the light
+fn unlit(color: Vec4) -> Material {
+    Material {
+        ambient: Vec4::ZERO.into(),
+        diffuse: Vec4::ZERO.into(),
+        specular: Vec4::ZERO.into(),
+        emission: color.into(),
+        ..Default::default()
+    }
+}
+
+// pixel `i` of a framebuffer in `format`, as RGB
+fn decode_pixel(format: ctru_sys::GSPGPU_FramebufferFormat, data: &[u8], i: usize) -> [u8; 3] {
+    let expand = |v: u16, bits: u32| (u32::from(v) * 255 / ((1 << bits) - 1)) as u8;
+    match format {
+        ctru_sys::GSP_RGBA8_OES => [data[i * 4 + 3], data[i * 4 + 2], data[i * 4 + 1]],
+        ctru_sys::GSP_BGR8_OES => [data[i * 3 + 2], data[i * 3 + 1], data[i * 3]],
+        _ => {
+            let v = u16::from_le_bytes([data[i * 2], data[i * 2 + 1]]);
+            match format {
+                ctru_sys::GSP_RGB565_OES => [expand(v >> 11, 5), expand(v >> 5 & 0x3f, 6), expand(v & 0x1f, 5)],
+                ctru_sys::GSP_RGB5_A1_OES => [expand(v >> 11, 5), expand(v >> 6 & 0x1f, 5), expand(v >> 1 & 0x1f, 5)],
+                _ => [expand(v >> 12, 4), expand(v >> 8 & 0xf, 4), expand(v >> 4 & 0xf, 4)], // RGBA4
+            }
+        }
+    }
+}
+
+// for frame captures, see Renderer::capture_frame
+fn describe_material(material: &Material) -> String {
+    let v = |v: FVec4| from_fvec4(v).to_array();
+    format!(
+        "ambient {:?} diffuse {:?} specular {:?} emission {:?} lightmap {} alpha_test {:?} double_sided {} blend {:?}",
+        v(material.ambient), v(material.diffuse), v(material.specular), v(material.emission),
+        material.lightmap, material.alpha_test, material.double_sided, material.blend,
+    )
+}
+
+// the texenv stages render sets up for `part`, in words
+fn describe_texenv(mesh: &Mesh, part: &Part, program: &str, has_skybox: bool) -> String {
+    let stage0 = if has_skybox && program == "reflect" {
+        format!("interpolate(texture0 skybox, primary, {})", mesh.reflectivity)
+    } else if part.texture.is_some() {
+        "modulate(primary, texture0)".to_owned()
+    } else {
+        "primary".to_owned()
+    };
+    let lightmap = mesh.lightmap.is_some() && program == "lightmap";
+    let emission_map = mesh.emission_map.is_some() && (program == "default" || program == "lightmap");
+    let stage1 = match (lightmap, emission_map) {
+        (true, false) => "modulate(previous, texture1 lightmap)",
+        (true, true) => "rgb multiply_add(previous, texture1 lightmap, texture2 emission)",
+        (false, true) => "rgb add(previous, texture2 emission)",
+        (false, false) => "previous",
+    };
+
+    format!("stage0 {stage0} stage1 {stage1}")
+}
+
+// a color blended over the whole finished picture, with its source and destination factors, and a
+// render texture it's multiplied by
+type Fill<'a> = (Vec4, ctru_sys::GPU_BLENDFACTOR, ctru_sys::GPU_BLENDFACTOR, Option<&'a sys::C3D_Tex>);
+
+// what a pass's post steps come to
+fn post_fills<'a>(
+    post: &[PostStep],
+    grade: ColorGrade,
+    brightness: f32,
+    bloom: Option<&'a (Bloom, bloom::Levels)>,
+    arena: &'a FrameArena,
+) -> Vec<Fill<'a>, &'a FrameArena> {
+    let multiply = |tint: Vec3| (tint.extend(1.), ctru_sys::GPU_DST_COLOR, ctru_sys::GPU_ZERO, None);
+    let fade = |color: Vec4| (color, ctru_sys::GPU_SRC_ALPHA, ctru_sys::GPU_ONE_MINUS_SRC_ALPHA, None);
+
+    let mut ret = Vec::new_in(arena);
+    for &step in post {
+        match step {
+            PostStep::ColorGrade => {
+                if grade.tint != Vec3::ONE {
+                    ret.push(multiply(grade.tint));
+                }
+                if grade.flash.w > 0. {
+                    ret.push(fade(grade.flash));
+                }
+                if brightness > 0. {
+                    // dst + brightness * (1 - dst), i.e. the "screen" blend mode
+                    ret.push((Vec3::splat(brightness).extend(1.), ctru_sys::GPU_ONE_MINUS_DST_COLOR, ctru_sys::GPU_ONE, None));
+                }
+            }
+            PostStep::Bloom => {
+                // the blurrier levels spread the glow further
+                if let Some((settings, levels)) = bloom {
+                    let color = Vec3::splat(settings.intensity / bloom::LEVELS as f32).extend(1.);
+                    ret.extend(levels.iter().map(|level| (color, ctru_sys::GPU_ONE, ctru_sys::GPU_ONE, Some(level.texture()))));
+                }
+            }
+            PostStep::Tint(tint) => ret.push(multiply(tint)),
+            PostStep::Fade(color) => ret.push(fade(color)),
+        }
+    }
+
+    ret
+}
+
+// the uniforms the renderer knows how to fill in, looked up by name in each program. a shader gets
+// whichever of these it declares, and can leave out the rest:
+//
+//     .fvec projection[4], modelView[4]
+//     .fvec lightVec, lightHalfVec, lightClr, material[4]
+//     .fvec uvTransform, time
+//     .fvec bones[60] (for skinned meshes)
+//
+// plus any a mesh sets with Renderer::set_param
+struct ProgramUniforms {
+    projection: Option<uniform::Index>,
+    model_view: Option<uniform::Index>,
+    light_vec: Option<uniform::Index>,
+    light_half_vec: Option<uniform::Index>,
+    light_color: Option<uniform::Index>,
+    material: Option<uniform::Index>,
+    uv_transform: Option<uniform::Index>,
+    time: Option<uniform::Index>,
+    bones: Option<uniform::Index>,
+}
+
+impl ProgramUniforms {
+    fn new(program: &Program) -> Self {
+        Self {
+            projection: program.get_uniform("projection").ok(),
+            model_view: program.get_uniform("modelView").ok(),
+            light_vec: program.get_uniform("lightVec").ok(),
+            light_half_vec: program.get_uniform("lightHalfVec").ok(),
+            light_color: program.get_uniform("lightClr").ok(),
+            material: program.get_uniform("material").ok(),
+            uv_transform: program.get_uniform("uvTransform").ok(),
+            time: program.get_uniform("time").ok(),
+            bones: program.get_uniform("bones").ok(),
+        }
+    }
+}
+
+// where each of `mesh`'s params is in its shader, looked up once instead of every draw. None for
+// ones it doesn't declare, and for all of them if the shader isn't registered
+fn param_uniforms(mesh: &Mesh, shaders: &ShaderRegistry) -> Vec<Option<uniform::Index>> {
+    let program = mesh.shader.as_deref().and_then(|name| shaders.get(name));
+    mesh.params.iter().map(|(name, _)| program.and_then(|program| program.get_uniform(name).ok())).collect()
+}
+
+// which program draws `mesh`: its own if it has one (and it exists), otherwise a builtin
+fn program_name<'a>(mesh: &'a Mesh, shaders: &ShaderRegistry, has_skybox: bool) -> &'a str {
+    match mesh.shader.as_deref() {
+        Some(name) if shaders.get(name).is_some() => name,
+        _ if mesh.is_skinned() => "skinned",
+        _ if mesh.is_lightmapped() && mesh.material.lightmap => "lightmap",
+        _ if mesh.streams() > 0 => "vertex_color",
+        _ if has_skybox && mesh.reflectivity > 0. => "reflect",
+        _ => "default",
+    }
+}
+
+// how a scene draw's first texenv stage makes its color
+#[derive(Copy, Clone, PartialEq)]
+enum Stage0 {
+    Reflect(u32), // by this much, out of 255
+    Textured,
+    Untextured,
+}
+
+// and what the second does with it
+#[derive(Copy, Clone, PartialEq)]
+enum Stage1 {
+    PassThrough,
+    Lightmap,
+    LightmapEmission,
+    Emission,
+}
+
+// what the scene's draws last set the GPU to, so the next one can leave what's the same alone.
+// it only holds while nothing else is setting any of it, so each pass's scene starts a new one
+#[derive(Default)]
+struct DrawState {
+    stage0: Option<Stage0>,
+    stage1: Option<Stage1>,
+    textures: [Option<(*mut std::ffi::c_void, u32)>; 3], // the data and parameters of each unit's
+    alpha_test: Option<Option<AlphaTest>>,
+    cull: Option<ctru_sys::GPU_CULLMODE>,
+    depth_write: Option<ctru_sys::GPU_WRITEMASK>,
+    blend: Option<BlendMode>,
+    skipped: u32,
+}
+
+impl DrawState {
+    // whether `slot` isn't `value` yet, making it so
+    fn change<T: PartialEq>(slot: &mut Option<T>, value: T, skipped: &mut u32) -> bool {
+        if *slot == Some(value) {
+            *skipped += 1;
+            return false;
+        }
+        *slot = Some(value);
+        true
+    }
+
+    // the same image with the same filtering counts as the same texture, since meshes have copies
+    // of what they share (see texture_cache)
+    fn bind_texture(&mut self, unit: usize, texture: &sys::C3D_Tex) {
+        let key = (unsafe { texture.__bindgen_anon_1.data }, texture.param);
+        if Self::change(&mut self.textures[unit], key, &mut self.skipped) {
+            unsafe { sys::C3D_TexBind(unit as i32, texture as *const _ as *mut _); }
+        }
+    }
+}
+
+pub struct Renderer<'gfx> {
+    context: Instance,
+
+    target: Target<'gfx>, // the top screen, for the left eye in stereo
+    right_target: Target<'gfx>,
+    _top_screen_3d: TopScreen3D<'gfx>, // keeps the top screen in stereo mode
+    depth_strength: f32,
+    bottom_target: Option<Target<'gfx>>, // see enable_bottom_screen
+    top_format: TargetFormat,
+    bottom_format: TargetFormat,
+    dual_screen: Option<DualScreen>,
+
+    projection: Matrix4,
+    fov_y: f32,
+    view: Mat4,
+    eye: Vec3, // where the camera is
+    clip_planes: ClipPlanes,
+    depth_mapping: DepthMapping,
+    clear_color: u32,
+    fog: Option<(Fog, Box<sys::C3D_FogLut>)>,
+    lights: Vec<Light>,
+    uniforms: HashMap<String, ProgramUniforms>, // by program name, filled in as programs get used
+    skybox_uniforms: (uniform::Index, uniform::Index), // projection, modelView
+    shaders: ShaderRegistry,
+
+    requests: Vec<Request>,
+    batched_requests: Vec<Request>,
+    decal_requests: Vec<Request>,
+    // merged from batched_requests each frame. the GPU can still be drawing last frame's while we
+    // build this frame's, so the previous ones are kept around for a frame
+    dynamic_batches: [Vec<Mesh>; 2],
+    // what meshes were using before compact_meshes moved them, kept the same way
+    retired_buffers: [Vec<RetiredBuffers>; 2],
+    // and meshes that have been unregistered
+    retired_meshes: [Vec<Mesh>; 2],
+    background: Option<(sys::C3D_Tex, Vec2)>,
+    sprite_requests: Vec<SpriteRequest>,
+    // written over meshes' streams at the start of the next frame, see update_stream
+    stream_updates: Vec<StreamUpdate>,
+    overlays: Vec<OverlayRequest>,
+    background_quad: Mesh,
+    bloom: Option<(Bloom, bloom::Levels)>,
+    bloom_quad: Mesh,
+    dither: [bool; 2], // top, bottom
+    dither_texture: Option<sys::C3D_Tex>, // made the first time it's wanted
+    skybox: Option<Cubemap>,
+    skybox_cube: Mesh,
+    meshes: Meshes,
+    texture_cache: TextureCache,
+    models: Vec<Model>,
+    occluders: Vec<Aabb>,
+    render_graph: Option<RenderGraph>,
+    brightness: f32,
+    color_grade: ColorGrade,
+    stats: FrameStats,
+    arena: FrameArena,
+    last_render: Option<Instant>,
+    time: f32,
+    dt: f32,
+    time_scale: f32,
+}
+
+impl<'gfx> Renderer<'gfx> {
+    pub fn new(gfx: &'gfx Gfx) -> Self {
+        Self::with_formats(gfx, TargetFormat::default(), TargetFormat::default())
+    }
+
+    // with the top screen drawn in `top`, and the bottom one in `bottom` once it's enabled
+    pub fn with_formats(gfx: &'gfx Gfx, top: TargetFormat, bottom: TargetFormat) -> Self {
+        let context = Instance::new().unwrap();
+        let top_screen_3d = TopScreen3D::from(&gfx.top_screen);
+        let (mut left, right) = top_screen_3d.split_mut();
+        left.set_framebuffer_format(top.color.framebuffer_format()); // for both eyes
+        let RawFrameBuffer { width, height, .. } = left.raw_framebuffer();
+        let target = context.render_target(width, height, left, top.depth).unwrap();
+        let RawFrameBuffer { width, height, .. } = right.raw_framebuffer();
+        let right_target = context.render_target(width, height, right, top.depth).unwrap();
+
+        let shaders = ShaderRegistry::new();
+        let skybox_program = shaders.get("skybox").unwrap();
+
+        let projection = Projection::perspective(DEFAULT_FOV_Y, AspectRatio::TopScreen, DEFAULT_CLIP_PLANES);
+
+        Self {
+            context,
+
+            target,
+            right_target,
+            _top_screen_3d: top_screen_3d,
+            depth_strength: 1.,
+            bottom_target: None,
+            top_format: top,
+            bottom_format: bottom,
+            dual_screen: None,
+
+            projection: projection.into(),
+            fov_y: DEFAULT_FOV_Y,
+            view: Mat4::IDENTITY,
+            eye: Vec3::ZERO,
+            clip_planes: DEFAULT_CLIP_PLANES,
+            depth_mapping: DepthMapping::default(),
+            clear_color: DEFAULT_CLEAR_COLOR,
+            fog: None,
+            lights: vec![],
+
+            uniforms: HashMap::new(),
+            skybox_uniforms: (
+                skybox_program.get_uniform("projection").unwrap(),
+                skybox_program.get_uniform("modelView").unwrap(),
+            ),
+
+            shaders,
+
+            requests: vec![],
+            batched_requests: vec![],
+            decal_requests: vec![],
+            dynamic_batches: [vec![], vec![]],
+            retired_buffers: [vec![], vec![]],
+            retired_meshes: [vec![], vec![]],
+            background: None,
+            sprite_requests: vec![],
+            stream_updates: vec![],
+            overlays: vec![],
+            background_quad: Mesh::from_data(&BACKGROUND_QUAD, None, None, Material {
+                emission: Vec4::ONE.into(), // unlit, so the texture comes through as is
+                ..Default::default()
+            }),
+            bloom: None,
+            bloom_quad: Mesh::from_data(&bloom::QUAD, None, None, Material::default()),
+            dither: [false; 2],
+            dither_texture: None,
+            skybox: None,
+            skybox_cube: Mesh::from_data(&CUBE_VERTICES, None, None, Material::default()),
+            meshes: Meshes::default(),
+            texture_cache: TextureCache::new(),
+            models: vec![],
+            occluders: vec![],
+            render_graph: None,
+            brightness: 0.,
+            color_grade: ColorGrade::default(),
+            stats: FrameStats::default(),
+            arena: FrameArena::new(FRAME_ARENA_SIZE),
+            last_render: None,
+            time: 0.,
+            dt: 0.,
+            time_scale: 1.,
+        }
+    }
+
+    // 0xRRGGBBAA
+    pub fn set_clear_color(&mut self, color: u32) {
+        self.clear_color = color;
+    }
+
+    pub fn set_perspective(&mut self, fov_y: f32, clip_planes: ClipPlanes) {
+        self.projection = Projection::perspective(fov_y, AspectRatio::TopScreen, clip_planes).into();
+        self.fov_y = fov_y;
+        self.clip_planes = clip_planes;
+        if let Some(dual) = &mut self.dual_screen {
+            dual.projections = dual_screen_projections(fov_y, dual.gap, clip_planes);
+        }
+
+        // the fog table depends on the clip planes
+        if let Some((fog, _)) = self.fog {
+            self.set_fog(Some(fog));
+        }
+    }
+
+    // for the 3d scene, on every screen. the fog table is worked out for the default
+    pub fn set_depth_mapping(&mut self, depth_mapping: DepthMapping) {
+        self.depth_mapping = depth_mapping;
+    }
+
+    // how strong the stereo effect is, times the 3d slider: 1 is the default, more makes the scene
+    // and the 2d layer stand out further, and 0 turns stereo off. the top screen is drawn once per
+    // eye while it's on and the slider's up, which takes the GPU about twice as long
+    pub fn set_depth_strength(&mut self, strength: f32) {
+        self.depth_strength = strength.max(0.);
+    }
+
+    // how far the eyes are apart this frame, from 0 (one picture) up. see parallax_shift
+    fn stereo(&self) -> f32 {
+        ctru::os::current_3d_slider_state() * self.depth_strength
+    }
+
+    // lets the renderer draw on the bottom screen too, for 2d things (see please_render_2d) or
+    // the other half of the scene (see enable_dual_screen). until then, the bottom screen is left
+    // for the debug console and the like. returns false if one of those is using it
+    pub fn enable_bottom_screen(&mut self, gfx: &'gfx Gfx) -> bool {
+        if self.bottom_target.is_some() {
+            return true;
+        }
+
+        let Ok(mut bottom_screen) = gfx.bottom_screen.try_borrow_mut() else {
+            return false;
+        };
+        bottom_screen.set_framebuffer_format(self.bottom_format.color.framebuffer_format());
+        let RawFrameBuffer { width, height, .. } = bottom_screen.raw_framebuffer();
+        self.bottom_target = Some(self.context.render_target(width, height, bottom_screen, self.bottom_format.depth).unwrap());
+
+        true
+    }
+
+    // gives the bottom screen back, turning off dual screen too
+    pub fn disable_bottom_screen(&mut self) {
+        self.bottom_target = None;
+        self.dual_screen = None;
+    }
+
+    // draws the scene on both screens, as if they were one tall window with the hinge across the
+    // middle, for games that scroll vertically. the camera looks at the middle of the hinge, and
+    // set_perspective's fov_y covers both screens and the gap between them. `gap` is how far apart
+    // the screens' pictures are, in pixels; the default is close to what they physically are on
+    // most models. overlays and the color grade cover each screen on its own. enables the bottom
+    // screen, and returns false if it can't
+    pub fn enable_dual_screen(&mut self, gfx: &'gfx Gfx, gap: Option<f32>) -> bool {
+        if !self.enable_bottom_screen(gfx) {
+            return false;
+        }
+
+        let gap = gap.unwrap_or(DEFAULT_SCREEN_GAP).max(0.);
+        self.dual_screen = Some(DualScreen {
+            gap,
+            projections: dual_screen_projections(self.fov_y, gap, self.clip_planes),
+        });
+
+        true
+    }
+
+    // back to the scene on the top screen only. the bottom screen stays enabled, see
+    // disable_bottom_screen
+    pub fn disable_dual_screen(&mut self) {
+        self.dual_screen = None;
+    }
+
+    // see bloom. false if there isn't the VRAM for it
+    pub fn set_bloom(&mut self, bloom: Option<Bloom>) -> bool {
+        self.bloom = match (bloom, self.bloom.take()) {
+            (Some(bloom), Some((_, levels))) => Some((bloom, levels)),
+            (Some(bloom), None) => match bloom::levels() {
+                Some(levels) => Some((bloom, levels)),
+                None => return false,
+            },
+            (None, _) => None,
+        };
+
+        true
+    }
+
+    pub fn bloom(&self) -> Option<Bloom> {
+        self.bloom.as_ref().map(|(bloom, _)| *bloom)
+    }
+
+    // ordered dithering on `screen` (see dither), for when its framebuffer is 16-bit. it does
+    // nothing while the screen's in a 24 or 32-bit format. false if there's no memory for it
+    pub fn set_dither(&mut self, screen: TargetScreen, dither: bool) -> bool {
+        if dither && self.dither_texture.is_none() {
+            let Some(texture) = dither::texture() else {
+                return false;
+            };
+            self.dither_texture = Some(texture);
+        }
+        self.dither[screen as usize] = dither;
+
+        true
+    }
+
+    // the passes to draw each frame with, or None for RenderGraph::default_for whichever screens
+    // are enabled. see render_graph
+    pub fn set_render_graph(&mut self, graph: Option<RenderGraph>) {
+        self.render_graph = graph;
+    }
+
+    // models given to please_render are placed in the world and seen from `camera`. without one,
+    // they're seen from the origin looking down -z
+    pub fn set_camera(&mut self, camera: &Camera) {
+        self.view = camera.view();
+        self.eye = camera.position;
+    }
+
+    // a solid box (in world space) that hides whatever's behind it. see occlusion
+    pub fn add_occluder(&mut self, occluder: Aabb) {
+        self.occluders.push(occluder);
+    }
+
+    pub fn clear_occluders(&mut self) {
+        self.occluders.clear();
+    }
+
+    // brightens the picture, the dark parts more than the light ones (like turning up the gamma),
+    // for screens where dark scenes are hard to make out. 0 (the default) leaves it alone, 1 turns
+    // everything white. see also backlight::Backlight
+    pub fn set_brightness(&mut self, amount: f32) {
+        self.brightness = amount.clamp(0., 1.);
+    }
+
+    pub fn set_color_grade(&mut self, grade: ColorGrade) {
+        self.color_grade = ColorGrade {
+            tint: grade.tint.clamp(Vec3::ZERO, Vec3::ONE),
+            saturation: grade.saturation.clamp(0., 1.),
+            flash: grade.flash.clamp(Vec4::ZERO, Vec4::ONE),
+        };
+    }
+
+    pub fn color_grade(&self) -> ColorGrade {
+        self.color_grade
+    }
+
+    // replaces the lights the scene's lit by, see light. none lights everything evenly
+    pub fn set_lights(&mut self, lights: &[Light]) {
+        self.lights = lights.to_vec();
+    }
+
+    pub fn lights(&self) -> &[Light] {
+        &self.lights
+    }
+
+    pub fn set_fog(&mut self, fog: Option<Fog>) {
+        self.fog = fog.map(|fog| {
+            let mut lut = Box::new(unsafe { std::mem::zeroed::<sys::C3D_FogLut>() });
+            unsafe {
+                sys::FogLut_Exp(lut.as_mut(), fog.density, 1.5, self.clip_planes.near, self.clip_planes.far);
+            }
+
+            (fog, lut)
+        });
+    }
+
+    pub fn register_mesh(&mut self, mesh: Mesh) -> MeshId {
+        self.meshes.insert(mesh)
+    }
+
+    // frees the mesh's vertex and index buffers, and the textures it loaded itself (not ones from a
+    // texture cache, which other meshes could have). the GPU could still be drawing it, so that
+    // happens a couple of frames later, like compact_meshes's. whatever it was asked to draw this
+    // frame isn't drawn, registered models stop drawing it, and using `mesh_id` after this panics.
+    // batch_static's batches share the textures of the meshes in them, which stay until the batches
+    // go too. false if it already was
+    pub fn unregister_mesh(&mut self, mesh_id: MeshId) -> bool {
+        let Some(mesh) = self.meshes.remove(mesh_id) else { return false };
+        // the textures only this mesh has left are freed with it, so nothing else can be drawing them
+        debug_assert!(
+            mesh.owned_textures.iter().filter(|texture| Rc::strong_count(texture) == 1).all(|texture| {
+                self.meshes.iter().flat_map(Mesh::textures).all(|other| unsafe { other.__bindgen_anon_1.data } != texture.data())
+            }),
+            "{mesh_id:?} is the last owner of a texture that another mesh draws with",
+        );
+        self.retired_meshes[0].push(mesh);
+
+        for requests in [&mut self.requests, &mut self.batched_requests, &mut self.decal_requests] {
+            requests.retain(|request| request.mesh_id != mesh_id);
+        }
+        self.sprite_requests.retain(|request| request.mesh_id != mesh_id);
+        self.stream_updates.retain(|update| update.mesh_id != mesh_id);
+        for model in &mut self.models {
+            model.forget_mesh(mesh_id);
+        }
+        true
+    }
+
+    // whether `mesh_id` hasn't been unregistered
+    pub fn is_registered(&self, mesh_id: MeshId) -> bool {
+        self.meshes.get(mesh_id).is_some()
+    }
+
+    // for loading meshes whose textures are shared with everything loaded the same way before, see
+    // Mesh::from_file_data_in
+    pub fn texture_cache(&mut self) -> &mut TextureCache {
+        &mut self.texture_cache
+    }
+
+    pub fn register_model(&mut self, model: Model) -> ModelId {
+        self.models.push(model);
+        ModelId(self.models.len() - 1)
+    }
+
+    // for moving its nodes, see Model::set_transform
+    pub fn model_mut(&mut self, model_id: ModelId) -> &mut Model {
+        &mut self.models[model_id.0]
+    }
+
+    // merges static meshes (level geometry, props that never move) placed at `placements` into as
+    // few new meshes as possible, each drawn with please_render(id, Matrix4::identity()). the
+    // originals stay registered, so they can still be drawn on their own, and the batches keep
+    // their textures if they're unregistered
+    pub fn batch_static(&mut self, placements: &[(MeshId, Matrix4)]) -> Vec<MeshId> {
+        let parts: Vec<_> = placements.iter().map(|&(mesh_id, model)| (&self.meshes[mesh_id], model)).collect();
+        let batches = batch::merge(&parts).unwrap_or_else(|e| panic!("{e}"));
+
+        batches.into_iter().map(|mesh| self.register_mesh(mesh)).collect()
+    }
+
+    // for a load screen: moves meshes' vertex buffers down into the free blocks of the mesh pool,
+    // highest first, so the slabs at the top empty out. they go back to the linear heap a couple
+    // of frames later, once the GPU's done with what was in them. returns how many meshes moved
+    pub fn compact_meshes(&mut self) -> usize {
+        let mut order = self.meshes.iter_mut().collect::<Vec<_>>();
+        order.sort_by_key(|mesh| std::cmp::Reverse(mesh.vertices_addr()));
+
+        let mut moved = 0;
+        for mesh in order {
+            if let Some(retired) = mesh.compact() {
+                self.retired_buffers[0].push(retired);
+                moved += 1;
+            }
+        }
+
+        moved
+    }
+
+    // drawn behind everything (but in front of a background), and reflected by meshes given a
+    // reflectivity
+    pub fn set_skybox(&mut self, skybox: Option<Cubemap>) {
+        self.skybox = skybox;
+    }
+
+    // 0 (the default) leaves the mesh as it is, 1 makes it a perfect mirror of the skybox. a
+    // reflective mesh's own texture isn't drawn, since the cubemap takes its place
+    pub fn set_reflectivity(&mut self, mesh_id: MeshId, reflectivity: f32) {
+        self.meshes[mesh_id].reflectivity = reflectivity.clamp(0., 1.);
+    }
+
+    // for a mesh with sub-meshes, it's every sub-mesh's material that's set
+    pub fn set_material(&mut self, mesh_id: MeshId, material: Material) {
+        let mesh = &mut self.meshes[mesh_id];
+        mesh.material = material;
+        for sub_mesh in &mut mesh.sub_meshes {
+            sub_mesh.material = material;
+        }
+    }
+
+    // the material of one of a mesh's sub-meshes, see Mesh::from_sub_mesh_data
+    pub fn set_sub_mesh_material(&mut self, mesh_id: MeshId, sub_mesh: usize, material: Material) {
+        self.meshes[mesh_id].sub_meshes[sub_mesh].material = material;
+    }
+
+    // None if the mesh isn't a flipbook. flipbooks play on their own as frames are rendered
+    pub fn playback(&mut self, mesh_id: MeshId) -> Option<&mut Playback> {
+        let mesh = &mut self.meshes[mesh_id];
+        mesh.flipbook.is_some().then_some(&mut mesh.playback)
+    }
+
+    // seconds since the first render, as given to shaders. see time_uniform
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    // how fast flipbooks and shaders' time go, for slow motion. see time::Time
+    pub fn set_time_scale(&mut self, scale: f32) {
+        self.time_scale = scale.max(0.);
+    }
+
+    fn advance_time(&mut self) {
+        let now = Instant::now();
+        let dt = self.last_render.map_or(0., |last| (now - last).as_secs_f32()) * self.time_scale;
+        self.last_render = Some(now);
+        self.time += dt;
+        self.dt = dt;
+
+        for mesh in self.meshes.iter_mut() {
+            if mesh.flipbook.is_some() && mesh.playback.playing {
+                mesh.playback.time += dt * mesh.playback.speed;
+            }
+        }
+    }
+
+    // poses a skinned mesh: `bones[i]` is where bone i has moved vertices to from where they are in
+    // the mesh, i.e. the bone's transform times its inverse bind matrix. bones left out stay where
+    // they were. panics if the mesh isn't skinned or there are more than MAX_BONES
+    pub fn set_bones(&mut self, mesh_id: MeshId, bones: &[Mat4]) {
+        assert!(bones.len() <= MAX_BONES, "{} bones, the limit is {MAX_BONES}", bones.len());
+
+        let mesh = &mut self.meshes[mesh_id];
+        assert!(mesh.is_skinned(), "only skinned meshes have bones");
+        for (slot, bone) in mesh.bones.iter_mut().zip(bones) {
+            *slot = [bone.row(0), bone.row(1), bone.row(2)];
+        }
+    }
+
+    // poses every skinned mesh of the model with the same bones, see set_bones
+    pub fn set_model_bones(&mut self, model_id: ModelId, bones: &[Mat4]) {
+        for i in 0..self.models[model_id.0].skinned().len() {
+            let mesh_id = self.models[model_id.0].skinned()[i];
+            self.set_bones(mesh_id, bones);
+        }
+    }
+
+    // draws the mesh with a program from the shader registry instead of the default one, or goes
+    // back to the default with None. a name that isn't registered also means the default
+    pub fn set_shader(&mut self, mesh_id: MeshId, name: Option<&str>) {
+        let mesh = &mut self.meshes[mesh_id];
+        mesh.shader = name.map(str::to_owned);
+        mesh.param_uniforms = param_uniforms(mesh, &self.shaders);
+    }
+
+    // changes part of one of a mesh's streams (see Mesh::from_streamed_data), from vertex `start`
+    // on, without touching the rest of the mesh. the GPU could still be drawing the last frame with
+    // the old values, so they're written when the next frame starts, and the last call for the same
+    // values wins. panics if the mesh doesn't have that stream or it's too short
+    pub fn update_stream(&mut self, mesh_id: MeshId, stream: usize, start: usize, data: &[[f32; 4]]) {
+        let mesh = &self.meshes[mesh_id];
+        assert!(stream < mesh.streams(), "the mesh has {} streams", mesh.streams());
+        assert!(start + data.len() <= mesh.stream(stream).len(), "stream {stream} has {} values", mesh.stream(stream).len());
+        self.stream_updates.push(StreamUpdate { mesh_id, stream, start, data: data.to_vec() });
+    }
+
+    // sets a `.fvec` uniform for the mesh's shader. an error if its shader doesn't declare `name`,
+    // though the value's still kept for whatever shader it gets next
+    pub fn set_param(&mut self, mesh_id: MeshId, name: &str, value: Vec4) -> Result<(), Box<dyn Error>> {
+        let mesh = &mut self.meshes[mesh_id];
+        let shader = mesh.shader.clone().filter(|shader| self.shaders.get(shader).is_some());
+        let index = shader.as_deref().and_then(|shader| self.shaders.get(shader).unwrap().get_uniform(name).ok());
+        match mesh.params.iter().position(|(n, _)| n == name) {
+            Some(i) => {
+                mesh.params[i].1 = value;
+                mesh.param_uniforms[i] = index;
+            }
+            None => {
+                mesh.params.push((name.to_owned(), value));
+                mesh.param_uniforms.push(index);
+            }
+        }
+
+        match shader {
+            Some(shader) if index.is_none() => Err(format!("shader {shader} has no uniform {name}").into()),
+            _ => Ok(()),
+        }
+    }
+
+    // adds (or replaces) a shader that meshes can use with set_shader. see ShaderRegistry::load
+    pub fn load_shader(&mut self, name: &str, shbin: &[u8], geometry_stride: Option<u8>) -> Result<(), Box<dyn Error>> {
+        self.shaders.load(name, shbin, geometry_stride)?;
+        self.uniforms.remove(name); // the new program can put its uniforms anywhere
+        for mesh in self.meshes.iter_mut().filter(|mesh| mesh.shader.as_deref() == Some(name)) {
+            mesh.param_uniforms = param_uniforms(mesh, &self.shaders);
+        }
+        if name == "skybox" {
+            let program = self.shaders.get(name).unwrap();
+            self.skybox_uniforms = (program.get_uniform("projection")?, program.get_uniform("modelView")?);
+        }
+
+        Ok(())
+    }
+
+    // replaces a shader with a new build of it, with the same geometry shader (if any) as before.
+    // see shader_reload
+    pub fn reload_shader(&mut self, name: &str, shbin: &[u8]) -> Result<(), Box<dyn Error>> {
+        self.load_shader(name, shbin, self.shaders.geometry_stride(name))
+    }
+
+    pub fn please_render(&mut self, mesh_id: MeshId, model: Matrix4) {
+        self.requests.push(Request { mesh_id, model, material: None });
+    }
+
+    // like please_render, with `animation` where it is now applied to the mesh's material (and each
+    // of its sub-meshes'), for this draw only
+    pub fn please_render_animated(&mut self, mesh_id: MeshId, model: Matrix4, animation: &MaterialAnimation) {
+        self.requests.push(Request { mesh_id, model, material: Some(animation.current()) });
+    }
+
+    // draws every mesh of the model, each where its node is relative to `model`
+    pub fn please_render_model(&mut self, model_id: ModelId, model: Matrix4) {
+        self.request_model(model_id, model, None);
+    }
+
+    // please_render_model with an animation on every mesh, see please_render_animated
+    pub fn please_render_model_animated(&mut self, model_id: ModelId, model: Matrix4, animation: &MaterialAnimation) {
+        self.request_model(model_id, model, Some(animation.current()));
+    }
+
+    fn request_model(&mut self, model_id: ModelId, model: Matrix4, material: Option<MaterialOverride>) {
+        let model = from_matrix4(model);
+        let meshes = self.models[model_id.0].meshes();
+        self.requests.extend(meshes.map(|(mesh_id, node)| Request { mesh_id, model: to_matrix4(model * node), material }));
+    }
+
+    // like please_render, but for lots of copies of small meshes (coins, bullets, grass). these are
+    // transformed on the CPU and merged into a few big meshes every frame, so they take a handful of
+    // draw calls between them instead of one each. not worth it for anything with many vertices
+    pub fn please_render_batched(&mut self, mesh_id: MeshId, model: Matrix4) {
+        let mesh = &self.meshes[mesh_id];
+        if mesh.is_skinned() || mesh.is_lightmapped() || mesh.streams() > 0 || mesh.sub_meshes() > 0 {
+            // can't be merged, see batch::merge
+            return self.please_render(mesh_id, model);
+        }
+        self.batched_requests.push(Request { mesh_id, model, material: None });
+    }
+
+    // draws a decal mesh (see decal::mesh) where `decal` says, after everything else
+    pub fn please_render_decal(&mut self, mesh_id: MeshId, decal: &Decal) {
+        self.decal_requests.push(Request { mesh_id, model: to_matrix4(decal.transform()), material: None });
+    }
+
+    // draws a mesh in the 2d layer, over the finished frame (after the color grade, before the
+    // overlays) with no lighting or depth, in the order they're asked for. `model` is in pixels,
+    // from (0, 0) at the bottom left of `screen` to its size at the top right, and the mesh wants
+    // to lie flat between z = 0 and -1. its texture (if any) is multiplied by `color`, and alpha
+    // blends. the material is ignored. nothing's drawn on the bottom screen unless it's enabled
+    pub fn please_render_2d(&mut self, screen: TargetScreen, mesh_id: MeshId, model: Matrix4, color: Vec4) {
+        self.please_render_2d_at_depth(screen, mesh_id, model, color, 0.);
+    }
+
+    // like please_render_2d, but seen `depth` behind the screen in stereo (in front if it's
+    // negative), from -1 to 1. the rest of the 2d layer is at 0, level with the screen, which is
+    // the easiest on the eyes. draw order is still the order they're asked for, whatever the depth
+    pub fn please_render_2d_at_depth(&mut self, screen: TargetScreen, mesh_id: MeshId, model: Matrix4, color: Vec4, depth: f32) {
+        self.sprite_requests.push(SpriteRequest { screen, mesh_id, model, color, depth: depth.clamp(-1., 1.) });
+    }
+
+    // draws a rectangle over the finished frame, after the color grade. `min` and `size` are in
+    // screen units, from (0, 0) at the bottom left to (1, 1) at the top right. the texture (if any)
+    // is stretched over it and multiplied by `color`, and alpha blends. see overlay::Overlay
+    pub fn please_render_overlay(&mut self, texture: Option<&sys::C3D_Tex>, min: Vec2, size: Vec2, color: Vec4) {
+        self.overlays.push(OverlayRequest { texture: texture.copied(), min, size, color });
+    }
+
+    // everything recorded in `commands`, as if it was asked for now
+    pub fn submit(&mut self, commands: &CommandBuffer) {
+        self.submit_transformed(commands, Matrix4::identity());
+    }
+
+    // like submit, with `transform` after each 3d request's own model matrix, to put what was
+    // recorded somewhere else. the 2d layer and the overlays are left where they were
+    pub fn submit_transformed(&mut self, commands: &CommandBuffer, transform: Matrix4) {
+        let transform = from_matrix4(transform);
+        let place = |model: Matrix4| to_matrix4(transform * from_matrix4(model));
+        for &command in commands.commands() {
+            match command {
+                Command::Mesh { mesh_id, model, material } => self.requests.push(Request { mesh_id, model: place(model), material }),
+                Command::Model { model_id, model, material } => self.request_model(model_id, place(model), material),
+                Command::Batched { mesh_id, model } => self.please_render_batched(mesh_id, place(model)),
+                Command::Decal { mesh_id, model } => self.decal_requests.push(Request { mesh_id, model: place(model), material: None }),
+                Command::Sprite { screen, mesh_id, model, color, depth } => {
+                    self.sprite_requests.push(SpriteRequest { screen, mesh_id, model, color, depth });
+                }
+                Command::Overlay { texture, min, size, color } => self.overlays.push(OverlayRequest { texture, min, size, color }),
+            }
+        }
+    }
+
+    // draws `texture` over the whole screen, behind everything else, for this frame only. `uv_max`
+    // is the part of the texture to show, from (0, 0) at the bottom left; textures have to be a
+    // power of two in size, so a 400x240 image in a 512x256 texture wants (400/512, 240/256)
+    pub fn please_render_background(&mut self, texture: &sys::C3D_Tex, uv_max: Vec2) {
+        self.background = Some((*texture, uv_max));
+    }
+
+    // any shader that declares `.fvec time` gets (seconds, sin(seconds), cos(seconds), frame delta)
+    // in it, since the PICA can't do trig itself
+    fn time_uniform(&self) -> Vec4 {
+        vec4(self.time, self.time.sin(), self.time.cos(), self.dt)
+    }
+
+    pub fn render(&mut self) {
+        self.advance_time();
+        let time = self.time_uniform();
+        self.arena.reset();
+        let arena = &self.arena;
+
+        self.dynamic_batches.swap(0, 1);
+        self.retired_buffers.swap(0, 1);
+        self.retired_meshes.swap(0, 1);
+        if !self.retired_buffers[0].is_empty() || !self.retired_meshes[0].is_empty() {
+            self.retired_buffers[0].clear();
+            self.retired_meshes[0].clear();
+            linear_pool::trim();
+        }
+        let mut parts = Vec::new_in(arena);
+        parts.extend(self.batched_requests.iter().map(|request| (&self.meshes[request.mesh_id], request.model)));
+        // without the memory for them this frame, the batched copies just aren't drawn
+        self.dynamic_batches[0] = batch::merge(&parts).unwrap_or_default();
+
+        // where they go is worked out here, since nothing can move the streams between now and when
+        // they're written
+        let mut stream_writes: Vec<StreamWrite, _> = Vec::with_capacity_in(self.stream_updates.len(), arena);
+        stream_writes.extend(self.stream_updates.drain(..).map(|update| {
+            self.meshes[update.mesh_id].write_stream(update.stream, update.start, update.data)
+        }));
+
+        let mut stats = FrameStats {
+            requests: (self.requests.len() + self.batched_requests.len() + self.decal_requests.len() + self.sprite_requests.len()) as u32,
+            ..Default::default()
+        };
+
+        // a skinned mesh's bones can take its vertices anywhere, so it's never culled
+        let culling = profiler::scope(System::Culling);
+        let default_graph;
+        let graph = match &self.render_graph {
+            Some(graph) => graph,
+            None => {
+                default_graph = RenderGraph::default_for(self.bottom_target.is_some(), self.dual_screen.is_some());
+                &default_graph
+            }
+        };
+        // culling's done from the renderer's camera, which is no use to passes with their own
+        let own_camera = graph.passes().iter().any(|render_pass| render_pass.camera.is_some());
+        let (eye, occluders) = (self.eye, if own_camera { &[][..] } else { &self.occluders[..] });
+        // (mesh, model, is it a decal, its animated material). decals go last, so they're drawn over
+        // what they're on
+        let mut draws: Vec<(&Mesh, Matrix4, bool, Option<MaterialOverride>), _> = Vec::with_capacity_in(self.requests.len(), arena);
+        draws.extend(self.requests.iter()
+            .map(|request| (&self.meshes[request.mesh_id], request.model, false, request.material))
+            .chain(self.dynamic_batches[0].iter().map(|mesh| (mesh, Matrix4::identity(), false, None)))
+            .chain(self.decal_requests.iter().map(|request| (&self.meshes[request.mesh_id], request.model, true, None)))
+            .filter(|(mesh, model, _, _)| {
+                let hidden = !occluders.is_empty()
+                    && !mesh.is_skinned()
+                    && occlusion::is_occluded(eye, &mesh.bounds.transformed(from_matrix4(*model)), occluders);
+                stats.occluded += hidden as u32;
+                !hidden
+            }));
+        drop(culling);
+
+        // grouped by program and then texture, so fewer draws change them. blended meshes go after
+        // the opaque ones, and decals stay last in the order they were asked for in
+        let has_skybox = self.skybox.is_some();
+        let is_blended = |mesh: &Mesh, animated: Option<MaterialOverride>| mesh.parts().any(|part| {
+            animated.map_or(*part.material, |animated| animated.apply(part.material)).blend == BlendMode::AlphaBlend
+        });
+        draws.sort_by_key(|&(mesh, _, is_decal, animated)| {
+            let blended = !is_decal && is_blended(mesh, animated);
+            let group = if blended || is_decal {
+                ("", 0)
+            } else {
+                let texture = mesh.parts().next().and_then(|part| part.texture);
+                (program_name(mesh, &self.shaders, has_skybox), texture.map_or(0, |texture| unsafe { texture.__bindgen_anon_1.data } as usize))
+            };
+            (is_decal, blended, group)
+        });
+        // and then the blended ones the furthest away first, so each is mixed with what's behind it.
+        // like culling, that's from the renderer's camera
+        let blended = draws.partition_point(|&(mesh, _, is_decal, animated)| !is_decal && !is_blended(mesh, animated))
+            ..draws.partition_point(|&(_, _, is_decal, _)| !is_decal);
+        let depth = |&(mesh, model, _, _): &(&Mesh, Matrix4, bool, Option<MaterialOverride>)| {
+            (self.view * from_matrix4(model)).transform_point3((mesh.bounds.min + mesh.bounds.max) / 2.).z
+        };
+        // the camera looks down -z, so further away is lower
+        draws[blended].sort_by(|a, b| depth(a).total_cmp(&depth(b)));
+
+        let mut sprites: Vec<(TargetScreen, &Mesh, Matrix4, Vec4, f32), _> = Vec::with_capacity_in(self.sprite_requests.len(), arena);
+        sprites.extend(self.sprite_requests.iter()
+            .map(|request| (request.screen, &self.meshes[request.mesh_id], request.model, request.color, request.depth)));
+
+        let (grade, brightness) = (self.color_grade, self.brightness);
+        let stereo = self.stereo();
+
+        for name in ["default"].into_iter().chain(draws.iter().map(|(mesh, _, _, _)| program_name(mesh, &self.shaders, has_skybox))) {
+            if !self.uniforms.contains_key(name) {
+                self.uniforms.insert(name.to_owned(), ProgramUniforms::new(self.shaders.get(name).unwrap()));
+            }
+        }
+
+        self.context.render_frame_with(|mut pass| {
+            // the frame doesn't begin until the GPU's done with the last one, so waiting for that
+            // isn't counted
+            let _submit = profiler::scope(System::RenderSubmit);
+            for write in &stream_writes {
+                unsafe { write.apply(); }
+            }
+
+            // clearing happens before anything's drawn, however late the pass asking for it is
+            let clear = |screen| graph.passes().iter()
+                .filter(|render_pass| render_pass.screen == screen)
+                .filter_map(|render_pass| render_pass.clear)
+                .reduce(|a, b| a | b);
+            if let Some(flags) = clear(TargetScreen::Top) {
+                self.target.clear(flags, self.clear_color, 0);
+                if stereo > 0. {
+                    self.right_target.clear(flags, self.clear_color, 0);
+                }
+            }
+            if let (Some(target), Some(flags)) = (&mut self.bottom_target, clear(TargetScreen::Bottom)) {
+                target.clear(flags, self.clear_color, 0);
+            }
+            if let Some((_, levels)) = &self.bloom {
+                levels[0].clear(); // the others are drawn all over
+            }
+
+            // passes with bloom first draw their scene again, for the bright parts of it. in stereo,
+            // the top screen's passes are done for each eye
+            let bloom = self.bloom.as_ref();
+            let jobs = graph.passes().iter().flat_map(|render_pass| {
+                let extract = bloom.is_some() && render_pass.layers.contains(Layers::SCENE) && render_pass.post.contains(&PostStep::Bloom);
+                let eyes: &[f32] = if render_pass.screen == TargetScreen::Top && stereo > 0. { &[-1., 1.] } else { &[0.] };
+                eyes.iter().flat_map(move |&eye| {
+                    extract.then_some((render_pass, true, eye)).into_iter().chain([(render_pass, false, eye)])
+                })
+            });
+            for (render_pass, extract, eye) in jobs {
+                let screen = render_pass.screen;
+                let eye = eye * stereo;
+                let top = if eye > 0. { &self.right_target } else { &self.target };
+                let (target, projection): (&Target, Matrix4) = match (screen, &self.bottom_target, &self.dual_screen) {
+                    (TargetScreen::Top, _, Some(dual)) => (top, dual.projections[0]),
+                    (TargetScreen::Top, _, None) => (top, self.projection),
+                    (TargetScreen::Bottom, Some(bottom), Some(dual)) => (bottom, dual.projections[1]),
+                    (TargetScreen::Bottom, Some(bottom), None) => {
+                        (bottom, Projection::perspective(self.fov_y, AspectRatio::BottomScreen, self.clip_planes).into())
+                    }
+                    (TargetScreen::Bottom, None, _) => continue,
+                };
+                let projection = if eye == 0. { projection } else { stereo_projection(projection, eye) };
+                let view = render_pass.camera.map_or(self.view, |camera| camera.view());
+                let layers = if extract { Layers::SCENE } else { render_pass.layers };
+
+                pass.bind_program(self.shaders.get("default").unwrap());
+
+                // the 2d layer doesn't test depth, so this is only for the scene
+                let (depth_scale, depth_offset) = self.depth_mapping.scale_offset(self.clip_planes, 0.);
+                unsafe { sys::C3D_DepthMap(!self.depth_mapping.w_buffer, depth_scale, depth_offset); }
+
+                // for the background and skybox, meshes set their own
+                unsafe { sys::C3D_AlphaTest(true, ctru_sys::GPU_GREATER, 0x10); }
+                unsafe { sys::C3D_CullFace(ctru_sys::GPU_CULL_NONE); }
+
+                match &self.fog {
+                    Some((fog, lut)) => unsafe {
+                        // the fog unit wants 0xBBGGRR
+                        let bgr = (fog.color >> 16) & 0xff | fog.color & 0xff00 | (fog.color & 0xff) << 16;
+                        sys::C3D_FogGasMode(ctru_sys::GPU_FOG, ctru_sys::GPU_PLAIN_DENSITY, false);
+                        sys::C3D_FogColor(bgr);
+                        sys::C3D_FogLutBind(lut.as_ref() as *const _ as *mut _);
+                    },
+                    None => unsafe {
+                        sys::C3D_FogGasMode(ctru_sys::GPU_NO_FOG, ctru_sys::GPU_PLAIN_DENSITY, false);
+                    },
+                }
+
+                match bloom.filter(|_| extract) {
+                    Some((_, levels)) => {
+                        if eye > 0. {
+                            levels[0].clear(); // of the left eye's, which it's done with
+                        }
+                        levels[0].draw_on();
+                    }
+                    None => pass.select_render_target(target).unwrap(),
+                }
+
+                pass.set_attr_info(&Mesh::attr_info());
+                if let Some((texture, uv_max)) = self.background.as_ref().filter(|_| layers.contains(Layers::BACKGROUND)) {
+                    let mut model_view = Matrix4::identity();
+                    model_view.scale(1. / uv_max.x, 1. / uv_max.y, 1.);
+                    let projection: Matrix4 = Projection::orthographic(0.0..1.0, 0.0..1.0, ClipPlanes { near: 0., far: 1. }).into();
+
+                    // the default program declares all of these
+                    let uniforms = &self.uniforms["default"];
+                    pass.bind_vertex_uniform(uniforms.projection.unwrap(), projection);
+                    pass.bind_vertex_uniform(uniforms.model_view.unwrap(), model_view);
+                    pass.bind_vertex_uniform(uniforms.light_color.unwrap(), Vec4::ONE);
+                    pass.bind_vertex_uniform(uniforms.material.unwrap(), self.background_quad.material);
+                    if let Some(uv_transform) = uniforms.uv_transform {
+                        pass.bind_vertex_uniform(uv_transform, vec4(1., 1., 0., 0.));
+                    }
+
+                    let stage0 = texenv::Stage::new(0).unwrap();
+                    pass.texenv(stage0)
+                        .src(texenv::Mode::BOTH, texenv::Source::Texture0, None, None)
+                        .func(texenv::Mode::BOTH, texenv::CombineFunc::Replace);
+
+                    unsafe {
+                        sys::C3D_TexBind(0, texture as *const _ as *mut _);
+                        sys::C3D_DepthTest(false, ctru_sys::GPU_ALWAYS, ctru_sys::GPU_WRITE_COLOR);
+                    }
+                    self.background_quad.draw_vertices();
+                    unsafe { sys::C3D_DepthTest(true, ctru_sys::GPU_GREATER, ctru_sys::GPU_WRITE_ALL); }
+
+                    stats.draw_calls += 1;
+                }
+
+                if let Some(skybox) = self.skybox.as_ref().filter(|_| layers.contains(Layers::SKYBOX)) {
+                    pass.bind_program(self.shaders.get("skybox").unwrap());
+                    pass.bind_vertex_uniform(self.skybox_uniforms.0, projection);
+                    // it's infinitely far away, so only the camera's rotation matters
+                    let rotation = Mat4::from_quat(Quat::from_mat4(&view));
+                    pass.bind_vertex_uniform(self.skybox_uniforms.1, to_matrix4(rotation));
+
+                    let stage0 = texenv::Stage::new(0).unwrap();
+                    pass.texenv(stage0)
+                        .src(texenv::Mode::BOTH, texenv::Source::Texture0, None, None)
+                        .func(texenv::Mode::BOTH, texenv::CombineFunc::Replace);
+
+                    unsafe {
+                        sys::C3D_TexBind(0, &skybox.texture as *const _ as *mut _);
+                        sys::C3D_DepthTest(false, ctru_sys::GPU_ALWAYS, ctru_sys::GPU_WRITE_COLOR);
+                    }
+                    self.skybox_cube.draw_vertices();
+                    unsafe { sys::C3D_DepthTest(true, ctru_sys::GPU_GREATER, ctru_sys::GPU_WRITE_ALL); }
+
+                    pass.bind_program(self.shaders.get("default").unwrap());
+                    stats.draw_calls += 1;
+                }
+
+                let graded = render_pass.post.contains(&PostStep::ColorGrade);
+                let desaturate = !extract && layers.contains(Layers::SCENE) && graded && grade.saturation < 1.;
+                if let Some((settings, _)) = bloom.filter(|_| extract) {
+                    // everything darker than the threshold goes black. desaturation's never on as
+                    // well, so this has stage 2 to itself
+                    let stage2 = texenv::Stage::new(2).unwrap();
+                    pass.texenv(stage2)
+                        .src(texenv::Mode::RGB, texenv::Source::Previous, Some(texenv::Source::Constant), None)
+                        .func(texenv::Mode::RGB, texenv::CombineFunc::Subtract)
+                        .src(texenv::Mode::ALPHA, texenv::Source::Previous, None, None)
+                        .func(texenv::Mode::ALPHA, texenv::CombineFunc::Replace);
+                    let level = (settings.threshold.clamp(0., 1.) * 255.) as u32;
+                    unsafe { (*sys::C3D_GetTexEnv(2)).color = level * 0x010101 | 0xff000000; }
+                }
+                if desaturate {
+                    // stages 0 and 1 draw the mesh (see below), and stage 1 also saves what it made to
+                    // the combiner buffer, which stages 3 onwards can read. stages 2 to 4 add up its
+                    // luminance, and stage 5 mixes that back with the color
+                    unsafe { sys::C3D_TexEnvBufUpdate(sys::C3D_RGB as i32, 1 << 1); }
+                    let ops = [ctru_sys::GPU_TEVOP_RGB_SRC_R, ctru_sys::GPU_TEVOP_RGB_SRC_G, ctru_sys::GPU_TEVOP_RGB_SRC_B];
+                    for (i, (op, weight)) in ops.into_iter().zip(LUMINANCE).enumerate() {
+                        let stage = texenv::Stage::new(2 + i).unwrap();
+                        // previous.r * 0.299, then buffer.g * 0.587 + previous, then the same for b
+                        let color = if i == 0 { texenv::Source::Previous } else { texenv::Source::PreviousBuffer };
+                        let (func, sum) = match i {
+                            0 => (texenv::CombineFunc::Modulate, None),
+                            _ => (texenv::CombineFunc::MultiplyAdd, Some(texenv::Source::Previous)),
+                        };
+                        // alpha passes straight through all of these
+                        pass.texenv(stage)
+                            .src(texenv::Mode::RGB, color, Some(texenv::Source::Constant), sum)
+                            .func(texenv::Mode::RGB, func)
+                            .src(texenv::Mode::ALPHA, texenv::Source::Previous, None, None)
+                            .func(texenv::Mode::ALPHA, texenv::CombineFunc::Replace);
+
+                        let level = (weight * 255.).round() as u32;
+                        unsafe {
+                            let env = sys::C3D_GetTexEnv(2 + i as i32);
+                            // the operands pick one channel of the color, and one of the constant (which
+                            // has the same weight in all of them)
+                            (*env).__bindgen_anon_1.opAll = op | ctru_sys::GPU_TEVOP_RGB_SRC_R << 4;
+                            (*env).color = level * 0x010101 | 0xff000000;
+                        }
+                    }
+
+                    // buffer * saturation + luminance * (1 - saturation)
+                    let stage5 = texenv::Stage::new(5).unwrap();
+                    pass.texenv(stage5)
+                        .src(texenv::Mode::RGB, texenv::Source::PreviousBuffer, Some(texenv::Source::Previous), Some(texenv::Source::Constant))
+                        .func(texenv::Mode::RGB, texenv::CombineFunc::Interpolate)
+                        .src(texenv::Mode::ALPHA, texenv::Source::Previous, None, None)
+                        .func(texenv::Mode::ALPHA, texenv::CombineFunc::Replace);
+                    let amount = (grade.saturation * 255.) as u32;
+                    unsafe { (*sys::C3D_GetTexEnv(5)).color = amount * 0x01010101; }
+                }
+
+                let mut bound = "default";
+                let mut in_decals = false;
+                let mut state = DrawState::default();
+                for &(mesh, model, is_decal, animated) in draws.iter().filter(|_| layers.contains(Layers::SCENE)) {
+                    if is_decal && !in_decals {
+                        // decals can't hide each other or anything else, so they don't write depth
+                        let (scale, offset) = self.depth_mapping.scale_offset(self.clip_planes, DECAL_DEPTH_BIAS);
+                        unsafe {
+                            sys::C3D_DepthMap(!self.depth_mapping.w_buffer, scale, offset);
+                            sys::C3D_DepthTest(true, ctru_sys::GPU_GREATER, ctru_sys::GPU_WRITE_COLOR);
+                        }
+                        state.depth_write = Some(ctru_sys::GPU_WRITE_COLOR);
+                        in_decals = true;
+                    }
+
+                    let name = program_name(mesh, &self.shaders, has_skybox);
+                    if name != bound {
+                        pass.bind_program(self.shaders.get(name).unwrap());
+                        bound = name;
+                    }
+                    let reflect = name == "reflect";
+                    let lightmapped = name == "lightmap";
+                    let uniforms = &self.uniforms[name];
+
+                    let [light_vec, light_half_vec, light_color] = if self.lights.is_empty() {
+                        light::UNLIT
+                    } else {
+                        let center = from_matrix4(model).transform_point3((mesh.bounds.min + mesh.bounds.max) / 2.);
+                        light::uniforms(&self.lights, center, view)
+                    };
+                    if let Some(index) = uniforms.projection {
+                        pass.bind_vertex_uniform(index, projection);
+                    }
+                    if let Some(index) = uniforms.model_view {
+                        pass.bind_vertex_uniform(index, to_matrix4(view * from_matrix4(model)));
+                    }
+                    if let Some(index) = uniforms.light_vec {
+                        pass.bind_vertex_uniform(index, light_vec);
+                    }
+                    if let Some(index) = uniforms.light_half_vec {
+                        pass.bind_vertex_uniform(index, light_half_vec);
+                    }
+                    if let Some(index) = uniforms.light_color {
+                        pass.bind_vertex_uniform(index, light_color);
+                    }
+                    if let (Some(index), true) = (uniforms.bones, mesh.is_skinned()) {
+                        let base = i32::from(index) as u8;
+                        for (i, rows) in mesh.bones.iter().enumerate() {
+                            let rows = rows.map(Into::into);
+                            pass.bind_vertex_uniform(uniform::Index::from(base + 3 * i as u8), Uniform::Float3(rows));
+                        }
+                    }
+                    for ((_, value), index) in mesh.params.iter().zip(&mesh.param_uniforms) {
+                        if let Some(index) = *index {
+                            pass.bind_vertex_uniform(index, *value);
+                        }
+                    }
+                    if let Some(index) = uniforms.time {
+                        pass.bind_vertex_uniform(index, time);
+                    }
+                    if let Some(uv_transform) = uniforms.uv_transform {
+                        let ([sx, sy], [ox, oy]) = match &mesh.flipbook {
+                            Some(flipbook) => flipbook.frame_uv(mesh.playback.frame(flipbook)),
+                            None => ([1., 1.], [0., 0.]),
+                        };
+                        pass.bind_vertex_uniform(uv_transform, vec4(sx, sy, ox, oy));
+                    }
+
+                    let stage1 = texenv::Stage::new(1).unwrap();
+                    let lightmap = mesh.lightmap.as_ref().filter(|_| lightmapped);
+                    // only these two pass the uvs on to texture unit 2 as well
+                    let emission_map = mesh.emission_map.as_ref().filter(|_| name == "default" || lightmapped);
+                    let stage1_mode = match (lightmap, emission_map) {
+                        (Some(_), None) => Stage1::Lightmap,
+                        (Some(_), Some(_)) => Stage1::LightmapEmission,
+                        (None, Some(_)) => Stage1::Emission,
+                        (None, None) => Stage1::PassThrough,
+                    };
+                    if DrawState::change(&mut state.stage1, stage1_mode, &mut state.skipped) {
+                        match stage1_mode {
+                            // whatever stage 0 made, darkened by the baked light
+                            Stage1::Lightmap => {
+                                pass.texenv(stage1)
+                                    .src(texenv::Mode::BOTH, texenv::Source::Previous, Some(texenv::Source::Texture1), None)
+                                    .func(texenv::Mode::BOTH, texenv::CombineFunc::Modulate);
+                            }
+                            // and then with the light the mesh gives off added, which the lightmap
+                            // doesn't darken. alpha is left alone, since emission maps don't have any
+                            Stage1::LightmapEmission => {
+                                pass.texenv(stage1)
+                                    .src(texenv::Mode::RGB, texenv::Source::Previous, Some(texenv::Source::Texture1), Some(texenv::Source::Texture2))
+                                    .func(texenv::Mode::RGB, texenv::CombineFunc::MultiplyAdd)
+                                    .src(texenv::Mode::ALPHA, texenv::Source::Previous, None, None)
+                                    .func(texenv::Mode::ALPHA, texenv::CombineFunc::Replace);
+                            }
+                            Stage1::Emission => {
+                                pass.texenv(stage1)
+                                    .src(texenv::Mode::RGB, texenv::Source::Previous, Some(texenv::Source::Texture2), None)
+                                    .func(texenv::Mode::RGB, texenv::CombineFunc::Add)
+                                    .src(texenv::Mode::ALPHA, texenv::Source::Previous, None, None)
+                                    .func(texenv::Mode::ALPHA, texenv::CombineFunc::Replace);
+                            }
+                            Stage1::PassThrough => {
+                                pass.texenv(stage1)
+                                    .src(texenv::Mode::BOTH, texenv::Source::Previous, None, None)
+                                    .func(texenv::Mode::BOTH, texenv::CombineFunc::Replace);
+                            }
+                        }
+                    }
+                    if let Some(lightmap) = lightmap {
+                        state.bind_texture(1, lightmap);
+                    }
+                    if let Some(emission_map) = emission_map {
+                        state.bind_texture(2, emission_map);
+                    }
+
+                    pass.set_attr_info(&mesh.draw_attr_info());
+                    for part in mesh.parts() {
+                        let material = animated.map_or(*part.material, |animated| animated.apply(part.material));
+                        if let Some(index) = uniforms.material {
+                            pass.bind_vertex_uniform(index, material);
+                        }
+                        // decals are always mixed with what they're on
+                        let blend = if in_decals { BlendMode::AlphaBlend } else { material.blend };
+                        let alpha_test = material.alpha_test.filter(|_| blend != BlendMode::Opaque);
+                        if DrawState::change(&mut state.alpha_test, alpha_test, &mut state.skipped) {
+                            match alpha_test {
+                                Some(test) => unsafe { sys::C3D_AlphaTest(true, test.func.gpu(), test.reference as i32) },
+                                None => unsafe { sys::C3D_AlphaTest(false, ctru_sys::GPU_ALWAYS, 0) },
+                            }
+                        }
+                        let cull = if material.double_sided { ctru_sys::GPU_CULL_NONE } else { ctru_sys::GPU_CULL_BACK_CCW };
+                        if DrawState::change(&mut state.cull, cull, &mut state.skipped) {
+                            unsafe { sys::C3D_CullFace(cull); }
+                        }
+                        if DrawState::change(&mut state.blend, blend, &mut state.skipped) {
+                            let (src, dst) = match blend {
+                                BlendMode::AlphaBlend => (ctru_sys::GPU_SRC_ALPHA, ctru_sys::GPU_ONE_MINUS_SRC_ALPHA),
+                                BlendMode::Opaque | BlendMode::AlphaTest => (ctru_sys::GPU_ONE, ctru_sys::GPU_ZERO),
+                            };
+                            unsafe { sys::C3D_AlphaBlend(ctru_sys::GPU_BLEND_ADD, ctru_sys::GPU_BLEND_ADD, src, dst, src, dst); }
+                        }
+                        // blended parts let what's behind them through, so they don't hide it
+                        // either
+                        let write = if blend == BlendMode::AlphaBlend { ctru_sys::GPU_WRITE_COLOR } else { ctru_sys::GPU_WRITE_ALL };
+                        if DrawState::change(&mut state.depth_write, write, &mut state.skipped) {
+                            unsafe { sys::C3D_DepthTest(true, ctru_sys::GPU_GREATER, write); }
+                        }
+
+                        let stage0 = texenv::Stage::new(0).unwrap();
+                        if let Some(skybox) = self.skybox.as_ref().filter(|_| reflect) {
+                            // lerp from the lit color to the reflection by the constant color
+                            let amount = (mesh.reflectivity * 255.) as u32;
+                            if DrawState::change(&mut state.stage0, Stage0::Reflect(amount), &mut state.skipped) {
+                                pass.texenv(stage0)
+                                    .src(texenv::Mode::BOTH, texenv::Source::Texture0, Some(texenv::Source::PrimaryColor), Some(texenv::Source::Constant))
+                                    .func(texenv::Mode::BOTH, texenv::CombineFunc::Interpolate);
+                                unsafe { (*sys::C3D_GetTexEnv(0)).color = amount * 0x01010101; }
+                            }
+                            state.bind_texture(0, &skybox.texture);
+                        } else if let Some(tex) = part.texture {
+                            if DrawState::change(&mut state.stage0, Stage0::Textured, &mut state.skipped) {
+                                pass.texenv(stage0)
+                                    .src(texenv::Mode::BOTH, texenv::Source::PrimaryColor, Some(texenv::Source::Texture0), None)
+                                    .func(texenv::Mode::BOTH, texenv::CombineFunc::Modulate);
+                            }
+                            state.bind_texture(0, tex);
+                        } else if DrawState::change(&mut state.stage0, Stage0::Untextured, &mut state.skipped) {
+                            pass.texenv(stage0)
+                                .src(texenv::Mode::BOTH, texenv::Source::PrimaryColor, None, None)
+                                .func(texenv::Mode::BOTH, texenv::CombineFunc::Modulate);
+                        }
+
+                        mesh.draw(&part);
+                        stats.draw_calls += 1;
+                    }
+                }
+                if state.stage1.is_some_and(|mode| mode != Stage1::PassThrough) {
+                    // back to passing stage 0 through
+                    pass.texenv(texenv::Stage::new(1).unwrap())
+                        .src(texenv::Mode::BOTH, texenv::Source::Previous, None, None)
+                        .func(texenv::Mode::BOTH, texenv::CombineFunc::Replace);
+                }
+                if state.blend.is_some_and(|blend| blend != BlendMode::AlphaBlend) {
+                    // back to citro3d's default blending, which the 2d layer expects
+                    unsafe {
+                        sys::C3D_AlphaBlend(
+                            ctru_sys::GPU_BLEND_ADD,
+                            ctru_sys::GPU_BLEND_ADD,
+                            ctru_sys::GPU_SRC_ALPHA,
+                            ctru_sys::GPU_ONE_MINUS_SRC_ALPHA,
+                            ctru_sys::GPU_SRC_ALPHA,
+                            ctru_sys::GPU_ONE_MINUS_SRC_ALPHA,
+                        );
+                    }
+                }
+                stats.skipped_state += state.skipped;
+
+                if in_decals {
+                    unsafe { sys::C3D_DepthMap(!self.depth_mapping.w_buffer, depth_scale, depth_offset); }
+                }
+                unsafe { sys::C3D_DepthTest(true, ctru_sys::GPU_GREATER, ctru_sys::GPU_WRITE_ALL); }
+
+                if desaturate {
+                    unsafe { sys::C3D_TexEnvBufUpdate(sys::C3D_RGB as i32, 0); }
+                    for i in 2..=5 {
+                        pass.texenv(texenv::Stage::new(i).unwrap())
+                            .src(texenv::Mode::BOTH, texenv::Source::Previous, None, None)
+                            .func(texenv::Mode::BOTH, texenv::CombineFunc::Replace);
+                        unsafe { (*sys::C3D_GetTexEnv(i as i32)).__bindgen_anon_1.opAll = 0; }
+                    }
+                }
+
+                if let Some((_, levels)) = bloom.filter(|_| extract) {
+                    pass.texenv(texenv::Stage::new(2).unwrap())
+                        .src(texenv::Mode::BOTH, texenv::Source::Previous, None, None)
+                        .func(texenv::Mode::BOTH, texenv::CombineFunc::Replace);
+
+                    // each level is the last one filtered down to half its size, which blurs it
+                    pass.bind_program(self.shaders.get("default").unwrap());
+                    pass.set_attr_info(&Mesh::attr_info());
+                    let uniforms = &self.uniforms["default"];
+                    let projection: Matrix4 = Projection::orthographic(0.0..1.0, 0.0..1.0, ClipPlanes { near: 0., far: 1. }).into();
+                    pass.bind_vertex_uniform(uniforms.projection.unwrap(), projection);
+                    pass.bind_vertex_uniform(uniforms.model_view.unwrap(), Matrix4::identity());
+                    pass.bind_vertex_uniform(uniforms.light_color.unwrap(), Vec4::ONE);
+                    pass.bind_vertex_uniform(uniforms.material.unwrap(), unlit(Vec4::ONE));
+                    if let Some(uv_transform) = uniforms.uv_transform {
+                        pass.bind_vertex_uniform(uv_transform, vec4(1., 1., 0., 0.));
+                    }
+                    pass.texenv(texenv::Stage::new(0).unwrap())
+                        .src(texenv::Mode::BOTH, texenv::Source::Texture0, None, None)
+                        .func(texenv::Mode::BOTH, texenv::CombineFunc::Replace);
+
+                    unsafe {
+                        sys::C3D_CullFace(ctru_sys::GPU_CULL_NONE);
+                        sys::C3D_DepthTest(false, ctru_sys::GPU_ALWAYS, ctru_sys::GPU_WRITE_COLOR);
+                        sys::C3D_AlphaTest(false, ctru_sys::GPU_ALWAYS, 0);
+                        sys::C3D_AlphaBlend(ctru_sys::GPU_BLEND_ADD, ctru_sys::GPU_BLEND_ADD, ctru_sys::GPU_ONE, ctru_sys::GPU_ZERO, ctru_sys::GPU_ONE, ctru_sys::GPU_ZERO);
+                    }
+                    for pair in levels.windows(2) {
+                        pair[1].draw_on();
+                        unsafe { sys::C3D_TexBind(0, pair[0].texture() as *const _ as *mut _); }
+                        self.bloom_quad.draw_vertices();
+                        stats.draw_calls += 1;
+                    }
+                    unsafe {
+                        sys::C3D_DepthTest(true, ctru_sys::GPU_GREATER, ctru_sys::GPU_WRITE_ALL);
+                        sys::C3D_AlphaBlend(
+                            ctru_sys::GPU_BLEND_ADD,
+                            ctru_sys::GPU_BLEND_ADD,
+                            ctru_sys::GPU_SRC_ALPHA,
+                            ctru_sys::GPU_ONE_MINUS_SRC_ALPHA,
+                            ctru_sys::GPU_SRC_ALPHA,
+                            ctru_sys::GPU_ONE_MINUS_SRC_ALPHA,
+                        );
+                    }
+                    continue;
+                }
+
+                // the 2d layer draws both sides of everything
+                unsafe { sys::C3D_CullFace(ctru_sys::GPU_CULL_NONE); }
+
+                let fills = post_fills(&render_pass.post, grade, brightness, bloom, arena);
+                let has_sprites = layers.contains(Layers::SPRITES) && sprites.iter().any(|sprite| sprite.0 == screen);
+                let overlays = if layers.contains(Layers::OVERLAYS) { &self.overlays[..] } else { &[] };
+                if !fills.is_empty() || has_sprites || !overlays.is_empty() {
+                    pass.bind_program(self.shaders.get("default").unwrap());
+                    pass.set_attr_info(&Mesh::attr_info());
+                    let projection: Matrix4 = Projection::orthographic(0.0..1.0, 0.0..1.0, ClipPlanes { near: 0., far: 1. }).into();
+
+                    let uniforms = &self.uniforms["default"];
+                    pass.bind_vertex_uniform(uniforms.projection.unwrap(), projection);
+                    pass.bind_vertex_uniform(uniforms.model_view.unwrap(), Matrix4::identity());
+                    pass.bind_vertex_uniform(uniforms.light_color.unwrap(), Vec4::ONE);
+                    if let Some(uv_transform) = uniforms.uv_transform {
+                        pass.bind_vertex_uniform(uv_transform, vec4(1., 1., 0., 0.));
+                    }
+
+                    let stage0 = texenv::Stage::new(0).unwrap();
+                    pass.texenv(stage0)
+                        .src(texenv::Mode::BOTH, texenv::Source::PrimaryColor, None, None)
+                        .func(texenv::Mode::BOTH, texenv::CombineFunc::Replace);
+
+                    unsafe {
+                        sys::C3D_DepthTest(false, ctru_sys::GPU_ALWAYS, ctru_sys::GPU_WRITE_COLOR);
+                        sys::C3D_AlphaTest(false, ctru_sys::GPU_ALWAYS, 0);
+                    }
+                    for &(color, src, dst, texture) in &fills {
+                        pass.bind_vertex_uniform(uniforms.material.unwrap(), unlit(color));
+
+                        unsafe { sys::C3D_AlphaBlend(ctru_sys::GPU_BLEND_ADD, ctru_sys::GPU_BLEND_ADD, src, dst, ctru_sys::GPU_ZERO, ctru_sys::GPU_ONE); }
+                        match texture {
+                            Some(texture) => {
+                                pass.texenv(stage0)
+                                    .src(texenv::Mode::BOTH, texenv::Source::PrimaryColor, Some(texenv::Source::Texture0), None)
+                                    .func(texenv::Mode::BOTH, texenv::CombineFunc::Modulate);
+                                unsafe { sys::C3D_TexBind(0, texture as *const _ as *mut _); }
+                                self.bloom_quad.draw_vertices();
+                            }
+                            None => {
+                                pass.texenv(stage0)
+                                    .src(texenv::Mode::BOTH, texenv::Source::PrimaryColor, None, None)
+                                    .func(texenv::Mode::BOTH, texenv::CombineFunc::Replace);
+                                self.background_quad.draw_vertices();
+                            }
+                        }
+                        stats.draw_calls += 1;
+                    }
+
+                    // back to citro3d's default blending, which the overlays use
+                    unsafe {
+                        sys::C3D_AlphaBlend(
+                            ctru_sys::GPU_BLEND_ADD,
+                            ctru_sys::GPU_BLEND_ADD,
+                            ctru_sys::GPU_SRC_ALPHA,
+                            ctru_sys::GPU_ONE_MINUS_SRC_ALPHA,
+                            ctru_sys::GPU_SRC_ALPHA,
+                            ctru_sys::GPU_ONE_MINUS_SRC_ALPHA,
+                        );
+                    }
+
+                    if has_sprites {
+                        let size = screen.size();
+                        let pixels: Matrix4 = Projection::orthographic(0.0..size.x, 0.0..size.y, ClipPlanes { near: 0., far: 1. }).into();
+                        pass.bind_vertex_uniform(uniforms.projection.unwrap(), pixels);
+                        for &(_, mesh, model, color, depth) in sprites.iter().filter(|sprite| sprite.0 == screen) {
+                            let shift = Mat4::from_translation(Vec3::new(parallax_shift(depth, eye), 0., 0.));
+                            pass.bind_vertex_uniform(uniforms.model_view.unwrap(), to_matrix4(shift * from_matrix4(model)));
+                            pass.bind_vertex_uniform(uniforms.material.unwrap(), unlit(color));
+                            pass.set_attr_info(&mesh.draw_attr_info());
+
+                            for part in mesh.parts() {
+                                // same as the overlays, below
+                                match part.texture {
+                                    Some(texture) => {
+                                        pass.texenv(stage0)
+                                            .src(texenv::Mode::BOTH, texenv::Source::PrimaryColor, Some(texenv::Source::Texture0), None)
+                                            .func(texenv::Mode::BOTH, texenv::CombineFunc::Modulate);
+                                        unsafe { sys::C3D_TexBind(0, texture as *const _ as *mut _); }
+                                    }
+                                    None => {
+                                        pass.texenv(stage0)
+                                            .src(texenv::Mode::BOTH, texenv::Source::PrimaryColor, None, None)
+                                            .func(texenv::Mode::BOTH, texenv::CombineFunc::Replace);
+                                    }
+                                }
+
+                                mesh.draw(&part);
+                                stats.draw_calls += 1;
+                            }
+                        }
+                        pass.bind_vertex_uniform(uniforms.projection.unwrap(), projection);
+                        pass.set_attr_info(&Mesh::attr_info());
+                    }
+
+                    for overlay in overlays {
+                        let model_view = Mat4::from_translation(overlay.min.extend(0.)) * Mat4::from_scale(overlay.size.extend(1.));
+                        pass.bind_vertex_uniform(uniforms.model_view.unwrap(), to_matrix4(model_view));
+                        pass.bind_vertex_uniform(uniforms.material.unwrap(), unlit(overlay.color));
+
+                        match &overlay.texture {
+                            Some(texture) => {
+                                pass.texenv(stage0)
+                                    .src(texenv::Mode::BOTH, texenv::Source::PrimaryColor, Some(texenv::Source::Texture0), None)
+                                    .func(texenv::Mode::BOTH, texenv::CombineFunc::Modulate);
+                                unsafe { sys::C3D_TexBind(0, texture as *const _ as *mut _); }
+                            }
+                            None => {
+                                pass.texenv(stage0)
+                                    .src(texenv::Mode::BOTH, texenv::Source::PrimaryColor, None, None)
+                                    .func(texenv::Mode::BOTH, texenv::CombineFunc::Replace);
+                            }
+                        }
+
+                        self.background_quad.draw_vertices();
+                        stats.draw_calls += 1;
+                    }
+
+                    unsafe { sys::C3D_DepthTest(true, ctru_sys::GPU_GREATER, ctru_sys::GPU_WRITE_ALL); }
+                }
+            }
+
+            // after everything else on the screen, since it's what's about to be cut down to 16 bits
+            let targets = [
+                (TargetScreen::Top, ctru_sys::GFX_TOP, Some(&self.target)),
+                (TargetScreen::Top, ctru_sys::GFX_TOP, Some(&self.right_target).filter(|_| stereo > 0.)),
+                (TargetScreen::Bottom, ctru_sys::GFX_BOTTOM, self.bottom_target.as_ref()),
+            ];
+            for (screen, gfx_screen, target) in targets {
+                let (Some(target), Some(texture), true) = (target, &self.dither_texture, self.dither[screen as usize]) else {
+                    continue;
+                };
+                let Some(step) = dither::step(unsafe { ctru_sys::gfxGetScreenFormat(gfx_screen) }) else {
+                    continue;
+                };
+
+                pass.select_render_target(target).unwrap();
+                pass.bind_program(self.shaders.get("default").unwrap());
+                pass.set_attr_info(&Mesh::attr_info());
+                let uniforms = &self.uniforms["default"];
+                let projection: Matrix4 = Projection::orthographic(0.0..1.0, 0.0..1.0, ClipPlanes { near: 0., far: 1. }).into();
+                pass.bind_vertex_uniform(uniforms.projection.unwrap(), projection);
+                pass.bind_vertex_uniform(uniforms.model_view.unwrap(), Matrix4::identity());
+                pass.bind_vertex_uniform(uniforms.light_color.unwrap(), Vec4::ONE);
+                // the pattern's scaled down to just under a step of each channel
+                pass.bind_vertex_uniform(uniforms.material.unwrap(), unlit(step));
+                if let Some(uv_transform) = uniforms.uv_transform {
+                    let size = screen.size();
+                    pass.bind_vertex_uniform(uv_transform, dither::uv_scale(size.x, size.y));
+                }
+
+                pass.texenv(texenv::Stage::new(0).unwrap())
+                    .src(texenv::Mode::BOTH, texenv::Source::PrimaryColor, Some(texenv::Source::Texture0), None)
+                    .func(texenv::Mode::BOTH, texenv::CombineFunc::Modulate);
+                unsafe {
+                    sys::C3D_TexBind(0, texture as *const _ as *mut _);
+                    sys::C3D_CullFace(ctru_sys::GPU_CULL_NONE);
+                    sys::C3D_DepthTest(false, ctru_sys::GPU_ALWAYS, ctru_sys::GPU_WRITE_COLOR);
+                    sys::C3D_AlphaTest(false, ctru_sys::GPU_ALWAYS, 0);
+                    // added on, leaving alpha alone
+                    sys::C3D_AlphaBlend(ctru_sys::GPU_BLEND_ADD, ctru_sys::GPU_BLEND_ADD, ctru_sys::GPU_ONE, ctru_sys::GPU_ONE, ctru_sys::GPU_ZERO, ctru_sys::GPU_ONE);
+                }
+                self.background_quad.draw_vertices();
+                stats.draw_calls += 1;
+
+                unsafe {
+                    sys::C3D_DepthTest(true, ctru_sys::GPU_GREATER, ctru_sys::GPU_WRITE_ALL);
+                    sys::C3D_AlphaBlend(
+                        ctru_sys::GPU_BLEND_ADD,
+                        ctru_sys::GPU_BLEND_ADD,
+                        ctru_sys::GPU_SRC_ALPHA,
+                        ctru_sys::GPU_ONE_MINUS_SRC_ALPHA,
+                        ctru_sys::GPU_SRC_ALPHA,
+                        ctru_sys::GPU_ONE_MINUS_SRC_ALPHA,
+                    );
+                }
+            }
+
+            pass
+        });
+        // how long the GPU took over the frame before this one, which is the last it's finished
+        profiler::record(System::Gpu, unsafe { sys::C3D_GetDrawingTime() });
+        profiler::end_frame();
+
+        stats.arena_bytes = self.arena.used() as u32;
+        self.stats = stats;
+        self.requests.clear();
+        self.batched_requests.clear();
+        self.decal_requests.clear();
+        self.sprite_requests.clear();
+        self.overlays.clear();
+        self.background = None;
+    }
+
+    pub fn stats(&self) -> FrameStats {
+        self.stats
+    }
+
+    // a copy of the top screen's framebuffer, as it was last presented. it's still in the LCD's
+    // layout (rotated, and in the screen's format), which is all comparing frames needs
+    pub fn read_framebuffer(&self) -> Vec<u8> {
+        unsafe {
+            let mut width = 0;
+            let mut height = 0;
+            let ptr = ctru_sys::gfxGetFramebuffer(ctru_sys::GFX_TOP, ctru_sys::GFX_LEFT, &mut width, &mut height);
+
+            let bytes_per_pixel = match ctru_sys::gfxGetScreenFormat(ctru_sys::GFX_TOP) {
+                ctru_sys::GSP_RGBA8_OES => 4,
+                ctru_sys::GSP_BGR8_OES => 3,
+                _ => 2,
+            };
+
+            std::slice::from_raw_parts(ptr, width as usize * height as usize * bytes_per_pixel).to_vec()
+        }
+    }
+
+    // the top screen as it was last presented, as 400x240 RGB pixels from the top left, whatever
+    // format it's in (see with_formats)
+    pub fn screenshot(&self) -> Vec<u8> {
+        let data = self.read_framebuffer();
+        let format = unsafe { ctru_sys::gfxGetScreenFormat(ctru_sys::GFX_TOP) };
+        let (width, height) = (TOP_SCREEN_SIZE.x as usize, TOP_SCREEN_SIZE.y as usize);
+
+        let mut ret = Vec::with_capacity(width * height * 3);
+        for y in 0..height {
+            for x in 0..width {
+                // the framebuffer's rows are the screen's columns, each from the bottom up
+                ret.extend(decode_pixel(format, &data, x * height + (height - 1 - y)));
+            }
+        }
+
+        ret
+    }
+
+    // what's been asked for so far this frame and how it's set up to be drawn, for looking into a
+    // rendering bug off the console (mesh_viewer replays the 3d scene). call it just before render,
+    // from a debug button say:
+    //
+    //     if input.is_pressed(KeyPad::SELECT) {
+    //         renderer.capture_frame().write(BufWriter::new(File::create("sdmc:/mm3ds/frame.txt")?))?;
+    //     }
+    pub fn capture_frame(&self) -> FrameCapture {
+        let entry = |name: &str, value: String| (name.to_owned(), value);
+        let target = |format: TargetFormat| format!("{:?} {:?}", format.color, format.depth);
+        let default_graph;
+        let graph = match &self.render_graph {
+            Some(graph) => graph,
+            None => {
+                default_graph = RenderGraph::default_for(self.bottom_target.is_some(), self.dual_screen.is_some());
+                &default_graph
+            }
+        };
+
+        let mut state = vec![
+            entry("top_target", target(self.top_format)),
+            entry("stereo", format!("{}", self.stereo())),
+            entry("bottom_target", if self.bottom_target.is_some() { target(self.bottom_format) } else { "disabled".to_owned() }),
+            entry("dual_screen_gap", format!("{:?}", self.dual_screen.as_ref().map(|dual| dual.gap))),
+            entry("fov_y", self.fov_y.to_degrees().to_string()),
+            entry("clip_planes", format!("{} {}", self.clip_planes.near, self.clip_planes.far)),
+            entry("depth_mapping", format!("{:?}", self.depth_mapping)),
+            entry("eye", format!("{:?}", self.eye)),
+            entry("clear_color", format!("{:08x}", self.clear_color)),
+            entry("fog", format!("{:?}", self.fog.as_ref().map(|(fog, _)| fog))),
+            entry("lights", format!("{:?}", self.lights)),
+            entry("brightness", self.brightness.to_string()),
+            entry("color_grade", format!("{:?}", self.color_grade)),
+            entry("bloom", format!("{:?}", self.bloom.as_ref().map(|(bloom, _)| bloom))),
+            entry("dither", format!("{:?}", self.dither)),
+            entry("skybox", self.skybox.is_some().to_string()),
+            entry("background", self.background.is_some().to_string()),
+        ];
+        state.extend(graph.passes().iter().map(|render_pass| entry("pass", format!("{render_pass:?}"))));
+        state.extend(self.sprite_requests.iter().map(|sprite| {
+            entry("sprite", format!("{:?} mesh {} color {:?} depth {}", sprite.screen, sprite.mesh_id.index, sprite.color, sprite.depth))
+        }));
+        state.extend(self.overlays.iter().map(|overlay| {
+            entry("overlay", format!("min {:?} size {:?} color {:?} textured {}", overlay.min, overlay.size, overlay.color, overlay.texture.is_some()))
+        }));
+
+        let has_skybox = self.skybox.is_some();
+        let requests = self.requests.iter().map(|request| ("request", request))
+            .chain(self.batched_requests.iter().map(|request| ("batched", request)))
+            .chain(self.decal_requests.iter().map(|request| ("decal", request)));
+        let mut meshes: Vec<CapturedMesh> = Vec::new();
+        let mut draws = Vec::new();
+        for (kind, request) in requests {
+            let id = request.mesh_id.index;
+            let mesh = &self.meshes[request.mesh_id];
+            if !meshes.iter().any(|captured| captured.id == id) {
+                meshes.push(CapturedMesh {
+                    id,
+                    color: from_fvec4(mesh.material.diffuse).to_array(),
+                    vertices: mesh.vertices().to_vec(),
+                    indices: mesh.index_data.clone().unwrap_or_default(),
+                });
+            }
+
+            let program = program_name(mesh, &self.shaders, has_skybox);
+            let mut state = vec![entry("kind", kind.to_owned()), entry("program", program.to_owned())];
+            if let Some(animated) = request.material {
+                state.push(entry("material_override", format!("{animated:?}")));
+            }
+            if !mesh.params.is_empty() {
+                state.push(entry("params", format!("{:?}", mesh.params)));
+            }
+            for part in mesh.parts() {
+                state.push(entry("part", format!("{} {}", describe_material(part.material), describe_texenv(mesh, &part, program, has_skybox))));
+            }
+            draws.push(CapturedDraw { mesh: id, model: from_matrix4(request.model).to_cols_array(), state });
+        }
+
+        FrameCapture {
+            state,
+            projection: from_matrix4(self.projection).to_cols_array(),
+            view: self.view.to_cols_array(),
+            meshes,
+            draws,
+        }
+    }
+}
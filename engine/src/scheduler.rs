@@ -0,0 +1,158 @@
+// callbacks for later, after a while or every so often, instead of counters kept by hand:
+//
+//     let mut scheduler = Scheduler::new(Clock::Gameplay);
+//     scheduler.after(2., |game: &mut Game| game.spawn_wave());
+//     let blink = scheduler.every_frames(10, |game: &mut Game| game.cursor_visible ^= true);
+//     ...
+//     time.update();
+//     scheduler.update(&time, &mut game);
+//     ...
+//     scheduler.cancel(blink);
+//
+// callbacks are given whatever the game passes to update, since they can't borrow it themselves.
+// seconds go by on the scheduler's clock, so they slow down with it and stop during a hit-stop, and
+// so do frames, which are counted only while the clock isn't stopped. with a fixed timestep
+// (Time::advance by the step, as many times as it takes), a frame is a step
+use crate::time::{Clock, Time};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TimerId(u32);
+
+#[derive(Copy, Clone, Debug)]
+enum Wait {
+    Seconds(f32),
+    Frames(u32),
+}
+
+struct Timer<C> {
+    id: TimerId,
+    left: Wait,
+    every: Option<Wait>, // None to go once
+    callback: Box<dyn FnMut(&mut C)>,
+}
+
+pub struct Scheduler<C> {
+    clock: Clock,
+    timers: Vec<Timer<C>>,
+    next_id: u32,
+}
+
+// most repeats of one timer in an update, so a long hitch can't call one forever
+const MAX_CATCH_UP: u32 = 8;
+
+impl<C> Scheduler<C> {
+    pub fn new(clock: Clock) -> Self {
+        Self { clock, timers: Vec::new(), next_id: 0 }
+    }
+
+    fn add(&mut self, left: Wait, every: Option<Wait>, callback: Box<dyn FnMut(&mut C)>) -> TimerId {
+        let id = TimerId(self.next_id);
+        self.next_id = self.next_id.wrapping_add(1);
+        self.timers.push(Timer { id, left, every, callback });
+        id
+    }
+
+    // once, `seconds` from now
+    pub fn after(&mut self, seconds: f32, callback: impl FnMut(&mut C) + 'static) -> TimerId {
+        self.add(Wait::Seconds(seconds), None, Box::new(callback))
+    }
+
+    // once, `frames` updates from now. 0 is the next update, like 1
+    pub fn after_frames(&mut self, frames: u32, callback: impl FnMut(&mut C) + 'static) -> TimerId {
+        self.add(Wait::Frames(frames), None, Box::new(callback))
+    }
+
+    // every `seconds`, starting `seconds` from now, until it's cancelled. a step longer than that
+    // calls it as many times as it's passed, up to a few. 0 is every update
+    pub fn every(&mut self, seconds: f32, callback: impl FnMut(&mut C) + 'static) -> TimerId {
+        if seconds <= 0. {
+            return self.every_frames(1, callback);
+        }
+        self.add(Wait::Seconds(seconds), Some(Wait::Seconds(seconds)), Box::new(callback))
+    }
+
+    // every `frames` updates (at least 1), starting `frames` from now, until it's cancelled
+    pub fn every_frames(&mut self, frames: u32, callback: impl FnMut(&mut C) + 'static) -> TimerId {
+        let frames = frames.max(1);
+        self.add(Wait::Frames(frames), Some(Wait::Frames(frames)), Box::new(callback))
+    }
+
+    // false if it had already gone off (and wasn't repeating) or been cancelled
+    pub fn cancel(&mut self, id: TimerId) -> bool {
+        let before = self.timers.len();
+        self.timers.retain(|timer| timer.id != id);
+        self.timers.len() < before
+    }
+
+    pub fn is_pending(&self, id: TimerId) -> bool {
+        self.timers.iter().any(|timer| timer.id == id)
+    }
+
+    // seconds (or frames) until `id` next goes off
+    pub fn seconds_left(&self, id: TimerId) -> Option<f32> {
+        self.timers.iter().find(|timer| timer.id == id).and_then(|timer| match timer.left {
+            Wait::Seconds(seconds) => Some(seconds.max(0.)),
+            Wait::Frames(_) => None,
+        })
+    }
+
+    pub fn frames_left(&self, id: TimerId) -> Option<u32> {
+        self.timers.iter().find(|timer| timer.id == id).and_then(|timer| match timer.left {
+            Wait::Frames(frames) => Some(frames),
+            Wait::Seconds(_) => None,
+        })
+    }
+
+    pub fn clear(&mut self) {
+        self.timers.clear();
+    }
+
+    // moves every timer on by the last frame on the clock, calling what's due in the order they
+    // were added. call once a frame (or step), after time.update
+    pub fn update(&mut self, time: &Time, context: &mut C) {
+        let dt = time.dt(self.clock);
+        let running = time.scale_of(self.clock) > 0.;
+        self.timers.retain_mut(|timer| {
+            let mut calls = 0;
+            let mut done = false;
+            match &mut timer.left {
+                Wait::Seconds(left) => {
+                    *left -= dt;
+                    match timer.every {
+                        Some(Wait::Seconds(every)) => {
+                            while *left <= 0. && calls < MAX_CATCH_UP {
+                                calls += 1;
+                                *left += every;
+                            }
+                            if *left <= 0. {
+                                *left = every; // too far behind to catch up
+                            }
+                        }
+                        _ if *left <= 0. => {
+                            calls = 1;
+                            done = true;
+                        }
+                        _ => {}
+                    }
+                }
+                Wait::Frames(left) if running => {
+                    if *left <= 1 {
+                        calls = 1;
+                        match timer.every {
+                            Some(Wait::Frames(every)) => *left = every,
+                            _ => done = true,
+                        }
+                    } else {
+                        *left -= 1;
+                    }
+                }
+                Wait::Frames(_) => {}
+            }
+
+            for _ in 0..calls {
+                (timer.callback)(context);
+            }
+            !done
+        });
+    }
+}
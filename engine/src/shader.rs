@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+use std::error::Error;
+
+use citro3d::shader;
+use citro3d::shader::Program;
+
+// shader binaries have to be 4-byte aligned, and include_bytes! makes no promises
+macro_rules! include_aligned {
+    ($path:literal) => {{
+        #[repr(C, align(4))]
+        struct Aligned<T: ?Sized>(T);
+
+        static ALIGNED: &Aligned<[u8]> = &Aligned(*include_bytes!($path));
+        &ALIGNED.0
+    }};
+}
+
+struct BuiltinShader {
+    name: &'static str,
+    shbin: &'static [u8],
+    geometry_stride: Option<u8>,
+}
+
+// generated by build.rs, one entry per shaders/<name>.v.pica
+include!(concat!(env!("OUT_DIR"), "/shaders.rs"));
+
+struct Shader {
+    _library: shader::Library, // the program points into this, so it has to stay alive
+    program: Program,
+    geometry_stride: Option<u8>,
+}
+
+pub struct ShaderRegistry {
+    shaders: HashMap<String, Shader>,
+}
+
+impl ShaderRegistry {
+    // a registry holding every shader that was compiled from shaders/
+    pub fn new() -> Self {
+        let mut ret = Self { shaders: HashMap::new() };
+        for builtin in BUILTIN_SHADERS {
+            ret.load(builtin.name, builtin.shbin, builtin.geometry_stride).unwrap();
+        }
+
+        ret
+    }
+
+    // entry point 0 of `shbin` is the vertex shader. if `geometry_stride` is given, entry point 1
+    // is used as the geometry shader. replaces any shader already registered as `name`
+    pub fn load(&mut self, name: &str, shbin: &[u8], geometry_stride: Option<u8>) -> Result<(), Box<dyn Error>> {
+        let library = shader::Library::from_bytes(shbin)?;
+        let mut program = Program::new(library.get(0).ok_or("shader has no vertex entry point")?)?;
+        if let Some(stride) = geometry_stride {
+            program.set_geometry_shader(library.get(1).ok_or("shader has no geometry entry point")?, stride)?;
+        }
+
+        self.shaders.insert(name.to_owned(), Shader { _library: library, program, geometry_stride });
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Program> {
+        self.shaders.get(name).map(|shader| &shader.program)
+    }
+
+    // what `name` was loaded with, or None if it has no geometry shader (or isn't registered)
+    pub fn geometry_stride(&self, name: &str) -> Option<u8> {
+        self.shaders.get(name).and_then(|shader| shader.geometry_stride)
+    }
+}
+
+impl Default for ShaderRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,92 @@
+// reloads shaders from the SD card when they change, so trying a shader out is copying its .shbin
+// over (e.g. with ftpd) rather than redeploying the whole app:
+//
+//     let mut shader_reload = ShaderReload::new("sdmc:/mm3ds/shaders");
+//     ...
+//     for (name, result) in shader_reload.poll(&mut renderer) {
+//         match result {
+//             Ok(()) => console.log(&format!("reloaded {name}")),
+//             Err(e) => console.log(&format!("{name}: {e}")),
+//         }
+//     }
+//
+// every <name>.shbin in the directory replaces the shader registered as `name` (or adds it, with no
+// geometry shader), and the renderer looks its uniforms up again. one that doesn't load leaves the
+// old one in place. the SD card's timestamps can't be relied on, so it goes by what's in the files
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::renderer::Renderer;
+
+// reading the directory costs a few milliseconds, so it's not done every frame
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+pub struct ShaderReload {
+    dir: PathBuf,
+    last_poll: Option<Instant>,
+    loaded: HashMap<String, u64>, // a hash of what was last loaded (or tried) for each one
+}
+
+fn hash(data: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    data.hash(&mut hasher);
+    hasher.finish()
+}
+
+// shader binaries have to be 4-byte aligned, which a Vec<u8> isn't promised to be
+fn aligned(data: &[u8]) -> Vec<u32> {
+    let mut ret = vec![0; data.len().div_ceil(4)];
+    for (word, bytes) in ret.iter_mut().zip(data.chunks(4)) {
+        let mut le = [0; 4];
+        le[..bytes.len()].copy_from_slice(bytes);
+        *word = u32::from_le_bytes(le);
+    }
+
+    ret
+}
+
+impl ShaderReload {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into(), last_poll: None, loaded: HashMap::new() }
+    }
+
+    // loads whatever's changed since last time, at most once a second, and says how each went.
+    // everything that's there is loaded the first time. call once a frame
+    pub fn poll(&mut self, renderer: &mut Renderer) -> Vec<(String, Result<(), Box<dyn Error>>)> {
+        let now = Instant::now();
+        if self.last_poll.is_some_and(|t| now - t < POLL_INTERVAL) {
+            return Vec::new();
+        }
+        self.last_poll = Some(now);
+
+        let mut ret = Vec::new();
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return ret;
+        };
+        for path in entries.flatten().map(|entry| entry.path()) {
+            let Some(name) = path.file_name().and_then(|name| name.to_str()?.strip_suffix(".shbin")) else {
+                continue;
+            };
+            let Ok(data) = fs::read(&path) else {
+                continue; // probably still being copied over
+            };
+
+            let hash = hash(&data);
+            if self.loaded.get(name) == Some(&hash) {
+                continue;
+            }
+            self.loaded.insert(name.to_owned(), hash);
+
+            let words = aligned(&data);
+            // words holds exactly data's bytes, in order, plus padding
+            let shbin = unsafe { std::slice::from_raw_parts(words.as_ptr().cast::<u8>(), data.len()) };
+            ret.push((name.to_owned(), renderer.reload_shader(name, shbin)));
+        }
+
+        ret
+    }
+}
@@ -0,0 +1,174 @@
+// poses skinned meshes from the skeleton in their .anim file. the clip that's playing is sampled
+// where it's got to, each joint is put where its parents have taken it, and what comes out is a
+// bone for each joint, which the vertex shader moves the vertices weighted to that joint by:
+//
+//     let mut player = AnimationPlayer::from_file_data(File::open("romfs:/character.anim")?)?;
+//     player.play("walk");
+//     ...
+//     player.update(dt);
+//     renderer.set_model_bones(character, &player.bones());
+//
+// or animator.bones(), for an Animator crossfading between states. the shader only has room for
+// MAX_BONES, so a skeleton with more joints than that can't be drawn
+use glam::{Mat4, Quat, Vec3, Vec4};
+
+use mm3ds_format::{Channel, Clip, Property, SkeletonData};
+
+use crate::math;
+use crate::profiler;
+use crate::profiler::System;
+
+// where a joint is relative to its parent
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct JointPose {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl JointPose {
+    pub fn matrix(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+
+    // `self` at 0 and `other` at 1
+    pub fn blend(&self, other: &JointPose, t: f32) -> JointPose {
+        JointPose {
+            translation: self.translation.lerp(other.translation, t),
+            rotation: math::slerp(self.rotation, other.rotation, t),
+            scale: self.scale.lerp(other.scale, t),
+        }
+    }
+}
+
+// every joint of a skeleton, in its order
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pose {
+    pub joints: Vec<JointPose>,
+}
+
+impl Pose {
+    // `self` at 0 and `other` at 1, for crossfading. both have to be of the same skeleton
+    pub fn blend(&self, other: &Pose, t: f32) -> Pose {
+        Pose { joints: self.joints.iter().zip(&other.joints).map(|(a, b)| a.blend(b, t)).collect() }
+    }
+}
+
+struct Joint {
+    name: String,
+    parent: Option<usize>,
+    inverse_bind: Mat4,
+    rest: JointPose,
+}
+
+pub struct Skeleton {
+    root: Mat4,
+    joints: Vec<Joint>,
+    order: Vec<usize>, // the joints with parents before their children
+}
+
+// a channel's value at `time`, between the keys either side of it and held past the first and last.
+// keys close together are what clips are made of, so rotations are nlerped
+fn sample(channel: &Channel, time: f32) -> Vec4 {
+    let keys = &channel.keys;
+    let i = keys.partition_point(|key| key.time <= time);
+    let (a, b) = match i {
+        0 => return Vec4::from(keys[0].value),
+        i if i == keys.len() => return Vec4::from(keys[i - 1].value),
+        i => (&keys[i - 1], &keys[i]),
+    };
+
+    let t = (time - a.time) / (b.time - a.time);
+    match channel.property {
+        Property::Rotation => math::nlerp(Quat::from_array(a.value), Quat::from_array(b.value), t).into(),
+        _ => Vec4::from(a.value).lerp(Vec4::from(b.value), t),
+    }
+}
+
+impl Skeleton {
+    pub fn new(data: &SkeletonData) -> Self {
+        let joints: Vec<Joint> = data.joints.iter()
+            .map(|joint| Joint {
+                name: joint.name.clone(),
+                parent: joint.parent.map(usize::from),
+                inverse_bind: Mat4::from_cols_array(&joint.inverse_bind),
+                rest: JointPose {
+                    translation: joint.translation.into(),
+                    rotation: Quat::from_array(joint.rotation),
+                    scale: joint.scale.into(),
+                },
+            })
+            .collect();
+
+        // the format has it that every joint gets back to a root, so sorting by how far away that is
+        // puts parents first
+        let depth = |mut i: usize| {
+            let mut ret = 0;
+            while let Some(parent) = joints[i].parent {
+                i = parent;
+                ret += 1;
+            }
+            ret
+        };
+        let mut order: Vec<usize> = (0..joints.len()).collect();
+        order.sort_by_key(|&i| depth(i));
+
+        Self { root: Mat4::from_cols_array(&data.root), joints, order }
+    }
+
+    pub fn len(&self) -> usize {
+        self.joints.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.joints.is_empty()
+    }
+
+    // which joint is called `name`, e.g. a hand to hold something in. see world_transforms
+    pub fn joint(&self, name: &str) -> Option<usize> {
+        self.joints.iter().position(|joint| joint.name == name)
+    }
+
+    // every joint where it was when the mesh was skinned
+    pub fn rest_pose(&self) -> Pose {
+        Pose { joints: self.joints.iter().map(|joint| joint.rest).collect() }
+    }
+
+    // `clip` at `time` seconds in. joints it doesn't move stay at rest
+    pub fn sample(&self, clip: &Clip, time: f32) -> Pose {
+        let _animation = profiler::scope(System::Animation);
+        let mut ret = self.rest_pose();
+        for channel in &clip.channels {
+            let Some(joint) = ret.joints.get_mut(channel.joint as usize).filter(|_| !channel.keys.is_empty()) else { continue };
+            let value = sample(channel, time);
+            match channel.property {
+                Property::Translation => joint.translation = value.truncate(),
+                Property::Rotation => joint.rotation = Quat::from_vec4(value),
+                Property::Scale => joint.scale = value.truncate(),
+            }
+        }
+
+        ret
+    }
+
+    // where each joint of `pose` is in the model
+    pub fn world_transforms(&self, pose: &Pose) -> Vec<Mat4> {
+        let mut ret = vec![Mat4::IDENTITY; self.joints.len()];
+        for &i in &self.order {
+            let parent = self.joints[i].parent.map_or(self.root, |parent| ret[parent]);
+            ret[i] = parent * pose.joints[i].matrix();
+        }
+
+        ret
+    }
+
+    // what Renderer::set_bones wants for `pose`, which takes the mesh from how it was skinned to how
+    // it's posed
+    pub fn bones(&self, pose: &Pose) -> Vec<Mat4> {
+        let _animation = profiler::scope(System::Animation);
+        self.world_transforms(pose).into_iter()
+            .zip(&self.joints)
+            .map(|(world, joint)| world * joint.inverse_bind)
+            .collect()
+    }
+}
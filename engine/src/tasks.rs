@@ -0,0 +1,162 @@
+// gameplay that takes several frames, written as one async block instead of a state machine kept
+// by hand. a task runs until it waits, and carries on from there on a later frame:
+//
+//     let mut tasks = Tasks::new();
+//     tasks.spawn(|cx| async move {
+//         cx.publish(FreezePlayer(true));
+//         cx.seconds(0.5).await;
+//         cx.publish(PlayAnimation { clip: "open".into() });
+//         cx.event::<AnimationFinished>(|done| done.clip == "open").await;
+//         cx.frames(10).await;
+//         cx.publish(ShowDialogue { line: "chest_opened" });
+//         cx.publish(FreezePlayer(false));
+//     });
+//     ...
+//     events.update();
+//     tasks.update(time.dt(Clock::Gameplay), &mut events);
+//
+// waiting is for seconds (of whatever dt update is given, so a task slows down and stops with its
+// clock), frames, a condition, or an event on the bus. a task only gets at the game through what it
+// captures and the events it publishes, which others read the next frame like any others. there's
+// no waking up: every task that's waiting is checked each update, which is cheap for the few a
+// game has going at once
+use std::cell::Cell;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+
+use crate::events::EventBus;
+
+// what update shares with the tasks while it's running them
+struct Frame {
+    dt: Cell<f32>,
+    // only set while update runs the tasks, which is the only time anything reads it
+    events: Cell<*mut EventBus>,
+}
+
+// a task's way to wait, and to get at the frame it's running in. only usable inside the task
+#[derive(Clone)]
+pub struct TaskContext {
+    frame: Rc<Frame>,
+}
+
+// gives up the rest of the frame, once
+struct NextFrame(bool);
+
+impl Future for NextFrame {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, _: &mut Context) -> Poll<()> {
+        if self.0 {
+            Poll::Ready(())
+        } else {
+            self.0 = true;
+            Poll::Pending
+        }
+    }
+}
+
+impl TaskContext {
+    // this frame's dt, as given to Tasks::update
+    pub fn dt(&self) -> f32 {
+        self.frame.dt.get()
+    }
+
+    fn with_events<R>(&self, f: impl FnOnce(&mut EventBus) -> R) -> R {
+        let events = self.frame.events.get();
+        assert!(!events.is_null(), "a TaskContext can only be used by its task");
+        // update's &mut EventBus outlives the polling, and nothing else holds on to it meanwhile
+        f(unsafe { &mut *events })
+    }
+
+    // for the rest of this frame
+    pub async fn next_frame(&self) {
+        NextFrame(false).await
+    }
+
+    // for at least `seconds` of dt. 0 carries straight on
+    pub async fn seconds(&self, seconds: f32) {
+        let mut waited = 0.;
+        while waited < seconds {
+            self.next_frame().await;
+            waited += self.dt();
+        }
+    }
+
+    // for `frames` updates. 0 carries straight on
+    pub async fn frames(&self, frames: u32) {
+        for _ in 0..frames {
+            self.next_frame().await;
+        }
+    }
+
+    // until `condition` is true, checking it now and then once an update
+    pub async fn until(&self, mut condition: impl FnMut() -> bool) {
+        while !condition() {
+            self.next_frame().await;
+        }
+    }
+
+    // until an event of type T that `filter` likes is readable on the bus, and returns it. looks at
+    // what's readable now first, so an event from last frame counts
+    pub async fn event<T: Clone + 'static>(&self, mut filter: impl FnMut(&T) -> bool) -> T {
+        loop {
+            if let Some(event) = self.with_events(|events| events.read::<T>().iter().find(|&event| filter(event)).cloned()) {
+                return event;
+            }
+            self.next_frame().await;
+        }
+    }
+
+    // onto the bus, readable next frame. see EventBus::publish
+    pub fn publish<T: 'static>(&self, event: T) {
+        self.with_events(|events| events.publish(event));
+    }
+}
+
+pub struct Tasks {
+    frame: Rc<Frame>,
+    tasks: Vec<Pin<Box<dyn Future<Output = ()>>>>,
+}
+
+impl Default for Tasks {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Tasks {
+    pub fn new() -> Self {
+        Self { frame: Rc::new(Frame { dt: Cell::new(0.), events: Cell::new(std::ptr::null_mut()) }), tasks: Vec::new() }
+    }
+
+    // starts `task` on the next update, which runs it up to its first wait
+    pub fn spawn<F: Future<Output = ()> + 'static>(&mut self, task: impl FnOnce(TaskContext) -> F) {
+        self.tasks.push(Box::pin(task(TaskContext { frame: self.frame.clone() })));
+    }
+
+    // runs every task until it waits or finishes, in the order they were spawned. call once a frame,
+    // after events.update
+    pub fn update(&mut self, dt: f32, events: &mut EventBus) {
+        self.frame.dt.set(dt);
+        self.frame.events.set(events);
+        let mut cx = Context::from_waker(Waker::noop());
+        self.tasks.retain_mut(|task| task.as_mut().poll(&mut cx).is_pending());
+        self.frame.events.set(std::ptr::null_mut());
+    }
+
+    // how many haven't finished
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    // drops every task where it is, e.g. to skip a cutscene
+    pub fn clear(&mut self) {
+        self.tasks.clear();
+    }
+}
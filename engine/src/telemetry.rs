@@ -0,0 +1,78 @@
+// streams one line of CSV per frame over UDP, so a play session can be recorded on the host with
+// something like `nc -ul 7777 > session.csv` and graphed afterwards
+use std::io;
+use std::net::{ToSocketAddrs, UdpSocket};
+use std::time::Instant;
+
+use citro3d::sys;
+use ctru::services::soc::Soc;
+
+use crate::renderer::FrameStats;
+
+const HEADER: &str = "frame,frame_ms,cpu_ms,gpu_ms,cmdbuf_usage,requests,draw_calls,linear_free,vram_free,heap_free\n";
+
+// the header is re-sent this often, so a viewer that starts late still knows the columns
+const HEADER_INTERVAL: u64 = 600;
+
+pub struct Telemetry {
+    _soc: Soc,
+    socket: UdpSocket,
+    frame: u64,
+    last_frame: Instant,
+    line: String,
+}
+
+impl Telemetry {
+    pub fn new(host: impl ToSocketAddrs) -> io::Result<Self> {
+        let soc = Soc::new().map_err(io::Error::other)?;
+
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        socket.connect(host)?;
+        socket.set_nonblocking(true)?; // never stall a frame on the network
+
+        Ok(Self {
+            _soc: soc,
+            socket,
+            frame: 0,
+            last_frame: Instant::now(),
+            line: String::new(),
+        })
+    }
+
+    // call once per frame, after Renderer::render
+    pub fn send_frame(&mut self, stats: FrameStats) {
+        use std::fmt::Write;
+
+        let now = Instant::now();
+        let frame_ms = (now - self.last_frame).as_secs_f32() * 1000.;
+        self.last_frame = now;
+
+        self.line.clear();
+        if self.frame % HEADER_INTERVAL == 0 {
+            self.line += HEADER;
+        }
+
+        let (cpu_ms, gpu_ms, cmdbuf_usage) = unsafe {
+            (sys::C3D_GetProcessingTime(), sys::C3D_GetDrawingTime(), sys::C3D_GetCmdBufUsage())
+        };
+        let (linear_free, vram_free, heap_free) = unsafe {
+            (
+                ctru_sys::linearSpaceFree(),
+                ctru_sys::vramSpaceFree(),
+                ctru_sys::osGetMemRegionFree(ctru_sys::MEMREGION_APPLICATION),
+            )
+        };
+
+        writeln!(
+            self.line,
+            "{},{frame_ms:.3},{cpu_ms:.3},{gpu_ms:.3},{cmdbuf_usage:.3},{},{},{linear_free},{vram_free},{heap_free}",
+            self.frame,
+            stats.requests,
+            stats.draw_calls,
+        ).unwrap();
+
+        // dropped packets are fine, this is best effort
+        let _ = self.socket.send(self.line.as_bytes());
+        self.frame += 1;
+    }
+}
@@ -0,0 +1,73 @@
+// textures by what's in them, so that meshes using the same image share one copy of it instead of
+// each loading their own. the renderer keeps one for whatever's loaded through it:
+//
+//     let character = Mesh::from_file_data_in(file, renderer.texture_cache())?;
+//     let enemy = Mesh::from_file_data_in(other_file, renderer.texture_cache())?;
+//
+// and Mesh::from_file_data shares textures between the meshes of one file. meshes don't delete the
+// textures they get from a cache, and the cache doesn't either; what's in it stays for as long as
+// the app runs
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io;
+
+use citro3d::sys;
+
+use crate::mesh::load_pooled_t3x;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TextureId(usize);
+
+#[derive(Default)]
+pub struct TextureCache {
+    textures: Vec<sys::C3D_Tex>,
+    by_content: HashMap<(u64, usize), TextureId>, // a hash of the t3x and its length
+    shared: usize, // loads that found their texture already there
+    saved_bytes: usize, // what those would have taken
+}
+
+fn hash(data: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    data.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl TextureCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // the texture in `t3x_data`, loaded the first time it's asked for. running out of linear memory
+    // is an io::ErrorKind::OutOfMemory, and a bad t3x io::ErrorKind::InvalidData
+    pub fn load(&mut self, t3x_data: &[u8]) -> io::Result<TextureId> {
+        let key = (hash(t3x_data), t3x_data.len());
+        if let Some(&id) = self.by_content.get(&key) {
+            self.shared += 1;
+            self.saved_bytes += self.textures[id.0].size as usize;
+            return Ok(id);
+        }
+
+        let id = TextureId(self.textures.len());
+        self.textures.push(load_pooled_t3x(t3x_data)?);
+        self.by_content.insert(key, id);
+        Ok(id)
+    }
+
+    pub fn get(&self, id: TextureId) -> &sys::C3D_Tex {
+        &self.textures[id.0]
+    }
+
+    // how many different textures have been loaded
+    pub fn len(&self) -> usize {
+        self.textures.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.textures.is_empty()
+    }
+
+    // how many loads shared a texture that was already there, and how many bytes that saved
+    pub fn shared(&self) -> (usize, usize) {
+        (self.shared, self.saved_bytes)
+    }
+}
@@ -0,0 +1,407 @@
+// tile maps made in Tiled (https://www.mapeditor.org), drawn in the 2d layer (see
+// Renderer::please_render_2d), for 2.5d games and menu backgrounds:
+//
+//     let mut map = Tilemap::load(&mut renderer, "romfs:/level1.tmx", &assets::TILES_T3X.read()?)?;
+//     ...
+//     scroll.x += speed * dt;
+//     map.please_render(&mut renderer, TargetScreen::Top, scroll);
+//
+// a .tmx map can have any number of tile layers, drawn in order, but only one tileset. that has
+// to be embedded in the map (not a separate .tsx), and its image is the t3x passed in. layers have
+// to be saved as CSV, which is Tiled's default. tiles can be flipped and rotated, and each layer's
+// parallax factor, offset, opacity and visibility work like they do in Tiled. object and image
+// layers are skipped. a map exported from Tiled as CSV is one layer, see Tilemap::from_csv
+//
+// the map is measured in pixels from its bottom left corner, and `scroll` is the bottom left
+// corner of the part that's on screen. it's split into square chunks of tiles, each one mesh, and
+// only the chunks on screen are drawn
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+
+use citro3d::sys;
+use glam::{Mat4, Vec2, Vec4};
+
+use crate::material::Material;
+use crate::math::to_matrix4;
+use crate::mesh::{Mesh, Vertex, load_t3x};
+use crate::renderer::{MeshId, Renderer, TargetScreen};
+
+// tiles along each side of a chunk
+const CHUNK_TILES: u32 = 16;
+
+// the top bits of a tile in a .tmx, see https://doc.mapeditor.org/en/stable/reference/global-tile-ids/
+const FLIPPED_HORIZONTALLY: u32 = 0x80000000;
+const FLIPPED_VERTICALLY: u32 = 0x40000000;
+const FLIPPED_DIAGONALLY: u32 = 0x20000000;
+const FLAGS: u32 = 0xf0000000; // including hexagonal maps' rotation, which we don't do
+
+// where the tiles are in the tileset's image, in pixels
+#[derive(Copy, Clone, Debug)]
+struct Tileset {
+    tile_size: Vec2,
+    columns: u32,
+    count: u32,
+    spacing: f32,
+    margin: f32,
+}
+
+impl Tileset {
+    // the top left corner of tile `id` in the image
+    fn corner(&self, id: u32) -> Vec2 {
+        let (column, row) = (id % self.columns, id / self.columns);
+        Vec2::splat(self.margin) + Vec2::new(column as f32, row as f32) * (self.tile_size + self.spacing)
+    }
+}
+
+// a tile as it's stored: its id in the tileset, and how it's flipped
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Tile {
+    id: u32,
+    flags: u32,
+}
+
+struct Chunk {
+    mesh_id: MeshId,
+    min: Vec2, // in map pixels
+    max: Vec2,
+}
+
+pub struct TileLayer {
+    pub name: String,
+    pub parallax: Vec2, // how fast it scrolls, 1 is with the map, 0 stays still
+    pub offset: Vec2, // in pixels
+    pub opacity: f32,
+    pub visible: bool,
+    columns: u32,
+    rows: u32,
+    tiles: Vec<Option<Tile>>, // rows from the top, like in Tiled
+    chunks: Vec<Chunk>,
+}
+
+impl TileLayer {
+    // the id of the tile at `column`, `row` from the top left, as Tiled numbers them within the
+    // tileset. None for an empty cell or one outside the layer. for collision and so on
+    pub fn tile(&self, column: u32, row: u32) -> Option<u32> {
+        if column >= self.columns || row >= self.rows {
+            return None;
+        }
+
+        self.tiles[(row * self.columns + column) as usize].map(|tile| tile.id)
+    }
+}
+
+pub struct Tilemap {
+    tile_size: Vec2, // of the grid, in pixels
+    columns: u32,
+    rows: u32,
+    layers: Vec<TileLayer>,
+}
+
+// the settings of a layer while it's being read in
+struct LayerDef {
+    name: String,
+    columns: u32,
+    rows: u32,
+    parallax: Vec2,
+    offset: Vec2,
+    opacity: f32,
+    visible: bool,
+    tiles: Option<Vec<Option<Tile>>>,
+}
+
+// every tag in an xml document: its name (with a / in front for closing tags), its attributes, and
+// the text after it up to the next tag. good enough for what Tiled writes, not xml in general
+fn tags(text: &str) -> impl Iterator<Item = (&str, HashMap<&str, &str>, &str)> {
+    text.split('<').skip(1).filter_map(|piece| {
+        let (head, body) = piece.split_once('>')?;
+        if head.starts_with(['?', '!']) {
+            return None;
+        }
+
+        let head = head.strip_suffix('/').unwrap_or(head);
+        let (name, mut rest) = head.split_once(char::is_whitespace).unwrap_or((head, ""));
+        let mut attributes = HashMap::new();
+        while let Some((key, value)) = rest.split_once('=') {
+            let value = value.trim_start();
+            let Some(quote) = value.chars().next().filter(|c| matches!(c, '"' | '\'')) else { break };
+            let Some((value, after)) = value[1..].split_once(quote) else { break };
+            attributes.insert(key.trim(), value);
+            rest = after;
+        }
+
+        Some((name, attributes, body))
+    })
+}
+
+fn attribute<T: std::str::FromStr>(attributes: &HashMap<&str, &str>, tag: &str, name: &str) -> Result<T, String> {
+    let value = attributes.get(name).ok_or_else(|| format!("<{tag}> has no {name}"))?;
+    value.parse().map_err(|_| format!("<{tag}>'s {name} can't be {value:?}"))
+}
+
+fn attribute_or<T: std::str::FromStr>(attributes: &HashMap<&str, &str>, tag: &str, name: &str, default: T) -> Result<T, String> {
+    if attributes.contains_key(name) {
+        attribute(attributes, tag, name)
+    } else {
+        Ok(default)
+    }
+}
+
+// the corners of a tile's quad (bottom left, bottom right, top right, top left), and the uvs of
+// each, with the tile flipped like Tiled does it: diagonally first, then horizontally, then
+// vertically
+fn quad(min: Vec2, tileset: &Tileset, tile: Tile, texture_size: Vec2) -> [Vertex; 4] {
+    let corner = tileset.corner(tile.id);
+    [(0., 1.), (1., 1.), (1., 0.), (0., 0.)].map(|(x, y): (f32, f32)| {
+        // which corner of the tile in the image ends up here, measured down from its top left
+        let (mut sx, mut sy) = (x, y);
+        if tile.flags & FLIPPED_HORIZONTALLY != 0 {
+            sx = 1. - sx;
+        }
+        if tile.flags & FLIPPED_VERTICALLY != 0 {
+            sy = 1. - sy;
+        }
+        if tile.flags & FLIPPED_DIAGONALLY != 0 {
+            (sx, sy) = (sy, sx);
+        }
+
+        // tex3ds puts the image at the top left of the texture, where v is 1
+        let pixel = corner + Vec2::new(sx, sy) * tileset.tile_size;
+        let pos = min + Vec2::new(x, 1. - y) * tileset.tile_size;
+        Vertex {
+            pos: [pos.x, pos.y, -0.5],
+            uv: [pixel.x / texture_size.x, 1. - pixel.y / texture_size.y],
+            normal: [0., 0., 1.],
+        }
+    })
+}
+
+impl Tilemap {
+    // `t3x_data` is the tileset's image
+    pub fn from_tmx(renderer: &mut Renderer, text: &str, t3x_data: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let mut map = None;
+        let mut tileset: Option<(u32, Tileset)> = None; // and the id of its first tile
+        let mut layer: Option<LayerDef> = None;
+        let mut layers = vec![];
+
+        for (tag, attributes, body) in tags(text) {
+            match tag {
+                "map" => {
+                    if attributes.get("orientation").is_some_and(|o| *o != "orthogonal") {
+                        return Err("only orthogonal maps are supported".into());
+                    }
+                    if attributes.get("infinite").is_some_and(|i| *i == "1") {
+                        return Err("infinite maps aren't supported, turn it off in the map's properties".into());
+                    }
+
+                    let tile_size = Vec2::new(attribute(&attributes, tag, "tilewidth")?, attribute(&attributes, tag, "tileheight")?);
+                    map = Some((attribute::<u32>(&attributes, tag, "width")?, attribute::<u32>(&attributes, tag, "height")?, tile_size));
+                }
+                "tileset" => {
+                    if tileset.is_some() {
+                        return Err("only one tileset is supported".into());
+                    }
+                    if attributes.contains_key("source") {
+                        return Err("the tileset has to be embedded in the map".into());
+                    }
+
+                    tileset = Some((attribute(&attributes, tag, "firstgid")?, Tileset {
+                        tile_size: Vec2::new(attribute(&attributes, tag, "tilewidth")?, attribute(&attributes, tag, "tileheight")?),
+                        columns: attribute::<u32>(&attributes, tag, "columns")?.max(1),
+                        count: attribute(&attributes, tag, "tilecount")?,
+                        spacing: attribute_or(&attributes, tag, "spacing", 0.)?,
+                        margin: attribute_or(&attributes, tag, "margin", 0.)?,
+                    }));
+                }
+                "layer" => {
+                    layer = Some(LayerDef {
+                        name: attributes.get("name").copied().unwrap_or_default().to_owned(),
+                        columns: attribute(&attributes, tag, "width")?,
+                        rows: attribute(&attributes, tag, "height")?,
+                        parallax: Vec2::new(attribute_or(&attributes, tag, "parallaxx", 1.)?, attribute_or(&attributes, tag, "parallaxy", 1.)?),
+                        // tiled's y goes down
+                        offset: Vec2::new(attribute_or(&attributes, tag, "offsetx", 0.)?, -attribute_or(&attributes, tag, "offsety", 0.)?),
+                        opacity: attribute_or(&attributes, tag, "opacity", 1.)?,
+                        visible: attribute_or(&attributes, tag, "visible", 1)? != 0,
+                        tiles: None,
+                    });
+                }
+                "data" => {
+                    let Some(def) = &mut layer else { continue };
+                    if attributes.get("encoding") != Some(&"csv") || attributes.contains_key("compression") {
+                        return Err(format!("layer {:?} has to be saved as CSV", def.name).into());
+                    }
+                    let Some((first, tileset)) = tileset else {
+                        return Err("the map has no tileset".into());
+                    };
+
+                    let mut tiles = Vec::with_capacity((def.columns * def.rows) as usize);
+                    for value in body.split([',', '\n', '\r', ' ', '\t']).filter(|value| !value.is_empty()) {
+                        let gid: u32 = value.parse().map_err(|_| format!("layer {:?}: {value:?} isn't a tile", def.name))?;
+                        let id = gid & !FLAGS;
+                        if id == 0 {
+                            tiles.push(None);
+                        } else if id < first || id - first >= tileset.count {
+                            return Err(format!("layer {:?} uses a tile ({id}) from a tileset that isn't the first", def.name).into());
+                        } else {
+                            tiles.push(Some(Tile { id: id - first, flags: gid & FLAGS }));
+                        }
+                    }
+                    def.tiles = Some(tiles);
+                }
+                "/layer" => {
+                    let Some(def) = layer.take() else { continue };
+                    layers.push(def);
+                }
+                _ => {}
+            }
+        }
+
+        let (columns, rows, tile_size) = map.ok_or("there's no <map>")?;
+        let (_, tileset) = tileset.ok_or("the map has no tileset")?;
+        Self::new(renderer, columns, rows, tile_size, tileset, layers, t3x_data)
+    }
+
+    // one layer of 0-based tile ids, -1 for an empty cell, one row of the map per line. Tiled's
+    // CSV export doesn't include the tileset, so this needs its tile size and how many tiles
+    // across it is, and the grid is assumed to be the same size as the tiles
+    pub fn from_csv(renderer: &mut Renderer, text: &str, t3x_data: &[u8], tile_size: Vec2, columns: u32) -> Result<Self, Box<dyn Error>> {
+        let mut tiles = vec![];
+        let mut map_columns = None;
+        let mut rows = 0;
+        for (i, line) in text.lines().map(str::trim).enumerate().filter(|(_, line)| !line.is_empty()) {
+            let row = line.split(',')
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(|value| value.parse::<i64>().map_err(|_| format!("line {}: {value:?} isn't a tile", i + 1)))
+                .collect::<Result<Vec<_>, _>>()?;
+            if *map_columns.get_or_insert(row.len()) != row.len() {
+                return Err(format!("line {} has {} tiles, the first had {}", i + 1, row.len(), map_columns.unwrap()).into());
+            }
+
+            tiles.extend(row.into_iter().map(|id| (id >= 0).then(|| Tile { id: id as u32, flags: 0 })));
+            rows += 1;
+        }
+
+        let columns_in_map = map_columns.ok_or("the map is empty")? as u32;
+        let tileset = Tileset {
+            tile_size,
+            columns: columns.max(1),
+            count: u32::MAX,
+            spacing: 0.,
+            margin: 0.,
+        };
+        let layer = LayerDef {
+            name: String::new(),
+            columns: columns_in_map,
+            rows,
+            parallax: Vec2::ONE,
+            offset: Vec2::ZERO,
+            opacity: 1.,
+            visible: true,
+            tiles: Some(tiles),
+        };
+
+        Self::new(renderer, columns_in_map, rows, tile_size, tileset, vec![layer], t3x_data)
+    }
+
+    // from_tmx, with the map read from `path`
+    pub fn load(renderer: &mut Renderer, path: &str, t3x_data: &[u8]) -> Result<Self, Box<dyn Error>> {
+        Ok(Self::from_tmx(renderer, &fs::read_to_string(path)?, t3x_data).map_err(|e| format!("{path}: {e}"))?)
+    }
+
+    fn new(renderer: &mut Renderer, columns: u32, rows: u32, tile_size: Vec2, tileset: Tileset, defs: Vec<LayerDef>, t3x_data: &[u8]) -> Result<Self, Box<dyn Error>> {
+        // shared by every chunk. meshes stay registered for as long as the renderer's around, so
+        // it never gets freed
+        let mut texture = load_t3x(t3x_data);
+        unsafe {
+            // the tiles are right next to each other in the image, and filtering would pull in
+            // their neighbours' edges
+            sys::C3D_TexSetFilter(&mut texture, ctru_sys::GPU_NEAREST, ctru_sys::GPU_NEAREST);
+        }
+        let texture_size = Vec2::new(texture.width as f32, texture.height as f32);
+
+        let mut layers = Vec::with_capacity(defs.len());
+        for def in defs {
+            let tiles = def.tiles.ok_or_else(|| format!("layer {:?} has no data", def.name))?;
+            if tiles.len() != (def.columns * def.rows) as usize {
+                return Err(format!("layer {:?} has {} tiles, not {}x{}", def.name, tiles.len(), def.columns, def.rows).into());
+            }
+
+            let mut chunks = vec![];
+            for chunk_row in (0..def.rows).step_by(CHUNK_TILES as usize) {
+                for chunk_column in (0..def.columns).step_by(CHUNK_TILES as usize) {
+                    let mut vertices = vec![];
+                    let mut indices = vec![];
+                    let (mut min, mut max) = (Vec2::INFINITY, Vec2::NEG_INFINITY);
+                    for row in chunk_row..(chunk_row + CHUNK_TILES).min(def.rows) {
+                        for column in chunk_column..(chunk_column + CHUNK_TILES).min(def.columns) {
+                            let Some(tile) = tiles[(row * def.columns + column) as usize] else { continue };
+                            // tiles bigger than the grid stick up out of their cell, like in Tiled
+                            let corner = Vec2::new(column as f32, (def.rows - 1 - row) as f32) * tile_size;
+                            let base = vertices.len() as u16;
+                            vertices.extend(quad(corner, &tileset, tile, texture_size));
+                            indices.extend([base, base + 1, base + 2, base + 2, base + 3, base]);
+                            min = min.min(corner);
+                            max = max.max(corner + tileset.tile_size);
+                        }
+                    }
+                    if vertices.is_empty() {
+                        continue;
+                    }
+
+                    let mut mesh = Mesh::from_data(&vertices, Some(&indices), None, Material::default());
+                    mesh.texture = Some(texture);
+                    chunks.push(Chunk { mesh_id: renderer.register_mesh(mesh), min, max });
+                }
+            }
+
+            layers.push(TileLayer {
+                name: def.name,
+                parallax: def.parallax,
+                offset: def.offset,
+                opacity: def.opacity,
+                visible: def.visible,
+                columns: def.columns,
+                rows: def.rows,
+                tiles,
+                chunks,
+            });
+        }
+
+        Ok(Self { tile_size, columns, rows, layers })
+    }
+
+    // in pixels
+    pub fn size(&self) -> Vec2 {
+        Vec2::new(self.columns as f32, self.rows as f32) * self.tile_size
+    }
+
+    pub fn tile_size(&self) -> Vec2 {
+        self.tile_size
+    }
+
+    // bottom to top
+    pub fn layers(&self) -> &[TileLayer] {
+        &self.layers
+    }
+
+    pub fn layer_mut(&mut self, name: &str) -> Option<&mut TileLayer> {
+        self.layers.iter_mut().find(|layer| layer.name == name)
+    }
+
+    // for this frame, with `scroll` at the bottom left corner of the screen
+    pub fn please_render(&self, renderer: &mut Renderer, screen: TargetScreen, scroll: Vec2) {
+        for layer in self.layers.iter().filter(|layer| layer.visible && layer.opacity > 0.) {
+            let offset = layer.offset - scroll * layer.parallax;
+            let model = to_matrix4(Mat4::from_translation(offset.extend(0.)));
+            let color = Vec4::ONE.with_w(layer.opacity);
+            for chunk in &layer.chunks {
+                let (min, max) = (chunk.min + offset, chunk.max + offset);
+                if max.cmpgt(Vec2::ZERO).all() && min.cmplt(screen.size()).all() {
+                    renderer.please_render_2d(screen, chunk.mesh_id, model, color);
+                }
+            }
+        }
+    }
+}
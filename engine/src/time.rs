@@ -0,0 +1,140 @@
+// the frame's time step, slowed down or stopped for slow motion and hit-stop. what should slow
+// down takes its dt from here, and what shouldn't (menus, transitions, the debug camera) takes the
+// real one:
+//
+//     let mut time = Time::new();
+//     ...
+//     time.update();
+//     if enemy_hit {
+//         time.hit_stop(0.08);
+//     }
+//     animator.update(time.dt(Clock::Animation));
+//     physics.step(time.dt(Clock::Physics));
+//     renderer.set_time_scale(time.scale_of(Clock::Animation));
+//     transition.update(time.real_dt());
+//
+// each clock's scale is the global one times its own, so a whole scene can go slow while, say,
+// the player's particles keep their speed
+use std::time::Instant;
+
+// the systems with a scale of their own
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Clock {
+    Gameplay, // whatever the game steps itself
+    Animation, // skeletons, material animations and flipbooks
+    Particles,
+    Physics,
+}
+
+const CLOCKS: usize = 4;
+
+// a scale that moves towards its target at a steady rate
+#[derive(Copy, Clone, Debug)]
+struct Ramp {
+    current: f32,
+    target: f32,
+    rate: f32, // per real second
+}
+
+impl Ramp {
+    const ONE: Ramp = Ramp { current: 1., target: 1., rate: 0. };
+
+    // to `target` over `seconds`, or straight away if that's 0
+    fn to(&mut self, target: f32, seconds: f32) {
+        self.target = target.max(0.);
+        if seconds > 0. {
+            self.rate = (self.target - self.current).abs() / seconds;
+        } else {
+            self.current = self.target;
+        }
+    }
+
+    fn advance(&mut self, dt: f32) {
+        let step = self.rate * dt;
+        self.current = if self.current < self.target {
+            (self.current + step).min(self.target)
+        } else {
+            (self.current - step).max(self.target)
+        };
+    }
+}
+
+pub struct Time {
+    last_update: Option<Instant>,
+    real_dt: f32,
+    elapsed: f32, // scaled by the global scale
+    scale: Ramp,
+    clocks: [Ramp; CLOCKS],
+    stopped_for: f32, // real seconds of hit-stop left
+}
+
+impl Default for Time {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Time {
+    pub fn new() -> Self {
+        Self { last_update: None, real_dt: 0., elapsed: 0., scale: Ramp::ONE, clocks: [Ramp::ONE; CLOCKS], stopped_for: 0. }
+    }
+
+    // call once a frame, before anything asks for a dt. the first frame's is 0
+    pub fn update(&mut self) {
+        let now = Instant::now();
+        let dt = self.last_update.map_or(0., |last| (now - last).as_secs_f32());
+        self.last_update = Some(now);
+        self.advance(dt);
+    }
+
+    // like update, by `real_dt` seconds instead of however long it's been, for fixed steps and
+    // replays
+    pub fn advance(&mut self, real_dt: f32) {
+        self.real_dt = real_dt;
+        self.stopped_for = (self.stopped_for - real_dt).max(0.);
+        self.scale.advance(real_dt);
+        for clock in &mut self.clocks {
+            clock.advance(real_dt);
+        }
+        self.elapsed += self.dt(Clock::Gameplay);
+    }
+
+    // how long the last frame really took, for everything that shouldn't slow down
+    pub fn real_dt(&self) -> f32 {
+        self.real_dt
+    }
+
+    // the last frame's step for `clock`, scaled
+    pub fn dt(&self, clock: Clock) -> f32 {
+        self.real_dt * self.scale_of(clock)
+    }
+
+    // gameplay seconds since the start
+    pub fn elapsed(&self) -> f32 {
+        self.elapsed
+    }
+
+    // the global scale times `clock`'s, 0 during a hit-stop
+    pub fn scale_of(&self, clock: Clock) -> f32 {
+        if self.stopped_for > 0. {
+            return 0.;
+        }
+        self.scale.current * self.clocks[clock as usize].current
+    }
+
+    // 1 is normal speed, 0.5 half and 0 stopped. `seconds` is how long it takes to get there, in
+    // real time, 0 for straight away
+    pub fn set_scale(&mut self, scale: f32, seconds: f32) {
+        self.scale.to(scale, seconds);
+    }
+
+    pub fn set_clock_scale(&mut self, clock: Clock, scale: f32, seconds: f32) {
+        self.clocks[clock as usize].to(scale, seconds);
+    }
+
+    // stops every clock for `seconds` of real time, then carries on as before. one that's already
+    // going is made longer if this one is
+    pub fn hit_stop(&mut self, seconds: f32) {
+        self.stopped_for = self.stopped_for.max(seconds);
+    }
+}
@@ -0,0 +1,152 @@
+// transitions between scenes, so switching levels or menus doesn't pop from one frame to the next.
+// the screen gets covered, the game switches over while nothing can be seen, then it's uncovered.
+// the transition carries whatever the game uses for its scenes, and hands it back at the moment
+// to switch:
+//
+//     enum Scene { Title, Level(u32) }
+//
+//     let mut scene = Scene::Title;
+//     let mut transition = Transition::new();
+//     ...
+//     if input.just_pressed(KeyPad::A) && !transition.is_active() {
+//         transition.start(Scene::Level(1), TransitionStyle::Fade(Vec4::W), 0.6);
+//     }
+//     if let Some(next) = transition.update(dt) {
+//         scene = next; // unload the old one, load the new one
+//     }
+//     ... render the scene ...
+//     transition.please_render(&mut renderer);
+//
+// there's no crossfade: that needs the last frame of the old scene kept in a texture, and the
+// renderer can only draw to the screen. the covering uses overlays, see overlay::Overlay
+use glam::{Vec2, Vec4};
+
+use crate::renderer::Renderer;
+
+// the way a wipe's edge moves across the screen
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WipeDirection {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TransitionStyle {
+    Cut, // switches straight away
+    Fade(Vec4), // to the color and back, Vec4::W for black or Vec4::ONE for white
+    Wipe(Vec4, WipeDirection), // the color sweeps over the screen, then off the other side
+}
+
+pub struct Transition<S> {
+    style: TransitionStyle,
+    next: Option<S>, // until it's been handed back
+    elapsed: f32,
+    duration: f32, // of the whole thing, covering and uncovering
+    active: bool,
+}
+
+impl<S> Default for Transition<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn smoothstep(x: f32) -> f32 {
+    let x = x.clamp(0., 1.);
+    x * x * (3. - 2. * x)
+}
+
+impl<S> Transition<S> {
+    pub fn new() -> Self {
+        Self {
+            style: TransitionStyle::Cut,
+            next: None,
+            elapsed: 0.,
+            duration: 0.,
+            active: false,
+        }
+    }
+
+    // goes to `to` over `seconds`, half of them covering the screen and half uncovering it. does
+    // nothing and returns false if a transition is already running, so one button press can't
+    // switch scenes twice
+    pub fn start(&mut self, to: S, style: TransitionStyle, seconds: f32) -> bool {
+        if self.active {
+            return false;
+        }
+
+        self.style = style;
+        self.next = Some(to);
+        self.elapsed = 0.;
+        self.duration = if style == TransitionStyle::Cut { 0. } else { seconds.max(0.) };
+        self.active = true;
+
+        true
+    }
+
+    // hands back the scene to switch to on the frame the screen is fully covered, once per
+    // transition
+    pub fn update(&mut self, dt: f32) -> Option<S> {
+        if !self.active {
+            return None;
+        }
+
+        self.elapsed += dt;
+        let covered = self.elapsed >= self.duration / 2.;
+        let next = if covered { self.next.take() } else { None };
+        if self.elapsed >= self.duration && self.next.is_none() {
+            self.active = false;
+        }
+
+        next
+    }
+
+    // whether a transition is running. games usually want to ignore input while it is
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    // how far along it is, from 0 to 1. the screen is fully covered at 0.5
+    pub fn progress(&self) -> f32 {
+        if !self.active || self.duration <= 0. {
+            return 0.;
+        }
+
+        (self.elapsed / self.duration).clamp(0., 1.)
+    }
+
+    // for this frame, after everything else
+    pub fn please_render(&self, renderer: &mut Renderer) {
+        if !self.active {
+            return;
+        }
+
+        let progress = self.progress();
+        match self.style {
+            TransitionStyle::Cut => {}
+            TransitionStyle::Fade(color) => {
+                let cover = smoothstep(1. - (progress * 2. - 1.).abs());
+                renderer.please_render_overlay(None, Vec2::ZERO, Vec2::ONE, color.with_w(color.w * cover));
+            }
+            TransitionStyle::Wipe(color, direction) => {
+                // the covered part of the axis the edge moves along, from the edge it starts at:
+                // growing to all of it, then shrinking away from that edge
+                let (start, end) = if progress < 0.5 {
+                    (0., smoothstep(progress * 2.))
+                } else {
+                    (smoothstep(progress * 2. - 1.), 1.)
+                };
+
+                let (min, size) = match direction {
+                    WipeDirection::Right => (Vec2::new(start, 0.), Vec2::new(end - start, 1.)),
+                    WipeDirection::Left => (Vec2::new(1. - end, 0.), Vec2::new(end - start, 1.)),
+                    WipeDirection::Up => (Vec2::new(0., start), Vec2::new(1., end - start)),
+                    WipeDirection::Down => (Vec2::new(0., 1. - end), Vec2::new(1., end - start)),
+                };
+                renderer.please_render_overlay(None, min, size, color);
+            }
+        }
+    }
+}
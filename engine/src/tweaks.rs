@@ -0,0 +1,160 @@
+use std::ops::RangeInclusive;
+
+use ctru::console::Console;
+use ctru::services::gfx::Gfx;
+use ctru::services::hid::KeyPad;
+
+use crate::input::Input;
+
+// hold L+R and press START to show or hide
+const TOGGLE_HELD: KeyPad = KeyPad::L.union(KeyPad::R);
+const TOGGLE_PRESSED: KeyPad = KeyPad::START;
+
+// the panel is laid out on the console's 40x30 grid of 8x8 pixel cells
+const CELL: u16 = 8;
+const COLUMNS: usize = 40;
+const FIRST_ROW: usize = 2;
+const NAME_WIDTH: usize = 12;
+const BAR_WIDTH: usize = 20;
+const VALUE_WIDTH: usize = COLUMNS - NAME_WIDTH - BAR_WIDTH;
+
+// a handle to a variable registered with a TweakPanel
+#[derive(Copy, Clone, Debug)]
+pub struct Tweak(usize);
+
+struct Variable {
+    name: String,
+    value: f32,
+    range: RangeInclusive<f32>,
+}
+
+// a list of sliders on the bottom screen, one per registered variable, dragged with the stylus.
+// games read the values back every frame, so tuning doesn't need a rebuild per change
+pub struct TweakPanel<'gfx> {
+    gfx: &'gfx Gfx,
+    console: Option<Console<'gfx>>,
+    variables: Vec<Variable>,
+    dragging: Option<usize>,
+    dirty: bool,
+}
+
+impl<'gfx> TweakPanel<'gfx> {
+    pub fn new(gfx: &'gfx Gfx) -> Self {
+        Self {
+            gfx,
+            console: None,
+            variables: vec![],
+            dragging: None,
+            dirty: true,
+        }
+    }
+
+    pub fn add(&mut self, name: &str, value: f32, range: RangeInclusive<f32>) -> Tweak {
+        self.variables.push(Variable {
+            name: name.to_owned(),
+            value: value.clamp(*range.start(), *range.end()),
+            range,
+        });
+        self.dirty = true;
+
+        Tweak(self.variables.len() - 1)
+    }
+
+    pub fn get(&self, tweak: Tweak) -> f32 {
+        self.variables[tweak.0].value
+    }
+
+    pub fn set(&mut self, tweak: Tweak, value: f32) {
+        let variable = &mut self.variables[tweak.0];
+        variable.value = value.clamp(*variable.range.start(), *variable.range.end());
+        self.dirty = true;
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.console.is_some()
+    }
+
+    // returns false if something else is using the bottom screen
+    pub fn show(&mut self) -> bool {
+        if self.console.is_none() {
+            let Ok(screen) = self.gfx.bottom_screen.try_borrow_mut() else {
+                return false;
+            };
+
+            self.console = Some(Console::new(screen));
+            self.dirty = true;
+        }
+
+        true
+    }
+
+    pub fn hide(&mut self) {
+        self.console = None;
+        self.dragging = None;
+    }
+
+    // call once per frame, after Input::update
+    pub fn update(&mut self, input: &Input) {
+        if input.held(TOGGLE_HELD) && input.just_pressed(TOGGLE_PRESSED) {
+            if self.is_visible() {
+                self.hide();
+            } else {
+                self.show();
+            }
+        }
+
+        if !self.is_visible() {
+            return;
+        }
+
+        match input.touch() {
+            Some((x, y)) => {
+                // grab the slider under the stylus, and keep it until the stylus is lifted
+                let row = (y / CELL) as usize;
+                if self.dragging.is_none() && row >= FIRST_ROW && row - FIRST_ROW < self.variables.len() {
+                    self.dragging = Some(row - FIRST_ROW);
+                }
+
+                if let Some(i) = self.dragging {
+                    let bar_start = (NAME_WIDTH as u16 * CELL) as f32;
+                    let bar_len = (BAR_WIDTH as u16 * CELL) as f32;
+                    let t = ((x as f32 - bar_start) / bar_len).clamp(0., 1.);
+
+                    let variable = &mut self.variables[i];
+                    variable.value = variable.range.start() + t * (variable.range.end() - variable.range.start());
+                    self.dirty = true;
+                }
+            }
+            None => self.dragging = None,
+        }
+
+        if self.dirty {
+            self.draw();
+            self.dirty = false;
+        }
+    }
+
+    fn draw(&self) {
+        let Some(console) = &self.console else {
+            return;
+        };
+
+        console.clear();
+        print!("\x1b[1;1Htweaks (L+R+START to hide)");
+
+        for (i, variable) in self.variables.iter().enumerate() {
+            let (start, end) = (*variable.range.start(), *variable.range.end());
+            let t = if end > start { (variable.value - start) / (end - start) } else { 0. };
+            let filled = (t * BAR_WIDTH as f32).round() as usize;
+
+            let name = variable.name.chars().take(NAME_WIDTH - 1).collect::<String>();
+            print!(
+                "\x1b[{};1H{name:<NAME_WIDTH$}{}{}{:>VALUE_WIDTH$.3}",
+                FIRST_ROW + i + 1, // ANSI rows start at 1
+                "=".repeat(filled),
+                "-".repeat(BAR_WIDTH - filled),
+                variable.value,
+            );
+        }
+    }
+}
@@ -0,0 +1,182 @@
+// moves a value to others over time, one after another, for menus sliding in, a chest lid opening
+// or a camera move in a cutscene:
+//
+//     let mut slide = Tween::new(Vec2::new(-120., 0.))
+//         .to(Vec2::ZERO, 0.3, Easing::Overshoot)
+//         .wait(1.5)
+//         .to(Vec2::new(-120., 0.), 0.2, Easing::EaseIn)
+//         .call(|| println!("gone"));
+//     ...
+//     slide.update(time.real_dt());
+//     layout.offset = slide.value().to_array();
+//
+// anything Tweenable can be tweened: numbers, glam's vectors, rotations (which go the short way
+// round), colors (for materials, FVec4s) and whole transforms (Matrix4s, through their scales,
+// rotations and translations, see math::interpolate). gameplay tweens take their dt from
+// Time::dt, so they slow down and stop with the game, and menus take Time::real_dt
+use citro3d::math::{FVec4, Matrix4};
+use glam::{Quat, Vec2, Vec3, Vec4};
+
+pub use crate::material_animation::Easing;
+use crate::math;
+
+pub trait Tweenable: Copy {
+    // `self` at 0 and `to` at 1. eased `t` can go a little outside of that
+    fn tween(self, to: Self, t: f32) -> Self;
+}
+
+impl Tweenable for f32 {
+    fn tween(self, to: f32, t: f32) -> f32 {
+        self + (to - self) * t
+    }
+}
+
+impl Tweenable for Vec2 {
+    fn tween(self, to: Vec2, t: f32) -> Vec2 {
+        self.lerp(to, t)
+    }
+}
+
+impl Tweenable for Vec3 {
+    fn tween(self, to: Vec3, t: f32) -> Vec3 {
+        self.lerp(to, t)
+    }
+}
+
+impl Tweenable for Vec4 {
+    fn tween(self, to: Vec4, t: f32) -> Vec4 {
+        self.lerp(to, t)
+    }
+}
+
+impl Tweenable for Quat {
+    fn tween(self, to: Quat, t: f32) -> Quat {
+        math::slerp(self, to, t)
+    }
+}
+
+impl Tweenable for FVec4 {
+    fn tween(self, to: FVec4, t: f32) -> FVec4 {
+        math::lerp_fvec4(self, to, t)
+    }
+}
+
+impl Tweenable for Matrix4 {
+    fn tween(self, to: Matrix4, t: f32) -> Matrix4 {
+        math::interpolate(self, to, t)
+    }
+}
+
+enum Step<T> {
+    To { to: T, duration: f32, easing: Easing },
+    Wait(f32),
+    Call(Box<dyn FnMut()>),
+}
+
+pub struct Tween<T> {
+    start: T, // where the whole chain starts, for looping and restart
+    from: T, // where the current step started
+    value: T,
+    steps: Vec<Step<T>>,
+    step: usize,
+    time: f32, // seconds into the current step
+    looping: bool,
+}
+
+impl<T: Tweenable> Tween<T> {
+    // at `start`, with nothing to do yet
+    pub fn new(start: T) -> Self {
+        Self { start, from: start, value: start, steps: Vec::new(), step: 0, time: 0., looping: false }
+    }
+
+    // then to `to` over `seconds`
+    pub fn to(mut self, to: T, seconds: f32, easing: Easing) -> Self {
+        self.steps.push(Step::To { to, duration: seconds, easing });
+        self
+    }
+
+    // then stays put for `seconds`
+    pub fn wait(mut self, seconds: f32) -> Self {
+        self.steps.push(Step::Wait(seconds));
+        self
+    }
+
+    // then calls `callback`, once each time the chain gets there
+    pub fn call(mut self, callback: impl FnMut() + 'static) -> Self {
+        self.steps.push(Step::Call(Box::new(callback)));
+        self
+    }
+
+    // starts over from the beginning once it's done, forever. one that takes no time at all only
+    // goes through once
+    pub fn looping(mut self, looping: bool) -> Self {
+        self.looping = looping;
+        self
+    }
+
+    pub fn value(&self) -> T {
+        self.value
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.step >= self.steps.len()
+    }
+
+    pub fn restart(&mut self) {
+        self.step = 0;
+        self.time = 0.;
+        self.from = self.start;
+        self.value = self.start;
+    }
+
+    // moves on by `dt` seconds, through as many steps as that takes, calling callbacks on the way
+    pub fn update(&mut self, dt: f32) {
+        if self.is_finished() {
+            return;
+        }
+
+        self.time += dt;
+        loop {
+            let Some(step) = self.steps.get_mut(self.step) else {
+                if !self.looping || self.duration() <= 0. {
+                    self.time = 0.;
+                    return;
+                }
+                self.step = 0;
+                self.from = self.start;
+                self.value = self.start;
+                continue;
+            };
+
+            match step {
+                Step::To { to, duration, easing } => {
+                    if self.time < *duration {
+                        self.value = self.from.tween(*to, easing.apply(self.time / *duration));
+                        return;
+                    }
+                    self.time -= duration.max(0.);
+                    self.value = *to;
+                    self.from = *to;
+                }
+                Step::Wait(duration) => {
+                    if self.time < *duration {
+                        return;
+                    }
+                    self.time -= duration.max(0.);
+                }
+                Step::Call(callback) => callback(),
+            }
+            self.step += 1;
+        }
+    }
+
+    // seconds from the start to the end, once through
+    pub fn duration(&self) -> f32 {
+        self.steps.iter()
+            .map(|step| match step {
+                Step::To { duration, .. } | Step::Wait(duration) => duration.max(0.),
+                Step::Call(_) => 0.,
+            })
+            .sum()
+    }
+}
@@ -0,0 +1,160 @@
+// water: a flat grid that the water shader pushes around with two travelling waves, lit with the
+// waves' normals, with a texture scrolling across it. each body of water gets its own settings,
+// which can come straight out of a level's TOML:
+//
+//     [[water]]
+//     size = [20.0, 12.0]
+//     scroll = [0.02, 0.05]
+//     waves = [
+//         { direction = [1.0, 0.3], wavelength = 4.0, height = 0.08, speed = 1.2 },
+//         { direction = [-0.4, 1.0], wavelength = 1.5, height = 0.03, speed = 0.7 },
+//     ]
+//
+//     let pond = Water::new(&mut renderer, &config, Some(&assets::WATER_T3X.read()?), Material::default());
+//     ...
+//     pond.please_render(&mut renderer, Matrix4::identity());
+//
+// there's no planar reflection yet: that takes rendering the scene mirrored into a texture, and
+// the renderer can only draw to the screen. the texture wants to be tileable, it repeats every
+// `tile` world units
+use citro3d::math::Matrix4;
+use glam::{Vec2, Vec3, Vec4};
+use serde::Deserialize;
+
+use crate::material::Material;
+use crate::mesh::{Mesh, Vertex};
+use crate::renderer::{MeshId, Renderer};
+
+// so the grid's indices fit in a u16
+const MAX_CELLS: u32 = 255;
+
+#[derive(Copy, Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WaveConfig {
+    pub direction: [f32; 2], // on the xz plane, the way the crests travel
+    pub wavelength: f32, // crest to crest, in world units
+    pub height: f32, // up from the surface, and as far down
+    pub speed: f32, // world units per second
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WaterConfig {
+    pub size: [f32; 2], // along x and z, centred on the origin, at y = 0
+    pub cells: u32, // the grid's resolution along its longer side. more is smoother and slower
+    pub waves: [WaveConfig; 2],
+    pub scroll: [f32; 2], // texture scroll, in tiles per second
+    pub tile: f32, // world units per repeat of the texture
+}
+
+impl Default for WaveConfig {
+    fn default() -> Self {
+        Self {
+            direction: [1., 0.],
+            wavelength: 3.,
+            height: 0.05,
+            speed: 1.,
+        }
+    }
+}
+
+impl Default for WaterConfig {
+    fn default() -> Self {
+        Self {
+            size: [10., 10.],
+            cells: 32,
+            waves: [
+                WaveConfig::default(),
+                WaveConfig { direction: [-0.3, 1.], wavelength: 1.3, height: 0.02, speed: 0.6 },
+            ],
+            scroll: [0.02, 0.03],
+            tile: 2.,
+        }
+    }
+}
+
+impl WaveConfig {
+    // what the water shader wants: xy = direction * 2pi / wavelength, z = how fast the phase
+    // moves, w = height
+    fn param(&self) -> Vec4 {
+        let k = std::f32::consts::TAU / self.wavelength.max(f32::EPSILON);
+        let direction = Vec2::from(self.direction).normalize_or(Vec2::X);
+        (direction * k).extend(self.speed * k).extend(self.height)
+    }
+}
+
+// the flat grid, facing up
+fn grid(config: &WaterConfig) -> (Vec<Vertex>, Vec<u16>) {
+    let size = Vec2::from(config.size);
+    let cells = config.cells.clamp(1, MAX_CELLS) as f32;
+    let cell = size.max_element() / cells;
+    let columns = ((size.x / cell).ceil() as u32).clamp(1, MAX_CELLS);
+    let rows = ((size.y / cell).ceil() as u32).clamp(1, MAX_CELLS);
+    let tile = config.tile.max(f32::EPSILON);
+
+    let mut vertices = Vec::with_capacity(((columns + 1) * (rows + 1)) as usize);
+    for row in 0..=rows {
+        for column in 0..=columns {
+            let x = (column as f32 / columns as f32 - 0.5) * size.x;
+            let z = (row as f32 / rows as f32 - 0.5) * size.y;
+            vertices.push(Vertex {
+                pos: [x, 0., z],
+                uv: [x / tile, -z / tile],
+                normal: [0., 1., 0.],
+            });
+        }
+    }
+
+    let mut indices = Vec::with_capacity((columns * rows * 6) as usize);
+    for row in 0..rows {
+        for column in 0..columns {
+            let corner = (row * (columns + 1) + column) as u16;
+            let below = corner + columns as u16 + 1;
+            indices.extend_from_slice(&[corner, below, corner + 1, corner + 1, below, below + 1]);
+        }
+    }
+
+    (vertices, indices)
+}
+
+pub struct Water {
+    mesh_id: MeshId,
+}
+
+impl Water {
+    pub fn new(renderer: &mut Renderer, config: &WaterConfig, t3x_data: Option<&[u8]>, material: Material) -> Self {
+        let (vertices, indices) = grid(config);
+        let mut mesh = Mesh::from_data(&vertices, Some(&indices), t3x_data, material);
+        // so the texture tiles as it scrolls
+        if let Some(texture) = &mut mesh.texture {
+            unsafe { citro3d::sys::C3D_TexSetWrap(texture, ctru_sys::GPU_REPEAT, ctru_sys::GPU_REPEAT); }
+        }
+        // the waves reach above and below the flat grid, and occlusion needs to know
+        let reach = Vec3::Y * config.waves.iter().map(|wave| wave.height.abs()).sum::<f32>();
+        mesh.bounds.min -= reach;
+        mesh.bounds.max += reach;
+
+        let ret = Self { mesh_id: renderer.register_mesh(mesh) };
+        renderer.set_shader(ret.mesh_id, Some("water"));
+        ret.set_config(renderer, config);
+
+        ret
+    }
+
+    // changes the waves and scrolling. the size and resolution are fixed once it's made
+    pub fn set_config(&self, renderer: &mut Renderer, config: &WaterConfig) {
+        let scroll = Vec2::from(config.scroll).extend(0.).extend(0.);
+        for (name, value) in [("waveA", config.waves[0].param()), ("waveB", config.waves[1].param()), ("scroll", scroll)] {
+            // shaders/water.v.pica declares every one of them
+            renderer.set_param(self.mesh_id, name, value).unwrap();
+        }
+    }
+
+    pub fn mesh_id(&self) -> MeshId {
+        self.mesh_id
+    }
+
+    pub fn please_render(&self, renderer: &mut Renderer, model: Matrix4) {
+        renderer.please_render(self.mesh_id, model);
+    }
+}
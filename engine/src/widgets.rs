@@ -0,0 +1,470 @@
+// buttons, toggles, sliders and lists on the bottom screen, for menus and touch controls. each one
+// is added once and stays until the Ui is dropped; the game reads their state back and polls for
+// what happened to them:
+//
+//     renderer.enable_bottom_screen(&gfx);
+//     let skin = Skin::from_toml(&mut renderer, &fs::read_to_string("romfs:/skin.toml")?, &assets::SKIN_T3X.read()?)?;
+//     let mut ui = Ui::new();
+//     let start = ui.add_button(Rect::new(100., 150., 120., 40.));
+//     let volume = ui.add_slider(Rect::new(60., 90., 200., 24.), 0.8, 0.0..=1.0);
+//     ...
+//     ui.update(&input);
+//     while let Some(event) = ui.poll() {
+//         match event {
+//             UiEvent::Clicked(id) if id == start => ...,
+//             UiEvent::Changed(id, value) if id == volume => ...,
+//             _ => {}
+//         }
+//     }
+//     ui.please_render(&mut renderer, &skin);
+//
+// they're used with the stylus, or with the d-pad moving focus between them (to the nearest one
+// in that direction) and A pressing the focused one. left and right move a focused slider, up and
+// down move through a focused list. there's no text rendering yet, so a list only draws its rows'
+// backgrounds and the game draws what's in them, see Ui::row_rect
+//
+// a skin is a texture with a nine-patch picture for each part (see nine_patch), found by a TOML
+// file. positions and sizes are in the texture's pixels, from its top left:
+//
+//     [button]
+//     min = [0, 0]
+//     size = [32, 32]
+//     borders = [8, 8, 8, 8]    # left, right, top, bottom
+//
+// and the same for button_pressed, focus (drawn over whatever has focus), toggle_off, toggle_on,
+// track and knob (a slider's), row and row_selected (a list's)
+use std::collections::VecDeque;
+use std::error::Error;
+use std::ops::RangeInclusive;
+
+use ctru::services::hid::KeyPad;
+use glam::{Vec2, Vec4};
+use serde::Deserialize;
+
+use crate::input::Input;
+use crate::mesh::load_t3x;
+use crate::nine_patch::{Borders, NinePatch};
+use crate::renderer::{BOTTOM_SCREEN_SIZE, Renderer, TargetScreen};
+
+// how much of its range a focused slider moves per press of left or right
+const SLIDER_STEP: f32 = 0.05;
+
+// a rectangle in pixels, from the bottom left of the screen
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Rect {
+    pub min: Vec2,
+    pub size: Vec2,
+}
+
+impl Rect {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self { min: Vec2::new(x, y), size: Vec2::new(width, height) }
+    }
+
+    pub fn max(&self) -> Vec2 {
+        self.min + self.size
+    }
+
+    pub fn centre(&self) -> Vec2 {
+        self.min + self.size / 2.
+    }
+
+    pub fn contains(&self, point: Vec2) -> bool {
+        point.cmpge(self.min).all() && point.cmplt(self.max()).all()
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PieceDef {
+    min: [f32; 2],
+    size: [f32; 2],
+    #[serde(default)]
+    borders: [f32; 4],
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SkinDef {
+    button: PieceDef,
+    button_pressed: PieceDef,
+    focus: PieceDef,
+    toggle_off: PieceDef,
+    toggle_on: PieceDef,
+    track: PieceDef,
+    knob: PieceDef,
+    row: PieceDef,
+    row_selected: PieceDef,
+}
+
+pub struct Skin {
+    button: NinePatch,
+    button_pressed: NinePatch,
+    focus: NinePatch,
+    toggle_off: NinePatch,
+    toggle_on: NinePatch,
+    track: NinePatch,
+    knob: NinePatch,
+    row: NinePatch,
+    row_selected: NinePatch,
+}
+
+impl Skin {
+    pub fn from_toml(renderer: &mut Renderer, text: &str, t3x_data: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let def: SkinDef = toml::from_str(text)?;
+        let texture = load_t3x(t3x_data);
+        let mut piece = |def: PieceDef| {
+            let [left, right, top, bottom] = def.borders;
+            NinePatch::from_texture(renderer, texture, def.min.into(), def.size.into(), Borders { left, right, top, bottom })
+        };
+
+        Ok(Self {
+            button: piece(def.button),
+            button_pressed: piece(def.button_pressed),
+            focus: piece(def.focus),
+            toggle_off: piece(def.toggle_off),
+            toggle_on: piece(def.toggle_on),
+            track: piece(def.track),
+            knob: piece(def.knob),
+            row: piece(def.row),
+            row_selected: piece(def.row_selected),
+        })
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct WidgetId(usize);
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum UiEvent {
+    Clicked(WidgetId), // a button
+    Toggled(WidgetId, bool),
+    Changed(WidgetId, f32), // a slider, with its new value
+    Selected(WidgetId, usize), // a list's row was tapped, or chosen with A
+}
+
+enum Kind {
+    Button,
+    Toggle { on: bool },
+    Slider { value: f32, range: RangeInclusive<f32> },
+    List { rows: usize, row_height: f32, selected: usize, scroll: usize },
+}
+
+struct Widget {
+    rect: Rect,
+    kind: Kind,
+}
+
+impl Widget {
+    // how many of a list's rows fit in it
+    fn visible_rows(&self) -> usize {
+        match self.kind {
+            Kind::List { row_height, .. } => (self.rect.size.y / row_height.max(1.)).floor() as usize,
+            _ => 0,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Ui {
+    widgets: Vec<Widget>,
+    focus: Option<usize>,
+    show_focus: bool, // only once the d-pad's been used, it's just noise with the stylus
+    pressed: Option<usize>, // the widget the stylus went down on
+    touch: Option<Vec2>, // where the stylus was last frame
+    events: VecDeque<UiEvent>,
+}
+
+impl Ui {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn add(&mut self, rect: Rect, kind: Kind) -> WidgetId {
+        self.widgets.push(Widget { rect, kind });
+        WidgetId(self.widgets.len() - 1)
+    }
+
+    pub fn add_button(&mut self, rect: Rect) -> WidgetId {
+        self.add(rect, Kind::Button)
+    }
+
+    pub fn add_toggle(&mut self, rect: Rect, on: bool) -> WidgetId {
+        self.add(rect, Kind::Toggle { on })
+    }
+
+    pub fn add_slider(&mut self, rect: Rect, value: f32, range: RangeInclusive<f32>) -> WidgetId {
+        let value = value.clamp(*range.start(), *range.end());
+        self.add(rect, Kind::Slider { value, range })
+    }
+
+    // `rows` rows, each `row_height` pixels tall, from the top. it scrolls to keep the selected
+    // row in view
+    pub fn add_list(&mut self, rect: Rect, rows: usize, row_height: f32) -> WidgetId {
+        self.add(rect, Kind::List { rows, row_height, selected: 0, scroll: 0 })
+    }
+
+    pub fn rect(&self, id: WidgetId) -> Rect {
+        self.widgets[id.0].rect
+    }
+
+    // for moving widgets around, e.g. when the layout changes
+    pub fn set_rect(&mut self, id: WidgetId, rect: Rect) {
+        self.widgets[id.0].rect = rect;
+    }
+
+    pub fn focus(&self) -> Option<WidgetId> {
+        self.focus.map(WidgetId)
+    }
+
+    pub fn set_focus(&mut self, id: Option<WidgetId>) {
+        self.focus = id.map(|id| id.0);
+    }
+
+    // a toggle's state, false for anything else
+    pub fn is_on(&self, id: WidgetId) -> bool {
+        matches!(self.widgets[id.0].kind, Kind::Toggle { on: true })
+    }
+
+    // a slider's value, 0 for anything else
+    pub fn value(&self, id: WidgetId) -> f32 {
+        match self.widgets[id.0].kind {
+            Kind::Slider { value, .. } => value,
+            _ => 0.,
+        }
+    }
+
+    // a list's selected row, 0 for anything else
+    pub fn selected(&self, id: WidgetId) -> usize {
+        match self.widgets[id.0].kind {
+            Kind::List { selected, .. } => selected,
+            _ => 0,
+        }
+    }
+
+    // sets a toggle, slider or list without sending an event
+    pub fn set_on(&mut self, id: WidgetId, value: bool) {
+        if let Kind::Toggle { on } = &mut self.widgets[id.0].kind {
+            *on = value;
+        }
+    }
+
+    pub fn set_value(&mut self, id: WidgetId, new: f32) {
+        if let Kind::Slider { value, range } = &mut self.widgets[id.0].kind {
+            *value = new.clamp(*range.start(), *range.end());
+        }
+    }
+
+    pub fn set_selected(&mut self, id: WidgetId, row: usize) {
+        self.select(id.0, row);
+    }
+
+    // changes how many rows a list has, e.g. when the things in it change
+    pub fn set_rows(&mut self, id: WidgetId, count: usize) {
+        if let Kind::List { rows, .. } = &mut self.widgets[id.0].kind {
+            *rows = count;
+        }
+        self.select(id.0, self.selected(id));
+    }
+
+    // where row `row` of a list is drawn, or None if it's scrolled out of view
+    pub fn row_rect(&self, id: WidgetId, row: usize) -> Option<Rect> {
+        let widget = &self.widgets[id.0];
+        let Kind::List { rows, row_height, scroll, .. } = widget.kind else { return None };
+        if row >= rows || row < scroll || row >= scroll + widget.visible_rows() {
+            return None;
+        }
+
+        let top = widget.rect.max().y - (row - scroll) as f32 * row_height;
+        Some(Rect::new(widget.rect.min.x, top - row_height, widget.rect.size.x, row_height))
+    }
+
+    // clamps the selection, and scrolls to it
+    fn select(&mut self, index: usize, row: usize) {
+        let visible = self.widgets[index].visible_rows().max(1);
+        if let Kind::List { rows, selected, scroll, .. } = &mut self.widgets[index].kind {
+            *selected = row.min(rows.saturating_sub(1));
+            if *selected < *scroll {
+                *scroll = *selected;
+            } else if *selected >= *scroll + visible {
+                *scroll = *selected + 1 - visible;
+            }
+        }
+    }
+
+    fn set_slider_from(&mut self, index: usize, x: f32) {
+        let widget = &mut self.widgets[index];
+        if let Kind::Slider { value, range } = &mut widget.kind {
+            let t = ((x - widget.rect.min.x) / widget.rect.size.x.max(1.)).clamp(0., 1.);
+            let new = range.start() + (range.end() - range.start()) * t;
+            if new != *value {
+                *value = new;
+                self.events.push_back(UiEvent::Changed(WidgetId(index), new));
+            }
+        }
+    }
+
+    // what A (or lifting the stylus) does to a widget
+    fn activate(&mut self, index: usize) {
+        let id = WidgetId(index);
+        match &mut self.widgets[index].kind {
+            Kind::Button => self.events.push_back(UiEvent::Clicked(id)),
+            Kind::Toggle { on } => {
+                *on = !*on;
+                self.events.push_back(UiEvent::Toggled(id, *on));
+            }
+            Kind::List { rows, selected, .. } if *rows > 0 => self.events.push_back(UiEvent::Selected(id, *selected)),
+            _ => {}
+        }
+    }
+
+    // the nearest widget from the focused one in `direction`, favouring ones that are straight
+    // that way over ones off to the side
+    fn neighbour(&self, from: usize, direction: Vec2) -> Option<usize> {
+        let centre = self.widgets[from].rect.centre();
+        self.widgets.iter().enumerate()
+            .filter(|&(i, _)| i != from)
+            .filter_map(|(i, widget)| {
+                let offset = widget.rect.centre() - centre;
+                let along = offset.dot(direction);
+                let across = offset.perp_dot(direction).abs();
+                (along > 0.).then_some((i, along + 2. * across))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i)
+    }
+
+    fn navigate(&mut self, direction: Vec2) {
+        self.show_focus = true;
+        let Some(focus) = self.focus else {
+            self.focus = (!self.widgets.is_empty()).then_some(0);
+            return;
+        };
+
+        // focused sliders and lists use some directions themselves
+        let widget = &self.widgets[focus];
+        match widget.kind {
+            Kind::Slider { .. } if direction.x != 0. => {
+                let x = widget.rect.min.x + widget.rect.size.x * (self.slider_t(focus) + direction.x * SLIDER_STEP);
+                self.set_slider_from(focus, x);
+                return;
+            }
+            Kind::List { rows, selected, .. } if direction.y != 0. => {
+                let row = selected as isize - direction.y as isize;
+                if row >= 0 && (row as usize) < rows {
+                    self.select(focus, row as usize);
+                    return;
+                }
+            }
+            _ => {}
+        }
+
+        if let Some(next) = self.neighbour(focus, direction) {
+            self.focus = Some(next);
+        }
+    }
+
+    // how far along a slider's value is, from 0 to 1
+    fn slider_t(&self, index: usize) -> f32 {
+        match &self.widgets[index].kind {
+            Kind::Slider { value, range } if range.end() > range.start() => (value - range.start()) / (range.end() - range.start()),
+            _ => 0.,
+        }
+    }
+
+    // call once per frame, after Input::update
+    pub fn update(&mut self, input: &Input) {
+        for (keys, direction) in [
+            (KeyPad::DPAD_UP, Vec2::Y),
+            (KeyPad::DPAD_DOWN, Vec2::NEG_Y),
+            (KeyPad::DPAD_LEFT, Vec2::NEG_X),
+            (KeyPad::DPAD_RIGHT, Vec2::X),
+        ] {
+            // holding a direction keeps moving, see Input::set_repeat
+            if input.repeated(keys) {
+                self.navigate(direction);
+            }
+        }
+        if input.just_pressed(KeyPad::A) {
+            if let Some(focus) = self.focus {
+                self.activate(focus);
+            }
+        }
+
+        // the touch screen's y goes down
+        let touch = input.touch().map(|(x, y)| Vec2::new(x as f32, BOTTOM_SCREEN_SIZE.y - y as f32));
+        match (self.touch, touch) {
+            (None, Some(point)) => {
+                self.pressed = self.widgets.iter().position(|widget| widget.rect.contains(point));
+                if let Some(index) = self.pressed {
+                    self.focus = Some(index);
+                    self.show_focus = false;
+                    if let Kind::List { row_height, scroll, .. } = self.widgets[index].kind {
+                        let row = ((self.widgets[index].rect.max().y - point.y) / row_height.max(1.)) as usize + scroll;
+                        if self.row_rect(WidgetId(index), row).is_some() {
+                            self.select(index, row);
+                            self.activate(index);
+                        }
+                    }
+                }
+            }
+            (Some(last), None) => {
+                // buttons and toggles go off when the stylus lifts, and only if it's still on them
+                if let Some(index) = self.pressed.take() {
+                    let lifted_on = self.widgets[index].rect.contains(last);
+                    if lifted_on && matches!(self.widgets[index].kind, Kind::Button | Kind::Toggle { .. }) {
+                        self.activate(index);
+                    }
+                }
+            }
+            _ => {}
+        }
+        if let (Some(point), Some(index)) = (touch, self.pressed) {
+            self.set_slider_from(index, point.x);
+        }
+        self.touch = touch;
+    }
+
+    // what happened to the widgets in the last update, oldest first
+    pub fn poll(&mut self) -> Option<UiEvent> {
+        self.events.pop_front()
+    }
+
+    // for this frame, on the bottom screen
+    pub fn please_render(&self, renderer: &mut Renderer, skin: &Skin) {
+        let screen = TargetScreen::Bottom;
+        for (i, widget) in self.widgets.iter().enumerate() {
+            let rect = widget.rect;
+            let held = self.pressed == Some(i) && self.touch.is_some_and(|point| rect.contains(point));
+            match &widget.kind {
+                Kind::Button => {
+                    let patch = if held { &skin.button_pressed } else { &skin.button };
+                    patch.please_render(renderer, screen, rect.min, rect.size, Vec4::ONE);
+                }
+                Kind::Toggle { on } => {
+                    let patch = if *on { &skin.toggle_on } else { &skin.toggle_off };
+                    patch.please_render(renderer, screen, rect.min, rect.size, Vec4::ONE);
+                }
+                Kind::Slider { .. } => {
+                    skin.track.please_render(renderer, screen, rect.min, rect.size, Vec4::ONE);
+                    // a square knob as tall as the slider, kept inside it
+                    let knob = Vec2::splat(rect.size.y);
+                    let x = rect.min.x + (rect.size.x - knob.x).max(0.) * self.slider_t(i);
+                    skin.knob.please_render(renderer, screen, Vec2::new(x, rect.min.y), knob, Vec4::ONE);
+                }
+                Kind::List { selected, scroll, .. } => {
+                    for row in *scroll..*scroll + widget.visible_rows() {
+                        let Some(row_rect) = self.row_rect(WidgetId(i), row) else { continue };
+                        let patch = if row == *selected { &skin.row_selected } else { &skin.row };
+                        patch.please_render(renderer, screen, row_rect.min, row_rect.size, Vec4::ONE);
+                    }
+                }
+            }
+        }
+
+        if let Some(focus) = self.focus.filter(|_| self.show_focus) {
+            let rect = self.widgets[focus].rect;
+            skin.focus.please_render(renderer, screen, rect.min, rect.size, Vec4::ONE);
+        }
+    }
+}
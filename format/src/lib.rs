@@ -0,0 +1,1988 @@
+// the MESH file format, shared between gltf_tool (which writes it) and the engine (which reads it)
+//
+// everything is little endian:
+//
+// magic "MSH8" (or "MSH7", "MSH6", "MSH5", "MSH4", "MSH3", "MSH2" or "MESH" for versions 7 to 1,
+// which have no nodes, and of which 6 to 1 end each mesh after its double_sided, layout, alpha mode,
+// lightmap, flipbook and texture)
+// n_meshes u32
+// for each mesh:
+//     color [f32; 4]
+//     n_vertices u32
+//     vertices [Vertex; n_vertices]
+//     n_indices u32
+//     indices [u16; n_indices]
+//     size_of_tex u32
+//     texture [u8; size_of_tex] (a t3x file, or nothing if size_of_tex is 0)
+//     flipbook_frames u16 (0 if the texture isn't a flipbook, and then nothing else follows)
+//     flipbook_columns u16
+//     flipbook_rows u16
+//     flipbook_fps f32
+//     size_of_lightmap u32
+//     lightmap [u8; size_of_lightmap] (a t3x file, or nothing if size_of_lightmap is 0)
+//     lightmap_uvs [[f32; 2]; n_vertices] (only if there's a lightmap)
+//     alpha_mode u8 (0 opaque, 1 mask, 2 blend)
+//     alpha_cutoff f32 (only for mask)
+//     layout u8 (which of these follow: bit 0 colors, bit 1 skin. at most one, and neither with a
+//         lightmap, since the engine puts all three in the same attribute)
+//     colors [[f32; 4]; n_vertices]
+//     skin [SkinVertex; n_vertices]
+//     double_sided u8 (1 if both sides of its triangles are drawn, 0 if only the front, the side
+//         they're counterclockwise from. files from before this field are double sided)
+//     emission [f32; 3] (light it gives off, added to its color. 0 with an emission map)
+//     size_of_emission_map u32
+//     emission_map [u8; size_of_emission_map] (a t3x file of the light each texel gives off, laid
+//         out with the mesh's uvs, or nothing if size_of_emission_map is 0)
+// n_nodes u32 (0 if the meshes are where they're drawn, without any nodes)
+// for each node, parents before their children:
+//     name string
+//     parent u16 (the node it hangs off, or 0xffff for a root)
+//     transform [f32; 16] (column major, relative to its parent)
+//     n_meshes u16
+//     meshes [u16; n_meshes] (the ones it draws, by where they are in the file. a mesh can be on
+//         more than one node)
+//
+// and the ANIM file format, which gltf_tool writes next to a mesh for the glTF's animations:
+//
+// magic "ANM2" (or "ANIM" for version 1, which has no skeleton, and whose clips end after their
+// events)
+// n_joints u16 (0 if the animations don't move a skeleton, and then no root or joints follow)
+// root [f32; 16] (column major, the transform of whatever's above the root joints)
+// for each joint, in the order a skinned mesh's SkinVertex joints count them:
+//     name string
+//     parent u16 (which joint it hangs off, or 0xffff for a root)
+//     inverse_bind [f32; 16] (column major, from the mesh into the joint, as the mesh was skinned)
+//     translation [f32; 3] (its rest pose, relative to its parent)
+//     rotation [f32; 4] (a quaternion, x y z w)
+//     scale [f32; 3]
+// n_clips u32
+// for each clip:
+//     name string
+//     duration f32 (seconds)
+//     n_events u32
+//     for each event, in order of time:
+//         time f32 (seconds since the start of the clip)
+//         name string
+//     n_channels u32
+//     for each channel:
+//         joint u16
+//         property u8 (0 translation, 1 rotation, 2 scale)
+//         n_keys u32 (at least 1)
+//         for each key, in order of time:
+//             time f32
+//             value [f32; 3] ([f32; 4] for a rotation), linearly interpolated between keys
+//
+// and the PACK file format, which bundles lots of small files into one so that loading all of them
+// takes a single open:
+//
+// magic "PACK"
+// n_files u32
+// for each file, sorted by name:
+//     name string (its path in the directory that was packed, with / between components)
+//     offset u32 (from the start of the pack)
+//     stored_size u32
+//     size u32 (once it's decompressed)
+//     compression u8 (0 stored as-is, 1 lz)
+// then the files' data, stored_size bytes at each offset
+//
+// lz is LZSS with a 4KiB window. a flag byte's bits, from the top, say whether each of the next
+// eight things is a literal byte (0) or a match (1). a match is two bytes, big endian, with its
+// length - 3 in the top 4 bits and how far back it starts - 1 in the other 12
+//
+// where a string is a u16 length followed by that many bytes of UTF-8
+//
+// and the frame capture format, text the engine writes to show what one frame asked the renderer
+// for, which mesh_viewer can replay. after a first line of "MM3DS FRAME 1", each line is a keyword
+// and then its values, separated by spaces:
+//
+// state name value (anything about how the frame was set up, just for reading: the targets, fog,
+//     the render graph and so on. the name has no spaces and the value is the rest of the line)
+// projection [f32; 16] (column major, as the top screen had it)
+// view [f32; 16]
+// mesh id color [f32; 4]
+// vertex pos [f32; 3] uv [f32; 2] normal [f32; 3] (of the mesh before it)
+// indices [u16] (of the mesh before it, or left out to draw its vertices in order)
+// draw mesh_id model [f32; 16]
+// set name value (like state, for the draw before it: its program, material, texenv and so on)
+use std::io;
+use std::io::{Read, Write};
+
+pub const MAGIC: [u8; 4] = *b"MSH8";
+pub const MAGIC_V7: [u8; 4] = *b"MSH7";
+pub const MAGIC_V6: [u8; 4] = *b"MSH6";
+pub const MAGIC_V5: [u8; 4] = *b"MSH5";
+pub const MAGIC_V4: [u8; 4] = *b"MSH4";
+pub const MAGIC_V3: [u8; 4] = *b"MSH3";
+pub const MAGIC_V2: [u8; 4] = *b"MSH2";
+pub const MAGIC_V1: [u8; 4] = *b"MESH";
+pub const ANIM_MAGIC: [u8; 4] = *b"ANM2";
+pub const ANIM_MAGIC_V1: [u8; 4] = *b"ANIM";
+pub const PACK_MAGIC: [u8; 4] = *b"PACK";
+pub const FRAME_MAGIC: &str = "MM3DS FRAME 1";
+
+// sanity limits, so a corrupt file gets rejected instead of asking for gigabytes of memory. indices
+// are u16, so there's no point in having more vertices than they can address
+pub const MAX_MESHES: u32 = 4096;
+pub const MAX_VERTICES: u32 = u16::MAX as u32 + 1;
+pub const MAX_INDICES: u32 = 1 << 20;
+pub const MAX_TEXTURE_SIZE: u32 = 8 << 20; // a 1024x1024 RGBA8 texture with mipmaps fits
+pub const MAX_CLIPS: u32 = 1024;
+pub const MAX_EVENTS: u32 = 4096;
+pub const MAX_JOINTS: u32 = u8::MAX as u32 + 1; // what a SkinVertex can point at
+pub const MAX_CHANNELS: u32 = 4096;
+pub const MAX_KEYS: u32 = 1 << 16;
+pub const MAX_NODES: u32 = 4096;
+pub const MAX_PACK_FILES: u32 = 1 << 16;
+pub const MAX_PACK_FILE_SIZE: u32 = 32 << 20;
+
+// the cutout the engine tested every mesh against before files had alpha modes, which files from
+// before then get instead
+pub const LEGACY_ALPHA_CUTOFF: f32 = 17. / 255.;
+
+// this is also the layout of the vertex buffer on the GPU
+//
+// position [f32; 3]
+// uv [f32; 2]
+// normal [f32; 3]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[repr(C)]
+pub struct Vertex {
+    pub pos: [f32; 3],
+    pub uv: [f32; 2],
+    pub normal: [f32; 3],
+}
+
+impl Vertex {
+    pub fn read(mut reader: impl Read) -> io::Result<Self> {
+        Ok(Self {
+            pos: reader.read_f32s()?,
+            uv: reader.read_f32s()?,
+            normal: reader.read_f32s()?,
+        })
+    }
+
+    pub fn write(&self, mut writer: impl Write) -> io::Result<()> {
+        writer.write_f32s(self.pos)?;
+        writer.write_f32s(self.uv)?;
+        writer.write_f32s(self.normal)
+    }
+}
+
+// which bones move a vertex of a skinned mesh, and by how much. weights should add up to 1, and
+// unused slots can have any bone with a weight of 0. this is also the layout of a skinned mesh's
+// second vertex buffer on the GPU
+//
+// joints [u8; 4]
+// weights [f32; 4]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[repr(C)]
+pub struct SkinVertex {
+    pub joints: [u8; 4],
+    pub weights: [f32; 4],
+}
+
+impl SkinVertex {
+    pub fn read(mut reader: impl Read) -> io::Result<Self> {
+        let mut joints = [0; 4];
+        reader.read_exact(&mut joints)?;
+        Ok(Self { joints, weights: reader.read_f32s()? })
+    }
+
+    pub fn write(&self, mut writer: impl Write) -> io::Result<()> {
+        writer.write_all(&self.joints)?;
+        writer.write_f32s(self.weights)
+    }
+}
+
+const LAYOUT_COLORS: u8 = 1 << 0;
+const LAYOUT_SKIN: u8 = 1 << 1;
+
+// an animated texture: an atlas of equally sized frames, read left to right and then top to
+// bottom. the mesh's UVs cover a single frame
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Flipbook {
+    pub frames: u16,
+    pub columns: u16,
+    pub rows: u16,
+    pub fps: f32,
+}
+
+impl Flipbook {
+    // the part of the texture `frame` covers, as (uv scale, uv offset)
+    pub fn frame_uv(&self, frame: u16) -> ([f32; 2], [f32; 2]) {
+        let frame = frame % self.frames;
+        let (column, row) = (frame % self.columns, frame / self.columns);
+        let scale = [1. / self.columns as f32, 1. / self.rows as f32];
+
+        // v goes up, rows go down
+        (scale, [column as f32 * scale[0], 1. - (row + 1) as f32 * scale[1]])
+    }
+}
+
+// light baked into a texture by gltf_tool, laid over the mesh with its own set of UVs (one per
+// vertex) so every surface gets its own part of the texture
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lightmap {
+    pub texture: Vec<u8>, // t3x file
+    pub uvs: Vec<[f32; 2]>,
+}
+
+// how a mesh's alpha (from its color and texture) is used, like glTF's alphaMode
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum AlphaMode {
+    #[default]
+    Opaque, // ignored
+    Mask(f32), // fully transparent below the cutoff, opaque from it up
+    Blend,
+}
+
+// one mesh of a MESH file, as plain data. the engine uploads this to the GPU
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MeshData {
+    pub color: [f32; 4],
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u16>,
+    pub texture: Option<Vec<u8>>, // t3x file
+    pub flipbook: Option<Flipbook>,
+    pub lightmap: Option<Lightmap>,
+    pub alpha: AlphaMode,
+    pub colors: Option<Vec<[f32; 4]>>, // one per vertex
+    pub skin: Option<Vec<SkinVertex>>, // one per vertex
+    pub double_sided: bool,
+    pub emission: [f32; 3],
+    pub emission_map: Option<Vec<u8>>, // t3x file
+}
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn check_limit(what: &str, n: u32, max: u32) -> io::Result<()> {
+    if n > max {
+        return Err(invalid_data(format!("{what} is {n}, but at most {max} is allowed")));
+    }
+
+    Ok(())
+}
+
+impl MeshData {
+    pub fn read(reader: impl Read) -> io::Result<Self> {
+        Self::read_version(reader, 8)
+    }
+
+    fn read_version(mut reader: impl Read, version: u32) -> io::Result<Self> {
+        let color = reader.read_f32s()?;
+
+        let n_vertices = reader.read_u32()?;
+        check_limit("vertex count", n_vertices, MAX_VERTICES)?;
+        let mut vertices = Vec::with_capacity(n_vertices as usize);
+        for _ in 0..n_vertices {
+            vertices.push(Vertex::read(&mut reader)?);
+        }
+
+        let n_indices = reader.read_u32()?;
+        check_limit("index count", n_indices, MAX_INDICES)?;
+        if n_indices % 3 != 0 {
+            return Err(invalid_data(format!("index count {n_indices} isn't a multiple of 3")));
+        }
+
+        let mut indices = Vec::with_capacity(n_indices as usize);
+        for i in 0..n_indices {
+            let index = reader.read_u16()?;
+            if index as u32 >= n_vertices {
+                return Err(invalid_data(format!("index {i} is {index}, but there are only {n_vertices} vertices")));
+            }
+
+            indices.push(index);
+        }
+
+        let texture = read_texture(&mut reader, "texture size")?;
+
+        let flipbook_frames = if version >= 2 { reader.read_u16()? } else { 0 };
+        let flipbook = if flipbook_frames != 0 {
+            let flipbook = Flipbook {
+                frames: flipbook_frames,
+                columns: reader.read_u16()?,
+                rows: reader.read_u16()?,
+                fps: reader.read_f32()?,
+            };
+
+            if texture.is_none() {
+                return Err(invalid_data("flipbook without a texture".to_owned()));
+            }
+            if flipbook.frames as u32 > flipbook.columns as u32 * flipbook.rows as u32 {
+                return Err(invalid_data(format!(
+                    "flipbook has {} frames, but only {}x{} fit in the texture",
+                    flipbook.frames, flipbook.columns, flipbook.rows,
+                )));
+            }
+            if !(flipbook.fps.is_finite() && flipbook.fps > 0.) {
+                return Err(invalid_data(format!("flipbook fps is {}", flipbook.fps)));
+            }
+
+            Some(flipbook)
+        } else { None };
+
+        let lightmap = if version >= 3 { read_texture(&mut reader, "lightmap size")? } else { None };
+        let lightmap = match lightmap {
+            Some(texture) => {
+                let mut uvs = Vec::with_capacity(n_vertices as usize);
+                for _ in 0..n_vertices {
+                    uvs.push(reader.read_f32s()?);
+                }
+
+                Some(Lightmap { texture, uvs })
+            }
+            None => None,
+        };
+
+        let alpha = if version >= 4 {
+            match reader.read_u8()? {
+                0 => AlphaMode::Opaque,
+                1 => {
+                    let cutoff = reader.read_f32()?;
+                    if !(0. ..=1.).contains(&cutoff) {
+                        return Err(invalid_data(format!("alpha cutoff is {cutoff}")));
+                    }
+
+                    AlphaMode::Mask(cutoff)
+                }
+                2 => AlphaMode::Blend,
+                mode => return Err(invalid_data(format!("unknown alpha mode {mode}"))),
+            }
+        } else { AlphaMode::Mask(LEGACY_ALPHA_CUTOFF) };
+
+        let layout = if version >= 5 { reader.read_u8()? } else { 0 };
+        if layout & !(LAYOUT_COLORS | LAYOUT_SKIN) != 0 {
+            return Err(invalid_data(format!("unknown vertex layout {layout:#x}")));
+        }
+        if layout == (LAYOUT_COLORS | LAYOUT_SKIN) || (layout != 0 && lightmap.is_some()) {
+            return Err(invalid_data("a mesh can only have one of colors, skin and a lightmap".to_owned()));
+        }
+
+        let colors = if layout & LAYOUT_COLORS != 0 {
+            (0..n_vertices).map(|_| reader.read_f32s()).collect::<io::Result<_>>().map(Some)?
+        } else { None };
+        let skin = if layout & LAYOUT_SKIN != 0 {
+            (0..n_vertices).map(|_| SkinVertex::read(&mut reader)).collect::<io::Result<_>>().map(Some)?
+        } else { None };
+
+        let double_sided = if version >= 6 {
+            match reader.read_u8()? {
+                0 => false,
+                1 => true,
+                n => return Err(invalid_data(format!("double_sided is {n}"))),
+            }
+        } else { true };
+
+        let (emission, emission_map) = if version >= 7 {
+            (reader.read_f32s()?, read_texture(&mut reader, "emission map size")?)
+        } else { ([0.; 3], None) };
+
+        Ok(Self { color, vertices, indices, texture, flipbook, lightmap, alpha, colors, skin, double_sided, emission, emission_map })
+    }
+
+    pub fn write(&self, mut writer: impl Write) -> io::Result<()> {
+        writer.write_f32s(self.color)?;
+
+        writer.write_u32(len_u32(self.vertices.len())?)?;
+        for vertex in &self.vertices {
+            vertex.write(&mut writer)?;
+        }
+
+        writer.write_u32(len_u32(self.indices.len())?)?;
+        for &index in &self.indices {
+            writer.write_u16(index)?;
+        }
+
+        if let Some(texture) = &self.texture {
+            writer.write_u32(len_u32(texture.len())?)?;
+            writer.write_all(texture)?;
+        } else {
+            writer.write_u32(0)?; // empty texture
+        }
+
+        if let Some(flipbook) = &self.flipbook {
+            writer.write_u16(flipbook.frames)?;
+            writer.write_u16(flipbook.columns)?;
+            writer.write_u16(flipbook.rows)?;
+            writer.write_f32(flipbook.fps)?;
+        } else {
+            writer.write_u16(0)?; // not a flipbook
+        }
+
+        if let Some(lightmap) = &self.lightmap {
+            if lightmap.uvs.len() != self.vertices.len() {
+                return Err(io::Error::other("a lightmap needs a uv for every vertex"));
+            }
+
+            writer.write_u32(len_u32(lightmap.texture.len())?)?;
+            writer.write_all(&lightmap.texture)?;
+            for &uv in &lightmap.uvs {
+                writer.write_f32s(uv)?;
+            }
+        } else {
+            writer.write_u32(0)?; // no lightmap
+        }
+
+        match self.alpha {
+            AlphaMode::Opaque => writer.write_u8(0)?,
+            AlphaMode::Mask(cutoff) => {
+                writer.write_u8(1)?;
+                writer.write_f32(cutoff)?;
+            }
+            AlphaMode::Blend => writer.write_u8(2)?,
+        }
+
+        let extras = [self.colors.is_some(), self.skin.is_some(), self.lightmap.is_some()];
+        if extras.iter().filter(|&&extra| extra).count() > 1 {
+            return Err(io::Error::other("a mesh can only have one of colors, skin and a lightmap"));
+        }
+        let mut layout = 0;
+        if let Some(colors) = &self.colors {
+            if colors.len() != self.vertices.len() {
+                return Err(io::Error::other("colors need one for every vertex"));
+            }
+            layout |= LAYOUT_COLORS;
+        }
+        if let Some(skin) = &self.skin {
+            if skin.len() != self.vertices.len() {
+                return Err(io::Error::other("skin needs a SkinVertex for every vertex"));
+            }
+            layout |= LAYOUT_SKIN;
+        }
+
+        writer.write_u8(layout)?;
+        for &color in self.colors.iter().flatten() {
+            writer.write_f32s(color)?;
+        }
+        for skin in self.skin.iter().flatten() {
+            skin.write(&mut writer)?;
+        }
+        writer.write_u8(self.double_sided as u8)?;
+
+        writer.write_f32s(self.emission)?;
+        if let Some(emission_map) = &self.emission_map {
+            writer.write_u32(len_u32(emission_map.len())?)?;
+            writer.write_all(emission_map)?;
+        } else {
+            writer.write_u32(0)?; // no emission map
+        }
+
+        Ok(())
+    }
+}
+
+// a t3x file prefixed with its size, or None if the size is 0
+fn read_texture(mut reader: impl Read, what: &str) -> io::Result<Option<Vec<u8>>> {
+    let size = reader.read_u32()?;
+    check_limit(what, size, MAX_TEXTURE_SIZE)?;
+    if size == 0 {
+        return Ok(None);
+    }
+
+    let mut buf = vec![0u8; size as usize];
+    reader.read_exact(&mut buf)?;
+    Ok(Some(buf))
+}
+
+fn len_u32(len: usize) -> io::Result<u32> {
+    u32::try_from(len).map_err(|_| io::Error::other("too many elements for a MESH file"))
+}
+
+// one node of the scene a MESH file was converted from, which puts some of its meshes somewhere
+// relative to its parent
+#[derive(Clone, Debug, PartialEq)]
+pub struct SceneNode {
+    pub name: String,
+    pub parent: Option<u16>, // always a node before this one
+    pub transform: [f32; 16], // column major
+    pub meshes: Vec<u16>,
+}
+
+impl SceneNode {
+    // node `i` of a file with `n_meshes`
+    fn read(mut reader: impl Read, i: u32, n_meshes: u32) -> io::Result<Self> {
+        let name = reader.read_string()?;
+        let parent = match reader.read_u16()? {
+            u16::MAX => None,
+            parent if (parent as u32) < i => Some(parent),
+            parent => return Err(invalid_data(format!("its parent is {parent}, which doesn't come before it"))),
+        };
+        let transform = reader.read_f32s()?;
+
+        let n = reader.read_u16()?;
+        let mut meshes = Vec::with_capacity(n as usize);
+        for _ in 0..n {
+            let mesh = reader.read_u16()?;
+            if mesh as u32 >= n_meshes {
+                return Err(invalid_data(format!("it draws mesh {mesh}, but there are only {n_meshes} meshes")));
+            }
+            meshes.push(mesh);
+        }
+
+        Ok(Self { name, parent, transform, meshes })
+    }
+
+    fn write(&self, mut writer: impl Write) -> io::Result<()> {
+        writer.write_string(&self.name)?;
+        writer.write_u16(self.parent.unwrap_or(u16::MAX))?;
+        writer.write_f32s(self.transform)?;
+        writer.write_u16(u16::try_from(self.meshes.len()).map_err(|_| io::Error::other("too many meshes on one node"))?)?;
+        for &mesh in &self.meshes {
+            writer.write_u16(mesh)?;
+        }
+
+        Ok(())
+    }
+}
+
+// what's in a MESH file: the meshes, each in its own space, and the nodes that put them in the
+// scene
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SceneData {
+    pub meshes: Vec<MeshData>,
+    pub nodes: Vec<SceneNode>, // empty if the meshes are where they're drawn
+}
+
+// just the meshes of a MESH file, leaving out where its nodes put them
+pub fn read_mesh_file(reader: impl Read) -> io::Result<Vec<MeshData>> {
+    Ok(read_scene_file(reader)?.meshes)
+}
+
+pub fn read_scene_file(mut reader: impl Read) -> io::Result<SceneData> {
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+
+    let version = match magic {
+        MAGIC => 8,
+        MAGIC_V7 => 7,
+        MAGIC_V6 => 6,
+        MAGIC_V5 => 5,
+        MAGIC_V4 => 4,
+        MAGIC_V3 => 3,
+        MAGIC_V2 => 2,
+        MAGIC_V1 => 1,
+        _ => return Err(invalid_data(format!("invalid mesh file (magic is {magic:?}, expected {MAGIC:?})"))),
+    };
+
+    let n_meshes = reader.read_u32()?;
+    check_limit("mesh count", n_meshes, MAX_MESHES)?;
+
+    let mut ret = Vec::with_capacity(n_meshes as usize);
+    for i in 0..n_meshes {
+        let mesh = MeshData::read_version(&mut reader, version)
+            .map_err(|e| io::Error::new(e.kind(), format!("mesh {i}: {e}")))?;
+        ret.push(mesh);
+    }
+
+    let n_nodes = if version >= 8 { reader.read_u32()? } else { 0 };
+    check_limit("node count", n_nodes, MAX_NODES)?;
+    let mut nodes = Vec::with_capacity(n_nodes as usize);
+    for i in 0..n_nodes {
+        let node = SceneNode::read(&mut reader, i, n_meshes)
+            .map_err(|e| io::Error::new(e.kind(), format!("node {i}: {e}")))?;
+        nodes.push(node);
+    }
+
+    Ok(SceneData { meshes: ret, nodes })
+}
+
+// a file of meshes without any nodes, so they're drawn where they are
+pub fn write_mesh_file(writer: impl Write, meshes: &[MeshData]) -> io::Result<()> {
+    write_file(writer, meshes, &[])
+}
+
+pub fn write_scene_file(writer: impl Write, scene: &SceneData) -> io::Result<()> {
+    write_file(writer, &scene.meshes, &scene.nodes)
+}
+
+fn write_file(mut writer: impl Write, meshes: &[MeshData], nodes: &[SceneNode]) -> io::Result<()> {
+    writer.write_all(&MAGIC)?;
+    writer.write_u32(len_u32(meshes.len())?)?;
+    for mesh in meshes {
+        mesh.write(&mut writer)?;
+    }
+
+    writer.write_u32(len_u32(nodes.len())?)?;
+    for (i, node) in nodes.iter().enumerate() {
+        if node.parent.is_some_and(|parent| parent as usize >= i) {
+            return Err(io::Error::other(format!("node {i} comes before its parent")));
+        }
+        if node.meshes.iter().any(|&mesh| mesh as usize >= meshes.len()) {
+            return Err(io::Error::other(format!("node {i} draws a mesh that isn't in the file")));
+        }
+        node.write(&mut writer)?;
+    }
+
+    Ok(())
+}
+
+// something that should happen at a point in a clip, e.g. a footstep sound
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClipEvent {
+    pub time: f32,
+    pub name: String,
+}
+
+// one bone of a skeleton
+#[derive(Clone, Debug, PartialEq)]
+pub struct Joint {
+    pub name: String,
+    pub parent: Option<u16>,
+    pub inverse_bind: [f32; 16],
+    pub translation: [f32; 3],
+    pub rotation: [f32; 4],
+    pub scale: [f32; 3],
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SkeletonData {
+    pub root: [f32; 16],
+    pub joints: Vec<Joint>,
+}
+
+impl SkeletonData {
+    // a skeleton of `n_joints`, or None for 0
+    fn read(mut reader: impl Read, n_joints: u16) -> io::Result<Option<Self>> {
+        if n_joints == 0 {
+            return Ok(None);
+        }
+        check_limit("joint count", n_joints as u32, MAX_JOINTS)?;
+
+        let root = reader.read_f32s()?;
+        let mut joints = Vec::with_capacity(n_joints as usize);
+        for i in 0..n_joints {
+            let name = reader.read_string()?;
+            let parent = match reader.read_u16()? {
+                u16::MAX => None,
+                parent if parent < n_joints => Some(parent),
+                parent => return Err(invalid_data(format!("joint {i}'s parent is {parent}, but there are only {n_joints} joints"))),
+            };
+            joints.push(Joint {
+                name,
+                parent,
+                inverse_bind: reader.read_f32s()?,
+                translation: reader.read_f32s()?,
+                rotation: reader.read_f32s()?,
+                scale: reader.read_f32s()?,
+            });
+        }
+
+        // every joint has to get to a root, or there'd be no way to pose it
+        for i in 0..joints.len() {
+            let mut joint = i;
+            for _ in 0..=joints.len() {
+                match joints[joint].parent {
+                    Some(parent) => joint = parent as usize,
+                    None => break,
+                }
+            }
+            if joints[joint].parent.is_some() {
+                return Err(invalid_data(format!("joint {i} is its own ancestor")));
+            }
+        }
+
+        Ok(Some(Self { root, joints }))
+    }
+
+    fn write(&self, mut writer: impl Write) -> io::Result<()> {
+        writer.write_f32s(self.root)?;
+        for joint in &self.joints {
+            writer.write_string(&joint.name)?;
+            writer.write_u16(joint.parent.unwrap_or(u16::MAX))?;
+            writer.write_f32s(joint.inverse_bind)?;
+            writer.write_f32s(joint.translation)?;
+            writer.write_f32s(joint.rotation)?;
+            writer.write_f32s(joint.scale)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Property {
+    Translation,
+    Rotation,
+    Scale,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Keyframe {
+    pub time: f32,
+    pub value: [f32; 4], // w is 0 for translations and scales
+}
+
+// how one property of one joint changes over a clip
+#[derive(Clone, Debug, PartialEq)]
+pub struct Channel {
+    pub joint: u16,
+    pub property: Property,
+    pub keys: Vec<Keyframe>,
+}
+
+impl Channel {
+    fn read(mut reader: impl Read, duration: f32, n_joints: u16) -> io::Result<Self> {
+        let joint = reader.read_u16()?;
+        if joint >= n_joints {
+            return Err(invalid_data(format!("it moves joint {joint}, but there are only {n_joints} joints")));
+        }
+        let property = match reader.read_u8()? {
+            0 => Property::Translation,
+            1 => Property::Rotation,
+            2 => Property::Scale,
+            n => return Err(invalid_data(format!("property is {n}"))),
+        };
+
+        let n_keys = reader.read_u32()?;
+        check_limit("key count", n_keys, MAX_KEYS)?;
+        if n_keys == 0 {
+            return Err(invalid_data("it has no keys".to_owned()));
+        }
+        let mut keys: Vec<Keyframe> = Vec::with_capacity(n_keys as usize);
+        for i in 0..n_keys {
+            let time = reader.read_f32()?;
+            if !(0. ..=duration).contains(&time) {
+                return Err(invalid_data(format!("key {i} is at {time}s, but the clip is {duration}s long")));
+            }
+            if keys.last().is_some_and(|last| last.time > time) {
+                return Err(invalid_data(format!("key {i} is before the one before it")));
+            }
+
+            let value = match property {
+                Property::Rotation => reader.read_f32s()?,
+                _ => {
+                    let [x, y, z] = reader.read_f32s()?;
+                    [x, y, z, 0.]
+                }
+            };
+            keys.push(Keyframe { time, value });
+        }
+
+        Ok(Self { joint, property, keys })
+    }
+
+    fn write(&self, mut writer: impl Write) -> io::Result<()> {
+        writer.write_u16(self.joint)?;
+        writer.write_u8(self.property as u8)?;
+        writer.write_u32(len_u32(self.keys.len())?)?;
+        for key in &self.keys {
+            writer.write_f32(key.time)?;
+            match self.property {
+                Property::Rotation => writer.write_f32s(key.value)?,
+                _ => writer.write_f32s([key.value[0], key.value[1], key.value[2]])?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// one animation of an ANIM file
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Clip {
+    pub name: String,
+    pub duration: f32,
+    pub events: Vec<ClipEvent>,
+    pub channels: Vec<Channel>,
+}
+
+impl Clip {
+    // one from a file whose skeleton has `n_joints`
+    pub fn read(reader: impl Read, n_joints: u16) -> io::Result<Self> {
+        Self::read_version(reader, 2, n_joints)
+    }
+
+    fn read_version(mut reader: impl Read, version: u32, n_joints: u16) -> io::Result<Self> {
+        let name = reader.read_string()?;
+        let duration = reader.read_f32()?;
+        if !(duration.is_finite() && duration >= 0.) {
+            return Err(invalid_data(format!("duration is {duration}")));
+        }
+
+        let n_events = reader.read_u32()?;
+        check_limit("event count", n_events, MAX_EVENTS)?;
+        let mut events: Vec<ClipEvent> = Vec::with_capacity(n_events as usize);
+        for i in 0..n_events {
+            let time = reader.read_f32()?;
+            if !(0. ..=duration).contains(&time) {
+                return Err(invalid_data(format!("event {i} is at {time}s, but the clip is {duration}s long")));
+            }
+            if events.last().is_some_and(|last| last.time > time) {
+                return Err(invalid_data(format!("event {i} is before the one before it")));
+            }
+
+            events.push(ClipEvent { time, name: reader.read_string()? });
+        }
+
+        let n_channels = if version >= 2 { reader.read_u32()? } else { 0 };
+        check_limit("channel count", n_channels, MAX_CHANNELS)?;
+        let mut channels = Vec::with_capacity(n_channels as usize);
+        for i in 0..n_channels {
+            let channel = Channel::read(&mut reader, duration, n_joints)
+                .map_err(|e| io::Error::new(e.kind(), format!("channel {i}: {e}")))?;
+            channels.push(channel);
+        }
+
+        Ok(Self { name, duration, events, channels })
+    }
+
+    pub fn write(&self, mut writer: impl Write) -> io::Result<()> {
+        writer.write_string(&self.name)?;
+        writer.write_f32(self.duration)?;
+        writer.write_u32(len_u32(self.events.len())?)?;
+        for event in &self.events {
+            writer.write_f32(event.time)?;
+            writer.write_string(&event.name)?;
+        }
+        writer.write_u32(len_u32(self.channels.len())?)?;
+        for channel in &self.channels {
+            channel.write(&mut writer)?;
+        }
+
+        Ok(())
+    }
+}
+
+// what's in an ANIM file
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AnimData {
+    pub skeleton: Option<SkeletonData>,
+    pub clips: Vec<Clip>,
+}
+
+pub fn read_anim_file(mut reader: impl Read) -> io::Result<AnimData> {
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    let version = match magic {
+        ANIM_MAGIC => 2,
+        ANIM_MAGIC_V1 => 1,
+        _ => return Err(invalid_data(format!("invalid anim file (magic is {magic:?}, expected {ANIM_MAGIC:?})"))),
+    };
+
+    let n_joints = if version >= 2 { reader.read_u16()? } else { 0 };
+    let skeleton = SkeletonData::read(&mut reader, n_joints).map_err(|e| io::Error::new(e.kind(), format!("skeleton: {e}")))?;
+
+    let n_clips = reader.read_u32()?;
+    check_limit("clip count", n_clips, MAX_CLIPS)?;
+
+    let mut clips = Vec::with_capacity(n_clips as usize);
+    for i in 0..n_clips {
+        let clip = Clip::read_version(&mut reader, version, n_joints)
+            .map_err(|e| io::Error::new(e.kind(), format!("clip {i}: {e}")))?;
+        clips.push(clip);
+    }
+
+    Ok(AnimData { skeleton, clips })
+}
+
+pub fn write_anim_file(mut writer: impl Write, anim: &AnimData) -> io::Result<()> {
+    writer.write_all(&ANIM_MAGIC)?;
+    let joints = anim.skeleton.as_ref().map_or(&[][..], |skeleton| &skeleton.joints);
+    if joints.len() > MAX_JOINTS as usize {
+        return Err(io::Error::other(format!("{} joints, the limit is {MAX_JOINTS}", joints.len())));
+    }
+    writer.write_u16(joints.len() as u16)?;
+    if let Some(skeleton) = anim.skeleton.as_ref().filter(|skeleton| !skeleton.joints.is_empty()) {
+        skeleton.write(&mut writer)?;
+    }
+
+    writer.write_u32(len_u32(anim.clips.len())?)?;
+    for clip in &anim.clips {
+        clip.write(&mut writer)?;
+    }
+
+    Ok(())
+}
+
+const LZ_WINDOW: usize = 4096;
+const LZ_MIN_MATCH: usize = 3;
+const LZ_MAX_MATCH: usize = 18;
+const LZ_HASH_SIZE: usize = 1 << 12;
+// how many earlier places with the same first bytes get tried for each match. more finds longer
+// matches, slower
+const LZ_MAX_CHAIN: usize = 64;
+
+fn lz_hash(bytes: &[u8]) -> usize {
+    (((bytes[0] as usize) << 8) ^ ((bytes[1] as usize) << 4) ^ bytes[2] as usize) & (LZ_HASH_SIZE - 1)
+}
+
+pub fn lz_compress(data: &[u8]) -> Vec<u8> {
+    let mut ret = Vec::with_capacity(data.len() / 2);
+
+    // for each hash, the last place it was seen, and for each place, the one before it with the
+    // same hash
+    let mut head = vec![usize::MAX; LZ_HASH_SIZE];
+    let mut prev = vec![usize::MAX; data.len()];
+
+    let mut flags_at = 0;
+    let mut n_tokens = 8;
+    let mut pos = 0;
+    while pos < data.len() {
+        if n_tokens == 8 {
+            flags_at = ret.len();
+            ret.push(0);
+            n_tokens = 0;
+        }
+
+        let (mut best_len, mut best_distance) = (0, 0);
+        if pos + LZ_MIN_MATCH <= data.len() {
+            let max_len = LZ_MAX_MATCH.min(data.len() - pos);
+            let mut candidate = head[lz_hash(&data[pos..])];
+            let mut chain = 0;
+            while candidate != usize::MAX && pos - candidate <= LZ_WINDOW && chain < LZ_MAX_CHAIN {
+                // this can run past `pos`, which is fine: the decompressor copies a byte at a time
+                let len = data[candidate..].iter()
+                    .zip(&data[pos..pos + max_len])
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                if len > best_len {
+                    (best_len, best_distance) = (len, pos - candidate);
+                    if len == max_len {
+                        break;
+                    }
+                }
+                candidate = prev[candidate];
+                chain += 1;
+            }
+        }
+
+        let advance = if best_len >= LZ_MIN_MATCH {
+            ret[flags_at] |= 0x80 >> n_tokens;
+            let token = (((best_len - LZ_MIN_MATCH) << 12) | (best_distance - 1)) as u16;
+            ret.extend(token.to_be_bytes());
+            best_len
+        } else {
+            ret.push(data[pos]);
+            1
+        };
+        n_tokens += 1;
+
+        for p in pos..pos + advance {
+            if p + LZ_MIN_MATCH <= data.len() {
+                let hash = lz_hash(&data[p..]);
+                prev[p] = head[hash];
+                head[hash] = p;
+            }
+        }
+        pos += advance;
+    }
+
+    ret
+}
+
+// `size` is how big the data was before it was compressed
+pub fn lz_decompress(data: &[u8], size: usize) -> io::Result<Vec<u8>> {
+    let mut ret = Vec::with_capacity(size);
+    let mut bytes = data.iter().copied();
+    let mut next = || bytes.next().ok_or_else(|| invalid_data("lz data ends early".to_owned()));
+
+    while ret.len() < size {
+        let flags = next()?;
+        for bit in 0..8 {
+            if ret.len() == size {
+                break;
+            }
+
+            if flags & (0x80 >> bit) == 0 {
+                ret.push(next()?);
+                continue;
+            }
+
+            let token = u16::from_be_bytes([next()?, next()?]);
+            let len = (token >> 12) as usize + LZ_MIN_MATCH;
+            let distance = (token & 0xfff) as usize + 1;
+            if distance > ret.len() || ret.len() + len > size {
+                return Err(invalid_data(format!("lz match of {len} bytes from {distance} back at byte {}", ret.len())));
+            }
+            for _ in 0..len {
+                ret.push(ret[ret.len() - distance]);
+            }
+        }
+    }
+
+    Ok(ret)
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Lz,
+}
+
+// where a file is in a pack
+#[derive(Clone, Debug, PartialEq)]
+pub struct PackEntry {
+    pub name: String,
+    pub offset: u32,
+    pub stored_size: u32,
+    pub size: u32,
+    pub compression: Compression,
+}
+
+impl PackEntry {
+    fn read(mut reader: impl Read) -> io::Result<Self> {
+        let name = reader.read_string()?;
+        let offset = reader.read_u32()?;
+        let stored_size = reader.read_u32()?;
+        let size = reader.read_u32()?;
+        check_limit("stored size", stored_size, MAX_PACK_FILE_SIZE)?;
+        check_limit("size", size, MAX_PACK_FILE_SIZE)?;
+        let compression = match reader.read_u8()? {
+            0 if stored_size == size => Compression::None,
+            0 => return Err(invalid_data(format!("stored size is {stored_size}, but size is {size}"))),
+            1 => Compression::Lz,
+            n => return Err(invalid_data(format!("invalid compression {n}"))),
+        };
+
+        Ok(Self { name, offset, stored_size, size, compression })
+    }
+
+    fn write(&self, mut writer: impl Write) -> io::Result<()> {
+        writer.write_string(&self.name)?;
+        writer.write_u32(self.offset)?;
+        writer.write_u32(self.stored_size)?;
+        writer.write_u32(self.size)?;
+        writer.write_u8(match self.compression {
+            Compression::None => 0,
+            Compression::Lz => 1,
+        })
+    }
+
+    // the file, from the stored_size bytes at its offset
+    pub fn unpack(&self, stored: Vec<u8>) -> io::Result<Vec<u8>> {
+        if stored.len() != self.stored_size as usize {
+            return Err(invalid_data(format!("{}: expected {} bytes, got {}", self.name, self.stored_size, stored.len())));
+        }
+
+        match self.compression {
+            Compression::None => Ok(stored),
+            Compression::Lz => lz_decompress(&stored, self.size as usize)
+                .map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", self.name))),
+        }
+    }
+}
+
+// reads the index at the start of a pack, leaving the reader just past it
+pub fn read_pack_index(mut reader: impl Read) -> io::Result<Vec<PackEntry>> {
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if magic != PACK_MAGIC {
+        return Err(invalid_data(format!("invalid pack file (magic is {magic:?}, expected {PACK_MAGIC:?})")));
+    }
+
+    let n_files = reader.read_u32()?;
+    check_limit("file count", n_files, MAX_PACK_FILES)?;
+
+    let mut ret = Vec::with_capacity(n_files as usize);
+    for i in 0..n_files {
+        let entry = PackEntry::read(&mut reader)
+            .map_err(|e| io::Error::new(e.kind(), format!("file {i}: {e}")))?;
+        ret.push(entry);
+    }
+
+    Ok(ret)
+}
+
+// `files` are (name, contents). with Compression::Lz, a file that doesn't get any smaller is
+// stored as-is
+pub fn write_pack_file(mut writer: impl Write, files: &[(String, Vec<u8>)], compression: Compression) -> io::Result<()> {
+    if files.len() > MAX_PACK_FILES as usize {
+        return Err(io::Error::other(format!("{} files is too many for a pack", files.len())));
+    }
+    let mut files = files.iter().collect::<Vec<_>>();
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut entries = vec![];
+    let mut stored = vec![];
+    for (name, data) in files {
+        let size = u32::try_from(data.len()).ok()
+            .filter(|&size| size <= MAX_PACK_FILE_SIZE)
+            .ok_or_else(|| io::Error::other(format!("{name} is too big for a pack")))?;
+
+        let compressed = if compression == Compression::Lz { Some(lz_compress(data)) } else { None };
+        let (compression, data) = match compressed {
+            Some(compressed) if compressed.len() < data.len() => (Compression::Lz, compressed),
+            _ => (Compression::None, data.clone()),
+        };
+
+        entries.push(PackEntry { name: name.clone(), offset: 0, stored_size: data.len() as u32, size, compression });
+        stored.push(data);
+    }
+
+    // the data starts right after the index
+    let mut offset = 8 + entries.iter().map(|entry| 2 + entry.name.len() + 13).sum::<usize>();
+    for entry in &mut entries {
+        entry.offset = u32::try_from(offset).map_err(|_| io::Error::other("too much data for a pack"))?;
+        offset += entry.stored_size as usize;
+    }
+
+    writer.write_all(&PACK_MAGIC)?;
+    writer.write_u32(entries.len() as u32)?;
+    for entry in &entries {
+        entry.write(&mut writer)?;
+    }
+    for data in stored {
+        writer.write_all(&data)?;
+    }
+
+    Ok(())
+}
+
+// a mesh as a frame capture has it, with just enough to draw it again
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CapturedMesh {
+    pub id: u32, // the engine's MeshId
+    pub color: [f32; 4],
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u16>, // empty to draw the vertices in order
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CapturedDraw {
+    pub mesh: u32, // a CapturedMesh's id
+    pub model: [f32; 16],
+    pub state: Vec<(String, String)>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FrameCapture {
+    pub state: Vec<(String, String)>,
+    pub projection: [f32; 16],
+    pub view: [f32; 16],
+    pub meshes: Vec<CapturedMesh>,
+    pub draws: Vec<CapturedDraw>,
+}
+
+fn parse<T: std::str::FromStr>(word: &str) -> io::Result<T> {
+    word.parse().map_err(|_| invalid_data(format!("{word:?} isn't a number")))
+}
+
+fn parse_all<T: std::str::FromStr + Copy + Default, const N: usize>(text: &str) -> io::Result<[T; N]> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    if words.len() != N {
+        return Err(invalid_data(format!("expected {N} numbers, got {}", words.len())));
+    }
+
+    let mut ret = [T::default(); N];
+    for (x, word) in ret.iter_mut().zip(words) {
+        *x = parse(word)?;
+    }
+    Ok(ret)
+}
+
+fn name_value(text: &str) -> (String, String) {
+    let (name, value) = text.split_once(' ').unwrap_or((text, ""));
+    (name.to_owned(), value.to_owned())
+}
+
+fn write_name_value(mut writer: impl Write, keyword: &str, (name, value): &(String, String)) -> io::Result<()> {
+    if name.is_empty() || name.contains(char::is_whitespace) || value.contains('\n') {
+        return Err(io::Error::other(format!("{name:?} = {value:?} can't go on one line of a frame capture")));
+    }
+    writeln!(writer, "{keyword} {name} {value}")
+}
+
+fn join<T: std::fmt::Display>(values: &[T]) -> String {
+    values.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(" ")
+}
+
+impl FrameCapture {
+    pub fn read(mut reader: impl Read) -> io::Result<Self> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        let mut lines = text.lines().enumerate();
+        let first = lines.next().map_or("", |(_, line)| line);
+        if first != FRAME_MAGIC {
+            return Err(invalid_data(format!("invalid frame capture (starts {first:?}, expected {FRAME_MAGIC:?})")));
+        }
+
+        let mut ret = Self::default();
+        for (i, line) in lines {
+            ret.read_line(line).map_err(|e| io::Error::new(e.kind(), format!("line {}: {e}", i + 1)))?;
+        }
+
+        for mesh in &ret.meshes {
+            if let Some(&i) = mesh.indices.iter().find(|&&i| i as usize >= mesh.vertices.len()) {
+                return Err(invalid_data(format!("mesh {} has index {i}, but {} vertices", mesh.id, mesh.vertices.len())));
+            }
+        }
+        if let Some(draw) = ret.draws.iter().find(|draw| !ret.meshes.iter().any(|mesh| mesh.id == draw.mesh)) {
+            return Err(invalid_data(format!("a draw of mesh {}, which isn't in the capture", draw.mesh)));
+        }
+
+        Ok(ret)
+    }
+
+    fn read_line(&mut self, line: &str) -> io::Result<()> {
+        let (keyword, rest) = line.split_once(' ').unwrap_or((line, ""));
+        match keyword {
+            "" => {}
+            "state" => self.state.push(name_value(rest)),
+            "projection" => self.projection = parse_all(rest)?,
+            "view" => self.view = parse_all(rest)?,
+            "mesh" => {
+                let (id, color) = rest.split_once(' ').unwrap_or((rest, ""));
+                self.meshes.push(CapturedMesh { id: parse(id)?, color: parse_all(color)?, ..Default::default() });
+            }
+            "vertex" => {
+                let [x, y, z, u, v, nx, ny, nz] = parse_all(rest)?;
+                self.last_mesh(keyword)?.vertices.push(Vertex { pos: [x, y, z], uv: [u, v], normal: [nx, ny, nz] });
+            }
+            "indices" => {
+                self.last_mesh(keyword)?.indices = rest.split_whitespace().map(parse).collect::<io::Result<_>>()?;
+            }
+            "draw" => {
+                let (mesh, model) = rest.split_once(' ').unwrap_or((rest, ""));
+                self.draws.push(CapturedDraw { mesh: parse(mesh)?, model: parse_all(model)?, state: vec![] });
+            }
+            "set" => {
+                let draw = self.draws.last_mut().ok_or_else(|| invalid_data("set before any draw".to_owned()))?;
+                draw.state.push(name_value(rest));
+            }
+            _ => return Err(invalid_data(format!("unknown keyword {keyword:?}"))),
+        }
+
+        Ok(())
+    }
+
+    fn last_mesh(&mut self, keyword: &str) -> io::Result<&mut CapturedMesh> {
+        self.meshes.last_mut().ok_or_else(|| invalid_data(format!("{keyword} before any mesh")))
+    }
+
+    pub fn write(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "{FRAME_MAGIC}")?;
+        for entry in &self.state {
+            write_name_value(&mut writer, "state", entry)?;
+        }
+        writeln!(writer, "projection {}", join(&self.projection))?;
+        writeln!(writer, "view {}", join(&self.view))?;
+
+        for mesh in &self.meshes {
+            writeln!(writer, "mesh {} {}", mesh.id, join(&mesh.color))?;
+            for v in &mesh.vertices {
+                writeln!(writer, "vertex {} {} {}", join(&v.pos), join(&v.uv), join(&v.normal))?;
+            }
+            if !mesh.indices.is_empty() {
+                writeln!(writer, "indices {}", join(&mesh.indices))?;
+            }
+        }
+
+        for draw in &self.draws {
+            writeln!(writer, "draw {} {}", draw.mesh, join(&draw.model))?;
+            for entry in &draw.state {
+                write_name_value(&mut writer, "set", entry)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub trait ReadExt {
+    fn read_u8(&mut self) -> io::Result<u8>;
+    fn read_u16(&mut self) -> io::Result<u16>;
+    fn read_u32(&mut self) -> io::Result<u32>;
+    fn read_f32(&mut self) -> io::Result<f32>;
+    fn read_f32s<const N: usize>(&mut self) -> io::Result<[f32; N]>;
+    fn read_string(&mut self) -> io::Result<String>;
+}
+
+impl<T: Read> ReadExt for T {
+    fn read_u8(&mut self) -> io::Result<u8> {
+        let mut buf = [0u8; 1];
+        self.read_exact(&mut buf)?;
+        Ok(buf[0])
+    }
+
+    fn read_u16(&mut self) -> io::Result<u16> {
+        let mut buf = [0u8; 2];
+        self.read_exact(&mut buf)?;
+        Ok(u16::from_le_bytes(buf))
+    }
+
+    fn read_u32(&mut self) -> io::Result<u32> {
+        let mut buf = [0u8; 4];
+        self.read_exact(&mut buf)?;
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    fn read_f32(&mut self) -> io::Result<f32> {
+        let mut buf = [0u8; 4];
+        self.read_exact(&mut buf)?;
+        Ok(f32::from_le_bytes(buf))
+    }
+
+    fn read_f32s<const N: usize>(&mut self) -> io::Result<[f32; N]> {
+        let mut ret = [0.; N];
+        for f in &mut ret {
+            *f = self.read_f32()?;
+        }
+
+        Ok(ret)
+    }
+
+    fn read_string(&mut self) -> io::Result<String> {
+        let mut buf = vec![0u8; self.read_u16()? as usize];
+        self.read_exact(&mut buf)?;
+        String::from_utf8(buf).map_err(|e| invalid_data(e.to_string()))
+    }
+}
+
+pub trait WriteExt {
+    fn write_u8(&mut self, n: u8) -> io::Result<()>;
+    fn write_u16(&mut self, n: u16) -> io::Result<()>;
+    fn write_u32(&mut self, n: u32) -> io::Result<()>;
+    fn write_f32(&mut self, f: f32) -> io::Result<()>;
+    fn write_f32s<const N: usize>(&mut self, fs: [f32; N]) -> io::Result<()>;
+    fn write_string(&mut self, s: &str) -> io::Result<()>;
+}
+
+impl<T: Write> WriteExt for T {
+    fn write_u8(&mut self, n: u8) -> io::Result<()> {
+        self.write_all(&[n])
+    }
+
+    fn write_u16(&mut self, n: u16) -> io::Result<()> {
+        self.write_all(&n.to_le_bytes())
+    }
+
+    fn write_u32(&mut self, n: u32) -> io::Result<()> {
+        self.write_all(&n.to_le_bytes())
+    }
+
+    fn write_f32(&mut self, f: f32) -> io::Result<()> {
+        self.write_all(&f.to_le_bytes())
+    }
+
+    fn write_f32s<const N: usize>(&mut self, fs: [f32; N]) -> io::Result<()> {
+        for f in fs {
+            self.write_f32(f)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_string(&mut self, s: &str) -> io::Result<()> {
+        let len = u16::try_from(s.len()).map_err(|_| io::Error::other("string is longer than 65535 bytes"))?;
+        self.write_u16(len)?;
+        self.write_all(s.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn triangle() -> MeshData {
+        MeshData {
+            color: [1., 0.5, 0.25, 1.],
+            vertices: vec![
+                Vertex { pos: [0., 0., 0.], uv: [0., 0.], normal: [0., 0., 1.] },
+                Vertex { pos: [1., 0., 0.], uv: [1., 0.], normal: [0., 0., 1.] },
+                Vertex { pos: [0., 1., 0.], uv: [0., 1.], normal: [0., 0., 1.] },
+            ],
+            indices: vec![0, 1, 2],
+            texture: None,
+            flipbook: None,
+            lightmap: None,
+            alpha: AlphaMode::Opaque,
+            colors: None,
+            skin: None,
+            double_sided: false,
+            emission: [0.; 3],
+            emission_map: None,
+        }
+    }
+
+    fn flipbook() -> MeshData {
+        MeshData {
+            texture: Some(vec![1, 2, 3, 4, 5]),
+            flipbook: Some(Flipbook { frames: 6, columns: 4, rows: 2, fps: 12. }),
+            ..triangle()
+        }
+    }
+
+    fn lightmapped() -> MeshData {
+        MeshData {
+            lightmap: Some(Lightmap { texture: vec![6, 7, 8], uvs: vec![[0., 0.], [0.5, 0.], [0., 0.5]] }),
+            ..triangle()
+        }
+    }
+
+    fn colored() -> MeshData {
+        MeshData { colors: Some(vec![[1., 0., 0., 1.], [0., 1., 0., 1.], [0., 0., 1., 0.5]]), ..triangle() }
+    }
+
+    fn glowing() -> MeshData {
+        MeshData { emission: [0.5, 0.25, 0.], ..triangle() }
+    }
+
+    fn emission_mapped() -> MeshData {
+        MeshData { emission_map: Some(vec![9, 10, 11]), ..triangle() }
+    }
+
+    fn skinned() -> MeshData {
+        let skin = SkinVertex { joints: [0, 1, 0, 0], weights: [0.75, 0.25, 0., 0.] };
+        MeshData { skin: Some(vec![skin; 3]), ..triangle() }
+    }
+
+    // what a file from before double_sided reads as
+    fn two_sided(mesh: MeshData) -> MeshData {
+        MeshData { double_sided: true, ..mesh }
+    }
+
+    // and one from before alpha modes
+    fn legacy(mesh: MeshData) -> MeshData {
+        MeshData { alpha: AlphaMode::Mask(LEGACY_ALPHA_CUTOFF), ..two_sided(mesh) }
+    }
+
+    fn file(meshes: &[MeshData]) -> Vec<u8> {
+        let mut buf = vec![];
+        write_mesh_file(&mut buf, meshes).unwrap();
+        buf
+    }
+
+    #[test]
+    fn round_trip() {
+        let textured = MeshData { texture: Some(vec![1, 2, 3, 4, 5]), ..triangle() };
+        let cutout = MeshData { alpha: AlphaMode::Mask(0.5), ..triangle() };
+        let blended = MeshData { alpha: AlphaMode::Blend, ..triangle() };
+        let meshes = vec![triangle(), textured, flipbook(), lightmapped(), cutout, blended, colored(), skinned(), two_sided(triangle()), glowing(), emission_mapped()];
+
+        assert_eq!(read_mesh_file(&file(&meshes)[..]).unwrap(), meshes);
+    }
+
+    #[test]
+    fn checked_in_mesh() {
+        let meshes = read_mesh_file(&include_bytes!("../../engine/gfx/character.mesh")[..]).unwrap();
+
+        assert!(!meshes.is_empty());
+        for mesh in meshes {
+            assert!(mesh.indices.iter().all(|&i| (i as usize) < mesh.vertices.len()));
+        }
+    }
+
+    #[test]
+    fn empty_file() {
+        assert_eq!(read_mesh_file(&file(&[])[..]).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn layout() {
+        let buf = file(&[triangle()]);
+
+        // magic + n_meshes + color + n_vertices + vertices + n_indices + indices + size_of_tex +
+        // flipbook_frames + size_of_lightmap + alpha_mode + layout + double_sided + emission +
+        // size_of_emission_map + n_nodes
+        assert_eq!(buf.len(), 4 + 4 + 16 + 4 + 3 * 32 + 4 + 3 * 2 + 4 + 2 + 4 + 1 + 1 + 1 + 12 + 4 + 4);
+        assert_eq!(&buf[..4], b"MSH8");
+        assert_eq!(&buf[4..8], &1u32.to_le_bytes());
+        assert_eq!(&buf[8..12], &1f32.to_le_bytes());
+    }
+
+    #[test]
+    fn version_1() {
+        // a version 1 file is a version 8 file without the flipbook, lightmap, alpha, layout,
+        // double_sided and emission fields, or the nodes
+        let mut buf = file(&[triangle()]);
+        buf.truncate(buf.len() - 2 - 4 - 1 - 1 - 1 - 16 - 4);
+        buf[..4].copy_from_slice(b"MESH");
+
+        assert_eq!(read_mesh_file(&buf[..]).unwrap(), vec![legacy(triangle())]);
+    }
+
+    #[test]
+    fn version_2() {
+        // a version 2 file is one without the lightmap either
+        let mut buf = file(&[triangle()]);
+        buf.truncate(buf.len() - 4 - 1 - 1 - 1 - 16 - 4);
+        buf[..4].copy_from_slice(b"MSH2");
+
+        assert_eq!(read_mesh_file(&buf[..]).unwrap(), vec![legacy(triangle())]);
+    }
+
+    #[test]
+    fn version_3() {
+        // a version 3 file has everything but the alpha mode
+        let mut buf = file(&[triangle()]);
+        buf.truncate(buf.len() - 1 - 1 - 1 - 16 - 4);
+        buf[..4].copy_from_slice(b"MSH3");
+
+        assert_eq!(read_mesh_file(&buf[..]).unwrap(), vec![legacy(triangle())]);
+    }
+
+    #[test]
+    fn version_4() {
+        // a version 4 file everything but the layout
+        let mut buf = file(&[triangle()]);
+        buf.truncate(buf.len() - 1 - 1 - 16 - 4);
+        buf[..4].copy_from_slice(b"MSH4");
+
+        assert_eq!(read_mesh_file(&buf[..]).unwrap(), vec![two_sided(triangle())]);
+    }
+
+    #[test]
+    fn version_5() {
+        // a version 5 file everything but double_sided
+        let mut buf = file(&[colored()]);
+        buf.truncate(buf.len() - 1 - 16 - 4);
+        buf[..4].copy_from_slice(b"MSH5");
+
+        assert_eq!(read_mesh_file(&buf[..]).unwrap(), vec![two_sided(colored())]);
+    }
+
+    #[test]
+    fn version_6() {
+        // a version 6 file everything but the emission
+        let mut buf = file(&[triangle()]);
+        buf.truncate(buf.len() - 16 - 4);
+        buf[..4].copy_from_slice(b"MSH6");
+
+        assert_eq!(read_mesh_file(&buf[..]).unwrap(), vec![triangle()]);
+    }
+
+    #[test]
+    fn version_7() {
+        // and a version 7 file everything but the nodes
+        let mut buf = file(&[triangle()]);
+        buf.truncate(buf.len() - 4);
+        buf[..4].copy_from_slice(b"MSH7");
+
+        assert_eq!(read_scene_file(&buf[..]).unwrap(), SceneData { meshes: vec![triangle()], nodes: vec![] });
+    }
+
+    fn node(name: &str, parent: Option<u16>, meshes: Vec<u16>) -> SceneNode {
+        let mut transform = IDENTITY;
+        transform[12] = 2.; // moved along x
+        SceneNode { name: name.to_owned(), parent, transform, meshes }
+    }
+
+    fn scene_file(scene: &SceneData) -> Vec<u8> {
+        let mut buf = vec![];
+        write_scene_file(&mut buf, scene).unwrap();
+        buf
+    }
+
+    #[test]
+    fn nodes_round_trip() {
+        let scene = SceneData {
+            meshes: vec![triangle(), colored()],
+            nodes: vec![node("body", None, vec![0]), node("arm", Some(0), vec![1]), node("other arm", Some(0), vec![1]), node("empty", Some(2), vec![])],
+        };
+
+        assert_eq!(read_scene_file(&scene_file(&scene)[..]).unwrap(), scene);
+        assert_eq!(read_mesh_file(&scene_file(&scene)[..]).unwrap(), scene.meshes);
+    }
+
+    #[test]
+    fn bad_nodes() {
+        let scene = SceneData { meshes: vec![triangle()], nodes: vec![node("body", None, vec![0])] };
+        // the node's parent, after its name
+        let at = file(&[triangle()]).len() + 2 + 4;
+
+        let mut buf = scene_file(&scene);
+        buf[at..at + 2].copy_from_slice(&0u16.to_le_bytes());
+        assert_invalid(&buf, "node 0: its parent is 0");
+
+        // and its one mesh, after the transform and n_meshes
+        let mut buf = scene_file(&scene);
+        buf[at + 2 + 64 + 2..][..2].copy_from_slice(&1u16.to_le_bytes());
+        assert_invalid(&buf, "draws mesh 1");
+
+        let child_first = SceneData { nodes: vec![node("arm", Some(1), vec![]), node("body", None, vec![])], ..scene.clone() };
+        assert!(write_scene_file(&mut vec![], &child_first).is_err());
+        let missing_mesh = SceneData { nodes: vec![node("body", None, vec![1])], ..scene };
+        assert!(write_scene_file(&mut vec![], &missing_mesh).is_err());
+    }
+
+    #[test]
+    fn bad_double_sided() {
+        let mut buf = file(&[triangle()]);
+        let at = buf.len() - 4 - 17;
+        buf[at] = 2;
+        assert_invalid(&buf, "double_sided is 2");
+    }
+
+    #[test]
+    fn bad_layout() {
+        let mut buf = file(&[triangle()]);
+        let at = buf.len() - 4 - 18;
+        buf[at] = 4;
+        assert_invalid(&buf, "unknown vertex layout 0x4");
+
+        let mut buf = file(&[lightmapped()]);
+        let at = buf.len() - 4 - 18;
+        buf[at] = LAYOUT_COLORS;
+        assert_invalid(&buf, "only have one of");
+
+        let both = MeshData { colors: colored().colors, ..skinned() };
+        assert!(write_mesh_file(&mut vec![], &[both]).is_err());
+        let mut short = colored();
+        short.colors.as_mut().unwrap().pop();
+        assert!(write_mesh_file(&mut vec![], &[short]).is_err());
+    }
+
+    #[test]
+    fn bad_alpha() {
+        let mut buf = file(&[triangle()]);
+        let at = buf.len() - 4 - 19;
+        buf[at] = 3;
+        assert_invalid(&buf, "unknown alpha mode 3");
+
+        assert_invalid(&file(&[MeshData { alpha: AlphaMode::Mask(2.), ..triangle() }]), "alpha cutoff is 2");
+    }
+
+    #[test]
+    fn lightmap_needs_every_uv() {
+        let mut mesh = lightmapped();
+        mesh.lightmap.as_mut().unwrap().uvs.pop();
+
+        assert!(write_mesh_file(&mut vec![], &[mesh]).is_err());
+    }
+
+    #[test]
+    fn flipbook_uvs() {
+        let flipbook = flipbook().flipbook.unwrap();
+
+        assert_eq!(flipbook.frame_uv(0), ([0.25, 0.5], [0., 0.5]));
+        assert_eq!(flipbook.frame_uv(5), ([0.25, 0.5], [0.25, 0.]));
+        assert_eq!(flipbook.frame_uv(6), flipbook.frame_uv(0));
+    }
+
+    #[test]
+    fn bad_flipbook() {
+        let too_many = Flipbook { frames: 9, ..flipbook().flipbook.unwrap() };
+        assert_invalid(&file(&[MeshData { flipbook: Some(too_many), ..flipbook() }]), "only 4x2 fit");
+
+        let untextured = MeshData { texture: None, ..flipbook() };
+        assert_invalid(&file(&[untextured]), "without a texture");
+    }
+
+    #[test]
+    fn bad_magic() {
+        let mut buf = file(&[triangle()]);
+        buf[..4].copy_from_slice(b"HSEM");
+
+        let err = read_mesh_file(&buf[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    fn assert_invalid(buf: &[u8], message: &str) {
+        let err = read_mesh_file(buf).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains(message), "{err}");
+    }
+
+    // offsets into file(&[triangle()])
+    const N_MESHES: usize = 4;
+    const N_VERTICES: usize = N_MESHES + 4 + 16;
+    const N_INDICES: usize = N_VERTICES + 4 + 3 * 32;
+    const SIZE_OF_TEX: usize = N_INDICES + 4 + 3 * 2;
+
+    fn patch_u32(buf: &mut [u8], offset: usize, n: u32) {
+        buf[offset..offset + 4].copy_from_slice(&n.to_le_bytes());
+    }
+
+    #[test]
+    fn huge_counts() {
+        for (offset, message) in [
+            (N_MESHES, "mesh count"),
+            (N_VERTICES, "vertex count"),
+            (N_INDICES, "index count"),
+            (SIZE_OF_TEX, "texture size"),
+        ] {
+            let mut buf = file(&[triangle()]);
+            patch_u32(&mut buf, offset, u32::MAX);
+            assert_invalid(&buf, message);
+        }
+    }
+
+    #[test]
+    fn index_out_of_bounds() {
+        let mut buf = file(&[triangle()]);
+        buf[N_INDICES + 4 + 2..N_INDICES + 4 + 4].copy_from_slice(&3u16.to_le_bytes());
+        assert_invalid(&buf, "index 1 is 3");
+    }
+
+    #[test]
+    fn partial_triangle() {
+        let mesh = MeshData { indices: vec![0, 1], ..triangle() };
+        assert_invalid(&file(&[mesh]), "multiple of 3");
+    }
+
+    #[test]
+    fn error_names_mesh() {
+        let bad = MeshData { indices: vec![0, 1, 7], ..triangle() };
+        assert_invalid(&file(&[triangle(), bad]), "mesh 1:");
+    }
+
+    #[test]
+    fn truncated() {
+        let buf = file(&[triangle()]);
+
+        for len in 0..buf.len() {
+            let err = read_mesh_file(&buf[..len]).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof, "truncated to {len} bytes");
+        }
+    }
+
+    fn walk() -> Clip {
+        Clip {
+            name: "walk".to_owned(),
+            duration: 1.,
+            events: vec![
+                ClipEvent { time: 0.25, name: "footstep".to_owned() },
+                ClipEvent { time: 0.75, name: "footstep".to_owned() },
+            ],
+            channels: vec![],
+        }
+    }
+
+    const IDENTITY: [f32; 16] = [1., 0., 0., 0., 0., 1., 0., 0., 0., 0., 1., 0., 0., 0., 0., 1.];
+
+    fn joint(name: &str, parent: Option<u16>) -> Joint {
+        Joint {
+            name: name.to_owned(),
+            parent,
+            inverse_bind: IDENTITY,
+            translation: [0., 1., 0.],
+            rotation: [0., 0., 0., 1.],
+            scale: [1.; 3],
+        }
+    }
+
+    // an arm that swings at the elbow
+    fn swing() -> AnimData {
+        AnimData {
+            skeleton: Some(SkeletonData { root: IDENTITY, joints: vec![joint("shoulder", None), joint("elbow", Some(0))] }),
+            clips: vec![Clip {
+                name: "swing".to_owned(),
+                duration: 1.,
+                events: vec![],
+                channels: vec![
+                    Channel {
+                        joint: 1,
+                        property: Property::Rotation,
+                        keys: vec![
+                            Keyframe { time: 0., value: [0., 0., 0., 1.] },
+                            Keyframe { time: 1., value: [0., 0., 1., 0.] },
+                        ],
+                    },
+                    Channel { joint: 0, property: Property::Translation, keys: vec![Keyframe { time: 0.5, value: [1., 2., 3., 0.] }] },
+                ],
+            }],
+        }
+    }
+
+    fn anim_file(anim: &AnimData) -> Vec<u8> {
+        let mut buf = vec![];
+        write_anim_file(&mut buf, anim).unwrap();
+        buf
+    }
+
+    fn clips_file(clips: &[Clip]) -> Vec<u8> {
+        anim_file(&AnimData { skeleton: None, clips: clips.to_vec() })
+    }
+
+    #[test]
+    fn anim_round_trip() {
+        let clips = vec![walk(), Clip { name: "idle".to_owned(), duration: 2., ..Clip::default() }];
+        assert_eq!(read_anim_file(&clips_file(&clips)[..]).unwrap().clips, clips);
+
+        assert_eq!(read_anim_file(&anim_file(&swing())[..]).unwrap(), swing());
+    }
+
+    #[test]
+    fn anim_version_1() {
+        let mut file = ANIM_MAGIC_V1.to_vec();
+        file.write_u32(1).unwrap();
+        file.write_string("walk").unwrap();
+        file.write_f32(1.).unwrap();
+        file.write_u32(2).unwrap();
+        for event in &walk().events {
+            file.write_f32(event.time).unwrap();
+            file.write_string(&event.name).unwrap();
+        }
+
+        assert_eq!(read_anim_file(&file[..]).unwrap(), AnimData { skeleton: None, clips: vec![walk()] });
+    }
+
+    #[test]
+    fn bad_skeletons() {
+        for (joints, message) in [
+            (vec![joint("a", Some(2)), joint("b", None)], "joint 0's parent is 2"),
+            (vec![joint("a", Some(1)), joint("b", Some(0))], "joint 0 is its own ancestor"),
+        ] {
+            let anim = AnimData { skeleton: Some(SkeletonData { root: IDENTITY, joints }), clips: vec![] };
+            let err = read_anim_file(&anim_file(&anim)[..]).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+            assert!(err.to_string().contains(message), "{err}");
+        }
+    }
+
+    #[test]
+    fn bad_channels() {
+        let keys = swing().clips[0].channels[0].keys.clone();
+        for (channel, message) in [
+            (Channel { joint: 2, ..swing().clips[0].channels[0].clone() }, "channel 0: it moves joint 2"),
+            (Channel { keys: vec![], ..swing().clips[0].channels[0].clone() }, "channel 0: it has no keys"),
+            (Channel { keys: keys.into_iter().rev().collect(), ..swing().clips[0].channels[0].clone() }, "key 1 is before"),
+            (Channel { keys: vec![Keyframe { time: 2., value: [0.; 4] }], ..swing().clips[0].channels[0].clone() }, "key 0 is at 2s"),
+        ] {
+            let mut anim = swing();
+            anim.clips[0].channels = vec![channel];
+            let err = read_anim_file(&anim_file(&anim)[..]).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+            assert!(err.to_string().contains(message), "{err}");
+        }
+
+        // a file without a skeleton can't move one
+        let err = read_anim_file(&clips_file(&swing().clips)[..]).unwrap_err();
+        assert!(err.to_string().contains("there are only 0 joints"), "{err}");
+    }
+
+    #[test]
+    fn bad_events() {
+        for (events, message) in [
+            (vec![ClipEvent { time: 1.5, name: "late".to_owned() }], "event 0 is at 1.5s"),
+            (walk().events.into_iter().rev().collect(), "event 1 is before"),
+        ] {
+            let err = read_anim_file(&clips_file(&[Clip { events, ..walk() }])[..]).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+            assert!(err.to_string().contains(message), "{err}");
+        }
+    }
+
+    // runs, repeats that overlap themselves, and noise that won't compress
+    fn lz_inputs() -> Vec<Vec<u8>> {
+        let mut noise = vec![];
+        let mut x = 12345u32;
+        for _ in 0..10000 {
+            x = x.wrapping_mul(1103515245).wrapping_add(12345);
+            noise.push((x >> 16) as u8);
+        }
+
+        vec![
+            vec![],
+            vec![7],
+            vec![0; 5000],
+            b"abcabcabcabcabcabcabcabcabcd".repeat(40),
+            file(&[triangle(), flipbook(), lightmapped()]),
+            noise,
+        ]
+    }
+
+    #[test]
+    fn lz_round_trip() {
+        for data in lz_inputs() {
+            let compressed = lz_compress(&data);
+            assert_eq!(lz_decompress(&compressed, data.len()).unwrap(), data);
+        }
+
+        assert!(lz_compress(&[0; 5000]).len() < 1000);
+    }
+
+    #[test]
+    fn lz_truncated() {
+        let compressed = lz_compress(&[0; 100]);
+        let err = lz_decompress(&compressed[..compressed.len() - 1], 100).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // a match from before the start
+        let err = lz_decompress(&[0x80, 0x00, 0x05], 3).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    fn pack_files() -> Vec<(String, Vec<u8>)> {
+        lz_inputs().into_iter().enumerate()
+            .map(|(i, data)| (format!("dir/file{i}.bin"), data))
+            .rev()
+            .collect()
+    }
+
+    fn unpack(pack: &[u8], entry: &PackEntry) -> Vec<u8> {
+        let start = entry.offset as usize;
+        entry.unpack(pack[start..start + entry.stored_size as usize].to_vec()).unwrap()
+    }
+
+    #[test]
+    fn pack_round_trip() {
+        let files = pack_files();
+        for compression in [Compression::None, Compression::Lz] {
+            let mut pack = vec![];
+            write_pack_file(&mut pack, &files, compression).unwrap();
+            let mut index = read_pack_index(&pack[..]).unwrap();
+
+            // sorted by name
+            assert!(index.windows(2).all(|w| w[0].name < w[1].name));
+            index.reverse();
+            for (entry, (name, data)) in index.iter().zip(&files) {
+                assert_eq!(&entry.name, name);
+                assert_eq!(&unpack(&pack, entry), data);
+            }
+
+            // the noise and the tiny files don't shrink, so they're stored as-is
+            let compressed = index.iter().filter(|entry| entry.compression == Compression::Lz).count();
+            assert_eq!(compressed, if compression == Compression::Lz { 3 } else { 0 });
+        }
+    }
+
+    fn frame_capture() -> FrameCapture {
+        let mut model = [0.; 16];
+        model[0] = 1.;
+        model[5] = 0.1; // doesn't come out exactly in decimal
+        model[10] = -1e-7;
+        model[15] = 1.;
+
+        let mesh = triangle();
+        FrameCapture {
+            state: vec![("clear_color".to_owned(), "68b0d8ff".to_owned()), ("fog".to_owned(), String::new())],
+            projection: model,
+            view: [2.; 16],
+            meshes: vec![
+                CapturedMesh { id: 3, color: mesh.color, vertices: mesh.vertices.clone(), indices: mesh.indices },
+                CapturedMesh { id: 7, color: [1.; 4], vertices: mesh.vertices, indices: vec![] },
+            ],
+            draws: vec![
+                CapturedDraw { mesh: 7, model, state: vec![("program".to_owned(), "default".to_owned())] },
+                CapturedDraw { mesh: 3, model: [0.; 16], state: vec![] },
+            ],
+        }
+    }
+
+    #[test]
+    fn frame_capture_round_trip() {
+        let capture = frame_capture();
+        let mut text = vec![];
+        capture.write(&mut text).unwrap();
+
+        assert!(text.starts_with(b"MM3DS FRAME 1\nstate clear_color 68b0d8ff\n"));
+        assert_eq!(FrameCapture::read(&text[..]).unwrap(), capture);
+    }
+
+    #[test]
+    fn bad_frame_capture() {
+        let read = |text: &str| FrameCapture::read(text.as_bytes()).unwrap_err().kind();
+        assert_eq!(read("MM3DS FRAME 2\n"), io::ErrorKind::InvalidData);
+        assert_eq!(read("MM3DS FRAME 1\nvertex 0 0 0 0 0 0 0 1\n"), io::ErrorKind::InvalidData);
+        assert_eq!(read("MM3DS FRAME 1\nmesh 1 1 1 1 1\nindices 0\n"), io::ErrorKind::InvalidData);
+        assert_eq!(read("MM3DS FRAME 1\ndraw 1 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0\n"), io::ErrorKind::InvalidData);
+
+        let mut capture = frame_capture();
+        capture.state.push(("two words".to_owned(), String::new()));
+        assert!(capture.write(&mut vec![]).is_err());
+    }
+
+    #[test]
+    fn bad_pack() {
+        let err = read_pack_index(&file(&[])[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let mut pack = vec![];
+        write_pack_file(&mut pack, &pack_files(), Compression::Lz).unwrap();
+        let index = read_pack_index(&pack[..]).unwrap();
+        assert!(index.iter().all(|entry| entry.size == 0 || entry.unpack(vec![]).is_err()));
+    }
+}
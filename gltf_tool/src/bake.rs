@@ -0,0 +1,273 @@
+// ambient occlusion, baked into a lightmap. every texel of the lightmap is a point on the mesh (found
+// through the mesh's second set of UVs), and it gets brighter the more of the sky it can see past
+// the rest of the scene. the 3DS can't afford to work this out at runtime, but it costs nothing to
+// look up
+use glam::{Vec2, Vec3};
+
+use mm3ds_format::MeshData;
+
+// rays per texel. more is smoother and slower
+const SAMPLES: u32 = 48;
+
+// how many texels past the edges of the triangles to spread the lightmap out, so filtering doesn't
+// pull in the black around them
+const PADDING: usize = 2;
+
+#[derive(Copy, Clone)]
+struct Triangle {
+    a: Vec3,
+    b: Vec3,
+    c: Vec3,
+}
+
+impl Triangle {
+    fn min(&self) -> Vec3 {
+        self.a.min(self.b).min(self.c)
+    }
+
+    fn max(&self) -> Vec3 {
+        self.a.max(self.b).max(self.c)
+    }
+
+    // möller-trumbore, how far along `dir` the ray hits
+    fn hit(&self, origin: Vec3, dir: Vec3) -> Option<f32> {
+        let ab = self.b - self.a;
+        let ac = self.c - self.a;
+        let p = dir.cross(ac);
+        let det = ab.dot(p);
+        if det.abs() < 1e-12 {
+            return None;
+        }
+
+        let to_origin = origin - self.a;
+        let u = to_origin.dot(p) / det;
+        if !(0. ..=1.).contains(&u) {
+            return None;
+        }
+
+        let q = to_origin.cross(ab);
+        let v = dir.dot(q) / det;
+        if v < 0. || u + v > 1. {
+            return None;
+        }
+
+        Some(ac.dot(q) / det)
+    }
+}
+
+// a bounding volume hierarchy of every triangle in the scene, so each ray only gets tested against
+// the few it could hit
+struct Node {
+    min: Vec3,
+    max: Vec3,
+    // a leaf holds triangles[start..start + count], a branch has its children at the next index
+    // and at `right`
+    start: usize,
+    count: usize,
+    right: usize,
+}
+
+struct Scene {
+    triangles: Vec<Triangle>,
+    nodes: Vec<Node>,
+}
+
+const LEAF_SIZE: usize = 4;
+
+impl Scene {
+    fn new(meshes: &[MeshData]) -> Self {
+        let mut triangles = vec![];
+        for mesh in meshes {
+            for face in mesh.indices.chunks_exact(3) {
+                let pos = |i: u16| Vec3::from(mesh.vertices[i as usize].pos);
+                triangles.push(Triangle { a: pos(face[0]), b: pos(face[1]), c: pos(face[2]) });
+            }
+        }
+
+        let mut ret = Self { triangles, nodes: vec![] };
+        if !ret.triangles.is_empty() {
+            ret.build(0, ret.triangles.len());
+        }
+
+        ret
+    }
+
+    // splits triangles[start..end] down the middle of its longest side, until the pieces are small
+    fn build(&mut self, start: usize, end: usize) -> usize {
+        let triangles = &mut self.triangles[start..end];
+        let min = triangles.iter().map(Triangle::min).fold(Vec3::INFINITY, Vec3::min);
+        let max = triangles.iter().map(Triangle::max).fold(Vec3::NEG_INFINITY, Vec3::max);
+
+        let index = self.nodes.len();
+        self.nodes.push(Node { min, max, start, count: end - start, right: 0 });
+        if end - start <= LEAF_SIZE {
+            return index;
+        }
+
+        let axis = (max - min).max_position();
+        let centre = |triangle: &Triangle| (triangle.a + triangle.b + triangle.c)[axis];
+        triangles.sort_unstable_by(|a, b| centre(a).total_cmp(&centre(b)));
+
+        let middle = start + (end - start) / 2;
+        self.build(start, middle);
+        let right = self.build(middle, end);
+        self.nodes[index].count = 0;
+        self.nodes[index].right = right;
+
+        index
+    }
+
+    // whether anything is in the way of the ray within `reach`
+    fn blocked(&self, origin: Vec3, dir: Vec3, reach: f32) -> bool {
+        if self.nodes.is_empty() {
+            return false;
+        }
+
+        let inv_dir = dir.recip();
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+
+            // slab test against the node's box
+            let t0 = (node.min - origin) * inv_dir;
+            let t1 = (node.max - origin) * inv_dir;
+            let near = t0.min(t1).max_element().max(0.);
+            let far = t0.max(t1).min_element().min(reach);
+            if near > far {
+                continue;
+            }
+
+            if node.count == 0 {
+                stack.push(index + 1);
+                stack.push(node.right);
+                continue;
+            }
+
+            let triangles = &self.triangles[node.start..node.start + node.count];
+            if triangles.iter().any(|triangle| triangle.hit(origin, dir).is_some_and(|t| t > 0. && t < reach)) {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    fn size(&self) -> f32 {
+        self.nodes.first().map_or(0., |root| (root.max - root.min).length())
+    }
+}
+
+// the low-discrepancy points (i / n, reversed bits of i) in the unit square
+fn hammersley(i: u32, n: u32) -> Vec2 {
+    Vec2::new((i as f32 + 0.5) / n as f32, i.reverse_bits() as f32 / (1u64 << 32) as f32)
+}
+
+// a different-looking number in 0..1 for every texel, so the samples don't line up into bands
+fn hash(x: usize, y: usize) -> f32 {
+    let mut h = (x as u32).wrapping_mul(0x8da6b343) ^ (y as u32).wrapping_mul(0xd8163841);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0x5bd1e995);
+    h ^= h >> 15;
+    h as f32 / u32::MAX as f32
+}
+
+// how much of the sky the point can see, from 0 to 1
+fn occlusion(scene: &Scene, pos: Vec3, normal: Vec3, reach: f32, jitter: f32) -> f32 {
+    let (tangent, bitangent) = normal.any_orthonormal_pair();
+    let origin = pos + normal * scene.size() * 1e-4;
+
+    let mut open = 0;
+    for i in 0..SAMPLES {
+        // cosine weighted, so the rays that matter most to a lit surface are the most common
+        let sample = hammersley(i, SAMPLES);
+        let r = sample.x.sqrt();
+        let (sin, cos) = (std::f32::consts::TAU * (sample.y + jitter).fract()).sin_cos();
+        let dir = tangent * r * cos + bitangent * r * sin + normal * (1. - sample.x).sqrt();
+
+        if !scene.blocked(origin, dir, reach) {
+            open += 1;
+        }
+    }
+
+    open as f32 / SAMPLES as f32
+}
+
+// lets covered texels bleed into the ones next to them that nothing covers
+fn pad(pixels: &mut [u8], covered: &mut [bool], size: usize) {
+    for _ in 0..PADDING {
+        let before = covered.to_vec();
+        for y in 0..size {
+            for x in 0..size {
+                if before[y * size + x] {
+                    continue;
+                }
+
+                let (mut sum, mut n) = (0u32, 0u32);
+                for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+                    let (nx, ny) = (x as isize + dx, y as isize + dy);
+                    if nx < 0 || ny < 0 || nx >= size as isize || ny >= size as isize {
+                        continue;
+                    }
+
+                    let neighbour = ny as usize * size + nx as usize;
+                    if before[neighbour] {
+                        sum += pixels[neighbour] as u32;
+                        n += 1;
+                    }
+                }
+
+                if n != 0 {
+                    pixels[y * size + x] = (sum / n) as u8;
+                    covered[y * size + x] = true;
+                }
+            }
+        }
+    }
+}
+
+// a `size`x`size` 8-bit greyscale image of meshes[mesh] laid out with `uvs`, rows from the top
+// like glTF's UVs. `reach` is how far away something can be and still darken a point, in world
+// units, defaulting to a fifth of the scene's size
+pub fn ambient_occlusion(meshes: &[MeshData], mesh: usize, uvs: &[[f32; 2]], size: usize, reach: Option<f32>) -> Vec<u8> {
+    let scene = Scene::new(meshes);
+    let reach = reach.unwrap_or(scene.size() / 5.);
+
+    let mut pixels = vec![0u8; size * size];
+    let mut covered = vec![false; size * size];
+    let mesh = &meshes[mesh];
+    for face in mesh.indices.chunks_exact(3) {
+        let face: [u16; 3] = face.try_into().unwrap();
+        let pos = face.map(|i| Vec3::from(mesh.vertices[i as usize].pos));
+        let texel = face.map(|i| Vec2::from(uvs[i as usize]) * size as f32);
+
+        let normal = (pos[1] - pos[0]).cross(pos[2] - pos[0]).normalize_or_zero();
+        let area = (texel[1] - texel[0]).perp_dot(texel[2] - texel[0]);
+        if normal == Vec3::ZERO || area.abs() < 1e-9 {
+            continue;
+        }
+
+        let min = texel[0].min(texel[1]).min(texel[2]).floor().max(Vec2::ZERO);
+        let max = texel[0].max(texel[1]).max(texel[2]).ceil().min(Vec2::splat(size as f32));
+        for y in min.y as usize..max.y as usize {
+            for x in min.x as usize..max.x as usize {
+                // barycentric coordinates of the texel's centre
+                let p = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+                let w0 = (texel[1] - p).perp_dot(texel[2] - p) / area;
+                let w1 = (texel[2] - p).perp_dot(texel[0] - p) / area;
+                let w2 = 1. - w0 - w1;
+                if w0 < 0. || w1 < 0. || w2 < 0. {
+                    continue;
+                }
+
+                let point = pos[0] * w0 + pos[1] * w1 + pos[2] * w2;
+                let light = occlusion(&scene, point, normal, reach, hash(x, y));
+                pixels[y * size + x] = (light * 255.).round() as u8;
+                covered[y * size + x] = true;
+            }
+        }
+    }
+
+    pad(&mut pixels, &mut covered, size);
+
+    pixels
+}
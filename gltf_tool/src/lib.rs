@@ -0,0 +1,580 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::Path;
+use std::process::Command;
+
+use gltf::buffer;
+use gltf::{Node, mesh::Mode};
+use gltf::image;
+
+use gltf::animation::Interpolation;
+use gltf::animation::util::ReadOutputs;
+
+use glam::{Mat3, Mat4, Vec3};
+use png::Encoder;
+
+use mm3ds_format::{
+    AlphaMode, AnimData, Channel, Clip, ClipEvent, Flipbook, Joint, Keyframe, Lightmap, MeshData, Property, SceneData,
+    SceneNode, SkeletonData, SkinVertex, Vertex,
+};
+
+mod bake;
+
+// as many bones as the engine's skinned shader has room for, see MAX_BONES in engine/src/mesh.rs
+const MAX_BONES: u16 = 20;
+
+const TMP_PNG_FILENAME: &str = "gltftoolscratchspace.png";
+const TMP_T3X_FILENAME: &str = "gltftoolscratchspace.t3x";
+
+// a material can mark its base color texture as a flipbook with custom properties (glTF extras):
+// flipbook_columns, flipbook_rows, flipbook_fps, and optionally flipbook_frames (which defaults to
+// columns * rows)
+fn flipbook(material: &gltf::Material) -> Option<Flipbook> {
+    let extras: serde_json::Value = serde_json::from_str(material.extras().as_ref()?.get()).ok()?;
+    let get = |key: &str| extras.get(key).and_then(serde_json::Value::as_f64);
+
+    let columns = get("flipbook_columns")? as u16;
+    let rows = get("flipbook_rows")? as u16;
+    Some(Flipbook {
+        frames: get("flipbook_frames").map_or(columns.saturating_mul(rows), |frames| frames as u16),
+        columns,
+        rows,
+        fps: get("flipbook_fps")? as f32,
+    })
+}
+
+// and can ask for ambient occlusion to be baked into a lightmap laid out with the primitive's
+// second set of UVs: lightmap_size (in texels, a power of two) and optionally lightmap_distance
+// (how far away something can be and still cast shade, in world units)
+fn lightmap_settings(material: &gltf::Material) -> Option<(usize, Option<f32>)> {
+    let extras: serde_json::Value = serde_json::from_str(material.extras().as_ref()?.get()).ok()?;
+    let size = extras.get("lightmap_size")?.as_u64()? as usize;
+    let distance = extras.get("lightmap_distance").and_then(serde_json::Value::as_f64);
+
+    Some((size, distance.map(|distance| distance as f32)))
+}
+
+// a lightmap to bake once all the meshes are known, since anything in the scene can shade them
+struct Bake {
+    mesh: usize,
+    material: Option<String>,
+    size: usize,
+    distance: Option<f32>,
+    uvs: Option<Vec<[f32; 2]>>,
+}
+
+// converts a png to a t3x in the given tex3ds format
+fn tex3ds(format: &str, write_png: impl FnOnce(BufWriter<File>)) -> Vec<u8> {
+    write_png(BufWriter::new(File::create_new(TMP_PNG_FILENAME).unwrap()));
+
+    // now lets tell tex3ds to convert that image into a t3x file
+
+    let status = Command::new("tex3ds")
+        .args(["-f", format, "-z", "auto"])
+        .args(["-o", TMP_T3X_FILENAME])
+        .arg(TMP_PNG_FILENAME)
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let ret = fs::read(TMP_T3X_FILENAME).unwrap();
+
+    // and clean up
+    std::fs::remove_file(TMP_T3X_FILENAME).unwrap();
+    std::fs::remove_file(TMP_PNG_FILENAME).unwrap();
+
+    ret
+}
+
+// how convert turns a glTF file into meshes
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Options {
+    // reverses every triangle, for assets that come out inside-out. the ones under a mirroring
+    // transform (a negative determinant) are reversed anyway, so this undoes that for them
+    pub flip_winding: bool,
+}
+
+// turns each triangle around, swapping which side is the front
+fn flip_winding(indices: &mut [u16]) {
+    for triangle in indices.chunks_exact_mut(3) {
+        triangle.swap(1, 2);
+    }
+}
+
+fn load_image(texture: gltf::Texture, buffers: &[buffer::Data]) -> image::Data {
+    image::Data::from_source(
+        texture.source().source(),
+        std::env::current_dir().ok().as_deref(), // TODO: change to path to file?
+        buffers
+    ).unwrap()
+}
+
+// an image's pixels as 8 bit rgba, whatever it was stored as. one and two channel images are grey
+// (the second channel being alpha), floats are clamped to 0..1, and anything without alpha is opaque
+fn rgba8(data: &image::Data) -> Vec<[u8; 4]> {
+    let float = |p: &[u8]| (f32::from_le_bytes([p[0], p[1], p[2], p[3]]).clamp(0., 1.) * 255.).round() as u8;
+    match data.format {
+        image::Format::R8 => data.pixels.iter().map(|&p| [p, p, p, 255]).collect(),
+        image::Format::R8G8 => data.pixels.chunks_exact(2).map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+        image::Format::R8G8B8 => data.pixels.chunks_exact(3).map(|p| [p[0], p[1], p[2], 255]).collect(),
+        image::Format::R8G8B8A8 => data.pixels.chunks_exact(4).map(|p| [p[0], p[1], p[2], p[3]]).collect(),
+        // little endian, so the high byte of each channel is the second
+        image::Format::R16 => data.pixels.chunks_exact(2).map(|p| [p[1], p[1], p[1], 255]).collect(),
+        image::Format::R16G16 => data.pixels.chunks_exact(4).map(|p| [p[1], p[1], p[1], p[3]]).collect(),
+        image::Format::R16G16B16 => data.pixels.chunks_exact(6).map(|p| [p[1], p[3], p[5], 255]).collect(),
+        image::Format::R16G16B16A16 => data.pixels.chunks_exact(8).map(|p| [p[1], p[3], p[5], p[7]]).collect(),
+        image::Format::R32G32B32FLOAT => data.pixels.chunks_exact(12).map(|p| [float(&p[0..]), float(&p[4..]), float(&p[8..]), 255]).collect(),
+        image::Format::R32G32B32A32FLOAT => data.pixels.chunks_exact(16).map(|p| [float(&p[0..]), float(&p[4..]), float(&p[8..]), float(&p[12..])]).collect(),
+    }
+}
+
+// rgba8 without the alpha
+fn rgb8(data: &image::Data) -> Vec<[u8; 3]> {
+    rgba8(data).into_iter().map(|[r, g, b, _]| [r, g, b]).collect()
+}
+
+// the emissive texture times the emissive factor, as glTF has it, for an emission map
+fn emission_map(mat: &gltf::Material, buffers: &[buffer::Data]) -> Option<Vec<u8>> {
+    let data = load_image(mat.emissive_texture()?.texture(), buffers);
+    let factor = mat.emissive_factor();
+    let pixels: Vec<u8> = rgb8(&data).into_iter()
+        .flat_map(|pixel| [0, 1, 2].map(|i| (pixel[i] as f32 * factor[i]).round() as u8))
+        .collect();
+
+    Some(tex3ds("auto-etc1", |writer| {
+        let mut encoder = Encoder::new(writer, data.width, data.height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header().unwrap().write_image_data(&pixels).unwrap();
+    }))
+}
+
+// the occlusion texture, as a lightmap laid out with the uvs it says. ambient occlusion is what a
+// baked lightmap is too, so it's drawn the same way. the occlusion is in the red channel, and
+// strength scales how much of it there is
+fn occlusion_lightmap(mat: &gltf::Material, tex_coords: impl FnOnce(u32) -> Option<Vec<[f32; 2]>>, buffers: &[buffer::Data]) -> Option<Lightmap> {
+    let occlusion = mat.occlusion_texture()?;
+    let uvs = tex_coords(occlusion.tex_coord())?;
+    let data = load_image(occlusion.texture(), buffers);
+    let strength = occlusion.strength();
+    let pixels: Vec<u8> = rgb8(&data).into_iter()
+        .map(|[r, _, _]| (255. * (1. + strength * (r as f32 / 255. - 1.))).round() as u8)
+        .collect();
+
+    let texture = tex3ds("l8", |writer| {
+        let mut encoder = Encoder::new(writer, data.width, data.height);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header().unwrap().write_image_data(&pixels).unwrap();
+    });
+
+    Some(Lightmap { texture, uvs })
+}
+
+// turns each triangle primitive of a glTF mesh into a mesh, and returns where they went in
+// `meshes`. `mirrored` if the mesh is under a mirroring transform
+fn convert_mesh(
+    mesh: gltf::Mesh,
+    mirrored: bool,
+    options: Options,
+    meshes: &mut Vec<MeshData>,
+    bakes: &mut Vec<Bake>,
+    buffers: &[buffer::Data],
+) -> Result<Vec<u16>, Box<dyn Error>> {
+    let mut ret = vec![];
+    for prim in mesh.primitives().filter(|prim| prim.mode() == Mode::Triangles) {
+        let reader = prim.reader(|buf| Some(&buffers[buf.index()]));
+
+        let mat = prim.material();
+        let mut texture = None;
+        if let Some(tex_info) = mat.pbr_metallic_roughness().base_color_texture() {
+            let data = load_image(tex_info.texture(), buffers);
+
+            // now that we have the image data, lets save it as a png to a temporary
+            // file, and embed what tex3ds makes of it into our mesh
+            let pixels: Vec<u8> = rgba8(&data).into_iter().flatten().collect();
+            texture = Some(tex3ds("auto-etc1", |writer| {
+                let mut encoder = Encoder::new(writer, data.width, data.height);
+                encoder.set_color(png::ColorType::Rgba);
+                encoder.set_depth(png::BitDepth::Eight);
+                encoder.write_header().unwrap().write_image_data(&pixels).unwrap();
+            }));
+        }
+        // a primitive without uvs or normals gets zeros for them
+        let positions = reader.read_positions().unwrap();
+        let n_vertices = positions.len();
+        let mut uvs = reader.read_tex_coords(0).map(|uvs| uvs.into_f32());
+        let mut normals = reader.read_normals();
+
+        let mut vertices = Vec::with_capacity(n_vertices);
+        for pos in positions {
+            vertices.push(Vertex {
+                pos,
+                uv: uvs.as_mut().and_then(Iterator::next).unwrap_or_default(),
+                normal: normals.as_mut().and_then(Iterator::next).unwrap_or_default(),
+            });
+        }
+
+        // a mesh only gets one of a skin, colors and a lightmap (see the MESH format), in
+        // that order of preference
+        let lightmap = lightmap_settings(&mat);
+        let skin = reader.read_joints(0).zip(reader.read_weights(0)).map(|(joints, weights)| {
+            joints.into_u16().zip(weights.into_f32())
+                .map(|(joints, weights)| Ok(SkinVertex { joints: skin_joints(joints, &mesh)?, weights }))
+                .collect::<Result<Vec<_>, Box<dyn Error>>>()
+        }).transpose()?;
+        let colors = reader.read_colors(0)
+            .filter(|_| skin.is_none() && lightmap.is_none())
+            .map(|colors| colors.into_rgba_f32().collect::<Vec<_>>());
+        let lightmap = lightmap.filter(|_| skin.is_none());
+        // a baked lightmap has the occlusion in it already
+        let occlusion = if skin.is_none() && colors.is_none() && lightmap.is_none() {
+            occlusion_lightmap(&mat, |set| reader.read_tex_coords(set).map(|uvs| uvs.into_f32().collect()), buffers)
+        } else { None };
+
+        if let Some((size, distance)) = lightmap {
+            bakes.push(Bake {
+                mesh: meshes.len(),
+                material: mat.name().map(str::to_owned),
+                size,
+                distance,
+                uvs: reader.read_tex_coords(1).map(|uvs| uvs.into_f32().collect()),
+            });
+        }
+
+        let mut indices: Vec<u16> = reader.read_indices().unwrap().into_u32().map(|n| n.try_into().unwrap()).collect();
+        // a mirroring transform turns the triangles inside-out, so they're turned back
+        if mirrored != options.flip_winding {
+            flip_winding(&mut indices);
+        }
+
+        let roughness = mat.pbr_metallic_roughness();
+        let emission_map = emission_map(&mat, buffers);
+
+        ret.push(next_index(meshes.len(), mm3ds_format::MAX_MESHES, "meshes")?);
+        meshes.push(MeshData {
+            vertices,
+            color: roughness.base_color_factor(),
+            indices,
+            flipbook: texture.as_ref().and_then(|_| flipbook(&mat)),
+            texture,
+            lightmap: occlusion,
+            alpha: match mat.alpha_mode() {
+                gltf::material::AlphaMode::Opaque => AlphaMode::Opaque,
+                // glTF's default cutoff is 0.5
+                gltf::material::AlphaMode::Mask => AlphaMode::Mask(mat.alpha_cutoff().unwrap_or(0.5).clamp(0., 1.)),
+                gltf::material::AlphaMode::Blend => AlphaMode::Blend,
+            },
+            colors,
+            skin,
+            double_sided: mat.double_sided(),
+            // the map has the factor in it already
+            emission: if emission_map.is_some() { [0.; 3] } else { mat.emissive_factor() },
+            emission_map,
+        });
+    }
+
+    Ok(ret)
+}
+
+// a vertex's joints, if the engine can pose them
+fn skin_joints(joints: [u16; 4], mesh: &gltf::Mesh) -> Result<[u8; 4], Box<dyn Error>> {
+    let mut ret = [0; 4];
+    for (to, joint) in ret.iter_mut().zip(joints) {
+        *to = u8::try_from(joint).ok().filter(|&joint| u16::from(joint) < MAX_BONES).ok_or_else(|| {
+            let name = mesh.name().map_or_else(|| mesh.index().to_string(), str::to_owned);
+            format!("mesh {name}: joint {joint} is past the {MAX_BONES} bones a skinned mesh can have")
+        })?;
+    }
+
+    Ok(ret)
+}
+
+// the index the next of `len` things gets, if the MESH format has room for it
+fn next_index(len: usize, max: u32, what: &str) -> Result<u16, Box<dyn Error>> {
+    u16::try_from(len).ok()
+        .filter(|&i| u32::from(i) < max)
+        .ok_or_else(|| format!("the scene has more than {max} {what}").into())
+}
+
+// the glTF meshes converted so far, by (mesh, mirrored). a mesh on more than one node is only
+// converted once, unless it's mirrored on some of them and not others, since the engine doesn't
+// turn a mirrored mesh's triangles around
+type Converted = HashMap<(usize, bool), Vec<u16>>;
+
+// adds `nodes` to the scene under `parent` (and where it is in the scene), and everything below them
+fn work_with_nodes<'a>(
+    nodes: impl IntoIterator<Item = Node<'a>>,
+    parent: Option<(u16, Mat4)>,
+    options: Options,
+    scene: &mut SceneData,
+    converted: &mut Converted,
+    bakes: &mut Vec<Bake>,
+    buffers: &[buffer::Data],
+) -> Result<(), Box<dyn Error>> {
+    for node in nodes {
+        let transform = Mat4::from_cols_array_2d(&node.transform().matrix());
+        let world = parent.map_or(Mat4::IDENTITY, |(_, world)| world) * transform;
+        let name = node.name().map_or_else(|| format!("node{}", node.index()), str::to_owned);
+        let index = next_index(scene.nodes.len(), mm3ds_format::MAX_NODES, "nodes")?;
+        scene.nodes.push(SceneNode {
+            name: name.clone(),
+            parent: parent.map(|(parent, _)| parent),
+            transform: transform.to_cols_array(),
+            meshes: vec![],
+        });
+
+        if let Some(mesh) = node.mesh() {
+            // a skinned mesh is posed by its joints, which are where they are whatever node the mesh
+            // is on, so glTF has the node's transform left out. it goes on a root of its own
+            let skinned = node.skin().is_some();
+            let mirrored = !skinned && world.determinant() < 0.;
+            let ids = match converted.get(&(mesh.index(), mirrored)) {
+                Some(ids) => ids.clone(),
+                None => {
+                    let key = (mesh.index(), mirrored);
+                    let ids = convert_mesh(mesh, mirrored, options, &mut scene.meshes, bakes, buffers)?;
+                    converted.insert(key, ids.clone());
+                    ids
+                }
+            };
+            if skinned {
+                next_index(scene.nodes.len(), mm3ds_format::MAX_NODES, "nodes")?;
+                scene.nodes.push(SceneNode { name, parent: None, transform: Mat4::IDENTITY.to_cols_array(), meshes: ids });
+            } else {
+                scene.nodes[index as usize].meshes = ids;
+            }
+        }
+
+        work_with_nodes(node.children(), Some((index, world)), options, scene, converted, bakes, buffers)?;
+    }
+
+    Ok(())
+}
+
+// a copy of each mesh for every node it's on, moved to where that node puts it, with which mesh
+// it's a copy of
+fn placed(scene: &SceneData) -> Vec<(usize, MeshData)> {
+    let mut world: Vec<Mat4> = Vec::with_capacity(scene.nodes.len());
+    let mut ret = vec![];
+    for node in &scene.nodes {
+        let transform = Mat4::from_cols_array(&node.transform);
+        let transform = node.parent.map_or(transform, |parent| world[parent as usize] * transform);
+        world.push(transform);
+
+        let normals = Mat3::from_mat4(transform).inverse().transpose();
+        for &i in &node.meshes {
+            let mut mesh = scene.meshes[i as usize].clone();
+            for vertex in &mut mesh.vertices {
+                vertex.pos = transform.transform_point3(vertex.pos.into()).into();
+                vertex.normal = (normals * Vec3::from(vertex.normal)).normalize_or_zero().into();
+            }
+            ret.push((i as usize, mesh));
+        }
+    }
+
+    ret
+}
+
+// converts the glTF file at `path` into meshes, each where its nodes put it, in the order they'll be
+// written to the MESH file, baking the lightmaps their materials ask for. see convert_scene for a
+// scene whose nodes can still be moved, and whose meshes are only there once however many nodes
+// they're on
+pub fn convert(path: impl AsRef<Path>) -> Result<Vec<MeshData>, Box<dyn Error>> {
+    convert_with(path, Options::default())
+}
+
+pub fn convert_with(path: impl AsRef<Path>, options: Options) -> Result<Vec<MeshData>, Box<dyn Error>> {
+    let scene = convert_scene_with(path, options)?;
+    Ok(placed(&scene).into_iter().map(|(_, mesh)| mesh).collect())
+}
+
+// converts every triangle primitive of the glTF file at `path` into a mesh in its own space, with the
+// nodes of its scene (the default one, or else the first) putting them where they go
+pub fn convert_scene(path: impl AsRef<Path>) -> Result<SceneData, Box<dyn Error>> {
+    convert_scene_with(path, Options::default())
+}
+
+pub fn convert_scene_with(path: impl AsRef<Path>, options: Options) -> Result<SceneData, Box<dyn Error>> {
+    let (document, buffers, _images) = gltf::import(path)?;
+    let roots = document.default_scene().or_else(|| document.scenes().next()).ok_or("the glTF file has no scenes")?;
+    let mut scene = SceneData::default();
+    let mut bakes = vec![];
+    work_with_nodes(roots.nodes(), None, options, &mut scene, &mut HashMap::new(), &mut bakes, buffers.as_ref())?;
+
+    // anything in the scene can shade a mesh, so it's baked with everything where the nodes put it
+    let placed: Vec<(usize, MeshData)> = placed(&scene);
+    let placed_meshes: Vec<MeshData> = placed.iter().map(|(_, mesh)| mesh.clone()).collect();
+    for bake in bakes {
+        let material = bake.material.as_deref().unwrap_or("(unnamed)");
+        let Some(uvs) = bake.uvs else {
+            return Err(format!("material {material} wants a lightmap, but mesh {} has no second set of UVs for it", bake.mesh).into());
+        };
+        if !bake.size.is_power_of_two() || !(8..=1024).contains(&bake.size) {
+            return Err(format!("material {material}: lightmap_size has to be a power of two from 8 to 1024, not {}", bake.size).into());
+        }
+
+        // as the first node it's on has it, since its copies all share the one lightmap
+        let at = placed.iter().position(|&(mesh, _)| mesh == bake.mesh).unwrap();
+        let pixels = bake::ambient_occlusion(&placed_meshes, at, &uvs, bake.size, bake.distance);
+        let texture = tex3ds("l8", |writer| {
+            let mut encoder = Encoder::new(writer, bake.size as u32, bake.size as u32);
+            encoder.set_color(png::ColorType::Grayscale);
+            encoder.set_depth(png::BitDepth::Eight);
+            encoder.write_header().unwrap().write_image_data(&pixels).unwrap();
+        });
+
+        scene.meshes[bake.mesh].lightmap = Some(Lightmap { texture, uvs });
+    }
+
+    Ok(scene)
+}
+
+// an animation can have events with a custom property (glTF extras) holding a list of them:
+// `events = [{"time": 0.25, "name": "footstep"}, ...]`
+fn clip_events(animation: &gltf::Animation) -> Vec<ClipEvent> {
+    let Some(extras) = animation.extras().as_ref() else { return vec![] };
+    let Ok(extras) = serde_json::from_str::<serde_json::Value>(extras.get()) else { return vec![] };
+    let Some(events) = extras.get("events").and_then(serde_json::Value::as_array) else { return vec![] };
+
+    let mut ret: Vec<ClipEvent> = events.iter()
+        .filter_map(|event| Some(ClipEvent {
+            time: event.get("time")?.as_f64()? as f32,
+            name: event.get("name")?.as_str()?.to_owned(),
+        }))
+        .collect();
+    ret.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+    ret
+}
+
+// the first skin's joints, as a skeleton, and the nodes they are
+fn skeleton(document: &gltf::Document, buffers: &[buffer::Data]) -> Result<Option<(SkeletonData, Vec<usize>)>, Box<dyn Error>> {
+    let Some(skin) = document.skins().next() else { return Ok(None) };
+
+    let mut parents = vec![None; document.nodes().count()];
+    for node in document.nodes() {
+        for child in node.children() {
+            parents[child.index()] = Some(node.index());
+        }
+    }
+
+    let nodes: Vec<usize> = skin.joints().map(|node| node.index()).collect();
+    let mut inverse_binds = skin.reader(|buf| Some(&buffers[buf.index()])).read_inverse_bind_matrices();
+    let mut joints = Vec::with_capacity(nodes.len());
+    for (i, joint) in skin.joints().enumerate() {
+        let (translation, rotation, scale) = joint.transform().decomposed();
+        joints.push(Joint {
+            name: joint.name().map_or_else(|| format!("joint{i}"), str::to_owned),
+            parent: parents[joint.index()]
+                .and_then(|parent| nodes.iter().position(|&node| node == parent))
+                .map(|parent| parent as u16),
+            // glTF's default is the identity, for joints that were skinned where they are
+            inverse_bind: inverse_binds.as_mut()
+                .and_then(Iterator::next)
+                .map_or(Mat4::IDENTITY, |matrix| Mat4::from_cols_array_2d(&matrix))
+                .to_cols_array(),
+            translation,
+            rotation,
+            scale,
+        });
+    }
+    if joints.len() > mm3ds_format::MAX_JOINTS as usize {
+        return Err(format!("the skin has {} joints, the limit is {}", joints.len(), mm3ds_format::MAX_JOINTS).into());
+    }
+
+    // the inverse bind matrices take the mesh into the joints from the top of the scene, so whatever's
+    // above the root joints goes into the skeleton too. this is the first root's
+    let mut root = Mat4::IDENTITY;
+    let mut above = nodes.first().and_then(|&node| {
+        let mut node = node;
+        while let Some(parent) = parents[node].filter(|parent| nodes.contains(parent)) {
+            node = parent;
+        }
+        parents[node]
+    });
+    while let Some(node) = above {
+        root = Mat4::from_cols_array_2d(&document.nodes().nth(node).unwrap().transform().matrix()) * root;
+        above = parents[node];
+    }
+
+    Ok(Some((SkeletonData { root: root.to_cols_array(), joints }, nodes)))
+}
+
+// what an animation channel does to a joint, with every key as linear as the ANIM format has it.
+// None for what the engine can't play, like morph targets
+fn channel(channel: &gltf::animation::Channel, joint: u16, buffers: &[buffer::Data]) -> Option<Channel> {
+    let reader = channel.reader(|buf| Some(&buffers[buf.index()]));
+    let times: Vec<f32> = reader.read_inputs()?.collect();
+    let (property, values): (Property, Vec<[f32; 4]>) = match reader.read_outputs()? {
+        ReadOutputs::Translations(values) => (Property::Translation, values.map(|[x, y, z]| [x, y, z, 0.]).collect()),
+        ReadOutputs::Rotations(values) => (Property::Rotation, values.into_f32().collect()),
+        ReadOutputs::Scales(values) => (Property::Scale, values.map(|[x, y, z]| [x, y, z, 0.]).collect()),
+        ReadOutputs::MorphTargetWeights(_) => return None,
+    };
+
+    let mut keys = vec![];
+    match channel.sampler().interpolation() {
+        Interpolation::Linear => {
+            keys.extend(times.iter().zip(values).map(|(&time, value)| Keyframe { time, value }));
+        }
+        // held until the next key, which a key with the old value at the same time does
+        Interpolation::Step => {
+            for (i, (&time, &value)) in times.iter().zip(&values).enumerate() {
+                if i > 0 {
+                    keys.push(Keyframe { time, value: values[i - 1] });
+                }
+                keys.push(Keyframe { time, value });
+            }
+        }
+        // each key's value comes between its tangents, which are left out
+        Interpolation::CubicSpline => {
+            keys.extend(times.iter().zip(values.chunks_exact(3)).map(|(&time, value)| Keyframe { time, value: value[1] }));
+        }
+    }
+    if keys.is_empty() {
+        return None;
+    }
+
+    Some(Channel { joint, property, keys })
+}
+
+// every animation in the glTF file at `path`, as clips for an ANIM file, with the skeleton they move
+// if there's a skin. a clip lasts until its last keyframe, and moves the joints of the first skin
+pub fn convert_anim(path: impl AsRef<Path>) -> Result<AnimData, Box<dyn Error>> {
+    let (document, buffers, _images) = gltf::import(path)?;
+    let skeleton = skeleton(&document, &buffers)?;
+    let nodes = skeleton.as_ref().map_or(&[][..], |(_, nodes)| nodes);
+
+    let mut clips = vec![];
+    for (i, animation) in document.animations().enumerate() {
+        let duration = animation.channels()
+            .filter_map(|channel| channel.reader(|buf| Some(&buffers[buf.index()])).read_inputs())
+            .flatten()
+            .fold(0., f32::max);
+
+        let mut events = clip_events(&animation);
+        for event in &mut events {
+            event.time = event.time.clamp(0., duration);
+        }
+
+        // channels on nodes that aren't joints move things the engine doesn't know about
+        let channels = animation.channels()
+            .filter_map(|c| {
+                let joint = nodes.iter().position(|&node| node == c.target().node().index())?;
+                channel(&c, joint as u16, &buffers)
+            })
+            .collect();
+
+        clips.push(Clip {
+            name: animation.name().map_or_else(|| format!("animation{i}"), str::to_owned),
+            duration,
+            events,
+            channels,
+        });
+    }
+
+    Ok(AnimData { skeleton: skeleton.map(|(skeleton, _)| skeleton), clips })
+}
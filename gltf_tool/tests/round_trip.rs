@@ -0,0 +1,80 @@
+use mm3ds_format::{AlphaMode, MeshData, SceneNode, Vertex};
+
+// test/triangle.gltf is a single orange triangle, on a node translated to z = -2
+fn convert_triangle() -> Vec<MeshData> {
+    gltf_tool::convert(concat!(env!("CARGO_MANIFEST_DIR"), "/test/triangle.gltf")).unwrap()
+}
+
+#[test]
+fn converts_triangle() {
+    let meshes = convert_triangle();
+    assert_eq!(meshes.len(), 1);
+
+    let mesh = &meshes[0];
+    assert_eq!(mesh.color, [1., 0.5, 0.25, 1.]);
+    assert_eq!(mesh.indices, [0, 1, 2]);
+    assert_eq!(mesh.texture, None);
+    assert_eq!(mesh.alpha, AlphaMode::Opaque);
+    assert!(!mesh.double_sided);
+    assert_eq!(mesh.vertices, [
+        Vertex { pos: [0., 0., -2.], uv: [0., 0.], normal: [0., 0., 1.] },
+        Vertex { pos: [1., 0., -2.], uv: [1., 0.], normal: [0., 0., 1.] },
+        Vertex { pos: [0., 1., -2.], uv: [0., 1.], normal: [0., 0., 1.] },
+    ]);
+}
+
+#[test]
+fn engine_reads_what_gltf_tool_writes() {
+    let meshes = convert_triangle();
+
+    let mut file = vec![];
+    mm3ds_format::write_mesh_file(&mut file, &meshes).unwrap();
+
+    assert_eq!(mm3ds_format::read_mesh_file(&file[..]).unwrap(), meshes);
+}
+
+// test/grey_textured.gltf is the triangle with an 8 bit greyscale (R8) base color texture
+#[test]
+fn converts_grey_texture() {
+    let meshes = gltf_tool::convert(concat!(env!("CARGO_MANIFEST_DIR"), "/test/grey_textured.gltf")).unwrap();
+    let texture = meshes[0].texture.as_ref().expect("the texture is converted");
+    assert!(!texture.is_empty());
+}
+
+#[test]
+fn triangle_scene_keeps_its_node() {
+    let scene = gltf_tool::convert_scene(concat!(env!("CARGO_MANIFEST_DIR"), "/test/triangle.gltf")).unwrap();
+    let transform = [1., 0., 0., 0., 0., 1., 0., 0., 0., 0., 1., 0., 0., 0., -2., 1.];
+    assert_eq!(scene.nodes, [SceneNode { name: "triangle".to_owned(), parent: None, transform, meshes: vec![0] }]);
+    // the node moves the mesh, so it's left where it was made
+    assert_eq!(scene.meshes[0].vertices[1].pos, [1., 0., 0.]);
+
+    let mut file = vec![];
+    mm3ds_format::write_scene_file(&mut file, &scene).unwrap();
+    assert_eq!(mm3ds_format::read_scene_file(&file[..]).unwrap(), scene);
+}
+
+#[test]
+fn triangle_has_no_clips() {
+    let anim = gltf_tool::convert_anim(concat!(env!("CARGO_MANIFEST_DIR"), "/test/triangle.gltf")).unwrap();
+    assert!(anim.clips.is_empty());
+    assert_eq!(anim.skeleton, None);
+}
+
+// test/mirrored.gltf is the same triangle with its node scaled by -1 in x
+#[test]
+fn mirrored_triangle_is_turned_back() {
+    let meshes = gltf_tool::convert(concat!(env!("CARGO_MANIFEST_DIR"), "/test/mirrored.gltf")).unwrap();
+    assert_eq!(meshes[0].indices, [0, 2, 1]);
+    assert_eq!(meshes[0].vertices[1].pos, [-1., 0., -2.]);
+}
+
+#[test]
+fn flip_winding_reverses_triangles() {
+    let options = gltf_tool::Options { flip_winding: true };
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/test/triangle.gltf");
+    assert_eq!(gltf_tool::convert_with(path, options).unwrap()[0].indices, [0, 2, 1]);
+
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/test/mirrored.gltf");
+    assert_eq!(gltf_tool::convert_with(path, options).unwrap()[0].indices, [0, 1, 2]);
+}
@@ -0,0 +1,364 @@
+use std::env;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+use glam::{Mat4, Vec3};
+use miniquad::*;
+
+use mm3ds_format::{FRAME_MAGIC, FrameCapture, MeshData, SceneData, Vertex};
+
+// t3x textures are tiled and usually ETC1 compressed, so they aren't decoded. instead, meshes with
+// UVs can be drawn with a checkerboard to check the mapping (toggle with U)
+//
+// it also replays frame captures from the engine (see Renderer::capture_frame), drawing each mesh
+// the frame asked for where it was asked for, from the console's camera (toggle with C). the
+// console's projection is made for the PICA, so the picture is this viewer's lighting and lens
+const CLEAR_COLOR: (f32, f32, f32, f32) = (0x68 as f32 / 255., 0xb0 as f32 / 255., 0xd8 as f32 / 255., 1.);
+const FOV_Y: f32 = 80.;
+
+struct GpuMesh {
+    bindings: Bindings,
+    n_indices: i32,
+    color: [f32; 4],
+    model: Mat4,
+}
+
+// something to draw, from a mesh file or a frame capture
+struct Drawn {
+    vertices: Vec<Vertex>,
+    indices: Vec<u16>,
+    color: [f32; 4],
+    model: Mat4,
+}
+
+impl Drawn {
+    fn from_mesh(mesh: MeshData) -> Self {
+        Self { vertices: mesh.vertices, indices: mesh.indices, color: mesh.color, model: Mat4::IDENTITY }
+    }
+
+    // every mesh on every node it's on, where the nodes put it. a file without nodes has its meshes
+    // where they are
+    fn from_scene(scene: SceneData) -> Vec<Self> {
+        if scene.nodes.is_empty() {
+            return scene.meshes.into_iter().map(Self::from_mesh).collect();
+        }
+
+        let mut world: Vec<Mat4> = Vec::with_capacity(scene.nodes.len());
+        let mut ret = vec![];
+        for node in &scene.nodes {
+            let transform = Mat4::from_cols_array(&node.transform);
+            let transform = node.parent.map_or(transform, |parent| world[parent as usize] * transform);
+            world.push(transform);
+            for &i in &node.meshes {
+                let mesh = &scene.meshes[i as usize];
+                ret.push(Self { vertices: mesh.vertices.clone(), indices: mesh.indices.clone(), color: mesh.color, model: transform });
+            }
+        }
+
+        ret
+    }
+
+    fn from_capture(capture: &FrameCapture) -> Vec<Self> {
+        capture.draws.iter()
+            .map(|draw| {
+                // read checks every draw's mesh is there
+                let mesh = capture.meshes.iter().find(|mesh| mesh.id == draw.mesh).unwrap();
+                let indices = if mesh.indices.is_empty() { (0..mesh.vertices.len() as u16).collect() } else { mesh.indices.clone() };
+                Self { vertices: mesh.vertices.clone(), indices, color: mesh.color, model: Mat4::from_cols_array(&draw.model) }
+            })
+            .collect()
+    }
+}
+
+#[repr(C)]
+struct Uniforms {
+    projection: Mat4,
+    model_view: Mat4,
+    diffuse: [f32; 4],
+    checker: f32,
+}
+
+struct Viewer {
+    ctx: Box<dyn RenderingBackend>,
+    pipeline: Pipeline,
+    meshes: Vec<GpuMesh>,
+
+    center: Vec3,
+    radius: f32,
+
+    // orbit camera
+    yaw: f32,
+    pitch: f32,
+    distance: f32,
+    dragging: bool,
+    last_mouse: (f32, f32),
+    checker: bool,
+
+    captured_view: Option<Mat4>, // the console's camera, for frame captures
+    use_captured_view: bool,
+}
+
+impl Viewer {
+    fn new(meshes: Vec<Drawn>, captured_view: Option<Mat4>) -> Self {
+        let mut ctx = window::new_rendering_backend();
+
+        let shader = ctx.new_shader(
+            ShaderSource::Glsl { vertex: shader::VERTEX, fragment: shader::FRAGMENT },
+            shader::meta(),
+        ).unwrap();
+
+        let pipeline = ctx.new_pipeline(
+            &[BufferLayout::default()],
+            &[
+                VertexAttribute::new("in_pos", VertexFormat::Float3),
+                VertexAttribute::new("in_uv", VertexFormat::Float2),
+                VertexAttribute::new("in_normal", VertexFormat::Float3),
+            ],
+            shader,
+            PipelineParams {
+                depth_test: Comparison::LessOrEqual,
+                depth_write: true,
+                ..Default::default()
+            },
+        );
+
+        let (min, max) = meshes.iter()
+            .flat_map(|mesh| mesh.vertices.iter().map(|v: &Vertex| mesh.model.transform_point3(v.pos.into())))
+            .fold((Vec3::INFINITY, Vec3::NEG_INFINITY), |(min, max), pos| (min.min(pos), max.max(pos)));
+        let (center, radius) = if min.cmple(max).all() {
+            ((min + max) / 2., ((max - min).length() / 2.).max(0.01))
+        } else {
+            (Vec3::ZERO, 1.) // no vertices at all
+        };
+
+        let meshes = meshes.iter()
+            .filter(|mesh| !mesh.indices.is_empty())
+            .map(|mesh| GpuMesh {
+                bindings: Bindings {
+                    vertex_buffers: vec![ctx.new_buffer(
+                        BufferType::VertexBuffer,
+                        BufferUsage::Immutable,
+                        BufferSource::slice(&mesh.vertices),
+                    )],
+                    index_buffer: ctx.new_buffer(
+                        BufferType::IndexBuffer,
+                        BufferUsage::Immutable,
+                        BufferSource::slice(&mesh.indices),
+                    ),
+                    images: vec![],
+                },
+                n_indices: mesh.indices.len() as i32,
+                color: mesh.color,
+                model: mesh.model,
+            })
+            .collect();
+
+        Self {
+            ctx,
+            pipeline,
+            meshes,
+
+            center,
+            radius,
+
+            yaw: 0.,
+            pitch: 0.,
+            distance: radius * 2.5,
+            dragging: false,
+            last_mouse: (0., 0.),
+            checker: false,
+
+            captured_view,
+            use_captured_view: captured_view.is_some(),
+        }
+    }
+}
+
+impl EventHandler for Viewer {
+    fn update(&mut self) {}
+
+    fn draw(&mut self) {
+        let (width, height) = window::screen_size();
+        let projection = Mat4::perspective_rh_gl(FOV_Y.to_radians(), width / height, self.radius * 0.01, self.radius * 100.);
+        let view = match self.captured_view.filter(|_| self.use_captured_view) {
+            Some(view) => view,
+            None => {
+                Mat4::from_translation(Vec3::new(0., 0., -self.distance))
+                    * Mat4::from_rotation_x(self.pitch)
+                    * Mat4::from_rotation_y(self.yaw)
+                    * Mat4::from_translation(-self.center)
+            }
+        };
+
+        self.ctx.begin_default_pass(PassAction::clear_color(CLEAR_COLOR.0, CLEAR_COLOR.1, CLEAR_COLOR.2, CLEAR_COLOR.3));
+        self.ctx.apply_pipeline(&self.pipeline);
+        for mesh in &self.meshes {
+            self.ctx.apply_bindings(&mesh.bindings);
+            self.ctx.apply_uniforms(UniformsSource::table(&Uniforms {
+                projection,
+                model_view: view * mesh.model,
+                diffuse: mesh.color,
+                checker: if self.checker { 1. } else { 0. },
+            }));
+            self.ctx.draw(0, mesh.n_indices, 1);
+        }
+        self.ctx.end_render_pass();
+
+        self.ctx.commit_frame();
+    }
+
+    fn mouse_button_down_event(&mut self, button: MouseButton, x: f32, y: f32) {
+        if button == MouseButton::Left {
+            self.dragging = true;
+            self.last_mouse = (x, y);
+        }
+    }
+
+    fn mouse_button_up_event(&mut self, button: MouseButton, _x: f32, _y: f32) {
+        if button == MouseButton::Left {
+            self.dragging = false;
+        }
+    }
+
+    fn mouse_motion_event(&mut self, x: f32, y: f32) {
+        if self.dragging {
+            self.yaw += (x - self.last_mouse.0) * 0.01;
+            self.pitch = (self.pitch + (y - self.last_mouse.1) * 0.01)
+                .clamp(-std::f32::consts::FRAC_PI_2, std::f32::consts::FRAC_PI_2);
+        }
+        self.last_mouse = (x, y);
+    }
+
+    fn mouse_wheel_event(&mut self, _x: f32, y: f32) {
+        self.distance = (self.distance * 0.9_f32.powf(y.signum())).clamp(self.radius * 0.1, self.radius * 50.);
+    }
+
+    fn key_down_event(&mut self, keycode: KeyCode, _keymods: KeyMods, _repeat: bool) {
+        match keycode {
+            KeyCode::U => self.checker = !self.checker,
+            KeyCode::C => self.use_captured_view = !self.use_captured_view,
+            KeyCode::R => {
+                self.yaw = 0.;
+                self.pitch = 0.;
+                self.distance = self.radius * 2.5;
+            }
+            KeyCode::Escape => window::order_quit(),
+            _ => {}
+        }
+    }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let Some(path) = env::args().nth(1) else {
+        eprintln!("Usage: {} <mesh file or frame capture>", env::args().next().unwrap());
+        std::process::exit(1);
+    };
+
+    let mut reader = BufReader::new(File::open(&path)?);
+    if reader.fill_buf()?.starts_with(FRAME_MAGIC.as_bytes()) {
+        let capture = FrameCapture::read(reader)?;
+        for (name, value) in &capture.state {
+            println!("{name}: {value}");
+        }
+        println!("{} draws of {} meshes", capture.draws.len(), capture.meshes.len());
+
+        let view = Mat4::from_cols_array(&capture.view);
+        let drawn = Drawn::from_capture(&capture);
+        start(format!("mesh_viewer - {path}"), move || Viewer::new(drawn, Some(view)));
+        return Ok(());
+    }
+
+    let scene = mm3ds_format::read_scene_file(reader)?;
+    for (i, mesh) in scene.meshes.iter().enumerate() {
+        println!(
+            "mesh {i}: {} vertices, {} indices, color {:?}, {}",
+            mesh.vertices.len(),
+            mesh.indices.len(),
+            mesh.color,
+            match &mesh.texture {
+                Some(texture) => format!("{} byte texture", texture.len()),
+                None => "no texture".to_owned(),
+            },
+        );
+    }
+    for node in &scene.nodes {
+        println!("node {}: meshes {:?}", node.name, node.meshes);
+    }
+
+    let drawn = Drawn::from_scene(scene);
+    start(format!("mesh_viewer - {path}"), move || Viewer::new(drawn, None));
+
+    Ok(())
+}
+
+fn start(window_title: String, viewer: impl FnOnce() -> Viewer + 'static) {
+    miniquad::start(
+        conf::Conf {
+            window_title,
+            window_width: 800,
+            window_height: 480, // 5:3, like the top screen
+            ..Default::default()
+        },
+        move || Box::new(viewer()),
+    );
+}
+
+mod shader {
+    use miniquad::*;
+
+    // this is the lighting from engine/shaders/default.v.pica, done per vertex the same way
+    pub const VERTEX: &str = r#"#version 100
+    attribute vec3 in_pos;
+    attribute vec2 in_uv;
+    attribute vec3 in_normal;
+
+    uniform mat4 projection;
+    uniform mat4 model_view;
+    uniform vec4 diffuse;
+
+    varying lowp vec4 color;
+    varying highp vec2 uv;
+
+    const vec4 light_vec = vec4(0.0, 0.0, 1.0, 0.0);
+    const vec4 light_clr = vec4(1.0);
+    const vec4 mat_amb = vec4(0.2, 0.2, 0.2, 0.0);
+    const vec4 mat_emi = vec4(0.0, 0.0, 0.0, 1.0);
+
+    void main() {
+        gl_Position = projection * model_view * vec4(in_pos, 1.0);
+        uv = in_uv;
+
+        vec3 n = normalize((model_view * vec4(in_normal, 0.0)).xyz);
+        float diffuse_level = clamp(-dot(light_vec.xyz, n), 0.0, 1.0);
+
+        color = min(vec4(1.0), mat_emi + light_clr * diffuse_level * diffuse + light_clr * mat_amb);
+    }
+    "#;
+
+    pub const FRAGMENT: &str = r#"#version 100
+    varying lowp vec4 color;
+    varying highp vec2 uv;
+
+    uniform lowp float checker;
+
+    void main() {
+        lowp float check = mod(floor(uv.x * 8.0) + floor(uv.y * 8.0), 2.0);
+        gl_FragColor = color * mix(1.0, 0.5 + 0.5 * check, checker);
+    }
+    "#;
+
+    pub fn meta() -> ShaderMeta {
+        ShaderMeta {
+            images: vec![],
+            uniforms: UniformBlockLayout {
+                uniforms: vec![
+                    UniformDesc::new("projection", UniformType::Mat4),
+                    UniformDesc::new("model_view", UniformType::Mat4),
+                    UniformDesc::new("diffuse", UniformType::Float4),
+                    UniformDesc::new("checker", UniformType::Float1),
+                ],
+            },
+        }
+    }
+}